@@ -33,3 +33,19 @@ fn bench_crc32_hasher(b: &mut Bencher) {
         digest.reset();
     });
 }
+
+#[bench]
+fn bench_fnv1a_hasher(b: &mut Bencher) {
+    use rdms::hasher::Fnv1a;
+    use std::hash::BuildHasher;
+
+    let mut n: u64 = 1;
+    let mut sum = 0;
+    let builder = Fnv1a;
+    b.iter(|| {
+        let mut hasher = builder.build_hasher();
+        hasher.write(&n.to_be_bytes());
+        sum += hasher.finish();
+        n += 1
+    });
+}
@@ -0,0 +1,408 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use crate::nobitmap::NoBitmap;
+use crate::{
+    core::{Entry, Reader, Writer},
+    mvcc::{self, MvccFactory},
+    robt::{self, RobtFactory},
+};
+
+use std::{convert, mem};
+
+use super::*;
+
+#[test]
+fn test_config_root() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let archive: bool = rng.gen();
+    let backup_interval = if rng.gen::<bool>() {
+        Some(time::Duration::from_secs(10))
+    } else {
+        None
+    };
+
+    let ref_root = Root {
+        version: 0,
+        snapshots: vec![],
+
+        archive,
+        backup_interval,
+    };
+
+    let ref_config = Config {
+        archive,
+        backup_interval,
+    };
+    let root = ref_config.clone().into();
+    assert_eq!(ref_root, root);
+
+    let config: Config = root.into();
+    assert_eq!(config, ref_config);
+}
+
+#[test]
+fn test_root() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    for _ in 0..100 {
+        let n = rng.gen::<usize>() % 4;
+        let ref_root = Root {
+            version: rng.gen::<usize>() % 1000,
+            snapshots: (0..n).map(|_| rng.gen::<usize>() % 1000).collect(),
+
+            archive: rng.gen(),
+            backup_interval: match rng.gen::<u8>() % 2 {
+                0 => None,
+                _ => Some(time::Duration::from_secs(rng.gen::<u64>() % 1000)),
+            },
+        };
+
+        let bytes: Vec<u8> = ref_root.clone().try_into().unwrap();
+        let root: Root = bytes.try_into().unwrap();
+        assert_eq!(root, ref_root);
+    }
+
+    let mut root: Root = Config::default().into();
+    assert_eq!(root.to_next_snapshot(), 0);
+    root = root.to_next(0).to_next(1);
+    assert_eq!((root.version, root.snapshots.clone()), (2, vec![1]));
+    assert_eq!(root.to_next_snapshot(), 2);
+
+    root.archive = true;
+    root = root.to_next(2);
+    assert_eq!((root.version, root.snapshots.clone()), (3, vec![1, 2]));
+    assert_eq!(root.to_next_snapshot(), 3);
+}
+
+#[test]
+fn test_root_file_name() {
+    let s = "my-index-backup-000.root".to_string();
+    let ss: &ffi::OsStr = s.as_ref();
+
+    let root = RootFileName(ss.to_os_string());
+    let (name, ver): (String, usize) = root.try_into().unwrap();
+    assert_eq!(name, "my-index".to_string());
+    assert_eq!(ver, 0);
+
+    let root: RootFileName = (name, 1).into();
+    assert_eq!(root.0, "my-index-backup-001.root");
+
+    let fname: ffi::OsString = root.clone().into();
+    assert_eq!(fname, "my-index-backup-001.root");
+
+    assert_eq!(root.to_string(), "my-index-backup-001.root".to_string());
+
+    let s = "my-index-dgm-000.root".to_string();
+    let ss: &ffi::OsStr = s.as_ref();
+    let res: Result<(String, usize)> = RootFileName(ss.to_os_string()).try_into();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_snapshot_name() {
+    let name = "my-index-backupsnap-000".to_string();
+
+    let snap_name = SnapshotName(name.clone());
+    let (name, snapshot): (String, usize) = snap_name.try_into().unwrap();
+    assert_eq!(name, "my-index".to_string());
+    assert_eq!(snapshot, 0);
+
+    let snap_name: SnapshotName = (name, 1).into();
+    assert_eq!(snap_name.to_string(), "my-index-backupsnap-001".to_string());
+    assert_eq!(
+        format!("{:?}", snap_name),
+        format!("{:?}", "my-index-backupsnap-001"),
+    );
+}
+
+#[test]
+fn test_backup_crud() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let config = Config {
+        archive: false,
+        backup_interval: None,
+    };
+
+    let mut ref_index = mvcc::Mvcc::new_lsm("backup-crud");
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-backup-crud");
+        dir.into_os_string()
+    };
+    let mut index = Backup::new(
+        //
+        &dir,
+        "backup-crud",
+        mvcc::mvcc_factory(true /*lsm*/),
+        new_disk_factory(),
+        config,
+    )
+    .unwrap();
+
+    let n_ops = 1_000;
+    let key_max = n_ops * 3;
+    for i in 0..10 {
+        let mut index_w = index.to_writer().unwrap();
+        let mut index_r = index.to_reader().unwrap();
+        for _ in 0..n_ops {
+            let key: i64 = rng.gen::<i64>().abs() % key_max;
+            let value: i64 = rng.gen::<i64>().abs();
+            match rng.gen::<u8>() % 3 {
+                0 => {
+                    index_w.set(key, value).unwrap();
+                    ref_index.set(key, value).unwrap();
+                }
+                1 => {
+                    let cas = match index_r.get(&key) {
+                        Ok(entry) => entry.to_seqno(),
+                        Err(Error::KeyNotFound) => std::u64::MIN,
+                        Err(err) => panic!("{:?}", err),
+                    };
+                    index_w.set_cas(key, value, cas).unwrap();
+                    ref_index.set_cas(key, value, cas).unwrap();
+                }
+                _ => {
+                    index_w.delete(&key).unwrap();
+                    ref_index.delete(&key).unwrap();
+                }
+            }
+        }
+        mem::drop(index_w);
+        mem::drop(index_r);
+
+        verify_read(key_max, &mut ref_index, &mut index);
+
+        index
+            .commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+        if i % 2 == 0 {
+            index.compact(Cutoff::new_lsm_empty()).unwrap();
+        }
+
+        let stats: Stats<mvcc::Stats, robt::Stats> = index.validate().unwrap();
+        assert_eq!(stats.backup_seqno, ref_index.to_seqno().unwrap());
+        assert_eq!(stats.n_archives, 0);
+        assert!(stats.disk.is_some());
+        assert_eq!(index.to_archives().unwrap().len(), 0);
+
+        if rng.gen::<bool>() {
+            index.close().unwrap();
+            index = Backup::open(
+                //
+                &dir,
+                "backup-crud",
+                mvcc::mvcc_factory(true /*lsm*/),
+                new_disk_factory(),
+            )
+            .unwrap();
+
+            let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+            assert_eq!(stats.n_backups, 0);
+            assert_eq!(stats.backup_seqno, ref_index.to_seqno().unwrap());
+        }
+
+        verify_read(key_max, &mut ref_index, &mut index);
+    }
+
+    index.purge().unwrap();
+}
+
+#[test]
+fn test_backup_archive() {
+    let config = Config {
+        archive: true,
+        backup_interval: None,
+    };
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-backup-archive");
+        dir.into_os_string()
+    };
+    let mut index = Backup::new(
+        //
+        &dir,
+        "backup-archive",
+        mvcc::mvcc_factory(false /*lsm*/),
+        new_disk_factory(),
+        config,
+    )
+    .unwrap();
+
+    // nothing to backup.
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert!(stats.disk.is_none());
+    assert_eq!(stats.n_backups, 0);
+
+    let mut seqnos = vec![];
+    for i in 0..5 {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..100 {
+            index_w.set(key, key * 10 + i).unwrap();
+        }
+        mem::drop(index_w);
+
+        index
+            .commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+        // no new mutations, no new snapshot.
+        index
+            .commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+        seqnos.push(index.to_seqno().unwrap());
+    }
+
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.validate().unwrap();
+    assert_eq!(stats.n_backups, 5);
+    assert_eq!(stats.n_archives, 4);
+    assert_eq!(stats.backup_seqno, 500);
+
+    let archives = index.to_archives().unwrap();
+    assert_eq!(archives.len(), 4);
+    for (i, name) in archives.iter().enumerate() {
+        let mut snap = new_disk_factory().open(&dir, name).unwrap();
+        assert_eq!(snap.to_seqno().unwrap(), seqnos[i]);
+        let mut r = snap.to_reader().unwrap();
+        for key in 0..100 {
+            let entry = r.get(&key).unwrap();
+            assert_eq!(entry.to_native_value(), Some(key * 10 + (i as i64)));
+        }
+        mem::drop(r);
+        snap.close().unwrap();
+    }
+
+    // re-open with latest snapshot, archives are preserved.
+    index.close().unwrap();
+    let mut index = Backup::open(
+        //
+        &dir,
+        "backup-archive",
+        mvcc::mvcc_factory(false /*lsm*/),
+        new_disk_factory(),
+    )
+    .unwrap();
+    assert_eq!(index.to_seqno().unwrap(), 500);
+    assert_eq!(index.to_archives().unwrap(), archives);
+    let mut r = index.to_reader().unwrap();
+    for key in 0..100 {
+        let entry = r.get(&key).unwrap();
+        assert_eq!(entry.to_native_value(), Some(key * 10 + 4));
+    }
+    mem::drop(r);
+
+    index.purge().unwrap();
+    for name in archives.iter() {
+        assert!(new_disk_factory().open(&dir, name).is_err());
+    }
+}
+
+#[test]
+fn test_backup_interval() {
+    let mut config: Config = Default::default();
+    config
+        .set_backup_interval(time::Duration::from_secs(1))
+        .unwrap();
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-backup-interval");
+        dir.into_os_string()
+    };
+    let mut index = Backup::new(
+        //
+        &dir,
+        "backup-interval",
+        mvcc::mvcc_factory(false /*lsm*/),
+        new_disk_factory(),
+        config,
+    )
+    .unwrap();
+
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..1000 {
+        index_w.set(key, key).unwrap();
+    }
+    mem::drop(index_w);
+
+    std::thread::sleep(time::Duration::from_secs(3));
+
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert!(stats.n_backups >= 1);
+    assert_eq!(stats.backup_seqno, 1000);
+    println!("{}", stats);
+
+    index.close().unwrap();
+}
+
+fn new_disk_factory() -> RobtFactory<i64, i64, NoBitmap> {
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+    robt::robt_factory::<i64, i64, NoBitmap>(config)
+}
+
+fn verify_read(
+    key_max: i64,
+    ref_index: &mut mvcc::Mvcc<i64, i64>,
+    index: &mut Backup<i64, i64, MvccFactory, RobtFactory<i64, i64, NoBitmap>>,
+) {
+    let mut index_r = index.to_reader().unwrap();
+
+    assert_eq!(ref_index.to_seqno().unwrap(), index.to_seqno().unwrap());
+
+    for key in 0..key_max {
+        let res = index_r.get_with_versions(&key);
+        let ref_res = ref_index.get_with_versions(&key);
+        match (res, ref_res) {
+            (Ok(entry), Ok(ref_entry)) => check_entry(&entry, &ref_entry),
+            (Err(Error::KeyNotFound), Err(Error::KeyNotFound)) => (),
+            (res, ref_res) => panic!(
+                "key:{} res:{} ref_res:{}",
+                key,
+                res.is_ok(),
+                ref_res.is_ok()
+            ),
+        }
+    }
+
+    let mut iter = index_r.iter_with_versions().unwrap();
+    let mut ref_iter = ref_index.iter_with_versions().unwrap();
+    loop {
+        match (iter.next(), ref_iter.next()) {
+            (Some(Ok(entry)), Some(Ok(ref_entry))) => check_entry(&entry, &ref_entry),
+            (None, None) => break,
+            (entry, ref_entry) => panic!(
+                "entry:{} ref_entry:{}",
+                entry.is_some(),
+                ref_entry.is_some()
+            ),
+        }
+    }
+}
+
+fn check_entry(e1: &Entry<i64, i64>, e2: &Entry<i64, i64>) {
+    assert_eq!(e1.to_key(), e2.to_key());
+    let key = e1.to_key();
+    assert_eq!(e1.to_seqno(), e2.to_seqno(), "key:{}", key);
+    assert_eq!(e1.to_native_value(), e2.to_native_value(), "key:{}", key);
+    assert_eq!(e1.is_deleted(), e2.is_deleted(), "key:{}", key);
+
+    let (xs, ys) = (e1.to_deltas(), e2.to_deltas());
+    assert_eq!(xs.len(), ys.len(), "key:{}", key);
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        assert_eq!(x.to_seqno(), y.to_seqno(), "key:{}", key);
+        assert_eq!(x.is_deleted(), y.is_deleted(), "key:{}", key);
+        assert_eq!(x.to_diff(), y.to_diff(), "key:{}", key);
+    }
+}
@@ -0,0 +1,339 @@
+//! Module `bloom` implement the [Bloom] trait for classic
+//! [bloom filter][bloom-filter].
+//!
+//! Filter is sized for the number of keys added to it and a configurable
+//! bits-per-key, refer to [BitsPerKey]. Since the number of keys is known
+//! only after all the keys are added, which fits robt's build-once model,
+//! bit-array is constructed lazily while serializing the filter via
+//! [to_vec][Bloom::to_vec].
+//!
+//! Keys are hashed into 32-bit digest, using hasher `H` that defaults to
+//! [Crc32], and `k` probe positions are derived from the digest using
+//! double hashing. With 10 bits per key, false-positive-rate is about 1%.
+//!
+//! [Bloom]: crate::core::Bloom
+//! [bloom-filter]: https://en.wikipedia.org/wiki/Bloom_filter
+
+use std::{cmp, collections::HashSet, convert::TryInto, hash::Hash, marker};
+
+use crate::core::{Bloom, HasherId, Result};
+use crate::error::Error;
+use crate::hasher::{self, Crc32};
+
+/// Type parameter to configure the bits-per-key for [BloomFilter].
+pub trait BitsPerKey {
+    /// Number of bits, in the bit-array, allocated for each key.
+    const BITS_PER_KEY: usize;
+}
+
+/// 8 bits per key, about 2% false-positive-rate.
+pub struct Bits8;
+
+/// 10 bits per key, about 1% false-positive-rate.
+pub struct Bits10;
+
+/// 16 bits per key, about 0.05% false-positive-rate.
+pub struct Bits16;
+
+impl BitsPerKey for Bits8 {
+    const BITS_PER_KEY: usize = 8;
+}
+
+impl BitsPerKey for Bits10 {
+    const BITS_PER_KEY: usize = 10;
+}
+
+impl BitsPerKey for Bits16 {
+    const BITS_PER_KEY: usize = 16;
+}
+
+/// Classic k-hash bloom filter, bits-per-key is configured via type
+/// parameter `P`, defaults to [Bits10].
+pub struct BloomFilter<P = Bits10, H = Crc32>
+where
+    P: BitsPerKey,
+    H: HasherId,
+{
+    builder: H,
+    inner: Inner,
+
+    _phantom_bits: marker::PhantomData<P>,
+}
+
+enum Inner {
+    // digests of keys added so far, bit-array is yet to be built.
+    Digests {
+        n_keys: usize,
+        digests: HashSet<u32>,
+    },
+    // bit-array, either de-serialized or merged.
+    Bits {
+        n_keys: usize,
+        k: usize,
+        n_bits: u32,
+        bits: Vec<u64>,
+    },
+}
+
+impl<P, H> BloomFilter<P, H>
+where
+    P: BitsPerKey,
+    H: HasherId,
+{
+    fn new(inner: Inner) -> BloomFilter<P, H> {
+        BloomFilter {
+            builder: Default::default(),
+            inner,
+
+            _phantom_bits: marker::PhantomData,
+        }
+    }
+
+    // number of probes, optimal value is bits_per_key * ln(2).
+    fn to_probes() -> usize {
+        let k = ((P::BITS_PER_KEY as f64) * 0.69) as usize;
+        cmp::min(cmp::max(k, 1), 30)
+    }
+
+    fn build_bits(digests: &HashSet<u32>) -> Result<(usize, u32, Vec<u64>)> {
+        let n_bits = cmp::max(digests.len() * P::BITS_PER_KEY, 64);
+        let mut bits = vec![0_u64; (n_bits + 63) / 64];
+        let n_bits = to_n_bits(&bits)?;
+        let k = Self::to_probes();
+        digests
+            .iter()
+            .for_each(|digest| set_bits(&mut bits, n_bits, k, *digest));
+        Ok((k, n_bits, bits))
+    }
+}
+
+// bit positions are addressed using 32-bit digest.
+fn to_n_bits(bits: &[u64]) -> Result<u32> {
+    let n_bits: u64 = convert_at!(bits.len())?;
+    convert_at!((n_bits * 64))
+}
+
+// probe positions are generated using double hashing, delta being the
+// digest rotated by 17 bits.
+fn set_bits(bits: &mut [u64], n_bits: u32, k: usize, digest: u32) {
+    let (mut h, delta) = (digest, digest.rotate_right(17));
+    for _ in 0..k {
+        let pos = h % n_bits;
+        bits[(pos / 64) as usize] |= 1_u64 << (pos % 64);
+        h = h.wrapping_add(delta);
+    }
+}
+
+fn test_bits(bits: &[u64], n_bits: u32, k: usize, digest: u32) -> bool {
+    if n_bits == 0 {
+        return false;
+    }
+    let (mut h, delta) = (digest, digest.rotate_right(17));
+    for _ in 0..k {
+        let pos = h % n_bits;
+        if (bits[(pos / 64) as usize] & (1_u64 << (pos % 64))) == 0 {
+            return false;
+        }
+        h = h.wrapping_add(delta);
+    }
+    true
+}
+
+impl<P, H> Bloom for BloomFilter<P, H>
+where
+    P: BitsPerKey,
+    H: HasherId,
+{
+    #[inline]
+    fn create() -> Self {
+        Self::new(Inner::Digests {
+            n_keys: 0,
+            digests: HashSet::new(),
+        })
+    }
+
+    #[inline]
+    fn len(&self) -> Result<usize> {
+        match &self.inner {
+            Inner::Digests { n_keys, .. } => Ok(*n_keys),
+            Inner::Bits { n_keys, .. } => Ok(*n_keys),
+        }
+    }
+
+    #[inline]
+    fn add_key<Q: ?Sized + Hash>(&mut self, element: &Q) {
+        let digest = hasher::to_digest32(&self.builder, element);
+        self.add_digest32(digest);
+    }
+
+    #[inline]
+    fn add_digest32(&mut self, digest: u32) {
+        match &mut self.inner {
+            Inner::Digests { n_keys, digests } => {
+                digests.insert(digest);
+                *n_keys += 1;
+            }
+            Inner::Bits {
+                n_keys,
+                k,
+                n_bits,
+                bits,
+            } => {
+                set_bits(bits, *n_bits, *k, digest);
+                *n_keys += 1;
+            }
+        }
+    }
+
+    #[inline]
+    fn contains<Q: ?Sized + Hash>(&self, element: &Q) -> bool {
+        let digest = hasher::to_digest32(&self.builder, element);
+        match &self.inner {
+            Inner::Digests { digests, .. } => digests.contains(&digest),
+            Inner::Bits {
+                k, n_bits, bits, ..
+            } => test_bits(bits, *n_bits, *k, digest),
+        }
+    }
+
+    /// Serialize the filter, building the bit-array if required.
+    ///
+    /// Format: `n_keys:u64 | k:u32 | n_words:u32 | [word:u64]...`,
+    /// all integers are encoded in big-endian.
+    fn to_vec(&self) -> Result<Vec<u8>> {
+        let (n_keys, k, bits) = match &self.inner {
+            Inner::Digests { n_keys, digests } => {
+                let (k, _, bits) = Self::build_bits(digests)?;
+                (*n_keys, k, bits)
+            }
+            Inner::Bits {
+                n_keys, k, bits, ..
+            } => (*n_keys, *k, bits.clone()),
+        };
+
+        let n_keys: u64 = convert_at!(n_keys)?;
+        let k: u32 = convert_at!(k)?;
+        let n_words: u32 = convert_at!(bits.len())?;
+
+        let mut buf = Vec::with_capacity(16 + bits.len() * 8);
+        buf.extend_from_slice(&n_keys.to_be_bytes());
+        buf.extend_from_slice(&k.to_be_bytes());
+        buf.extend_from_slice(&n_words.to_be_bytes());
+        bits.iter()
+            .for_each(|word| buf.extend_from_slice(&word.to_be_bytes()));
+        Ok(buf)
+    }
+
+    fn from_vec(buf: &[u8]) -> Result<BloomFilter<P, H>> {
+        if buf.len() < 16 {
+            err_at!(DecodeFail, msg: format!("bloom filter {}", buf.len()))?
+        }
+        let n_keys: usize = convert_at!(u64::from_be_bytes(array_at!(buf[..8])?))?;
+        let k: usize = convert_at!(u32::from_be_bytes(array_at!(buf[8..12])?))?;
+        let n_words: usize = convert_at!(u32::from_be_bytes(array_at!(buf[12..16])?))?;
+        if buf.len() != (16 + n_words * 8) {
+            let msg = format!("bloom filter {} words:{}", buf.len(), n_words);
+            err_at!(DecodeFail, msg: msg)?
+        }
+
+        let mut bits = Vec::with_capacity(n_words);
+        for off in (16..buf.len()).step_by(8) {
+            bits.push(u64::from_be_bytes(array_at!(buf[off..off + 8])?));
+        }
+        let n_bits = to_n_bits(&bits)?;
+        Ok(Self::new(Inner::Bits {
+            n_keys,
+            k,
+            n_bits,
+            bits,
+        }))
+    }
+
+    /// Merge two filters. Digests yet to be built are added to the other
+    /// filter's bit-array, bit-arrays can be merged only when they are
+    /// of same size.
+    fn or(&self, other: &BloomFilter<P, H>) -> Result<BloomFilter<P, H>> {
+        let inner = match (&self.inner, &other.inner) {
+            (
+                Inner::Digests {
+                    n_keys,
+                    digests: xs,
+                },
+                Inner::Digests {
+                    n_keys: m_keys,
+                    digests: ys,
+                },
+            ) => Inner::Digests {
+                n_keys: n_keys + m_keys,
+                digests: xs.union(ys).cloned().collect(),
+            },
+            (
+                Inner::Bits {
+                    n_keys,
+                    k,
+                    n_bits,
+                    bits,
+                },
+                Inner::Digests {
+                    n_keys: m_keys,
+                    digests,
+                },
+            )
+            | (
+                Inner::Digests {
+                    n_keys: m_keys,
+                    digests,
+                },
+                Inner::Bits {
+                    n_keys,
+                    k,
+                    n_bits,
+                    bits,
+                },
+            ) => {
+                let mut bits = bits.clone();
+                digests
+                    .iter()
+                    .for_each(|d| set_bits(&mut bits, *n_bits, *k, *d));
+                Inner::Bits {
+                    n_keys: n_keys + m_keys,
+                    k: *k,
+                    n_bits: *n_bits,
+                    bits,
+                }
+            }
+            (
+                Inner::Bits {
+                    n_keys,
+                    k,
+                    n_bits,
+                    bits,
+                },
+                Inner::Bits {
+                    n_keys: m_keys,
+                    k: m,
+                    bits: ys,
+                    ..
+                },
+            ) if k == m && bits.len() == ys.len() => Inner::Bits {
+                n_keys: n_keys + m_keys,
+                k: *k,
+                n_bits: *n_bits,
+                bits: bits.iter().zip(ys.iter()).map(|(x, y)| x | y).collect(),
+            },
+            (Inner::Bits { .. }, Inner::Bits { .. }) => {
+                err_at!(InvalidInput, msg: format!("bloom filter size mismatch"))?
+            }
+        };
+        Ok(Self::new(inner))
+    }
+
+    #[inline]
+    fn to_hasher_id() -> String {
+        H::to_hasher_id()
+    }
+}
+
+#[cfg(test)]
+#[path = "bloom_test.rs"]
+mod bloom_test;
@@ -0,0 +1,82 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use super::*;
+
+#[test]
+fn test_bloom_filter() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let keys: Vec<u64> = (0..100_000).map(|_| rng.gen::<u64>() | 1).collect();
+
+    let mut filter: BloomFilter = Bloom::create();
+    assert_eq!(filter.len().unwrap(), 0);
+    keys.iter().for_each(|key| filter.add_key(key));
+    assert_eq!(filter.len().unwrap(), keys.len());
+
+    // no false negatives, before and after serialization.
+    keys.iter().for_each(|key| assert!(filter.contains(key)));
+    let filter = BloomFilter::<Bits10>::from_vec(&filter.to_vec().unwrap()).unwrap();
+    assert_eq!(filter.len().unwrap(), keys.len());
+    keys.iter().for_each(|key| assert!(filter.contains(key)));
+
+    // false positives, even numbers were never added.
+    let n = 100_000;
+    let fps = (0..n)
+        .filter(|_| filter.contains(&(rng.gen::<u64>() & !1)))
+        .count();
+    println!("bits-per-key:10 fpr:{}", (fps as f64) / (n as f64));
+    assert!(fps < (n * 2 / 100), "{}/{}", fps, n);
+
+    let mut filter: BloomFilter<Bits16> = Bloom::create();
+    keys.iter().for_each(|key| filter.add_key(key));
+    let filter = BloomFilter::<Bits16>::from_vec(&filter.to_vec().unwrap()).unwrap();
+    let fps = (0..n)
+        .filter(|_| filter.contains(&(rng.gen::<u64>() & !1)))
+        .count();
+    println!("bits-per-key:16 fpr:{}", (fps as f64) / (n as f64));
+    assert!(fps < (n * 2 / 1000), "{}/{}", fps, n);
+}
+
+#[test]
+fn test_bloom_filter_empty() {
+    let filter: BloomFilter = Bloom::create();
+    let filter = BloomFilter::<Bits10>::from_vec(&filter.to_vec().unwrap()).unwrap();
+    assert_eq!(filter.len().unwrap(), 0);
+    assert!(!filter.contains(&10_u64));
+
+    assert!(BloomFilter::<Bits10>::from_vec(&[1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_bloom_filter_or() {
+    let (keys1, keys2): (Vec<u64>, Vec<u64>) = ((0..1000).collect(), (1000..3000).collect());
+
+    let mut f1: BloomFilter = Bloom::create();
+    keys1.iter().for_each(|key| f1.add_key(key));
+    let mut f2: BloomFilter = Bloom::create();
+    keys2.iter().for_each(|key| f2.add_key(key));
+
+    // digests with digests.
+    let f = f1.or(&f2).unwrap();
+    assert_eq!(f.len().unwrap(), 3000);
+    keys1.iter().for_each(|key| assert!(f.contains(key)));
+    keys2.iter().for_each(|key| assert!(f.contains(key)));
+
+    // bit-array with digests, like in incremental build.
+    let old = BloomFilter::<Bits10>::from_vec(&f1.to_vec().unwrap()).unwrap();
+    let f = old.or(&f2).unwrap();
+    assert_eq!(f.len().unwrap(), 3000);
+    keys1.iter().for_each(|key| assert!(f.contains(key)));
+    keys2.iter().for_each(|key| assert!(f.contains(key)));
+    let f = BloomFilter::<Bits10>::from_vec(&f.to_vec().unwrap()).unwrap();
+    keys2.iter().for_each(|key| assert!(f.contains(key)));
+
+    // bit-array with bit-array.
+    let f = old.or(&old).unwrap();
+    assert_eq!(f.len().unwrap(), 2000);
+    keys1.iter().for_each(|key| assert!(f.contains(key)));
+    let new = BloomFilter::<Bits10>::from_vec(&f2.to_vec().unwrap()).unwrap();
+    assert!(old.or(&new).is_err());
+}
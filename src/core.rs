@@ -14,7 +14,7 @@
 use std::{
     borrow::Borrow,
     ffi, fmt,
-    hash::Hash,
+    hash::{BuildHasher, Hash},
     marker,
     ops::{Bound, RangeBounds},
     result,
@@ -23,7 +23,7 @@ use std::{
 pub use crate::entry::Entry;
 pub(crate) use crate::entry::{Delta, InnerDelta, Value};
 
-use crate::{error::Error, util};
+use crate::{error::Error, robt::RateLimiter, util};
 #[allow(unused_imports)]
 use crate::{
     llrb::Llrb,
@@ -237,6 +237,11 @@ where
 
     /// Index type for identification purpose.
     fn to_type(&self) -> String;
+
+    /// Throttle disk writes, of indexes subsequently created or opened
+    /// by this factory, using `limiter`. Default implementation ignores
+    /// the limiter.
+    fn set_rate_limiter(&mut self, _limiter: RateLimiter) {}
 }
 
 /// Trait to commit a batch of pre-sorted entries into target index.
@@ -340,6 +345,34 @@ where
     fn purge(self) -> Result<()>;
 }
 
+/// Trait to compact a subset of index, selected by key range.
+pub trait CompactRange<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Same as [compact][Index::compact], except that `cutoff` shall
+    /// apply only to entries whose key fall within `range`, and only
+    /// when `filter` returns true for the entry. Index shall not be
+    /// rewritten if there is nothing to purge within the range.
+    ///
+    /// Return number of entries purged, either fully or their older
+    /// versions.
+    fn compact_range<R, F>(&mut self, range: R, cutoff: Cutoff, filter: F) -> Result<usize>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&Entry<K, V>) -> Result<bool>;
+}
+
+/// Trait to list files backing a disk index, so that they can be shared,
+/// by hard-linking, with another index directory.
+pub trait IndexFiles {
+    /// Return files, with full path, backing the latest snapshot of this
+    /// index. Files are valid only until the index is committed,
+    /// compacted or purged.
+    fn to_files(&self) -> Result<Vec<ffi::OsString>>;
+}
+
 /// Trait to self-validate index's internal state.
 pub trait Validate<T: fmt::Display> {
     /// Call this to make sure all is well. Note that this can be
@@ -348,6 +381,13 @@ pub trait Validate<T: fmt::Display> {
     fn validate(&mut self) -> Result<T>;
 }
 
+/// Trait to gather index statistics. Unlike [Validate], this is expected
+/// to be cheap, and can be called on a live index.
+pub trait ToStats<T: fmt::Display> {
+    /// Return statistics for this index.
+    fn to_stats(&self) -> Result<T>;
+}
+
 /// Trait to manage keys in a bitmapped Bloom-filter.
 pub trait Bloom: Sized {
     /// Create an empty bit-map.
@@ -367,13 +407,29 @@ pub trait Bloom: Sized {
     fn contains<Q: ?Sized + Hash>(&self, element: &Q) -> bool;
 
     /// Serialize the bit-map to binary array.
-    fn to_vec(&self) -> Vec<u8>;
+    fn to_vec(&self) -> Result<Vec<u8>>;
 
     /// Deserialize the binary array to bit-map.
     fn from_vec(buf: &[u8]) -> Result<Self>;
 
     /// Merge two bitmaps.
     fn or(&self, other: &Self) -> Result<Self>;
+
+    /// Return the identity of hasher used to compute key digests, refer
+    /// to [HasherId]. Return empty string if bitmap does not hash its
+    /// keys.
+    fn to_hasher_id() -> String;
+}
+
+/// Trait to identify a hasher used by [Bloom] implementations.
+///
+/// Key digests are persisted along with the index, hence hasher's
+/// identity is also persisted, so that a bitmap built using one hasher
+/// is never probed using another. Implementing types must be
+/// deterministic across process restarts.
+pub trait HasherId: BuildHasher + Default {
+    /// Unique name for this hasher.
+    fn to_hasher_id() -> String;
 }
 
 /// Trait define read operations for rdms-index.
@@ -389,6 +445,25 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized + Hash;
 
+    /// Check whether `key` might be present in index, without touching
+    /// the index, typically by probing its bloom filter. There can be
+    /// false positives but no false negatives. Default implementation
+    /// returns true.
+    fn may_contain<Q>(&self, _key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        true
+    }
+
+    /// Return whether [Reader::may_contain] is backed by a filter, like
+    /// bloom filter, for this index. Default implementation returns
+    /// false.
+    fn has_bitmap(&self) -> bool {
+        false
+    }
+
     /// Iterate over all entries in this index. Returned entry may not
     /// have all its previous versions, if it is costly to fetch from disk.
     fn iter(&mut self) -> Result<IndexIter<K, V>>;
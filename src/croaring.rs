@@ -1,32 +1,47 @@
 //! Module `croaring` implement the [Bloom] trait for
 //! [roaring bitmap][roaring-bitmap].
 //!
+//! Key digests are computed using hasher `H`, defaults to [Crc32].
+//!
 //! [Bloom]: crate::core::Bloom
 //! [roaring-bitmap]: https://roaringbitmap.org
 
-use crc::crc32::{self, Hasher32};
 use croaring::bitmap::Bitmap;
 
 use std::{convert::TryInto, hash::Hash};
 
-use crate::core::{Bloom, Result};
+use crate::core::{Bloom, HasherId, Result};
 use crate::error::Error;
+use crate::hasher::{self, Crc32};
 
-// TODO: right now we are using crc32, make hasher generic.
-
-pub struct CRoaring {
-    hasher: crc32::Digest,
+pub struct CRoaring<H = Crc32>
+where
+    H: HasherId,
+{
+    builder: H,
     bitmap: Bitmap,
 }
 
-impl Bloom for CRoaring {
-    #[inline]
-    fn create() -> Self {
+impl<H> CRoaring<H>
+where
+    H: HasherId,
+{
+    fn new(bitmap: Bitmap) -> CRoaring<H> {
         CRoaring {
-            hasher: crc32::Digest::new(crc32::IEEE),
-            bitmap: Bitmap::create(),
+            builder: Default::default(),
+            bitmap,
         }
     }
+}
+
+impl<H> Bloom for CRoaring<H>
+where
+    H: HasherId,
+{
+    #[inline]
+    fn create() -> Self {
+        Self::new(Bitmap::create())
+    }
 
     #[inline]
     fn len(&self) -> Result<usize> {
@@ -35,9 +50,8 @@ impl Bloom for CRoaring {
 
     #[inline]
     fn add_key<Q: ?Sized + Hash>(&mut self, element: &Q) {
-        self.hasher.reset();
-        element.hash(&mut self.hasher);
-        self.add_digest32(self.hasher.sum32());
+        let digest = hasher::to_digest32(&self.builder, element);
+        self.add_digest32(digest);
     }
 
     #[inline]
@@ -47,29 +61,27 @@ impl Bloom for CRoaring {
 
     #[inline]
     fn contains<Q: ?Sized + Hash>(&self, element: &Q) -> bool {
-        let mut hasher = crc32::Digest::new(crc32::IEEE);
-        element.hash(&mut hasher);
-        self.bitmap.contains(hasher.sum32())
+        let digest = hasher::to_digest32(&self.builder, element);
+        self.bitmap.contains(digest)
+    }
+
+    #[inline]
+    fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(self.bitmap.serialize())
     }
 
     #[inline]
-    fn to_vec(&self) -> Vec<u8> {
-        self.bitmap.serialize()
+    fn from_vec(buf: &[u8]) -> Result<CRoaring<H>> {
+        Ok(Self::new(Bitmap::deserialize(buf)))
     }
 
     #[inline]
-    fn from_vec(buf: &[u8]) -> Result<CRoaring> {
-        Ok(CRoaring {
-            hasher: crc32::Digest::new(crc32::IEEE),
-            bitmap: Bitmap::deserialize(buf),
-        })
+    fn or(&self, other: &CRoaring<H>) -> Result<CRoaring<H>> {
+        Ok(Self::new(self.bitmap.or(&other.bitmap)))
     }
 
     #[inline]
-    fn or(&self, other: &CRoaring) -> Result<CRoaring> {
-        Ok(CRoaring {
-            hasher: crc32::Digest::new(crc32::IEEE),
-            bitmap: self.bitmap.or(&other.bitmap),
-        })
+    fn to_hasher_id() -> String {
+        H::to_hasher_id()
     }
 }
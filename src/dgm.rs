@@ -1,10 +1,6 @@
 //! Module `dgm` implement data-indexing optimized for
 //! disk-greater-than-memory.
 
-// TODO: Delete/Set/SetCAS does lookup into disk snapshot for the
-// old value, which means returned value can be None, while there
-// is an older value. May be we have to provide a separate API ?
-
 use log::{debug, error, info};
 use toml;
 
@@ -18,19 +14,37 @@ use std::{
     marker, mem,
     ops::{Bound, DerefMut, RangeBounds},
     path, result,
-    sync::{mpsc, Arc, Mutex, MutexGuard},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering::SeqCst},
+        mpsc, Arc, Mutex, MutexGuard,
+    },
     thread, time,
 };
 
 use crate::{
-    core::{self, Cutoff, Validate, Writer},
+    core::{self, CompactRange, Cutoff, IndexFiles, ToJson, ToStats, Validate, WalWriter, Writer},
     core::{CommitIter, CommitIterator, Result, Serialize, WriteIndexFactory},
     core::{Diff, DiskIndexFactory, Entry, Footprint, Index, IndexIter, Reader},
+    dgm_cache::EntryCache,
+    dgm_wal::{DgmWal, DgmWalWriter},
     error::Error,
-    lsm, scans, thread as rt, util,
+    lsm,
+    rdms::ReplayWriter,
+    robt::RateLimiter,
+    scans, thread as rt, util,
+};
+
+pub use crate::dgm_cache::CachePolicy;
+pub use crate::dgm_compact::{
+    Compaction, CompactionStrategy, CustomCompaction, LeveledCompaction, RatioCompaction,
+    TieredCompaction,
 };
 
 const N_COMMITS: usize = 2;
+// number of shards in the working-set cache, each shard is separately
+// latched, refer to EntryCache.
+const CACHE_SHARDS: usize = 16;
 
 /// Configuration type for Dgm indexes.
 #[derive(Clone, Debug, PartialEq)]
@@ -41,6 +55,14 @@ pub struct Config {
     disk_ratio: f64,
     commit_interval: Option<time::Duration>,
     compact_interval: Option<time::Duration>,
+    cache_limit: Option<usize>,
+    cache_policy: CachePolicy,
+    compaction: Compaction,
+    rate_limit: Option<usize>,
+    write_mode: WriteMode,
+    wal_dir: Option<ffi::OsString>,
+    wal_fsync: bool,
+    wal_batch_size: usize,
 }
 
 impl Default for Config {
@@ -52,6 +74,14 @@ impl Default for Config {
             disk_ratio: Self::DISK_RATIO,
             commit_interval: Some(Self::COMMIT_INTERVAL),
             compact_interval: Some(Self::COMPACT_INTERVAL),
+            cache_limit: Default::default(),
+            cache_policy: Default::default(),
+            compaction: Default::default(),
+            rate_limit: Default::default(),
+            write_mode: Default::default(),
+            wal_dir: Default::default(),
+            wal_fsync: Default::default(),
+            wal_batch_size: Self::WAL_BATCH_SIZE,
         }
     }
 }
@@ -84,6 +114,12 @@ impl Config {
     /// for details.
     pub const COMPACT_INTERVAL: time::Duration = time::Duration::from_secs(10);
 
+    /// Default number of write operations batched together before
+    /// flushing them into the write-ahead-log.
+    /// Refer to [set_wal_batch_size][Config::set_wal_batch_size] method
+    /// for details.
+    pub const WAL_BATCH_SIZE: usize = 1;
+
     /// Set entire Dgm index for log-structured-merge. This means
     /// the oldest level (snapshot) will preserve all previous mutations
     /// to an entry, until they are compacted off with cutoff.
@@ -130,6 +166,143 @@ impl Config {
         self.compact_interval = Some(interval);
         Ok(self)
     }
+
+    /// Set maximum footprint for caching entries read from disk levels,
+    /// aka "working-set-dgm" configuration. Cached entries are
+    /// invalidated by writes into m0. Cache is sharded by key's hash and
+    /// each shard is bounded by an equal share of `limit`. By default
+    /// cache is disabled.
+    pub fn set_cache_limit(&mut self, limit: usize) -> Result<&mut Self> {
+        self.cache_limit = Some(limit);
+        Ok(self)
+    }
+
+    /// Set eviction policy for cached entries, applicable only when
+    /// cache is enabled via [set_cache_limit][Config::set_cache_limit].
+    /// Default policy is [CachePolicy::Lru].
+    pub fn set_cache_policy(&mut self, policy: CachePolicy) -> Result<&mut Self> {
+        self.cache_policy = policy;
+        Ok(self)
+    }
+
+    /// Set compaction strategy, deciding when to create a new disk level
+    /// and which disk levels to merge together. Default strategy is
+    /// [Compaction::Ratio], driven by `mem_ratio` and `disk_ratio`.
+    /// Strategy is persisted along with the index, for
+    /// [Compaction::Custom] only its name is persisted.
+    pub fn set_compaction(&mut self, compaction: Compaction) -> Result<&mut Self> {
+        match &compaction {
+            Compaction::Leveled(c) if c.fanout <= 1.0 => {
+                err_at!(InvalidInput, msg: format!("leveled fanout {}", c.fanout))?
+            }
+            Compaction::Tiered(c) if c.bucket_ratio < 1.0 => {
+                err_at!(InvalidInput, msg: format!("tiered ratio {}", c.bucket_ratio))?
+            }
+            Compaction::Tiered(c) if c.min_levels < 2 => {
+                err_at!(InvalidInput, msg: format!("tiered levels {}", c.min_levels))?
+            }
+            Compaction::Custom(c) if c.to_name().is_empty() => {
+                err_at!(InvalidInput, msg: format!("custom compaction without name"))?
+            }
+            _ => (),
+        }
+        self.compaction = compaction;
+        Ok(self)
+    }
+
+    /// Set maximum rate, in bytes per second, for writing disk levels
+    /// during commit and compaction, so that background writes do not
+    /// starve foreground reads. By default writes are not throttled.
+    /// Refer to [Dgm::set_rate_limit] for adjusting the rate at runtime.
+    pub fn set_rate_limit(&mut self, bytes_per_sec: usize) -> Result<&mut Self> {
+        if bytes_per_sec == 0 {
+            err_at!(InvalidInput, msg: format!("rate limit {}", bytes_per_sec))?
+        }
+        self.rate_limit = Some(bytes_per_sec);
+        Ok(self)
+    }
+
+    /// Set the default write mode for writer handles, deciding whether
+    /// old values are looked up in all levels. Default mode is
+    /// [WriteMode::ReadBeforeWrite]. Refer to [DgmWriter::set_write_mode]
+    /// for overriding the mode on a writer handle.
+    pub fn set_write_mode(&mut self, mode: WriteMode) -> Result<&mut Self> {
+        self.write_mode = mode;
+        Ok(self)
+    }
+
+    /// Enable write-ahead-logging, journals shall be kept under `dir`,
+    /// which must not be the same as the index directory. Every write
+    /// operation is appended to the log before applying it on memory
+    /// snapshot, and logged operations are replayed when index is
+    /// re-opened. If `fsync` is true, every append is synced to disk.
+    /// Committing entries into an index with write-ahead-logging, via
+    /// [Index::commit], is not allowed. By default write-ahead-logging
+    /// is disabled.
+    pub fn set_wal(&mut self, dir: &ffi::OsStr, fsync: bool) -> Result<&mut Self> {
+        if dir.to_str().is_none() {
+            err_at!(InvalidInput, msg: format!("wal dir {:?}", dir))?
+        }
+        self.wal_dir = Some(dir.to_os_string());
+        self.wal_fsync = fsync;
+        Ok(self)
+    }
+
+    /// Set the number of write operations to batch together before
+    /// flushing them into the write-ahead-log. Larger batches improve
+    /// write throughput, while operations in a batch yet to be flushed
+    /// are lost on a crash. _Default: WAL_BATCH_SIZE_.
+    ///
+    /// Writes across all writer handles are serialized on the log. With
+    /// the default batch size every write is flushed on its own, hence
+    /// write throughput is bounded by the flush latency, more so when
+    /// `fsync` is enabled.
+    pub fn set_wal_batch_size(&mut self, batch_size: usize) -> Result<&mut Self> {
+        if batch_size == 0 {
+            err_at!(InvalidInput, msg: format!("wal batch size {}", batch_size))?
+        }
+        self.wal_batch_size = batch_size;
+        Ok(self)
+    }
+}
+
+/// Write mode for [DgmWriter] handles, deciding the old value returned
+/// by set, set_cas and delete operations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WriteMode {
+    /// Skip looking up the old value, set and delete operations shall
+    /// always return None. Suitable for maximum ingest throughput.
+    Blind,
+    /// Lookup the old value in m0, m1 and all disk levels, disk levels
+    /// not containing the key are skipped using their bloom filter.
+    ReadBeforeWrite,
+}
+
+impl Default for WriteMode {
+    fn default() -> WriteMode {
+        WriteMode::ReadBeforeWrite
+    }
+}
+
+impl fmt::Display for WriteMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            WriteMode::Blind => write!(f, "blind"),
+            WriteMode::ReadBeforeWrite => write!(f, "read-before-write"),
+        }
+    }
+}
+
+impl FromStr for WriteMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<WriteMode> {
+        match s {
+            "blind" => Ok(WriteMode::Blind),
+            "read-before-write" => Ok(WriteMode::ReadBeforeWrite),
+            _ => err_at!(InvalidInput, msg: format!("write mode {}", s)),
+        }
+    }
 }
 
 impl From<Root> for Config {
@@ -141,6 +314,14 @@ impl From<Root> for Config {
             disk_ratio: root.disk_ratio,
             commit_interval: root.commit_interval,
             compact_interval: root.compact_interval,
+            cache_limit: root.cache_limit,
+            cache_policy: root.cache_policy,
+            compaction: root.compaction,
+            rate_limit: root.rate_limit,
+            write_mode: root.write_mode,
+            wal_dir: root.wal_dir,
+            wal_fsync: root.wal_fsync,
+            wal_batch_size: root.wal_batch_size,
         }
     }
 }
@@ -158,6 +339,14 @@ struct Root {
     disk_ratio: f64,
     commit_interval: Option<time::Duration>,  // in seconds.
     compact_interval: Option<time::Duration>, // in seconds.
+    cache_limit: Option<usize>,
+    cache_policy: CachePolicy,
+    compaction: Compaction,
+    rate_limit: Option<usize>,
+    write_mode: WriteMode,
+    wal_dir: Option<ffi::OsString>,
+    wal_fsync: bool,
+    wal_batch_size: usize,
 }
 
 impl From<Config> for Root {
@@ -174,6 +363,14 @@ impl From<Config> for Root {
             disk_ratio: config.disk_ratio,
             commit_interval: config.commit_interval,
             compact_interval: config.compact_interval,
+            cache_limit: config.cache_limit,
+            cache_policy: config.cache_policy,
+            compaction: config.compaction,
+            rate_limit: config.rate_limit,
+            write_mode: config.write_mode,
+            wal_dir: config.wal_dir,
+            wal_fsync: config.wal_fsync,
+            wal_batch_size: config.wal_batch_size,
         }
     }
 }
@@ -203,6 +400,14 @@ impl TryFrom<Root> for Vec<u8> {
                 Some(interval) => convert_at!(interval.as_secs())?,
                 None => -1,
             };
+            let cache_limit: i64 = match root.cache_limit {
+                Some(cache_limit) => convert_at!(cache_limit)?,
+                None => -1,
+            };
+            let rate_limit: i64 = match root.rate_limit {
+                Some(rate_limit) => convert_at!(rate_limit)?,
+                None => -1,
+            };
 
             dict.insert("version".to_string(), Integer(version));
             dict.insert("levels".to_string(), Integer(levels));
@@ -213,6 +418,33 @@ impl TryFrom<Root> for Vec<u8> {
             dict.insert("disk_ratio".to_string(), Float(disk_ratio));
             dict.insert("commit_interval".to_string(), Integer(m_interval));
             dict.insert("compact_interval".to_string(), Integer(c_interval));
+            dict.insert("cache_limit".to_string(), Integer(cache_limit));
+            let policy = root.cache_policy.to_string();
+            dict.insert("cache_policy".to_string(), S(policy));
+            let mut args = vec![S(root.compaction.to_string())];
+            match &root.compaction {
+                Compaction::Ratio => (),
+                Compaction::Leveled(c) => args.push(S(c.fanout.to_string())),
+                Compaction::Tiered(c) => {
+                    args.push(S(c.bucket_ratio.to_string()));
+                    args.push(S(c.min_levels.to_string()));
+                }
+                Compaction::Custom(c) => args.push(S(c.to_name())),
+            }
+            dict.insert("compaction".to_string(), Array(args));
+            dict.insert("rate_limit".to_string(), Integer(rate_limit));
+            let mode = root.write_mode.to_string();
+            dict.insert("write_mode".to_string(), S(mode));
+            if let Some(wal_dir) = &root.wal_dir {
+                let wal_dir = match wal_dir.to_str() {
+                    Some(wal_dir) => Ok(wal_dir.to_string()),
+                    None => err_at!(InvalidInput, msg: format!("wal dir {:?}", wal_dir)),
+                }?;
+                dict.insert("wal_dir".to_string(), S(wal_dir));
+            }
+            dict.insert("wal_fsync".to_string(), Boolean(root.wal_fsync));
+            let wal_batch_size: i64 = convert_at!(root.wal_batch_size)?;
+            dict.insert("wal_batch_size".to_string(), Integer(wal_batch_size));
 
             let (arg1, arg2) = match root.lsm_cutoff {
                 Some(cutoff) => match cutoff {
@@ -319,6 +551,75 @@ impl TryFrom<Vec<u8>> for Root {
             duration if duration < 0 => None,
             duration => Some(time::Duration::from_secs(convert_at!(duration)?)),
         };
+        // cache settings are optional, older root files don't have them.
+        root.cache_limit = match dict.get("cache_limit") {
+            Some(_) => match to_i64("cache_limit", dict)? {
+                cache_limit if cache_limit < 0 => None,
+                cache_limit => Some(convert_at!(cache_limit)?),
+            },
+            None => None,
+        };
+        root.cache_policy = match dict.get("cache_policy") {
+            Some(field) => match field.as_str() {
+                Some(policy) => policy.parse()?,
+                None => err_at!(InvalidFile, msg: format!("cache_policy:{}", field))?,
+            },
+            None => Default::default(),
+        };
+        // compaction is optional, older root files don't have it.
+        root.compaction = match dict.get("compaction") {
+            Some(field) => {
+                let args: Option<Vec<&str>> = match field.as_array() {
+                    Some(array) => array.iter().map(|arg| arg.as_str()).collect(),
+                    None => None,
+                };
+                match args.as_ref().map(|args| args.as_slice()) {
+                    Some(["ratio"]) => Compaction::Ratio,
+                    Some(["leveled", fanout]) => Compaction::Leveled(LeveledCompaction {
+                        fanout: parse_at!(fanout, f64)?,
+                    }),
+                    Some(["tiered", ratio, n]) => Compaction::Tiered(TieredCompaction {
+                        bucket_ratio: parse_at!(ratio, f64)?,
+                        min_levels: parse_at!(n, usize)?,
+                    }),
+                    Some(["custom", name]) => Compaction::Custom(CustomCompaction::from_name(name)),
+                    _ => err_at!(InvalidFile, msg: format!("compaction:{}", field))?,
+                }
+            }
+            None => Default::default(),
+        };
+        root.rate_limit = match dict.get("rate_limit") {
+            // zero rate is not throttled, refer to RateLimiter::new.
+            Some(_) => match to_i64("rate_limit", dict)? {
+                rate_limit if rate_limit < 0 => None,
+                rate_limit => Some(convert_at!(rate_limit)?),
+            },
+            None => None,
+        };
+        // write_mode is optional, older root files don't have it.
+        root.write_mode = match dict.get("write_mode") {
+            Some(field) => match field.as_str() {
+                Some(mode) => mode.parse()?,
+                None => err_at!(InvalidFile, msg: format!("write_mode:{}", field))?,
+            },
+            None => Default::default(),
+        };
+        // wal settings are optional, older root files don't have them.
+        root.wal_dir = match dict.get("wal_dir") {
+            Some(field) => match field.as_str() {
+                Some(wal_dir) => Some(wal_dir.into()),
+                None => err_at!(InvalidFile, msg: format!("wal_dir:{}", field))?,
+            },
+            None => None,
+        };
+        root.wal_fsync = match dict.get("wal_fsync") {
+            Some(_) => to_bool("wal_fsync", dict)?,
+            None => false,
+        };
+        root.wal_batch_size = match dict.get("wal_batch_size") {
+            Some(_) => to_usize("wal_batch_size", dict)?,
+            None => Config::WAL_BATCH_SIZE,
+        };
         root.lsm_cutoff = match dict.get("lsm_cutoff") {
             Some(field) => match field.as_array() {
                 Some(array) => match array.as_slice() {
@@ -365,12 +666,44 @@ impl TryFrom<Vec<u8>> for Root {
 }
 
 impl Root {
+    fn to_strategy(&self) -> Result<Arc<dyn CompactionStrategy>> {
+        match &self.compaction {
+            Compaction::Ratio => Ok(Arc::new(RatioCompaction {
+                mem_ratio: self.mem_ratio,
+                disk_ratio: self.disk_ratio,
+            })),
+            Compaction::Leveled(c) => Ok(Arc::new(*c)),
+            Compaction::Tiered(c) => Ok(Arc::new(*c)),
+            Compaction::Custom(c) => match c.to_strategy() {
+                Some(strategy) => Ok(strategy),
+                None => {
+                    let msg = format!("compaction {:?} not supplied", c.to_name());
+                    err_at!(InvalidInput, msg: msg)
+                }
+            },
+        }
+    }
+
     fn to_next(&self) -> Root {
         let mut new_root = self.clone();
         new_root.version += 1;
         new_root
     }
 
+    fn to_cache<K, V>(&self) -> Option<EntryCache<K, V>>
+    where
+        K: Clone + Ord + Footprint,
+        V: Clone + Diff + Footprint,
+    {
+        let policy = self.cache_policy;
+        self.cache_limit
+            .map(|limit| EntryCache::new(limit, policy, CACHE_SHARDS))
+    }
+
+    fn to_limiter(&self) -> RateLimiter {
+        RateLimiter::new(self.rate_limit)
+    }
+
     fn to_cutoff(&self, n_high_compacts: usize) -> Cutoff {
         match n_high_compacts % 2 {
             0 if self.tombstone_cutoff.is_some() => {
@@ -578,9 +911,15 @@ where
     n_high_compacts: usize,
     n_ccommits: usize,
     n_compacts: usize,
+    counters: Counters,
     m0: Snapshot<K, V, M::I>,         // write index
     m1: Option<Snapshot<K, V, M::I>>, // flush index
     disks: Vec<Snapshot<K, V, D::I>>, // NLEVELS
+    cache: Option<EntryCache<K, V>>,  // working-set cache
+    limiter: RateLimiter,             // shared with disk levels
+    bloom: Arc<BloomCounters>,        // shared with readers and writers
+    wal: Option<DgmWal<K, V>>,        // write-ahead-log
+    pinned: bool,                     // disk levels pinned by checkpoint
 
     writers: Vec<
         Arc<
@@ -672,6 +1011,27 @@ where
         Ok(())
     }
 
+    // cut memory snapshot upto its latest seqno, blocking all the writer
+    // threads while collecting its entries, so that entries updated after
+    // the cut are not lost in non-mvcc snapshots.
+    fn cut_m0(&mut self) -> Result<(u64, Vec<u8>, Vec<Result<Entry<K, V>>>)> {
+        // block all the writer threads.
+        let mut w_handles = vec![];
+        for writer in self.writers.iter() {
+            w_handles.push(writer.lock().unwrap())
+        }
+
+        let m0 = self.m0.as_mut_m0()?;
+        let seqno = m0.to_seqno()?;
+        let metadata = m0.to_metadata()?;
+        let entries = {
+            let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+            m0.to_reader()?.scan(within)?.collect()
+        };
+
+        Ok((seqno, metadata, entries))
+    }
+
     fn shift_into_m0(&mut self) -> Result<()> {
         // block all the readers.
         let mut r_handles = vec![];
@@ -764,19 +1124,26 @@ where
             err_at!(Fatal, msg: format!("exhausted all levels !!"))?
         }
 
-        let mf = self.m0.footprint()? as f64;
+        let strategy = self.root.to_strategy()?;
+        let mf = self.m0.footprint()?;
         let mut iter = self.disks.iter_mut().enumerate();
         loop {
             match iter.next() {
                 None => break Ok(self.disks.len() - 1), // first commit
                 Some((_, Snapshot::None)) => (),        // continue loop
                 Some((lvl, disk)) => {
-                    let df = disk.footprint()? as f64;
+                    let df = disk.footprint()?;
                     // println!("mf:{}, df:{}", mf, df);
                     match disk {
                         Compact(_) => break Ok(lvl - 1),
                         Active(_) => {
-                            if (mf / df) < self.root.mem_ratio {
+                            let new_level = match &self.root.compaction {
+                                Compaction::Ratio => strategy.is_new_level(mf, df),
+                                // when levels are running out, merge into
+                                // the latest level irrespective of strategy.
+                                _ => lvl > 1 && strategy.is_new_level(mf, df),
+                            };
+                            if new_level {
                                 break Ok(lvl - 1);
                             } else {
                                 break Ok(lvl);
@@ -840,30 +1207,28 @@ where
 
     fn find_compact_levels(
         disks: &[Snapshot<K, V, D::I>],
-        disk_ratio: f64,
+        strategy: &dyn CompactionStrategy,
     ) -> Result<Option<(Vec<usize>, usize)>> {
-        let mut levels = Self::active_compact_levels(disks)?;
-
-        match levels.len() {
-            0 | 1 => Ok(None),
-            _n => loop {
-                let target_level = levels.remove(levels.len() - 1);
-                let ratio = {
-                    let t_footprint = disks[target_level].footprint()?;
-                    let mut footprint = 0;
-                    for level in levels.clone().into_iter() {
-                        footprint += disks[level].footprint()?;
-                    }
-                    // println!("compact_fp {} {}", footprint, t_footprint);
-                    (footprint as f64) / (t_footprint as f64)
-                };
+        let levels = Self::active_compact_levels(disks)?;
+        let mut footprints = vec![];
+        for level in levels.iter() {
+            footprints.push((*level, disks[*level].footprint()?));
+        }
 
-                if ratio > disk_ratio {
-                    break Ok(Some((levels, target_level)));
-                } else if levels.len() == 1 {
-                    break Ok(None);
+        match strategy.to_compact_levels(&footprints) {
+            None => Ok(None),
+            Some((ss, d)) => {
+                // sources and target shall be consecutive active levels.
+                let ok = match levels.iter().position(|l| *l == d) {
+                    Some(off) if off >= ss.len() => levels[(off - ss.len())..off] == ss[..],
+                    _ => false,
+                };
+                if !ok {
+                    let msg = format!("invalid compaction {:?}->{}", ss, d);
+                    err_at!(Fatal, msg: msg)?
                 }
-            },
+                Ok(Some((ss, d)))
+            }
         }
     }
 
@@ -871,8 +1236,8 @@ where
         &mut self, // return (levels, sources, target)
     ) -> Result<Option<(Vec<usize>, Vec<usize>, usize)>> {
         let levels = {
-            let disk_ratio = self.root.disk_ratio;
-            Self::find_compact_levels(&self.disks, disk_ratio)?
+            let strategy = self.root.to_strategy()?;
+            Self::find_compact_levels(&self.disks, strategy.as_ref())?
         };
 
         match levels {
@@ -978,6 +1343,122 @@ where
 
         Ok((src_disks, d))
     }
+
+    fn to_dgm_stats<A, B>(
+        &self,
+        m0: A,
+        m1: Option<A>,
+        disks: Vec<(usize, B)>, // (level, stats)
+    ) -> Result<Stats<A, B>>
+    where
+        A: fmt::Display,
+        B: fmt::Display,
+    {
+        let mut levels = vec![];
+        for (level, stats) in disks.into_iter() {
+            let disk = &self.disks[level];
+            let to_seqno = match disk.as_disk()? {
+                Some(d) => d.to_seqno()?,
+                None => err_at!(Fatal, msg: format!("level {} is empty", level))?,
+            };
+            levels.push(LevelStats {
+                level,
+                footprint: disk.footprint()?,
+                from_seqno: std::u64::MIN,
+                to_seqno,
+                stats,
+            });
+        }
+        // levels are ordered from latest to oldest, entries in a level
+        // are newer than the latest entry in its next older level.
+        for i in 1..levels.len() {
+            levels[i - 1].from_seqno = levels[i].to_seqno;
+        }
+
+        let mem_footprint = self.m0.footprint()?
+            + match &self.m1 {
+                Some(m1) => m1.footprint()?,
+                None => 0,
+            };
+        let disk_footprint = levels.iter().map(|l| l.footprint).sum();
+        let (cache_footprint, n_cache_entries, counts) = match &self.cache {
+            Some(cache) => (cache.footprint()?, cache.len()?, cache.to_counts()?),
+            None => (0, 0, (0, 0, 0)),
+        };
+        let (n_cache_hits, n_cache_misses, n_cache_evictions) = counts;
+        let throttle_time: u64 = convert_at!(self.limiter.to_throttled()?.as_nanos())?;
+        let n_bloom_skips = self.bloom.n_skips.load(SeqCst);
+        let n_bloom_false_positives = self.bloom.n_false_positives.load(SeqCst);
+
+        Ok(Stats {
+            name: self.name.clone(),
+            m0,
+            m1,
+            disks: levels,
+            mem_footprint,
+            disk_footprint,
+            n_commits: self.counters.n_commits,
+            n_compacts: self.counters.n_compacts,
+            n_high_compacts: self.n_high_compacts,
+            commit_time: self.counters.commit_time,
+            compact_time: self.counters.compact_time,
+            n_committed_bytes: self.counters.n_committed_bytes,
+            n_written_bytes: self.counters.n_written_bytes,
+            cache_footprint,
+            n_cache_entries,
+            n_cache_hits,
+            n_cache_misses,
+            n_cache_evictions,
+            throttle_time,
+            n_bloom_skips,
+            n_bloom_false_positives,
+        })
+    }
+}
+
+// running counters for Dgm index, refer to [Stats] for details.
+#[derive(Clone, Default)]
+struct Counters {
+    n_commits: usize,
+    n_compacts: usize,
+    commit_time: u64,
+    compact_time: u64,
+    n_committed_bytes: usize,
+    n_written_bytes: usize,
+}
+
+impl Counters {
+    fn add_commit(&mut self, start: time::SystemTime, m_fp: isize, d_fp: isize) -> Result<()> {
+        let elapsed = err_at!(TimeFail, start.elapsed())?;
+        let m_fp: usize = convert_at!(m_fp)?;
+        let d_fp: usize = convert_at!(d_fp)?;
+        let elapsed: u64 = convert_at!(elapsed.as_nanos())?;
+
+        self.n_commits += 1;
+        self.commit_time += elapsed;
+        self.n_committed_bytes += m_fp;
+        self.n_written_bytes += d_fp;
+        Ok(())
+    }
+
+    fn add_compact(&mut self, start: time::SystemTime, d_fp: isize) -> Result<()> {
+        let elapsed = err_at!(TimeFail, start.elapsed())?;
+        let d_fp: usize = convert_at!(d_fp)?;
+        let elapsed: u64 = convert_at!(elapsed.as_nanos())?;
+
+        self.n_compacts += 1;
+        self.compact_time += elapsed;
+        self.n_written_bytes += d_fp;
+        Ok(())
+    }
+}
+
+// bloom filter counters for disk levels, shared by all readers and
+// writers, refer to [Stats] for details.
+#[derive(Default)]
+struct BloomCounters {
+    n_skips: AtomicUsize,
+    n_false_positives: AtomicUsize,
 }
 
 enum Snapshot<K, V, I>
@@ -1137,6 +1618,13 @@ where
         }
     }
 
+    fn as_m1(&self) -> Result<&I> {
+        match self {
+            Snapshot::Flush(m) => Ok(m),
+            _ => err_at!(Fatal, msg: format!("m1 not flush snapshot")),
+        }
+    }
+
     fn as_mut_m1(&mut self) -> Result<&mut I> {
         match self {
             Snapshot::Flush(m) => Ok(m),
@@ -1210,6 +1698,8 @@ where
 {
     /// Create a new Dgm instance on disk. Supplied directory `dir` will be
     /// removed, if it already exist, and new directory shall be created.
+    /// If write-ahead-log is configured, existing journals in the log
+    /// directory shall be purged.
     pub fn new(
         dir: &ffi::OsStr, // directory path
         name: &str,
@@ -1218,13 +1708,36 @@ where
         config: Config,
     ) -> Result<Box<Dgm<K, V, M, D>>>
     where
-        K: 'static + Send,
-        V: 'static + Send,
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
         M: 'static + Send,
         D: 'static + Send,
         <M as WriteIndexFactory<K, V>>::I: 'static + Send,
         <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
-        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send + WalWriter<K, V>,
+        <D as DiskIndexFactory<K, V>>::I: 'static + Send,
+        <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+        <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
+    {
+        Self::do_new(dir, name, mem_factory, disk_factory, config, false)
+    }
+
+    fn do_new(
+        dir: &ffi::OsStr, // directory path
+        name: &str,
+        mem_factory: M,
+        mut disk_factory: D,
+        config: Config,
+        replay: bool,
+    ) -> Result<Box<Dgm<K, V, M, D>>>
+    where
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
+        M: 'static + Send,
+        D: 'static + Send,
+        <M as WriteIndexFactory<K, V>>::I: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send + WalWriter<K, V>,
         <D as DiskIndexFactory<K, V>>::I: 'static + Send,
         <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
         <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
@@ -1242,6 +1755,9 @@ where
         };
 
         let m0 = Snapshot::new_write(mem_factory.new(name)?);
+        let cache = root.to_cache();
+        let limiter = root.to_limiter();
+        disk_factory.set_rate_limiter(limiter.clone());
         let inner = InnerDgm {
             dir: dir.to_os_string(),
             name: name.to_string(),
@@ -1253,9 +1769,15 @@ where
             n_high_compacts: Default::default(),
             n_ccommits: Default::default(),
             n_compacts: Default::default(),
+            counters: Default::default(),
             m0,
             m1: None,
             disks,
+            cache,
+            limiter,
+            bloom: Default::default(),
+            wal: None,
+            pinned: false,
 
             writers: Default::default(),
             readers: Default::default(),
@@ -1268,12 +1790,18 @@ where
             inner: Arc::new(Mutex::new(inner)),
         });
 
+        index.start_wal(replay)?;
         index.start_auto_commit()?;
         index.start_auto_compact()?;
 
         Ok(index)
     }
 
+    /// Open an existing Dgm instance from disk. If write-ahead-log is
+    /// configured, operations logged after the latest disk level shall be
+    /// replayed on the memory snapshot. Index configured with
+    /// [Compaction::Custom] shall be opened via
+    /// [open_with_compaction][Dgm::open_with_compaction].
     pub fn open(
         dir: &ffi::OsStr, // directory path
         name: &str,
@@ -1281,18 +1809,76 @@ where
         disk_factory: D,
     ) -> Result<Box<Dgm<K, V, M, D>>>
     where
-        K: 'static + Send,
-        V: 'static + Send,
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
         M: 'static + Send,
         D: 'static + Send,
         <M as WriteIndexFactory<K, V>>::I: 'static + Send,
         <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
-        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send + WalWriter<K, V>,
+        <D as DiskIndexFactory<K, V>>::I: 'static + Send,
+        <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+        <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
+    {
+        Self::do_open(dir, name, mem_factory, disk_factory, None)
+    }
+
+    /// Same as [open][Dgm::open], but supply the compaction strategy. If
+    /// `compaction` does not match the persisted strategy, by name for
+    /// [Compaction::Custom], open shall fail.
+    pub fn open_with_compaction(
+        dir: &ffi::OsStr, // directory path
+        name: &str,
+        mem_factory: M,
+        disk_factory: D,
+        compaction: Compaction,
+    ) -> Result<Box<Dgm<K, V, M, D>>>
+    where
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
+        M: 'static + Send,
+        D: 'static + Send,
+        <M as WriteIndexFactory<K, V>>::I: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send + WalWriter<K, V>,
+        <D as DiskIndexFactory<K, V>>::I: 'static + Send,
+        <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+        <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
+    {
+        Self::do_open(dir, name, mem_factory, disk_factory, Some(compaction))
+    }
+
+    fn do_open(
+        dir: &ffi::OsStr, // directory path
+        name: &str,
+        mem_factory: M,
+        mut disk_factory: D,
+        compaction: Option<Compaction>,
+    ) -> Result<Box<Dgm<K, V, M, D>>>
+    where
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
+        M: 'static + Send,
+        D: 'static + Send,
+        <M as WriteIndexFactory<K, V>>::I: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send + WalWriter<K, V>,
         <D as DiskIndexFactory<K, V>>::I: 'static + Send,
         <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
         <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
     {
-        let (root, root_file) = Self::find_root_file(dir, name)?;
+        let (mut root, root_file) = Self::find_root_file(dir, name)?;
+        if let Some(compaction) = compaction {
+            if compaction != root.compaction {
+                let msg = format!("compaction {:?} != {:?}", compaction, root.compaction);
+                err_at!(InvalidInput, msg: msg)?
+            }
+            root.compaction = compaction;
+        }
+        // fail early, if strategy is not supplied for custom compaction.
+        root.to_strategy()?;
+        let limiter = root.to_limiter();
+        disk_factory.set_rate_limiter(limiter.clone());
 
         let mut disks: Vec<Snapshot<K, V, D::I>> = vec![];
         (0..Config::NLEVELS).for_each(|_| disks.push(Default::default()));
@@ -1313,9 +1899,10 @@ where
 
         if disks.iter().any(|s| s.is_active()) == false {
             // no active disk snapshots found, create a new instance.
-            Self::new(dir, name, mem_factory, disk_factory, config)
+            Self::do_new(dir, name, mem_factory, disk_factory, config, true)
         } else {
             let m0 = Snapshot::new_write(mem_factory.new(name)?);
+            let cache = root.to_cache();
             let inner = InnerDgm {
                 dir: dir.to_os_string(),
                 name: name.to_string(),
@@ -1327,9 +1914,15 @@ where
                 n_high_compacts: Default::default(),
                 n_ccommits: Default::default(),
                 n_compacts: Default::default(),
+                counters: Default::default(),
                 m0,
                 m1: None,
                 disks,
+                cache,
+                limiter,
+                bloom: Default::default(),
+                wal: None,
+                pinned: false,
 
                 writers: Default::default(),
                 readers: Default::default(),
@@ -1353,6 +1946,7 @@ where
                     .commit(core::CommitIter::new_empty(), |_| metadata.clone())?;
             }
 
+            index.start_wal(true /*replay*/)?;
             index.start_auto_commit()?;
             index.start_auto_compact()?;
 
@@ -1360,6 +1954,80 @@ where
         }
     }
 
+    /// Adjust the maximum rate, in bytes per second, for writing disk
+    /// levels. Applies to on-going commit and compaction as well. If
+    /// `rate` is None, writes are not throttled. New rate is persisted
+    /// along with the next commit or compaction.
+    pub fn set_rate_limit(&self, rate: Option<usize>) -> Result<()> {
+        let mut inner = self.as_inner()?;
+        inner.limiter.set_rate(rate)?;
+        inner.root.rate_limit = inner.limiter.to_rate()?;
+        Ok(())
+    }
+
+    /// Return statistics for this index, along with statistics for memory
+    /// snapshots and live disk levels. Unlike [validate][Validate::validate]
+    /// this is cheap, and can be called on a live index.
+    pub fn to_stats<A, B>(&self) -> Result<Stats<A, B>>
+    where
+        A: fmt::Display,
+        B: fmt::Display,
+        M::I: ToStats<A>,
+        D::I: ToStats<B>,
+    {
+        let inner = self.as_inner()?;
+
+        let m0 = inner.m0.as_m0()?.to_stats()?;
+        let m1 = match &inner.m1 {
+            Some(m1) => Some(m1.as_m1()?.to_stats()?),
+            None => None,
+        };
+        let mut disks = vec![];
+        for (level, disk) in inner.disks.iter().enumerate() {
+            match disk.as_disk()? {
+                Some(d) => match d.to_seqno() {
+                    // level is being built by an on-going commit.
+                    Err(Error::UnInitialized(_)) => continue,
+                    _ => disks.push((level, d.to_stats()?)),
+                },
+                None => (),
+            }
+        }
+
+        inner.to_dgm_stats(m0, m1, disks)
+    }
+
+    fn start_wal(&mut self, replay: bool) -> Result<()>
+    where
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
+        <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: WalWriter<K, V>,
+    {
+        let mut inner = self.as_inner()?;
+        let wal_dir = match inner.root.wal_dir.clone() {
+            Some(wal_dir) => wal_dir,
+            None => return Ok(()),
+        };
+        let (name, fsync) = (inner.name.clone(), inner.root.wal_fsync);
+        let batch_size = inner.root.wal_batch_size;
+
+        inner.wal = if replay {
+            let seqno = inner.to_disk_seqno()?;
+            let mut db = ReplayWriter(inner.m0.as_mut_m0()?.to_writer()?);
+            let (wal, n_ops) = DgmWal::load(&wal_dir, &name, fsync, batch_size, &mut db, seqno)?;
+            info!(
+                target: "dgm   ",
+                "{:?}/{} replayed {} ops from wal after seqno {}",
+                inner.dir, name, n_ops, seqno
+            );
+            Some(wal)
+        } else {
+            Some(DgmWal::create(&wal_dir, &name, fsync, batch_size)?)
+        };
+
+        Ok(())
+    }
+
     fn start_auto_commit(&mut self) -> Result<()>
     where
         K: 'static + Send,
@@ -1445,6 +2113,24 @@ where
         }
     }
 
+    // wait for on-going commit, compaction and checkpoint to complete.
+    fn to_idle_inner(&self) -> Result<MutexGuard<InnerDgm<K, V, M, D>>> {
+        loop {
+            let inn = self.as_inner()?;
+            let busy = inn.pinned
+                || inn.m1.is_some()
+                || inn.disks.iter().any(|d| match d {
+                    Snapshot::Commit(_) | Snapshot::Compact(_) => true,
+                    _ => false,
+                });
+            if !busy {
+                break Ok(inn);
+            }
+            mem::drop(inn);
+            thread::sleep(time::Duration::from_millis(10));
+        }
+    }
+
     fn disk_footprint(&self) -> Result<isize> {
         let inner = self.as_inner()?;
 
@@ -1465,9 +2151,228 @@ where
             })
     }
 
+    /// Checkpoint this index into directory `dir`, which shall either
+    /// be missing or empty, and shall not overlap with this index's
+    /// directory. Disk levels are pinned, by holding
+    /// off commit and compaction, and their files are hard-linked, or
+    /// copied if linking fails, into `dir`. Entries from the memory
+    /// snapshot, upto the returned seqno, are flushed into a new level.
+    /// Writes can proceed while the checkpoint is in progress. Opening
+    /// `dir` using [Dgm::open] shall yield an index identical to this
+    /// index, as of the returned seqno.
+    ///
+    /// Value-log files are append-only, and shared with the checkpoint
+    /// when linked. Entries appended by either index are never
+    /// referenced by the other.
+    pub fn checkpoint(&self, dir: &ffi::OsStr) -> Result<u64>
+    where
+        D::I: IndexFiles,
+    {
+        let start = time::SystemTime::now();
+
+        // pin disk levels and cut the memory snapshot, under the lock.
+        let (name, root, seqno, levels, m0) = {
+            let mut inn = self.to_idle_inner()?;
+
+            Self::validate_checkpoint_dir(dir, &inn.dir)?;
+            err_at!(IoError, fs::create_dir_all(dir))?;
+
+            let disk_seqno = inn.to_disk_seqno()?;
+            let (seqno, metadata, entries) = inn.cut_m0()?;
+
+            let mut levels = vec![];
+            for level in 0..inn.disks.len() {
+                if let Some(d) = inn.disks[level].as_disk()? {
+                    levels.push((level, d.to_files()?));
+                }
+            }
+            inn.pinned = true;
+
+            // checkpoint holds all entries upto seqno, it shall not share
+            // the write-ahead-log with this index.
+            let mut root = inn.root.clone();
+            root.wal_dir = None;
+
+            let m0 = if seqno > disk_seqno {
+                Some((metadata, entries))
+            } else {
+                None
+            };
+            (inn.name.clone(), root, seqno, levels, m0)
+        };
+
+        // memory snapshot is flushed into a level newer than the latest
+        // level. If levels are exhausted, it is merged into the latest
+        // level, whose files shall be copied instead of linked.
+        let (m0_level, merge) = match levels.first() {
+            None => (Config::NLEVELS - 1, false),
+            Some((0, _)) => (0, m0.is_some()),
+            Some((level, _)) => (level - 1, false),
+        };
+
+        let res = {
+            let mut res = Ok(());
+            for (level, files) in levels.iter() {
+                let copy = merge && *level == m0_level;
+                res = Self::link_files(dir, files, copy);
+                if res.is_err() {
+                    break;
+                }
+            }
+            res
+        };
+        self.as_inner()?.pinned = false;
+        res?;
+
+        if let Some((metadata, entries)) = m0 {
+            let mut cp = {
+                let inn = self.as_inner()?;
+                let level_name: LevelName = (name.clone(), m0_level).into();
+                let level_name = level_name.to_string();
+                if merge {
+                    inn.disk_factory.open(dir, &level_name)?
+                } else {
+                    inn.disk_factory.new(dir, &level_name)?
+                }
+            };
+            let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+            let iter = core::CommitIter::new(entries.into_iter(), within);
+            cp.commit(iter, |_| metadata.clone())?;
+            cp.close()?;
+        }
+
+        Self::new_root_file(dir, &name, root)?;
+
+        let elapsed = err_at!(TimeFail, start.elapsed())?;
+        info!(
+            target: "dgm   ",
+            "{}, checkpoint into {:?} upto seqno:{} in {:?}",
+            name, dir, seqno, elapsed
+        );
+
+        Ok(seqno)
+    }
+
+    // checkpoint directory `dir` shall not be same as, or an ancestor
+    // of, or a descendant of `index_dir`, and shall be empty if exists.
+    fn validate_checkpoint_dir(dir: &ffi::OsStr, index_dir: &ffi::OsStr) -> Result<()> {
+        let cp_dir = util::to_canonical_path(dir)?;
+        let index_dir = util::to_canonical_path(index_dir)?;
+        if cp_dir.starts_with(&index_dir) || index_dir.starts_with(&cp_dir) {
+            let msg = format!("checkpoint {:?} overlaps {:?}", cp_dir, index_dir);
+            err_at!(InvalidInput, msg: msg)?
+        }
+
+        if path::Path::new(dir).exists() {
+            let mut entries = err_at!(IoError, fs::read_dir(dir))?;
+            if entries.next().is_some() {
+                err_at!(InvalidInput, msg: format!("checkpoint {:?} not empty", dir))?
+            }
+        }
+        Ok(())
+    }
+
+    // hard-link `files` into `dir`, copy them if `copy` is true or if
+    // linking fails, say across file systems.
+    fn link_files(dir: &ffi::OsStr, files: &[ffi::OsString], copy: bool) -> Result<()> {
+        for file in files.iter() {
+            let dst = match path::Path::new(file).file_name() {
+                Some(file_name) => {
+                    let mut dst = path::PathBuf::from(dir);
+                    dst.push(file_name);
+                    dst
+                }
+                None => err_at!(InvalidFile, msg: format!("{:?}", file))?,
+            };
+            if copy || fs::hard_link(file, &dst).is_err() {
+                err_at!(IoError, fs::copy(file, &dst))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compact entries whose key fall within `range`, applying `cutoff`
+    /// only to those entries. Disk levels that have nothing to purge,
+    /// within the range, are not rewritten. An entry is purged only
+    /// if its versions in older levels can also be purged, so that
+    /// deleted entries are not resurrected.
+    ///
+    /// Return (level, n_purged) for each disk level, from latest to
+    /// oldest. On-going commit and compaction shall be completed before
+    /// and blocked during this call.
+    pub fn compact_range<R>(&self, range: R, cutoff: Cutoff) -> Result<Vec<(usize, usize)>>
+    where
+        K: Hash,
+        R: RangeBounds<K>,
+        D::I: CompactRange<K, V>,
+    {
+        let start = time::SystemTime::now();
+
+        match cutoff {
+            Cutoff::Mono => err_at!(InvalidInput, msg: format!("can't have mono-cutoff")),
+            _ => Ok(()),
+        }?;
+        let range = util::to_owned_range(&range);
+
+        let mut inn = self.to_idle_inner()?;
+
+        let levels: Vec<usize> = {
+            let iter = inn.disks.iter().enumerate();
+            iter.filter_map(|(l, d)| if d.is_active() { Some(l) } else { None })
+                .collect()
+        };
+
+        // purge from oldest to latest level.
+        let mut res = vec![];
+        for (i, level) in levels.iter().enumerate().rev() {
+            let mut rs = vec![];
+            for older in levels[(i + 1)..].iter() {
+                match inn.disks[*older].as_mut_disk()? {
+                    Some(d) => rs.push(d.to_reader()?),
+                    None => err_at!(Fatal, msg: format!("level {} is not a disk", older))?,
+                }
+            }
+            let filter = |entry: &Entry<K, V>| -> Result<bool> {
+                for r in rs.iter_mut() {
+                    match r.get(entry.as_key()) {
+                        Ok(older) if older.purge(cutoff).is_some() => return Ok(false),
+                        Ok(_) | Err(Error::KeyNotFound) => (),
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(true)
+            };
+            let n_purged = match inn.disks[*level].as_mut_disk()? {
+                Some(d) => d.compact_range(range.clone(), cutoff, filter)?,
+                None => err_at!(Fatal, msg: format!("level {} is not a disk", level))?,
+            };
+            res.push((*level, n_purged));
+        }
+        res.reverse();
+
+        let n_purged: usize = res.iter().map(|(_, n)| n).sum();
+        if n_purged > 0 {
+            inn.repopulate_readers(false /*commit*/)?;
+            if let Some(cache) = &inn.cache {
+                cache.clear()?;
+            }
+        }
+
+        let elapsed = err_at!(TimeFail, start.elapsed())?;
+        info!(
+            target: "dgm   ",
+            "{}, compact range purged {} entries in {:?}",
+            inn.name, n_purged, elapsed
+        );
+
+        Ok(res)
+    }
+
     fn do_commit(inner: &Arc<Mutex<InnerDgm<K, V, M, D>>>) -> Result<()> {
-        let (metadata, mut d, r_m1, level) = {
-            let mut inn = to_inner_lock(inner)?;
+        let start = time::SystemTime::now();
+
+        let (metadata, mut d, r_m1, level, m1_footprint) = {
+            let mut inn = to_unpinned_lock(inner)?;
 
             if inn.m0.as_m0()?.to_seqno()? == inn.to_disk_seqno()? {
                 return Ok(());
@@ -1475,6 +2380,10 @@ where
 
             let level = inn.commit_level()?;
             inn.shift_into_m0()?;
+            let m1_footprint = match &inn.m1 {
+                Some(m1) => m1.footprint()?,
+                None => 0,
+            };
 
             inn.move_to_commit(level)?;
 
@@ -1484,7 +2393,8 @@ where
                 Some(m1) => Some(m1.as_mut_m1()?.to_reader()?),
                 None => None,
             };
-            (inn.m0.as_mut_m0()?.to_metadata()?, d, r_m1, level)
+            let metadata = inn.m0.as_mut_m0()?.to_metadata()?;
+            (metadata, d, r_m1, level, m1_footprint)
         };
         // println!("do_commit {}", level);
 
@@ -1504,6 +2414,14 @@ where
             // don't drop _m1 before repopulate_readers().
             let _m1 = mem::replace(&mut inn.m1, None);
             inn.repopulate_readers(true /*commit*/)?;
+            // entries upto disk seqno are durable, purge them from wal.
+            let seqno = inn.to_disk_seqno()?;
+            if let Some(wal) = &mut inn.wal {
+                wal.purge_till(Bound::Included(seqno))?;
+            }
+
+            let d_footprint = inn.disks[level].footprint()?;
+            inn.counters.add_commit(start, m1_footprint, d_footprint)?;
 
             let root_file = inn.root_file.clone();
             inn.root = inn.root.to_next();
@@ -1528,7 +2446,7 @@ where
         }?;
 
         let (cutoff, levels, s_levels, d_level) = {
-            let mut inn = to_inner_lock(inner)?;
+            let mut inn = to_unpinned_lock(inner)?;
 
             let (levels, s_levels, d_level) = match inn.compact_levels()? {
                 None => return Ok(0),
@@ -1580,8 +2498,10 @@ where
         levels: Vec<usize>,
         d_level: usize,
     ) -> Result<usize> {
+        let start = time::SystemTime::now();
+
         let mut high_disk = {
-            let mut inn = to_inner_lock(inner)?;
+            let mut inn = to_unpinned_lock(inner)?;
             inn.move_to_compact(&levels)?;
 
             inn.root = inn.root.to_next();
@@ -1600,6 +2520,9 @@ where
             inn.n_ccommits = Default::default();
             inn.n_compacts += 1;
 
+            let d_footprint = inn.disks[d_level].footprint()?;
+            inn.counters.add_compact(start, d_footprint)?;
+
             let root_file = inn.root_file.clone();
             inn.root_file = Self::new_root_file(
                 //
@@ -1621,8 +2544,10 @@ where
         s_levels: Vec<usize>,
         d_level: usize,
     ) -> Result<usize> {
+        let start = time::SystemTime::now();
+
         let (s_disks, mut disk) = {
-            let mut inn = to_inner_lock(inner)?;
+            let mut inn = to_unpinned_lock(inner)?;
 
             inn.move_to_compact(&levels)?;
 
@@ -1659,6 +2584,9 @@ where
             inn.repopulate_readers(false /*commit*/)?;
             inn.n_ccommits += 1;
 
+            let d_footprint = inn.disks[d_level].footprint()?;
+            inn.counters.add_compact(start, d_footprint)?;
+
             let root_file = inn.root_file.clone();
             inn.root_file = Self::new_root_file(
                 //
@@ -1793,43 +2721,46 @@ where
         }?;
 
         let m0 = inner.m0.as_mut_m0()?;
-        let _ = m0.validate()?; // TODO: handle return.
+        let m0_stats = m0.validate()?;
         let mut m0_r = m0.to_reader()?;
         let mut seqnos = vec![validate_snapshot(m0_r.iter()?, true, None, None)?];
 
-        match &mut inner.m1 {
+        let m1_stats = match &mut inner.m1 {
             Some(m1) => {
                 let m1 = m1.as_mut_m1()?;
-                let _ = m1.validate()?; // TODO: handle return
+                let m1_stats = m1.validate()?;
                 let mut m1_r = m1.to_reader()?;
                 seqnos.push(validate_snapshot(m1_r.iter()?, true, None, None)?);
+                Some(m1_stats)
             }
-            None => (),
-        }
+            None => None,
+        };
 
+        let (n_ccommits, n_compacts) = (inner.n_ccommits, inner.n_compacts);
         let mut disks = vec![];
-        for disk in inner.disks.iter_mut() {
+        for (level, disk) in inner.disks.iter_mut().enumerate() {
             match disk.as_mut_disk()? {
-                Some(disk) => disks.push(disk),
+                Some(disk) => disks.push((level, disk)),
                 None => (),
             }
         }
 
+        let mut disk_stats = vec![];
         let n = disks.len();
         if n > 0 {
-            for disk in disks.drain(..n - 1) {
-                let _ = disk.validate()?; // TODO: handle return
+            for (level, disk) in disks.drain(..n - 1) {
+                disk_stats.push((level, disk.validate()?));
                 let mut disk = disk.to_reader()?;
                 seqnos.push(validate_snapshot(disk.iter()?, true, None, None)?);
             }
             // validate the last disk snapshot.
-            let disk = disks.remove(0);
-            let _ = disk.validate()?; // TODO: handle return
+            let (level, disk) = disks.remove(0);
+            disk_stats.push((level, disk.validate()?));
             {
                 let mut disk = disk.to_reader()?;
                 let lc = root.lsm_cutoff.clone();
                 let tc = root.tombstone_cutoff.clone();
-                if inner.n_ccommits == 0 && inner.n_compacts > 0 {
+                if n_ccommits == 0 && n_compacts > 0 {
                     seqnos.push(validate_snapshot(disk.iter()?, root.lsm, lc, tc)?);
                 } else {
                     seqnos.push(validate_snapshot(disk.iter()?, true, None, None)?);
@@ -1855,10 +2786,7 @@ where
             }
         }
 
-        Ok(Stats {
-            _phantom_key: marker::PhantomData,
-            _phantom_val: marker::PhantomData,
-        })
+        inner.to_dgm_stats(m0_stats, m1_stats, disk_stats)
     }
 }
 
@@ -1976,6 +2904,9 @@ macro_rules! do_close_purge {
                 _ => err_at!(Fatal, msg: format!("unreachable"))?,
             }
         }
+        if let Some(wal) = inner.wal.take() {
+            wal.$func()?;
+        }
 
         Ok(())
     }};
@@ -1988,6 +2919,7 @@ where
     <V as Diff>::D: Serialize,
     M: WriteIndexFactory<K, V>,
     D: DiskIndexFactory<K, V>,
+    <M::I as Index<K, V>>::W: WalWriter<K, V>,
 {
     type W = DgmWriter<
         K,
@@ -2050,6 +2982,7 @@ where
     <V as Diff>::D: Serialize,
     M: WriteIndexFactory<K, V>,
     D: DiskIndexFactory<K, V>,
+    <M::I as Index<K, V>>::W: WalWriter<K, V>,
 {
     type W = DgmWriter<
         K,
@@ -2109,13 +3042,20 @@ where
                 r_m0,
                 r_m1,
                 r_disks,
+                cache: inner.cache.clone(),
+                bloom: Arc::clone(&inner.bloom),
 
                 _phantom_key: marker::PhantomData,
                 _phantom_val: marker::PhantomData,
             }
         };
 
-        let arc_w = Arc::new(Mutex::new(Ws { w, rs }));
+        let mode = inner.root.write_mode;
+        let wal = match &mut inner.wal {
+            Some(wal) => Some(wal.to_writer()?),
+            None => None,
+        };
+        let arc_w = Arc::new(Mutex::new(Ws { w, rs, mode, wal }));
         inner.writers.push(Arc::clone(&arc_w));
         Ok(DgmWriter::new(&inner.name, arc_w))
     }
@@ -2142,6 +3082,8 @@ where
             r_m0,
             r_m1,
             r_disks,
+            cache: inner.cache.clone(),
+            bloom: Arc::clone(&inner.bloom),
 
             _phantom_key: marker::PhantomData,
             _phantom_val: marker::PhantomData,
@@ -2153,15 +3095,30 @@ where
         Ok(DgmReader::new(&inner.name, arc_rs))
     }
 
-    fn commit<C, F>(&mut self, scanner: CommitIter<K, V, C>, metacb: F) -> Result<()>
+    fn commit<C, F>(&mut self, mut scanner: CommitIter<K, V, C>, metacb: F) -> Result<()>
     where
         C: CommitIterator<K, V>,
         F: Fn(Vec<u8>) -> Vec<u8>,
     {
+        let is_wal = self.as_inner()?.wal.is_some();
+        if is_wal && scanner.scan()?.next().is_some() {
+            // entries bypassing the log are lost on a crash, and logging
+            // them afresh shall lose their seqno and older versions.
+            err_at!(APIMisuse, msg: format!("commit entries into dgm with wal"))?
+        }
         {
             let mut inner = self.as_inner()?;
             let m0 = inner.m0.as_mut_m0()?;
-            m0.commit(scanner, metacb)?;
+            if is_wal {
+                // no entries to commit, only update the metadata.
+                m0.commit(CommitIter::new_empty(), metacb)?;
+            } else {
+                m0.commit(scanner, metacb)?;
+            }
+            // entries committed into m0 bypass the writer handles.
+            if let Some(cache) = &inner.cache {
+                cache.clear()?;
+            }
         }
         Self::do_commit(&self.inner)
     }
@@ -2215,6 +3172,18 @@ where
         w
     }
 
+    /// Set write mode for this handle, overriding the mode configured
+    /// via [Config::set_write_mode].
+    pub fn set_write_mode(&mut self, mode: WriteMode) -> Result<()> {
+        self.as_writer()?.mode = mode;
+        Ok(())
+    }
+
+    /// Return the write mode for this handle.
+    pub fn to_write_mode(&self) -> Result<WriteMode> {
+        Ok(self.as_writer()?.mode)
+    }
+
     fn as_writer(&self) -> Result<MutexGuard<Ws<K, V, W, A, B>>> {
         match self.w.lock() {
             Ok(value) => Ok(value),
@@ -2228,36 +3197,50 @@ where
     K: Clone + Ord + Hash + Serialize + Footprint,
     V: Clone + Diff + Serialize + Footprint,
     <V as Diff>::D: Serialize,
-    W: Writer<K, V>,
+    W: Writer<K, V> + WalWriter<K, V>,
     A: Reader<K, V>,
     B: Reader<K, V>,
 {
+    /// Set {key, value} in index. In [WriteMode::Blind] mode return
+    /// None, in [WriteMode::ReadBeforeWrite] mode return the old entry,
+    /// if present in any of the levels.
     fn set(&mut self, key: K, value: V) -> Result<Option<Entry<K, V>>> {
         let mut w_rs = self.as_writer()?;
-        w_rs.w.set(key, value)
+        let old = w_rs.set_m0(key.clone(), value)?;
+        if let Some(cache) = &w_rs.rs.cache {
+            cache.invalidate(&key)?;
+        }
+        w_rs.to_old_entry(&key, old)
     }
 
+    /// Set {key, value} in index if the latest seqno for key, across
+    /// all the levels, matches `cas`. Old entry is looked up in all the
+    /// levels, irrespective of the write mode, and returned if present.
+    /// Compare-and-set is atomic with respect to other writer handles.
     fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
         let mut w_rs = self.as_writer()?;
-
-        match Rs::get(&mut w_rs.rs, &key) {
-            Ok(old) if cas == old.to_seqno() => Ok(()),
-            Err(Error::KeyNotFound) if cas == 0 => Ok(()),
-            Ok(old) => Err(Error::InvalidCAS(old.to_seqno())),
-            Err(Error::KeyNotFound) => Err(Error::InvalidCAS(0)),
-            Err(err) => Err(err),
-        }?;
-
-        w_rs.w.set(key, value)
+        let old = w_rs.set_cas_m0(key.clone(), value, cas)?;
+        if let Some(cache) = &w_rs.rs.cache {
+            cache.invalidate(&key)?;
+        }
+        Ok(old)
     }
 
+    /// Delete key from index. In [WriteMode::Blind] mode return None,
+    /// in [WriteMode::ReadBeforeWrite] mode return the old entry, if
+    /// present in any of the levels.
     fn delete<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
     where
         K: Borrow<Q>,
         Q: ToOwned<Owned = K> + Ord + ?Sized,
     {
         let mut w_rs = self.as_writer()?;
-        w_rs.w.delete(key)
+        let old = w_rs.delete_m0(key)?;
+        let key = key.to_owned();
+        if let Some(cache) = &w_rs.rs.cache {
+            cache.invalidate(&key)?;
+        }
+        w_rs.to_old_entry(&key, old)
     }
 }
 
@@ -2271,6 +3254,99 @@ where
 {
     w: W,
     rs: Rs<K, V, A, B>,
+    mode: WriteMode,
+    wal: Option<DgmWalWriter<K, V>>,
+}
+
+impl<K, V, W, A, B> Ws<K, V, W, A, B>
+where
+    K: Clone + Ord + Hash + Serialize + Footprint,
+    V: Clone + Diff + Serialize + Footprint,
+    <V as Diff>::D: Serialize,
+    W: Writer<K, V> + WalWriter<K, V>,
+    A: Reader<K, V>,
+    B: Reader<K, V>,
+{
+    // if write-ahead-log is enabled, append to the log before applying
+    // on m0, using the seqno assigned by the log.
+    fn set_m0(&mut self, key: K, value: V) -> Result<Option<Entry<K, V>>> {
+        match &mut self.wal {
+            Some(wal) => wal.set(&mut self.w, key, value),
+            None => self.w.set(key, value),
+        }
+    }
+
+    // compare-and-set shall be atomic with respect to other writer
+    // handles. With write-ahead-log, writes are serialized by the log,
+    // hence validate and apply under the log's latch. Otherwise validate
+    // older levels and let m0 validate its own entry, retrying if m0 was
+    // updated by another writer handle in between.
+    fn set_cas_m0(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
+        match &mut self.wal {
+            Some(wal) => {
+                let rs = &mut self.rs;
+                let validate = |key: &K| Ok(Self::validate_cas(rs, key, cas)?.0);
+                wal.set_cas(&mut self.w, key, value, validate)
+            }
+            None => loop {
+                let (old, m0_cas) = Self::validate_cas(&mut self.rs, &key, cas)?;
+                match self.w.set_cas(key.clone(), value.clone(), m0_cas) {
+                    Ok(_) => break Ok(old),
+                    Err(Error::InvalidCAS(_)) => continue,
+                    Err(err) => break Err(err),
+                }
+            },
+        }
+    }
+
+    // validate `cas` with the latest entry for key, across all levels.
+    // Return the latest entry, along with the cas to be applied on m0,
+    // which is the seqno of m0's entry if present, else ZERO.
+    fn validate_cas(
+        rs: &mut Rs<K, V, A, B>,
+        key: &K,
+        cas: u64,
+    ) -> Result<(Option<Entry<K, V>>, u64)> {
+        let (old, m0_cas) = match rs.r_m0.get(key) {
+            Ok(old) => {
+                let seqno = old.to_seqno();
+                (Some(old), seqno)
+            }
+            Err(Error::KeyNotFound) => (rs.get_older(key)?, 0),
+            Err(err) => return Err(err),
+        };
+        // same as mem-index, deleted entry can be re-created with cas as ZERO.
+        match &old {
+            None if cas == 0 => Ok(()),
+            None => Err(Error::InvalidCAS(0)),
+            Some(old) if cas == old.to_seqno() => Ok(()),
+            Some(old) if cas == 0 && old.is_deleted() => Ok(()),
+            Some(old) => Err(Error::InvalidCAS(old.to_seqno())),
+        }?;
+
+        Ok((old, m0_cas))
+    }
+
+    fn delete_m0<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        match &mut self.wal {
+            Some(wal) => wal.delete(&mut self.w, key),
+            None => self.w.delete(key),
+        }
+    }
+
+    // `old` is the entry returned by m0 for key, which is authoritative
+    // if present, otherwise lookup older levels.
+    fn to_old_entry(&mut self, key: &K, old: Option<Entry<K, V>>) -> Result<Option<Entry<K, V>>> {
+        match (self.mode, old) {
+            (WriteMode::Blind, _) => Ok(None),
+            (WriteMode::ReadBeforeWrite, Some(old)) => Ok(Some(old)),
+            (WriteMode::ReadBeforeWrite, None) => self.rs.get_older(key),
+        }
+    }
 }
 
 // type alias to reader associated type for each snapshot (aka disk-index)
@@ -2284,6 +3360,8 @@ where
     r_m0: M,
     r_m1: Option<M>,
     r_disks: Vec<D>,
+    cache: Option<EntryCache<K, V>>,
+    bloom: Arc<BloomCounters>,
 
     _phantom_key: marker::PhantomData<K>,
     _phantom_val: marker::PhantomData<V>,
@@ -2299,9 +3377,16 @@ where
 {
     fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
-        K: Borrow<Q>,
+        K: Borrow<Q> + Hash,
         Q: Ord + ?Sized + Hash,
     {
+        // epoch shall be loaded before looking up m0, refer to
+        // EntryCache::insert.
+        let epoch = match &self.cache {
+            Some(cache) => cache.to_epoch(key)?,
+            None => 0,
+        };
+
         match self.r_m0.get(key) {
             Ok(entry) => return Ok(entry),
             Err(Error::KeyNotFound) => (),
@@ -2316,17 +3401,72 @@ where
             }
         }
 
-        let mut iter = self.r_disks.iter_mut();
-        loop {
-            match iter.next() {
-                Some(disk) => match disk.get(key) {
-                    Ok(entry) => break Ok(entry),
-                    Err(Error::KeyNotFound) => (),
-                    Err(err) => break Err(err),
-                },
-                None => break Err(Error::KeyNotFound),
+        if let Some(cache) = &self.cache {
+            if let Some(entry) = cache.get(key)? {
+                return Ok(entry);
             }
         }
+
+        let entry = self.get_disks(key)?;
+
+        if let Some(cache) = &self.cache {
+            cache.insert(&entry, epoch)?;
+        }
+        Ok(entry)
+    }
+
+    // lookup key in m1 and disk levels, skipping m0.
+    fn get_older<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q> + Hash,
+        Q: Ord + ?Sized + Hash,
+    {
+        if let Some(m1) = &mut self.r_m1 {
+            match m1.get(key) {
+                Ok(entry) => return Ok(Some(entry)),
+                Err(Error::KeyNotFound) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        if let Some(cache) = &self.cache {
+            if let Some(entry) = cache.get(key)? {
+                return Ok(Some(entry));
+            }
+        }
+
+        match self.get_disks(key) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(Error::KeyNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    // lookup key in disk levels, from latest to oldest. Levels whose
+    // bloom filter doesn't contain the key are skipped.
+    fn get_disks<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        for disk in self.r_disks.iter_mut() {
+            // levels without a filter are neither skipped, nor their
+            // misses counted as false positives.
+            let has_bitmap = disk.has_bitmap();
+            if has_bitmap && disk.may_contain(key) == false {
+                self.bloom.n_skips.fetch_add(1, SeqCst);
+                continue;
+            }
+            match disk.get(key) {
+                Ok(entry) => return Ok(entry),
+                Err(Error::KeyNotFound) if has_bitmap => {
+                    self.bloom.n_false_positives.fetch_add(1, SeqCst);
+                }
+                Err(Error::KeyNotFound) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Err(Error::KeyNotFound)
     }
 
     fn iter(mut rs: MutexGuard<Rs<K, V, M, D>>) -> Result<IndexIter<K, V>> {
@@ -2407,6 +3547,7 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized + Hash,
     {
+        // cache holds only the latest version, hence bypassed.
         let m0_entry = match rs.r_m0.get_with_versions(key) {
             Ok(entry) => Ok(Some(entry)),
             Err(Error::KeyNotFound) => Ok(None),
@@ -2424,14 +3565,23 @@ where
             None => Ok(m0_entry),
         }?;
 
+        let bloom = Arc::clone(&rs.bloom);
         let mut iter = rs.r_disks.iter_mut();
         let entry = loop {
             entry = match iter.next() {
+                Some(disk) if disk.has_bitmap() && disk.may_contain(key) == false => {
+                    bloom.n_skips.fetch_add(1, SeqCst);
+                    Ok(entry)
+                }
                 Some(disk) => match (disk.get_with_versions(key), entry) {
                     (Ok(e), Some(entry)) => Ok(Some(entry.xmerge(e)?)),
                     (Ok(e), None) => Ok(Some(e)),
-                    (Err(Error::KeyNotFound), Some(entry)) => Ok(Some(entry)),
-                    (Err(Error::KeyNotFound), None) => Ok(None),
+                    (Err(Error::KeyNotFound), entry) => {
+                        if disk.has_bitmap() {
+                            bloom.n_false_positives.fetch_add(1, SeqCst);
+                        }
+                        Ok(entry)
+                    }
                     (Err(err), _) => Err(err),
                 },
                 None => break entry,
@@ -2586,7 +3736,7 @@ where
 
 impl<K, V, M, D> Reader<K, V> for DgmReader<K, V, M, D>
 where
-    K: Clone + Ord + Serialize + Footprint,
+    K: Clone + Ord + Hash + Serialize + Footprint,
     V: Clone + Diff + Serialize + Footprint,
     <V as Diff>::D: Serialize,
     M: Reader<K, V>,
@@ -3030,14 +4180,117 @@ where
     }
 }
 
-/// TODO: populate with meaningful stats for Dgm index.
+// wait for on-going checkpoint to unpin the disk levels.
+fn to_unpinned_lock<K, V, M, D>(
+    inner: &Arc<Mutex<InnerDgm<K, V, M, D>>>,
+) -> Result<MutexGuard<InnerDgm<K, V, M, D>>>
+where
+    K: Clone + Ord + Serialize + Footprint,
+    V: Clone + Diff + Serialize + Footprint,
+    <V as Diff>::D: Serialize,
+    M: WriteIndexFactory<K, V>,
+    D: DiskIndexFactory<K, V>,
+    M::I: Footprint,
+    D::I: Footprint + Clone,
+{
+    loop {
+        let inn = to_inner_lock(inner)?;
+        if !inn.pinned {
+            break Ok(inn);
+        }
+        mem::drop(inn);
+        thread::sleep(time::Duration::from_millis(10));
+    }
+}
+
+/// Statistic type, for Dgm index, returned by [Dgm::to_stats] and
+/// [validate][Validate::validate]. Type parameter `A` is the statistic
+/// type for memory snapshots and `B` is the statistic type for disk
+/// snapshots.
 pub struct Stats<A, B>
 where
     A: fmt::Display,
     B: fmt::Display,
 {
-    _phantom_key: marker::PhantomData<A>,
-    _phantom_val: marker::PhantomData<B>,
+    pub name: String,
+    /// Statistics for memory snapshot handling the write operations.
+    pub m0: A,
+    /// Statistics for memory snapshot being flushed to disk, if any.
+    pub m1: Option<A>,
+    /// Statistics for live disk levels, ordered from latest to oldest.
+    pub disks: Vec<LevelStats<B>>,
+    /// Memory footprint for both memory snapshots.
+    pub mem_footprint: isize,
+    /// Disk footprint for all the live disk levels.
+    pub disk_footprint: isize,
+    /// Number of times memory snapshot was commited to disk.
+    pub n_commits: usize,
+    /// Number of compactions, includes compactions merging one or more
+    /// levels into an older level.
+    pub n_compacts: usize,
+    /// Number of compactions into the oldest level.
+    pub n_high_compacts: usize,
+    /// Total time spent commiting memory snapshot to disk, in nanoseconds.
+    pub commit_time: u64,
+    /// Total time spent compacting disk levels, in nanoseconds.
+    pub compact_time: u64,
+    /// Total memory footprint of snapshots commited to disk.
+    pub n_committed_bytes: usize,
+    /// Total bytes written to disk by commits and compactions. Disk levels
+    /// are rebuilt on every commit and compaction, hence this is
+    /// computed as the footprint of the target level after each operation.
+    pub n_written_bytes: usize,
+    /// Footprint of entries cached from disk levels, zero if cache is
+    /// disabled.
+    pub cache_footprint: usize,
+    /// Number of entries cached from disk levels.
+    pub n_cache_entries: usize,
+    /// Number of lookups, missing the memory snapshots, that are served
+    /// from the cache.
+    pub n_cache_hits: usize,
+    /// Number of lookups, missing the memory snapshots, that are not
+    /// found in the cache.
+    pub n_cache_misses: usize,
+    /// Number of entries evicted from the cache.
+    pub n_cache_evictions: usize,
+    /// Total time disk writes were throttled by the rate limiter, in
+    /// nanoseconds.
+    pub throttle_time: u64,
+    /// Number of disk level lookups skipped by probing the level's
+    /// bloom filter.
+    pub n_bloom_skips: usize,
+    /// Number of disk level lookups that passed the level's bloom
+    /// filter, but missed the key.
+    pub n_bloom_false_positives: usize,
+}
+
+impl<A, B> Stats<A, B>
+where
+    A: fmt::Display,
+    B: fmt::Display,
+{
+    /// Return the number of live disk levels.
+    pub fn to_levels(&self) -> usize {
+        self.disks.len()
+    }
+
+    /// Return write amplification, ratio between bytes written to disk
+    /// and bytes commited from memory.
+    pub fn to_write_amplification(&self) -> f64 {
+        match self.n_committed_bytes {
+            0 => 0.0,
+            n => (self.n_written_bytes as f64) / (n as f64),
+        }
+    }
+
+    /// Return cache hit ratio, ratio between lookups served from the
+    /// cache and lookups that were looked up in the cache.
+    pub fn to_cache_hit_ratio(&self) -> f64 {
+        match self.n_cache_hits + self.n_cache_misses {
+            0 => 0.0,
+            n => (self.n_cache_hits as f64) / (n as f64),
+        }
+    }
 }
 
 impl<A, B> fmt::Display for Stats<A, B>
@@ -3046,7 +4299,156 @@ where
     B: fmt::Display,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
-        write!(f, "Dgm::Stats<>")
+        write!(f, "dgm.name = {}\n", self.name)?;
+        write!(
+            f,
+            "dgm.stats = {{ n_levels={}, mem_footprint={}, disk_footprint={} }}\n",
+            self.to_levels(),
+            self.mem_footprint,
+            self.disk_footprint,
+        )?;
+        write!(
+            f,
+            "dgm.stats = {{ n_commits={}, n_compacts={}, n_high_compacts={} }}\n",
+            self.n_commits, self.n_compacts, self.n_high_compacts,
+        )?;
+        let (ct, mt) = (
+            time::Duration::from_nanos(self.commit_time),
+            time::Duration::from_nanos(self.compact_time),
+        );
+        write!(
+            f,
+            "dgm.stats = {{ commit_time=\"{:?}\", compact_time=\"{:?}\" }}\n",
+            ct, mt
+        )?;
+        write!(
+            f,
+            "dgm.stats = {{ n_committed_bytes={}, n_written_bytes={}, write_amp={:.2} }}\n",
+            self.n_committed_bytes,
+            self.n_written_bytes,
+            self.to_write_amplification(),
+        )?;
+        write!(
+            f,
+            "dgm.stats = {{ cache_footprint={}, n_cache_entries={}, n_cache_evictions={} }}\n",
+            self.cache_footprint, self.n_cache_entries, self.n_cache_evictions,
+        )?;
+        write!(
+            f,
+            "dgm.stats = {{ n_cache_hits={}, n_cache_misses={}, cache_hit_ratio={:.2} }}\n",
+            self.n_cache_hits,
+            self.n_cache_misses,
+            self.to_cache_hit_ratio(),
+        )?;
+        write!(
+            f,
+            "dgm.stats = {{ throttle_time=\"{:?}\" }}\n",
+            time::Duration::from_nanos(self.throttle_time),
+        )?;
+        write!(
+            f,
+            "dgm.stats = {{ n_bloom_skips={}, n_bloom_false_positives={} }}\n",
+            self.n_bloom_skips, self.n_bloom_false_positives,
+        )?;
+        write!(f, "dgm.m0 =\n{}", self.m0)?;
+        if let Some(m1) = &self.m1 {
+            write!(f, "\ndgm.m1 =\n{}", m1)?;
+        }
+        for level in self.disks.iter() {
+            write!(f, "\n{}", level)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A, B> ToJson for Stats<A, B>
+where
+    A: fmt::Display + ToJson,
+    B: fmt::Display + ToJson,
+{
+    fn to_json(&self) -> String {
+        let m1 = match &self.m1 {
+            Some(m1) => m1.to_json(),
+            None => "null".to_string(),
+        };
+        let disks: Vec<String> = self.disks.iter().map(|l| l.to_json()).collect();
+        let props = [
+            format!(r#""name": "{}""#, self.name),
+            format!(r#""n_levels": {}"#, self.to_levels()),
+            format!(r#""mem_footprint": {}"#, self.mem_footprint),
+            format!(r#""disk_footprint": {}"#, self.disk_footprint),
+            format!(r#""n_commits": {}"#, self.n_commits),
+            format!(r#""n_compacts": {}"#, self.n_compacts),
+            format!(r#""n_high_compacts": {}"#, self.n_high_compacts),
+            format!(r#""commit_time": {}"#, self.commit_time),
+            format!(r#""compact_time": {}"#, self.compact_time),
+            format!(r#""n_committed_bytes": {}"#, self.n_committed_bytes),
+            format!(r#""n_written_bytes": {}"#, self.n_written_bytes),
+            format!(r#""write_amp": {}"#, self.to_write_amplification()),
+            format!(r#""cache_footprint": {}"#, self.cache_footprint),
+            format!(r#""n_cache_entries": {}"#, self.n_cache_entries),
+            format!(r#""n_cache_hits": {}"#, self.n_cache_hits),
+            format!(r#""n_cache_misses": {}"#, self.n_cache_misses),
+            format!(r#""n_cache_evictions": {}"#, self.n_cache_evictions),
+            format!(r#""cache_hit_ratio": {}"#, self.to_cache_hit_ratio()),
+            format!(r#""throttle_time": {}"#, self.throttle_time),
+            format!(r#""n_bloom_skips": {}"#, self.n_bloom_skips),
+            format!(
+                r#""n_bloom_false_positives": {}"#,
+                self.n_bloom_false_positives
+            ),
+            format!(r#""m0": {}"#, self.m0.to_json()),
+            format!(r#""m1": {}"#, m1),
+            format!(r#""disks": [{}]"#, disks.join(", ")),
+        ];
+        format!(r#"{{ {} }}"#, props.join(", "))
+    }
+}
+
+/// Statistic type for a single disk level in Dgm index.
+pub struct LevelStats<B>
+where
+    B: fmt::Display,
+{
+    /// Disk level, lower the level newer the entries.
+    pub level: usize,
+    /// Disk footprint for this level.
+    pub footprint: isize,
+    /// Entries in this level are newer than this seqno.
+    pub from_seqno: u64,
+    /// Latest seqno in this level.
+    pub to_seqno: u64,
+    /// Statistics from disk snapshot.
+    pub stats: B,
+}
+
+impl<B> fmt::Display for LevelStats<B>
+where
+    B: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(
+            f,
+            "dgm.level = {{ level={}, footprint={}, seqno=({}, {}] }}\n",
+            self.level, self.footprint, self.from_seqno, self.to_seqno,
+        )?;
+        write!(f, "{}", self.stats)
+    }
+}
+
+impl<B> ToJson for LevelStats<B>
+where
+    B: fmt::Display + ToJson,
+{
+    fn to_json(&self) -> String {
+        let props = [
+            format!(r#""level": {}"#, self.level),
+            format!(r#""footprint": {}"#, self.footprint),
+            format!(r#""from_seqno": {}"#, self.from_seqno),
+            format!(r#""to_seqno": {}"#, self.to_seqno),
+            format!(r#""stats": {}"#, self.stats.to_json()),
+        ];
+        format!(r#"{{ {} }}"#, props.join(", "))
     }
 }
 
@@ -0,0 +1,346 @@
+//! Module `dgm_cache` implement a footprint bounded cache for entries
+//! read from [Dgm][crate::dgm::Dgm] disk levels.
+//!
+//! This is the "Mc" index of "working-set-dgm" configuration, refer to
+//! `docs/snapshots.md`. Entries are cached on disk hits, and only the
+//! latest value of an entry is cached, deleted entries are not cached.
+//! Writes into m0 shall invalidate the cached entry. Entries are evicted
+//! as per [CachePolicy] when the cached footprint exceeds the configured
+//! limit.
+//!
+//! Cache is sharded by key's hash, each shard is separately latched and
+//! is bounded by an equal share of the footprint limit. Since only the
+//! latest value is cached, lookups for older versions shall bypass the
+//! cache.
+
+use std::{
+    borrow::Borrow,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    convert::TryInto,
+    fmt,
+    hash::{Hash, Hasher},
+    mem, result,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    core::{Diff, Entry, Footprint, Result, Value},
+    error::Error,
+};
+
+/// Eviction policy for entries cached by Dgm index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CachePolicy {
+    /// Evict the least recently used entry.
+    Lru,
+    /// Evict the least frequently used entry, ties are broken by
+    /// evicting the least recently used entry.
+    Lfu,
+}
+
+impl Default for CachePolicy {
+    fn default() -> CachePolicy {
+        CachePolicy::Lru
+    }
+}
+
+impl fmt::Display for CachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            CachePolicy::Lru => write!(f, "lru"),
+            CachePolicy::Lfu => write!(f, "lfu"),
+        }
+    }
+}
+
+impl FromStr for CachePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<CachePolicy> {
+        match s {
+            "lru" => Ok(CachePolicy::Lru),
+            "lfu" => Ok(CachePolicy::Lfu),
+            _ => err_at!(InvalidInput, msg: format!("cache policy {}", s)),
+        }
+    }
+}
+
+impl CachePolicy {
+    // entries are evicted in the order of their rank, lowest first.
+    fn to_rank(&self, n_access: u64, tick: u64) -> (u64, u64) {
+        match self {
+            CachePolicy::Lru => (tick, 0),
+            CachePolicy::Lfu => (n_access, tick),
+        }
+    }
+}
+
+/// Shared, footprint bounded, cache for entries read from disk.
+///
+/// Cloning an EntryCache shall return a new handle to the same cache.
+pub(crate) struct EntryCache<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    shards: Arc<Vec<Mutex<InnerCache<K, V>>>>,
+}
+
+impl<K, V> Clone for EntryCache<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn clone(&self) -> EntryCache<K, V> {
+        EntryCache {
+            shards: Arc::clone(&self.shards),
+        }
+    }
+}
+
+struct InnerCache<K, V> {
+    limit: usize,
+    policy: CachePolicy,
+    footprint: usize,
+    tick: u64,
+    // incremented for every invalidation within this shard, refer to
+    // EntryCache::insert.
+    epoch: u64,
+    entries: BTreeMap<K, Item<V>>,
+    // rank -> key, ordered from the next entry to evict.
+    ranks: BTreeMap<(u64, u64), K>,
+
+    n_hits: usize,
+    n_misses: usize,
+    n_evictions: usize,
+}
+
+struct Item<V> {
+    value: V,
+    seqno: u64,
+    footprint: usize,
+    n_access: u64,
+    rank: (u64, u64),
+}
+
+impl<K, V> EntryCache<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Create a new cache that can hold upto `limit` bytes of entries,
+    /// split into `n_shards` shards.
+    pub(crate) fn new(limit: usize, policy: CachePolicy, n_shards: usize) -> EntryCache<K, V> {
+        let n_shards = std::cmp::max(n_shards, 1);
+        let shards = (0..n_shards)
+            .map(|_| {
+                Mutex::new(InnerCache {
+                    limit: limit / n_shards,
+                    policy,
+                    footprint: 0,
+                    tick: 0,
+                    epoch: 0,
+                    entries: BTreeMap::new(),
+                    ranks: BTreeMap::new(),
+
+                    n_hits: 0,
+                    n_misses: 0,
+                    n_evictions: 0,
+                })
+            })
+            .collect();
+        EntryCache {
+            shards: Arc::new(shards),
+        }
+    }
+
+    /// Return the number of bytes cached.
+    pub(crate) fn footprint(&self) -> Result<usize> {
+        let mut footprint = 0;
+        for shard in self.shards.iter() {
+            footprint += Self::as_shard(shard)?.footprint;
+        }
+        Ok(footprint)
+    }
+
+    /// Return the number of entries cached.
+    pub(crate) fn len(&self) -> Result<usize> {
+        let mut n = 0;
+        for shard in self.shards.iter() {
+            n += Self::as_shard(shard)?.entries.len();
+        }
+        Ok(n)
+    }
+
+    /// Return (hits, misses, evictions) counted so far.
+    pub(crate) fn to_counts(&self) -> Result<(usize, usize, usize)> {
+        let (mut hits, mut misses, mut evictions) = (0, 0, 0);
+        for shard in self.shards.iter() {
+            let inner = Self::as_shard(shard)?;
+            hits += inner.n_hits;
+            misses += inner.n_misses;
+            evictions += inner.n_evictions;
+        }
+        Ok((hits, misses, evictions))
+    }
+
+    /// Drop all cached entries. Shall be called when entries are
+    /// written into m0 without invalidating them.
+    pub(crate) fn clear(&self) -> Result<()> {
+        for shard in self.shards.iter() {
+            let mut inner = Self::as_shard(shard)?;
+            inner.epoch += 1;
+            inner.entries.clear();
+            inner.ranks.clear();
+            inner.footprint = 0;
+        }
+        Ok(())
+    }
+
+    fn as_shard(shard: &Mutex<InnerCache<K, V>>) -> Result<MutexGuard<InnerCache<K, V>>> {
+        match shard.lock() {
+            Ok(inner) => Ok(inner),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+impl<K, V> EntryCache<K, V>
+where
+    K: Clone + Ord + Hash + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Return the current epoch of the shard holding `key`, to be
+    /// supplied while inserting the entry read from disk. Shall be called
+    /// before looking up the memory levels.
+    pub(crate) fn to_epoch<Q>(&self, key: &Q) -> Result<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        Ok(self.as_inner(key)?.epoch)
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        let mut inner = self.as_inner(key)?;
+
+        inner.tick += 1;
+        let (tick, policy) = (inner.tick, inner.policy);
+        let (old_rank, rank, value, seqno) = match inner.entries.get_mut(key) {
+            Some(item) => {
+                let old_rank = item.rank;
+                item.n_access += 1;
+                item.rank = policy.to_rank(item.n_access, tick);
+                (old_rank, item.rank, item.value.clone(), item.seqno)
+            }
+            None => {
+                inner.n_misses += 1;
+                return Ok(None);
+            }
+        };
+        let key = match inner.ranks.remove(&old_rank) {
+            Some(key) => key,
+            None => err_at!(Fatal, msg: format!("dgm cache missing rank"))?,
+        };
+        inner.ranks.insert(rank, key.clone());
+        inner.n_hits += 1;
+
+        let value = Value::new_upsert_value(value, seqno);
+        Ok(Some(Entry::new(key, value)))
+    }
+
+    /// Cache `entry` read from disk. Entry shall be ignored if the
+    /// entry's shard was invalidated after `epoch`, since the entry
+    /// could have been updated in the meantime.
+    pub(crate) fn insert(&self, entry: &Entry<K, V>, epoch: u64) -> Result<()> {
+        let key = entry.to_key();
+        let mut inner = self.as_inner(&key)?;
+        if epoch != inner.epoch {
+            return Ok(());
+        }
+
+        let value = match entry.to_native_value() {
+            Some(value) => value,
+            None => return Ok(()), // deleted entry or value on disk.
+        };
+        let footprint: usize = {
+            let (k, v) = (key.footprint()?, value.footprint()?);
+            let n: usize = convert_at!(k + v)?;
+            n + mem::size_of::<K>() + mem::size_of::<V>()
+        };
+        if footprint > inner.limit {
+            return Ok(());
+        }
+
+        if let Some(old) = inner.entries.remove(&key) {
+            inner.ranks.remove(&old.rank);
+            inner.footprint -= old.footprint;
+        }
+        // make room for the new entry.
+        while (inner.footprint + footprint) > inner.limit {
+            let rank = match inner.ranks.keys().next() {
+                Some(rank) => *rank,
+                None => break,
+            };
+            if let Some(key) = inner.ranks.remove(&rank) {
+                if let Some(old) = inner.entries.remove(&key) {
+                    inner.footprint -= old.footprint;
+                    inner.n_evictions += 1;
+                }
+            }
+        }
+
+        inner.tick += 1;
+        let rank = inner.policy.to_rank(1, inner.tick);
+        let item = Item {
+            value,
+            seqno: entry.to_seqno(),
+            footprint,
+            n_access: 1,
+            rank,
+        };
+        inner.entries.insert(key.clone(), item);
+        inner.ranks.insert(rank, key);
+        inner.footprint += footprint;
+
+        Ok(())
+    }
+
+    /// Drop the cached entry for `key`, if any. Shall be called after
+    /// every write into m0.
+    pub(crate) fn invalidate<Q>(&self, key: &Q) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        let mut inner = self.as_inner(key)?;
+        inner.epoch += 1;
+        if let Some(old) = inner.entries.remove(key) {
+            inner.ranks.remove(&old.rank);
+            inner.footprint -= old.footprint;
+        }
+        Ok(())
+    }
+
+    // lock the shard holding `key`.
+    fn as_inner<Q>(&self, key: &Q) -> Result<MutexGuard<InnerCache<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let n: u64 = convert_at!(self.shards.len())?;
+        let off: usize = convert_at!((hasher.finish() % n))?;
+        Self::as_shard(&self.shards[off])
+    }
+}
+
+#[cfg(test)]
+#[path = "dgm_cache_test.rs"]
+mod dgm_cache_test;
@@ -0,0 +1,128 @@
+use super::*;
+
+fn new_entry(key: i64, value: i64, seqno: u64) -> Entry<i64, i64> {
+    Entry::new(key, Value::new_upsert_value(value, seqno))
+}
+
+#[test]
+fn test_cache_lru() {
+    // each i64 entry is 16 bytes.
+    let cache: EntryCache<i64, i64> = EntryCache::new(48, CachePolicy::Lru, 1);
+
+    assert!(cache.get(&10).unwrap().is_none());
+    for key in 0..3 {
+        let epoch = cache.to_epoch(&key).unwrap();
+        cache.insert(&new_entry(key, key * 10, 1), epoch).unwrap();
+    }
+    assert_eq!(cache.len().unwrap(), 3);
+    assert_eq!(cache.footprint().unwrap(), 48);
+
+    let entry = cache.get(&0).unwrap().unwrap();
+    assert_eq!(entry.to_key(), 0);
+    assert_eq!(entry.to_native_value(), Some(0));
+    assert_eq!(entry.to_seqno(), 1);
+
+    // key 1 is the least recently used entry.
+    let epoch = cache.to_epoch(&3).unwrap();
+    cache.insert(&new_entry(3, 30, 2), epoch).unwrap();
+    assert!(cache.get(&1).unwrap().is_none());
+    assert_eq!(cache.len().unwrap(), 3);
+    assert_eq!(cache.footprint().unwrap(), 48);
+    assert_eq!(cache.to_counts().unwrap(), (1, 2, 1));
+
+    // insert after invalidation is ignored.
+    let epoch = cache.to_epoch(&0).unwrap();
+    cache.invalidate(&0).unwrap();
+    cache.insert(&new_entry(0, 0, 1), epoch).unwrap();
+    assert!(cache.get(&0).unwrap().is_none());
+    assert_eq!(cache.len().unwrap(), 2);
+    assert_eq!(cache.footprint().unwrap(), 32);
+
+    // deleted entries are not cached.
+    let mut entry = new_entry(4, 40, 3);
+    entry.delete(4).unwrap();
+    let epoch = cache.to_epoch(&4).unwrap();
+    cache.insert(&entry, epoch).unwrap();
+    assert!(cache.get(&4).unwrap().is_none());
+    assert_eq!(cache.len().unwrap(), 2);
+
+    let cache1 = cache.clone();
+    assert_eq!(cache1.get(&3).unwrap().unwrap().to_native_value(), Some(30));
+    assert_eq!(cache.to_counts().unwrap(), (2, 4, 1));
+
+    cache.clear().unwrap();
+    assert!(cache.get(&3).unwrap().is_none());
+    assert_eq!(cache.len().unwrap(), 0);
+    assert_eq!(cache.footprint().unwrap(), 0);
+}
+
+#[test]
+fn test_cache_lfu() {
+    let cache: EntryCache<i64, i64> = EntryCache::new(48, CachePolicy::Lfu, 1);
+
+    for key in 0..3 {
+        let epoch = cache.to_epoch(&key).unwrap();
+        cache.insert(&new_entry(key, key * 10, 1), epoch).unwrap();
+    }
+    for _ in 0..3 {
+        cache.get(&0).unwrap().unwrap();
+        cache.get(&2).unwrap().unwrap();
+    }
+    cache.get(&1).unwrap().unwrap();
+
+    // key 1 is the least frequently used, though recently used.
+    let epoch = cache.to_epoch(&3).unwrap();
+    cache.insert(&new_entry(3, 30, 2), epoch).unwrap();
+    assert!(cache.get(&1).unwrap().is_none());
+    assert!(cache.get(&0).unwrap().is_some());
+    assert!(cache.get(&2).unwrap().is_some());
+    assert!(cache.get(&3).unwrap().is_some());
+    assert_eq!(cache.to_counts().unwrap(), (10, 1, 1));
+}
+
+#[test]
+fn test_cache_shards() {
+    // each i64 entry is 16 bytes, and each shard can hold 4 entries.
+    let n_shards = 4;
+    let cache: EntryCache<i64, i64> = EntryCache::new(256, CachePolicy::Lru, n_shards);
+
+    for key in 0..100 {
+        let epoch = cache.to_epoch(&key).unwrap();
+        cache.insert(&new_entry(key, key * 10, 1), epoch).unwrap();
+    }
+    assert_eq!(cache.len().unwrap(), 16);
+    assert_eq!(cache.footprint().unwrap(), 256);
+    assert_eq!(cache.to_counts().unwrap(), (0, 0, 84));
+
+    // invalidation shall only reject inserts into the same shard.
+    let epochs: Vec<u64> = (0..100).map(|key| cache.to_epoch(&key).unwrap()).collect();
+    let key = 200;
+    let epoch = cache.to_epoch(&key).unwrap();
+    cache.invalidate(&key).unwrap();
+    assert_eq!(cache.to_epoch(&key).unwrap(), epoch + 1);
+    let changed: Vec<i64> = (0..100)
+        .filter(|key| cache.to_epoch(key).unwrap() != epochs[*key as usize])
+        .collect();
+    assert!(changed.len() < 100, "{}", changed.len());
+    for key in 0..100 {
+        let epoch = epochs[key as usize];
+        cache.insert(&new_entry(key, key * 20, 2), epoch).unwrap();
+        let entry = cache.get(&key).unwrap();
+        if changed.contains(&key) {
+            assert!(entry.map(|e| e.to_seqno()) != Some(2));
+        } else {
+            assert_eq!(entry.unwrap().to_native_value(), Some(key * 20));
+        }
+    }
+    assert_eq!(cache.footprint().unwrap(), 256);
+}
+
+#[test]
+fn test_cache_policy() {
+    for policy in vec![CachePolicy::Lru, CachePolicy::Lfu].into_iter() {
+        let s = policy.to_string();
+        assert_eq!(s.parse::<CachePolicy>().unwrap(), policy);
+    }
+    assert!("fifo".parse::<CachePolicy>().is_err());
+    assert_eq!(CachePolicy::default(), CachePolicy::Lru);
+}
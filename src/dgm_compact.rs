@@ -0,0 +1,245 @@
+//! Module `dgm_compact` implement the compaction strategies for
+//! [Dgm][crate::dgm::Dgm] index.
+//!
+//! A strategy is consulted by commit and compaction, to decide whether
+//! the memory snapshot shall be committed into a new disk level, and to
+//! decide which disk levels shall be merged together. Disk levels are
+//! always supplied from the latest level to the oldest level.
+//!
+//! Applications can plug in their own strategy, by implementing
+//! [CompactionStrategy] and configuring it as [Compaction::Custom].
+
+use std::{fmt, result, sync::Arc};
+
+/// Decide how Dgm disk levels shall grow and shrink.
+pub trait CompactionStrategy: Send + Sync {
+    /// Return true if memory snapshot, of `mem_footprint` bytes, shall be
+    /// committed into a new disk level, false if it shall be merged into
+    /// the latest disk level, of `disk_footprint` bytes.
+    fn is_new_level(&self, mem_footprint: isize, disk_footprint: isize) -> bool;
+
+    /// Pick disk levels to compact, `levels` is the list of
+    /// `(level, footprint)` ordered from latest to oldest. Return the
+    /// source levels and the target level, into which source levels shall
+    /// be merged. Sources and target shall be consecutive levels from
+    /// `levels`, sources being the newer ones.
+    fn to_compact_levels(&self, levels: &[(usize, isize)]) -> Option<(Vec<usize>, usize)>;
+}
+
+/// Compaction strategy for Dgm index, persisted along with the index.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Compaction {
+    /// Ratio based compaction, refer to [RatioCompaction]. Ratios are
+    /// picked from `mem_ratio` and `disk_ratio` configuration.
+    Ratio,
+    /// Leveled compaction, refer to [LeveledCompaction].
+    Leveled(LeveledCompaction),
+    /// Size-tiered compaction, refer to [TieredCompaction].
+    Tiered(TieredCompaction),
+    /// Application supplied strategy, refer to [CustomCompaction].
+    Custom(CustomCompaction),
+}
+
+impl Default for Compaction {
+    fn default() -> Compaction {
+        Compaction::Ratio
+    }
+}
+
+impl fmt::Display for Compaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            Compaction::Ratio => write!(f, "ratio"),
+            Compaction::Leveled(_) => write!(f, "leveled"),
+            Compaction::Tiered(_) => write!(f, "tiered"),
+            Compaction::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
+/// Application supplied compaction strategy, identified by its name.
+/// Only the name is persisted along with the index, hence the same
+/// strategy, by name, shall be supplied when re-opening the index,
+/// refer to [Dgm::open_with_compaction][crate::dgm::Dgm::open_with_compaction].
+#[derive(Clone)]
+pub struct CustomCompaction {
+    name: String,
+    strategy: Option<Arc<dyn CompactionStrategy>>,
+}
+
+impl CustomCompaction {
+    /// Create a custom compaction, `name` shall uniquely identify the
+    /// strategy across restarts.
+    pub fn new(name: &str, strategy: Arc<dyn CompactionStrategy>) -> CustomCompaction {
+        CustomCompaction {
+            name: name.to_string(),
+            strategy: Some(strategy),
+        }
+    }
+
+    // custom compaction loaded from disk, strategy is yet to be supplied.
+    pub(crate) fn from_name(name: &str) -> CustomCompaction {
+        CustomCompaction {
+            name: name.to_string(),
+            strategy: None,
+        }
+    }
+
+    /// Return the name of this strategy.
+    pub fn to_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Return the strategy, None if this was loaded from disk and the
+    /// strategy is yet to be supplied by the application.
+    pub fn to_strategy(&self) -> Option<Arc<dyn CompactionStrategy>> {
+        self.strategy.as_ref().map(Arc::clone)
+    }
+}
+
+impl fmt::Debug for CustomCompaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "CustomCompaction<{}>", self.name)
+    }
+}
+
+impl PartialEq for CustomCompaction {
+    fn eq(&self, other: &CustomCompaction) -> bool {
+        self.name == other.name
+    }
+}
+
+/// Default compaction policy. A new level is created when memory
+/// footprint is less than `mem_ratio` of the latest disk level. Newer
+/// levels are merged into an older level when their combined footprint
+/// exceeds `disk_ratio` of the older level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RatioCompaction {
+    pub mem_ratio: f64,
+    pub disk_ratio: f64,
+}
+
+impl CompactionStrategy for RatioCompaction {
+    fn is_new_level(&self, mem_footprint: isize, disk_footprint: isize) -> bool {
+        ((mem_footprint as f64) / (disk_footprint as f64)) < self.mem_ratio
+    }
+
+    fn to_compact_levels(&self, levels: &[(usize, isize)]) -> Option<(Vec<usize>, usize)> {
+        let mut levels = levels.to_vec();
+        match levels.len() {
+            0 | 1 => None,
+            _n => loop {
+                let (target, t_footprint) = levels.remove(levels.len() - 1);
+                let footprint: isize = levels.iter().map(|(_, fp)| fp).sum();
+                let ratio = (footprint as f64) / (t_footprint as f64);
+
+                if ratio > self.disk_ratio {
+                    let sources = levels.into_iter().map(|(l, _)| l).collect();
+                    break Some((sources, target));
+                } else if levels.len() == 1 {
+                    break None;
+                }
+            },
+        }
+    }
+}
+
+/// Leveled compaction, optimized for read-heavy workloads. Every older
+/// level is expected to be `fanout` times larger than its newer level.
+/// Memory snapshot is merged into the latest level until the latest
+/// level is `fanout` times larger than the memory snapshot, and a
+/// level is merged into its older level when the older level is less
+/// than `fanout` times larger.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LeveledCompaction {
+    pub fanout: f64,
+}
+
+impl Default for LeveledCompaction {
+    fn default() -> LeveledCompaction {
+        LeveledCompaction {
+            fanout: Self::FANOUT,
+        }
+    }
+}
+
+impl LeveledCompaction {
+    /// Default size multiplier between a level and its older level.
+    pub const FANOUT: f64 = 10.0;
+}
+
+impl CompactionStrategy for LeveledCompaction {
+    fn is_new_level(&self, mem_footprint: isize, disk_footprint: isize) -> bool {
+        (disk_footprint as f64) >= ((mem_footprint as f64) * self.fanout)
+    }
+
+    fn to_compact_levels(&self, levels: &[(usize, isize)]) -> Option<(Vec<usize>, usize)> {
+        levels.windows(2).find_map(|w| {
+            let ((source, s_footprint), (target, t_footprint)) = (w[0], w[1]);
+            if (t_footprint as f64) < ((s_footprint as f64) * self.fanout) {
+                Some((vec![source], target))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Size-tiered compaction, optimized for write-heavy workloads. Memory
+/// snapshot is always committed into a new level. When `min_levels`, or
+/// more, consecutive levels are of similar size, that is when the
+/// largest of them is within `bucket_ratio` times the smallest of them,
+/// they are merged into a single level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TieredCompaction {
+    pub bucket_ratio: f64,
+    pub min_levels: usize,
+}
+
+impl Default for TieredCompaction {
+    fn default() -> TieredCompaction {
+        TieredCompaction {
+            bucket_ratio: Self::BUCKET_RATIO,
+            min_levels: Self::MIN_LEVELS,
+        }
+    }
+}
+
+impl TieredCompaction {
+    /// Default size ratio between the largest and smallest level in a tier.
+    pub const BUCKET_RATIO: f64 = 2.0;
+
+    /// Default minimum number of levels in a tier, before they are merged.
+    pub const MIN_LEVELS: usize = 4;
+}
+
+impl CompactionStrategy for TieredCompaction {
+    fn is_new_level(&self, _mem_footprint: isize, _disk_footprint: isize) -> bool {
+        true
+    }
+
+    fn to_compact_levels(&self, levels: &[(usize, isize)]) -> Option<(Vec<usize>, usize)> {
+        for start in 0..levels.len() {
+            let (mut min, mut max) = (levels[start].1, levels[start].1);
+            let mut end = start;
+            for (_, footprint) in levels[(start + 1)..].iter() {
+                let (lmin, lmax) = (min.min(*footprint), max.max(*footprint));
+                if (lmax as f64) > ((lmin.max(1) as f64) * self.bucket_ratio) {
+                    break;
+                }
+                min = lmin;
+                max = lmax;
+                end += 1;
+            }
+            if (end - start + 1) >= self.min_levels {
+                let sources = levels[start..end].iter().map(|(l, _)| *l).collect();
+                return Some((sources, levels[end].0));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+#[path = "dgm_compact_test.rs"]
+mod dgm_compact_test;
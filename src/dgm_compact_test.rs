@@ -0,0 +1,91 @@
+use super::*;
+
+#[test]
+fn test_ratio_compaction() {
+    let s = RatioCompaction {
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+    };
+    assert!(s.is_new_level(100, 1000));
+    assert!(!s.is_new_level(600, 1000));
+
+    assert_eq!(s.to_compact_levels(&[]), None);
+    assert_eq!(s.to_compact_levels(&[(15, 1000)]), None);
+    let levels = vec![(12, 100), (13, 300), (15, 1000)];
+    assert_eq!(s.to_compact_levels(&levels), None);
+    let levels = vec![(12, 300), (13, 300), (15, 1000)];
+    assert_eq!(s.to_compact_levels(&levels), Some((vec![12, 13], 15)));
+    let levels = vec![(12, 200), (13, 300), (15, 10000)];
+    assert_eq!(s.to_compact_levels(&levels), Some((vec![12], 13)));
+}
+
+#[test]
+fn test_leveled_compaction() {
+    let s: LeveledCompaction = Default::default();
+    assert_eq!(s.fanout, LeveledCompaction::FANOUT);
+    assert!(s.is_new_level(100, 1000));
+    assert!(!s.is_new_level(100, 999));
+
+    assert_eq!(s.to_compact_levels(&[(15, 1000)]), None);
+    let levels = vec![(12, 10), (13, 100), (15, 1000)];
+    assert_eq!(s.to_compact_levels(&levels), None);
+    let levels = vec![(12, 10), (13, 200), (15, 1000)];
+    assert_eq!(s.to_compact_levels(&levels), Some((vec![13], 15)));
+    let levels = vec![(12, 20), (13, 100), (15, 100)];
+    assert_eq!(s.to_compact_levels(&levels), Some((vec![12], 13)));
+}
+
+#[test]
+fn test_tiered_compaction() {
+    let s: TieredCompaction = Default::default();
+    assert_eq!(s.bucket_ratio, TieredCompaction::BUCKET_RATIO);
+    assert_eq!(s.min_levels, TieredCompaction::MIN_LEVELS);
+    assert!(s.is_new_level(1000, 10));
+
+    let levels = vec![(10, 100), (11, 150), (12, 120), (15, 1000)];
+    assert_eq!(s.to_compact_levels(&levels), None);
+    let levels = vec![(9, 100), (10, 100), (11, 150), (12, 120), (15, 1000)];
+    assert_eq!(s.to_compact_levels(&levels), Some((vec![9, 10, 11], 12)));
+    let levels = vec![
+        (8, 10),
+        (9, 900),
+        (10, 1000),
+        (11, 1500),
+        (12, 1200),
+        (13, 1700),
+        (15, 8000),
+    ];
+    assert_eq!(
+        s.to_compact_levels(&levels),
+        Some((vec![9, 10, 11, 12], 13))
+    );
+}
+
+#[test]
+fn test_compaction() {
+    assert_eq!(Compaction::default(), Compaction::Ratio);
+    assert_eq!(Compaction::Ratio.to_string(), "ratio");
+    let c = Compaction::Leveled(Default::default());
+    assert_eq!(c.to_string(), "leveled");
+    let c = Compaction::Tiered(Default::default());
+    assert_eq!(c.to_string(), "tiered");
+}
+
+#[test]
+fn test_custom_compaction() {
+    let strategy = Arc::new(LeveledCompaction { fanout: 4.0 });
+    let c = CustomCompaction::new("my-leveled", strategy);
+    assert_eq!(c.to_name(), "my-leveled");
+    let s = c.to_strategy().unwrap();
+    assert!(s.is_new_level(100, 400));
+    assert!(!s.is_new_level(101, 400));
+
+    // strategies are identified by name.
+    let d = CustomCompaction::from_name("my-leveled");
+    assert!(d.to_strategy().is_none());
+    assert_eq!(c, d);
+    assert_ne!(c, CustomCompaction::from_name("other"));
+
+    let c = Compaction::Custom(c);
+    assert_eq!(c.to_string(), "custom");
+}
@@ -1,9 +1,11 @@
 use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
 
 use crate::nobitmap::NoBitmap;
+use crate::xorfilter::Xor8;
 use crate::{
     mvcc::{self, MvccFactory},
     robt::{self, RobtFactory},
+    shllrb, skiplist,
 };
 
 use std::convert;
@@ -16,6 +18,20 @@ fn test_config_root() {
     let mut rng = SmallRng::from_seed(seed.to_le_bytes());
 
     let m0_limit = if rng.gen::<bool>() { Some(1000) } else { None };
+    let cache_limit = if rng.gen::<bool>() { Some(10000) } else { None };
+    let rate_limit = if rng.gen::<bool>() { Some(1000) } else { None };
+    let write_mode = if rng.gen::<bool>() {
+        WriteMode::Blind
+    } else {
+        WriteMode::ReadBeforeWrite
+    };
+    let wal_dir: Option<ffi::OsString> = if rng.gen::<bool>() {
+        Some("/tmp/dgm-wal".into())
+    } else {
+        None
+    };
+    let wal_fsync: bool = rng.gen();
+    let wal_batch_size: usize = (rng.gen::<usize>() % 128) + 1;
 
     let ref_root = Root {
         version: 0,
@@ -29,6 +45,14 @@ fn test_config_root() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        cache_limit,
+        cache_policy: CachePolicy::Lfu,
+        compaction: Compaction::Leveled(Default::default()),
+        rate_limit,
+        write_mode,
+        wal_dir: wal_dir.clone(),
+        wal_fsync,
+        wal_batch_size,
     };
 
     let ref_config = Config {
@@ -38,6 +62,14 @@ fn test_config_root() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        cache_limit,
+        cache_policy: CachePolicy::Lfu,
+        compaction: Compaction::Leveled(Default::default()),
+        rate_limit,
+        write_mode,
+        wal_dir: wal_dir.clone(),
+        wal_fsync,
+        wal_batch_size,
     };
     let root = ref_config.clone().into();
     assert_eq!(ref_root, root);
@@ -55,10 +87,34 @@ fn test_root1() {
         None,
         Some(Bound::Included(101)),
         Some(Bound::Excluded(1001)),
+        Some(Bound::Included(2001)),
     ];
     let m0_limit = if rng.gen::<bool>() { Some(1000) } else { None };
+    let cache_limit = if rng.gen::<bool>() { Some(10000) } else { None };
+    let wal_dir: Option<ffi::OsString> = if rng.gen::<bool>() {
+        Some("/tmp/dgm-wal".into())
+    } else {
+        None
+    };
+
+    let compactions = vec![
+        Compaction::Ratio,
+        Compaction::Leveled(Default::default()),
+        Compaction::Tiered(TieredCompaction {
+            bucket_ratio: 1.5,
+            min_levels: 3,
+        }),
+        Compaction::Custom(CustomCompaction::new(
+            "my-leveled",
+            Arc::new(LeveledCompaction { fanout: 4.0 }),
+        )),
+    ];
+
+    // zero rate, not throttled, shall round-trip as well.
+    let rate_limits = vec![Some(1024 * 1024), Some(0), None, Some(1)];
 
-    for cutoff in cutoffs.into_iter() {
+    let iter = cutoffs.into_iter().zip(compactions.into_iter());
+    for ((cutoff, compaction), rate_limit) in iter.zip(rate_limits.into_iter()) {
         let ref_root = Root {
             version: 0,
             levels: Config::NLEVELS,
@@ -71,6 +127,14 @@ fn test_root1() {
             disk_ratio: 0.65,
             commit_interval: Some(time::Duration::from_secs(10)),
             compact_interval: Some(time::Duration::from_secs(10)),
+            cache_limit,
+            cache_policy: CachePolicy::Lfu,
+            compaction,
+            rate_limit,
+            write_mode: WriteMode::Blind,
+            wal_dir: wal_dir.clone(),
+            wal_fsync: true,
+            wal_batch_size: 64,
         };
         let bytes: Vec<u8> = ref_root.clone().try_into().unwrap();
         println!("{:?}", std::str::from_utf8(&bytes));
@@ -93,6 +157,14 @@ fn test_root2() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        cache_limit: None,
+        cache_policy: CachePolicy::Lru,
+        compaction: Default::default(),
+        rate_limit: None,
+        write_mode: Default::default(),
+        wal_dir: None,
+        wal_fsync: false,
+        wal_batch_size: Config::WAL_BATCH_SIZE,
     };
     let root = root.to_next();
     let ref_root = Root {
@@ -107,6 +179,14 @@ fn test_root2() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        cache_limit: None,
+        cache_policy: CachePolicy::Lru,
+        compaction: Default::default(),
+        rate_limit: None,
+        write_mode: Default::default(),
+        wal_dir: None,
+        wal_fsync: false,
+        wal_batch_size: Config::WAL_BATCH_SIZE,
     };
     assert_eq!(root, ref_root);
 }
@@ -125,6 +205,14 @@ fn test_root3() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        cache_limit: None,
+        cache_policy: CachePolicy::Lru,
+        compaction: Default::default(),
+        rate_limit: None,
+        write_mode: Default::default(),
+        wal_dir: None,
+        wal_fsync: false,
+        wal_batch_size: Config::WAL_BATCH_SIZE,
     };
 
     let cutoffs = vec![
@@ -268,6 +356,26 @@ fn test_dgm_crud() {
         disk_ratio: 0.5,
         commit_interval: None,
         compact_interval: None,
+        cache_limit: if rng.gen::<bool>() {
+            Some(16 * 1000)
+        } else {
+            None
+        },
+        cache_policy: if rng.gen::<bool>() {
+            CachePolicy::Lru
+        } else {
+            CachePolicy::Lfu
+        },
+        compaction: match rng.gen::<u8>() % 3 {
+            0 => Compaction::Ratio,
+            1 => Compaction::Leveled(Default::default()),
+            _ => Compaction::Tiered(Default::default()),
+        },
+        rate_limit: None,
+        write_mode: WriteMode::ReadBeforeWrite,
+        wal_dir: None,
+        wal_fsync: false,
+        wal_batch_size: Config::WAL_BATCH_SIZE,
     };
 
     println!("seed: {}", seed);
@@ -322,7 +430,8 @@ fn test_dgm_crud() {
                             check_entry1(&entry, &refn);
                         }
                         (None, None) => (),
-                        _ => (),
+                        (Some(_), None) => panic!("unexpected old entry {}", key),
+                        (None, Some(_)) => panic!("missing old entry {}", key),
                     }
                     false
                 }
@@ -345,7 +454,8 @@ fn test_dgm_crud() {
                             check_entry1(&entry, &refn);
                         }
                         (None, None) => (),
-                        _ => (),
+                        (Some(_), None) => panic!("unexpected old entry {}", key),
+                        (None, Some(_)) => panic!("missing old entry {}", key),
                     }
                     false
                 }
@@ -357,7 +467,8 @@ fn test_dgm_crud() {
                             check_entry1(&entry, &refn);
                         }
                         (None, None) => (),
-                        _ => (),
+                        (Some(_), None) => panic!("unexpected old entry {}", key),
+                        (None, Some(_)) => panic!("missing old entry {}", key),
                     }
                     true
                 }
@@ -367,7 +478,8 @@ fn test_dgm_crud() {
         mem::drop(index_w);
         mem::drop(index_r);
 
-        index.validate().unwrap();
+        let stats: Stats<mvcc::Stats, robt::Stats> = index.validate().unwrap();
+        check_stats(&stats, &index.to_stats().unwrap());
         // println!("seqno {}", ref_index.to_seqno().unwrap());
 
         verify_read(key_max, &mut ref_index, &mut index, &mut rng);
@@ -451,6 +563,14 @@ fn test_dgm_non_lsm() {
         disk_ratio: 0.5,
         commit_interval: None,
         compact_interval: None,
+        cache_limit: None,
+        cache_policy: Default::default(),
+        compaction: Default::default(),
+        rate_limit: None,
+        write_mode: WriteMode::ReadBeforeWrite,
+        wal_dir: None,
+        wal_fsync: false,
+        wal_batch_size: Config::WAL_BATCH_SIZE,
     };
 
     let dir = {
@@ -523,6 +643,717 @@ fn test_dgm_non_lsm() {
     index.validate().unwrap();
 }
 
+#[test]
+fn test_dgm_custom_compaction() {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+    struct Counted {
+        n_calls: Arc<AtomicUsize>,
+        inner: LeveledCompaction,
+    }
+
+    impl CompactionStrategy for Counted {
+        fn is_new_level(&self, mem_footprint: isize, disk_footprint: isize) -> bool {
+            self.n_calls.fetch_add(1, SeqCst);
+            self.inner.is_new_level(mem_footprint, disk_footprint)
+        }
+
+        fn to_compact_levels(&self, levels: &[(usize, isize)]) -> Option<(Vec<usize>, usize)> {
+            self.n_calls.fetch_add(1, SeqCst);
+            self.inner.to_compact_levels(levels)
+        }
+    }
+
+    let n_calls = Arc::new(AtomicUsize::new(0));
+    let new_compaction = |name: &str| {
+        let strategy = Counted {
+            n_calls: Arc::clone(&n_calls),
+            inner: Default::default(),
+        };
+        Compaction::Custom(CustomCompaction::new(name, Arc::new(strategy)))
+    };
+
+    let (mut config, dir, mem_factory, disk_factory) =
+        new_fixture::<NoBitmap>("test-dgm-custom-compaction");
+    config.set_compaction(new_compaction("counted")).unwrap();
+    let mut index = Dgm::new(&dir, "dgm-custom", mem_factory, disk_factory, config).unwrap();
+
+    for round in 0..3 {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..1000 {
+            index_w.set(key, key + round).unwrap();
+        }
+        mem::drop(index_w);
+        index
+            .commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+        index.compact(Cutoff::new_lsm_empty()).unwrap();
+    }
+    assert!(n_calls.load(SeqCst) > 0);
+    index.validate().unwrap();
+    mem::drop(index);
+
+    // strategy shall be supplied, by the same name, when re-opening.
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    let disk_factory = new_disk_factory::<NoBitmap>();
+    let res = Dgm::open(&dir, "dgm-custom", mem_factory, disk_factory);
+    assert!(res.is_err());
+
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    let compaction = new_compaction("other");
+    let res = Dgm::open_with_compaction(
+        &dir,
+        "dgm-custom",
+        mem_factory,
+        new_disk_factory::<NoBitmap>(),
+        compaction,
+    );
+    assert!(res.is_err());
+
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    let compaction = new_compaction("counted");
+    let mut index = Dgm::open_with_compaction(
+        &dir,
+        "dgm-custom",
+        mem_factory,
+        new_disk_factory::<NoBitmap>(),
+        compaction,
+    )
+    .unwrap();
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..1000 {
+        let entry = index_r.get(&key).unwrap();
+        assert_eq!(entry.to_native_value(), Some(key + 2));
+    }
+    mem::drop(index_r);
+    index.validate().unwrap();
+}
+
+#[test]
+fn test_dgm_skiplist() {
+    let (config, dir, _, disk_factory) = new_fixture::<NoBitmap>("test-dgm-skiplist");
+    let mem_factory = skiplist::skiplist_factory(true /*lsm*/);
+    let mut index = Dgm::new(&dir, "dgm-skiplist", mem_factory, disk_factory, config).unwrap();
+
+    let n_keys = 2000;
+    for round in 0..3 {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..n_keys {
+            match key % 7 {
+                0 => index_w.delete(&key).unwrap(),
+                _ => index_w.set(key, (key * 10) + round).unwrap(),
+            };
+        }
+        mem::drop(index_w);
+        index
+            .commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+    }
+
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..n_keys {
+        let entry = index_r.get(&key).unwrap();
+        match key % 7 {
+            0 => assert!(entry.is_deleted(), "key {}", key),
+            _ => assert_eq!(entry.to_native_value(), Some((key * 10) + 2)),
+        }
+    }
+    mem::drop(index_r);
+
+    let stats: Stats<skiplist::Stats, robt::Stats> = index.to_stats().unwrap();
+    println!("{}", stats);
+    index.validate().unwrap();
+}
+
+#[test]
+fn test_dgm_cache() {
+    let (mut config, dir, mem_factory, disk_factory) = new_fixture::<NoBitmap>("test-dgm-cache");
+    config
+        .set_cache_limit(16 * 1000)
+        .unwrap()
+        .set_cache_policy(CachePolicy::Lfu)
+        .unwrap();
+    let mut index = Dgm::new(&dir, "dgm-cache", mem_factory, disk_factory, config).unwrap();
+
+    let n_keys = 2000;
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..n_keys {
+        index_w.set(key, key * 10).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+
+    // first round of lookups populate the cache, and second round
+    // is served from the cache.
+    let mut index_w = index.to_writer().unwrap();
+    let mut index_r = index.to_reader().unwrap();
+    for _ in 0..2 {
+        for key in 0..100 {
+            let entry = index_r.get(&key).unwrap();
+            assert_eq!(entry.to_native_value(), Some(key * 10));
+        }
+    }
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert_eq!(stats.n_cache_entries, 100);
+    assert_eq!(stats.cache_footprint, 1600);
+    assert_eq!((stats.n_cache_hits, stats.n_cache_misses), (100, 100));
+    assert_eq!(stats.n_cache_evictions, 0);
+    assert_eq!(stats.to_cache_hit_ratio(), 0.5);
+
+    // writes shall invalidate the cached entry.
+    index_w.set(10, 1).unwrap();
+    index_w.set(20, 2).unwrap();
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert_eq!(stats.n_cache_entries, 98);
+    assert_eq!(index_r.get(&10).unwrap().to_native_value(), Some(1));
+    assert_eq!(index_r.get(&20).unwrap().to_native_value(), Some(2));
+
+    // cache is bounded by its footprint, each shard holding its share.
+    for key in 0..n_keys {
+        index_r.get(&key).unwrap();
+    }
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    let n_entries = CACHE_SHARDS * ((16 * 1000 / CACHE_SHARDS) / 16);
+    assert_eq!(stats.n_cache_entries, n_entries);
+    assert_eq!(stats.cache_footprint, n_entries * 16);
+    assert!(stats.n_cache_evictions > 0);
+    println!("{}", stats);
+
+    mem::drop(index_w);
+    mem::drop(index_r);
+    index.validate().unwrap();
+}
+
+#[test]
+fn test_dgm_bloom() {
+    let (config, dir, mem_factory, disk_factory) = new_fixture::<Xor8>("test-dgm-bloom");
+    let mut index = Dgm::new(&dir, "dgm-bloom", mem_factory, disk_factory, config).unwrap();
+
+    let n_keys = 1000;
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..n_keys {
+        index_w.set(key * 2, key).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+
+    // bloom filter has no false negatives.
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..n_keys {
+        let entry = index_r.get(&(key * 2)).unwrap();
+        assert_eq!(entry.to_native_value(), Some(key));
+    }
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert_eq!((stats.n_bloom_skips, stats.n_bloom_false_positives), (0, 0));
+    let n_levels = stats.to_levels();
+
+    // missing keys are mostly skipped by the bloom filter.
+    for key in 0..n_keys {
+        match index_r.get(&(key * 2 + 1)) {
+            Err(Error::KeyNotFound) => (),
+            Err(err) => panic!("unexpected {:?}", err),
+            Ok(_) => panic!("unexpected entry for {}", key * 2 + 1),
+        }
+    }
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    let (n_skips, n_fps) = (stats.n_bloom_skips, stats.n_bloom_false_positives);
+    assert_eq!(n_skips + n_fps, (n_keys as usize) * n_levels);
+    assert!(n_skips > n_fps * 10, "{}", stats);
+
+    // and so are read-before-write lookups.
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..100 {
+        assert!(index_w.set(key * 2 + 1, key).unwrap().is_none());
+    }
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    let n = stats.n_bloom_skips + stats.n_bloom_false_positives;
+    assert_eq!(n, n_skips + n_fps + (100 * n_levels));
+    println!("{}", stats);
+
+    mem::drop(index_w);
+    mem::drop(index_r);
+    index.validate().unwrap();
+
+    // levels without bloom filter are neither skipped nor counted.
+    let (config, dir, mem_factory, disk_factory) = new_fixture::<NoBitmap>("test-dgm-nobloom");
+    let mut index = Dgm::new(&dir, "dgm-nobloom", mem_factory, disk_factory, config).unwrap();
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..n_keys {
+        index_w.set(key * 2, key).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..n_keys {
+        assert!(index_r.get(&(key * 2 + 1)).is_err());
+    }
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert_eq!((stats.n_bloom_skips, stats.n_bloom_false_positives), (0, 0));
+    mem::drop(index_r);
+}
+
+#[test]
+fn test_dgm_write_mode() {
+    let (mut config, dir, mem_factory, disk_factory) =
+        new_fixture::<NoBitmap>("test-dgm-write-mode");
+    config.set_lsm(false).unwrap();
+    assert_eq!(config.write_mode, WriteMode::ReadBeforeWrite);
+    let mut index = Dgm::new(&dir, "dgm-write-mode", mem_factory, disk_factory, config).unwrap();
+
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..100 {
+        assert!(index_w.set(key, key * 10).unwrap().is_none());
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+
+    // old values are looked up in disk levels.
+    let mut index_w = index.to_writer().unwrap();
+    assert_eq!(index_w.to_write_mode().unwrap(), WriteMode::ReadBeforeWrite);
+    let old = index_w.set(10, 1).unwrap().unwrap();
+    assert_eq!(old.to_native_value(), Some(100));
+    assert_eq!(old.to_seqno(), 11);
+    let old = index_w.set(10, 2).unwrap().unwrap();
+    assert_eq!(old.to_native_value(), Some(1));
+    assert_eq!(old.to_seqno(), 101);
+    let old = index_w.delete(&20).unwrap().unwrap();
+    assert_eq!(old.to_native_value(), Some(200));
+    assert!(index_w.delete(&1000).unwrap().is_none());
+
+    // cas is validated against the seqno in disk levels.
+    match index_w.set_cas(30, 3, 0) {
+        Err(Error::InvalidCAS(31)) => (),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid cas"),
+    }
+    let old = index_w.set_cas(30, 3, 31).unwrap().unwrap();
+    assert_eq!(old.to_native_value(), Some(300));
+    assert!(index_w.set_cas(2000, 1, 0).unwrap().is_none());
+    let old = index_w.set_cas(20, 4, 0).unwrap().unwrap();
+    assert!(old.is_deleted());
+
+    // blind writes skip the lookup, while cas is still validated.
+    index_w.set_write_mode(WriteMode::Blind).unwrap();
+    assert_eq!(index_w.to_write_mode().unwrap(), WriteMode::Blind);
+    assert!(index_w.set(40, 4).unwrap().is_none());
+    assert!(index_w.delete(&50).unwrap().is_none());
+    let old = index_w.set_cas(60, 6, 61).unwrap().unwrap();
+    assert_eq!(old.to_native_value(), Some(600));
+
+    let mut index_r = index.to_reader().unwrap();
+    assert_eq!(index_r.get(&10).unwrap().to_native_value(), Some(2));
+    assert_eq!(index_r.get(&20).unwrap().to_native_value(), Some(4));
+    assert_eq!(index_r.get(&40).unwrap().to_native_value(), Some(4));
+    assert!(index_r.get(&50).unwrap().is_deleted());
+
+    mem::drop(index_w);
+    mem::drop(index_r);
+    index.validate().unwrap();
+}
+
+#[test]
+fn test_dgm_set_cas_concurrent() {
+    let (mut config, dir, _, disk_factory) = new_fixture::<NoBitmap>("test-dgm-set-cas-concurrent");
+    config.set_lsm(false).unwrap();
+    let mem_factory = mvcc::mvcc_factory(false /*lsm*/);
+    let mut index = Dgm::new(&dir, "dgm-set-cas", mem_factory, disk_factory, config).unwrap();
+
+    // counters start from disk levels.
+    let (n_keys, n_threads, n_incrs) = (4, 4, 200);
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..n_keys {
+        index_w.set(key, 0).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+
+    let mut handles = vec![];
+    for _ in 0..n_threads {
+        let mut r = index.to_reader().unwrap();
+        let mut w = index.to_writer().unwrap();
+        handles.push(thread::spawn(move || {
+            for i in 0..(n_incrs * n_keys) {
+                let key = i % n_keys;
+                loop {
+                    let entry = r.get(&key).unwrap();
+                    let value = entry.to_native_value().unwrap();
+                    match w.set_cas(key, value + 1, entry.to_seqno()) {
+                        Ok(_) => break,
+                        Err(Error::InvalidCAS(_)) => continue,
+                        Err(err) => panic!("unexpected {:?}", err),
+                    }
+                }
+            }
+        }));
+    }
+    for handle in handles.into_iter() {
+        handle.join().unwrap();
+    }
+
+    // every increment shall be applied exactly once.
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..n_keys {
+        let value = index_r.get(&key).unwrap().to_native_value();
+        assert_eq!(value, Some(n_threads * n_incrs));
+    }
+    mem::drop(index_r);
+    index.validate().unwrap();
+}
+
+#[test]
+fn test_dgm_rate_limit() {
+    let (mut config, dir, mem_factory, disk_factory) =
+        new_fixture::<NoBitmap>("test-dgm-rate-limit");
+    assert!(config.set_rate_limit(0).is_err());
+    let mut index = Dgm::new(&dir, "dgm-rate", mem_factory, disk_factory, config).unwrap();
+
+    let n_keys = 2000;
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..n_keys {
+        index_w.set(key, key * 10).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert_eq!(stats.throttle_time, 0);
+    let n_written_bytes = stats.n_written_bytes;
+
+    // throttle the next commit at runtime.
+    index.set_rate_limit(Some(n_written_bytes / 2)).unwrap();
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..n_keys {
+        index_w.set(key, key * 100).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let stats: Stats<mvcc::Stats, robt::Stats> = index.to_stats().unwrap();
+    assert!(stats.throttle_time > 0, "{}", stats);
+    println!("{}", stats);
+
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..n_keys {
+        let entry = index_r.get(&key).unwrap();
+        assert_eq!(entry.to_native_value(), Some(key * 100));
+    }
+    mem::drop(index_r);
+    index.validate().unwrap();
+    mem::drop(index);
+
+    // rate limit is persisted along with the index.
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    let disk_factory = new_disk_factory::<NoBitmap>();
+    let index = Dgm::open(&dir, "dgm-rate", mem_factory, disk_factory).unwrap();
+    let rate_limit = index.as_inner().unwrap().root.rate_limit;
+    assert_eq!(rate_limit, Some(n_written_bytes / 2));
+    index.set_rate_limit(None).unwrap();
+    let rate_limit = index.as_inner().unwrap().root.rate_limit;
+    assert_eq!(rate_limit, None);
+}
+
+#[test]
+fn test_dgm_checkpoint() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let (config, dir, mem_factory, disk_factory) = new_fixture::<NoBitmap>("test-dgm-checkpoint");
+    let cp_dir = {
+        let mut cp_dir = std::env::temp_dir();
+        cp_dir.push("test-dgm-checkpoint-cp");
+        cp_dir.into_os_string()
+    };
+    fs::remove_dir_all(&cp_dir).ok();
+    let mut index = Dgm::new(&dir, "dgm-cp", mem_factory, disk_factory, config).unwrap();
+    let mut ref_index = mvcc::Mvcc::new_lsm("dgm-cp");
+
+    let key_max = 3_000;
+    for i in 0..4 {
+        let mut index_w = index.to_writer().unwrap();
+        for _ in 0..1_000 {
+            let key: i64 = rng.gen::<i64>().abs() % key_max;
+            let value: i64 = rng.gen::<i64>().abs();
+            if rng.gen::<u8>() % 4 == 0 {
+                index_w.delete(&key).unwrap();
+                ref_index.delete(&key).unwrap();
+            } else {
+                index_w.set(key, value).unwrap();
+                ref_index.set(key, value).unwrap();
+            }
+        }
+        mem::drop(index_w);
+        // leave the last batch in memory.
+        if i < 3 {
+            index
+                .commit(CommitIter::new_empty(), convert::identity)
+                .unwrap();
+        }
+        if i == 1 {
+            index.compact(Cutoff::new_lsm_empty()).unwrap();
+        }
+    }
+
+    // checkpoint shall not overlap index dir, nor clobber a directory.
+    let sub_dir = {
+        let mut sub_dir = path::PathBuf::from(&dir);
+        sub_dir.push("cp");
+        sub_dir.into_os_string()
+    };
+    let parent_dir = std::env::temp_dir().into_os_string();
+    let alias_dir = {
+        let mut alias_dir = path::PathBuf::from(&dir);
+        alias_dir.push(".");
+        alias_dir.into_os_string()
+    };
+    for cdir in vec![&dir, &sub_dir, &parent_dir, &alias_dir].into_iter() {
+        match index.checkpoint(cdir) {
+            Err(Error::InvalidInput(_)) => (),
+            res => panic!("unexpected {:?}", res),
+        }
+    }
+    assert!(!path::Path::new(&sub_dir).exists());
+    let full_dir = {
+        let mut full_dir = std::env::temp_dir();
+        full_dir.push("test-dgm-checkpoint-full");
+        fs::create_dir_all(&full_dir).unwrap();
+        full_dir.push("file");
+        fs::write(&full_dir, b"data").unwrap();
+        full_dir.pop();
+        full_dir.into_os_string()
+    };
+    match index.checkpoint(&full_dir) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    let seqno = index.checkpoint(&cp_dir).unwrap();
+    assert_eq!(seqno, ref_index.to_seqno().unwrap());
+
+    // writes after checkpoint are not visible in the checkpoint, and
+    // files linked into the checkpoint survive compaction.
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..100 {
+        index_w.set(key, key).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    index.compact(Cutoff::new_lsm_empty()).unwrap();
+    index.validate().unwrap();
+
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    let disk_factory = new_disk_factory::<NoBitmap>();
+    let mut cp_index = Dgm::open(&cp_dir, "dgm-cp", mem_factory, disk_factory).unwrap();
+    assert_eq!(cp_index.to_seqno().unwrap(), seqno);
+    verify_read(key_max, &mut ref_index, &mut cp_index, &mut rng);
+    cp_index.validate().unwrap();
+}
+
+#[test]
+fn test_dgm_wal() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let wal_dir = {
+        let mut wal_dir = std::env::temp_dir();
+        wal_dir.push("test-dgm-wal-log");
+        wal_dir.into_os_string()
+    };
+    fs::remove_dir_all(&wal_dir).ok();
+
+    let (mut config, dir, mem_factory, disk_factory) = new_fixture::<NoBitmap>("test-dgm-wal");
+    config.set_wal(&wal_dir, false /*fsync*/).unwrap();
+    let mut index = Dgm::new(&dir, "dgm-wal", mem_factory, disk_factory, config).unwrap();
+    let mut ref_index = mvcc::Mvcc::new_lsm("dgm-wal");
+
+    let key_max = 3_000;
+    // i == 0, crash before the first commit.
+    // i == 1, crash with committed and un-committed entries.
+    // i == 2, crash with all entries committed.
+    for i in 0..3 {
+        let mut index_w = index.to_writer().unwrap();
+        for _ in 0..1_000 {
+            let key: i64 = rng.gen::<i64>().abs() % key_max;
+            let value: i64 = rng.gen::<i64>().abs();
+            if rng.gen::<u8>() % 4 == 0 {
+                index_w.delete(&key).unwrap();
+                ref_index.delete(&key).unwrap();
+            } else {
+                index_w.set(key, value).unwrap();
+                ref_index.set(key, value).unwrap();
+            }
+        }
+        mem::drop(index_w);
+        if i > 0 {
+            index
+                .commit(CommitIter::new_empty(), convert::identity)
+                .unwrap();
+        }
+        if i == 1 {
+            let mut index_w = index.to_writer().unwrap();
+            for key in 0..100 {
+                index_w.set(key, key).unwrap();
+                ref_index.set(key, key).unwrap();
+            }
+        }
+
+        // drop without closing the index.
+        mem::drop(index);
+
+        let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+        let disk_factory = new_disk_factory::<NoBitmap>();
+        index = Dgm::open(&dir, "dgm-wal", mem_factory, disk_factory).unwrap();
+        verify_read(key_max, &mut ref_index, &mut index, &mut rng);
+        index.validate().unwrap();
+    }
+
+    index.close().unwrap();
+}
+
+#[test]
+fn test_dgm_wal_commit() {
+    let wal_dir = {
+        let mut wal_dir = std::env::temp_dir();
+        wal_dir.push("test-dgm-wal-commit-log");
+        wal_dir.into_os_string()
+    };
+    fs::remove_dir_all(&wal_dir).ok();
+
+    let (mut config, dir, _, disk_factory) = new_fixture::<NoBitmap>("test-dgm-wal-commit");
+    config.set_wal(&wal_dir, false /*fsync*/).unwrap();
+    let mem_factory = shllrb::shllrb_factory(true /*lsm*/, 4 /*max_shards*/);
+    let mut index = Dgm::new(&dir, "dgm-wal-cmt", mem_factory, disk_factory, config).unwrap();
+
+    // entries from another index cannot be committed, log cannot
+    // preserve their seqno.
+    let mut other = mvcc::Mvcc::new_lsm("other");
+    for key in 0..1000 {
+        other.set(key, key * 10).unwrap();
+    }
+    let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+    match index.commit(CommitIter::new(other.as_mut(), within), convert::identity) {
+        Err(Error::APIMisuse(_)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    // writes after the commit shall be replayed from the log.
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..1000 {
+        index_w.set(key, key).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let mut index_w = index.to_writer().unwrap();
+    for key in 1000..1100 {
+        index_w.set(key, key).unwrap();
+    }
+    for key in 0..100 {
+        index_w.delete(&key).unwrap();
+    }
+    mem::drop(index_w);
+
+    // drop without closing the index.
+    mem::drop(index);
+
+    let mem_factory = shllrb::shllrb_factory(true /*lsm*/, 4 /*max_shards*/);
+    let disk_factory = new_disk_factory::<NoBitmap>();
+    let mut index = Dgm::open(&dir, "dgm-wal-cmt", mem_factory, disk_factory).unwrap();
+
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..1100 {
+        let entry = index_r.get(&key).unwrap();
+        match key {
+            key if key < 100 => assert!(entry.is_deleted(), "key {}", key),
+            key => assert_eq!(entry.to_native_value(), Some(key)),
+        }
+    }
+    mem::drop(index_r);
+
+    index.validate().unwrap();
+    index.close().unwrap();
+}
+
+#[test]
+fn test_dgm_compact_range() {
+    let (mut config, dir, mem_factory, disk_factory) =
+        new_fixture::<NoBitmap>("test-dgm-compact-range");
+    // every commit shall go into a new level.
+    config.set_mem_ratio(1.0).unwrap();
+    let mut index = Dgm::new(&dir, "dgm-range", mem_factory, disk_factory, config).unwrap();
+
+    // older level, with tombstones for 200..300
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..1000 {
+        index_w.set(key, key * 10).unwrap();
+    }
+    for key in 200..300 {
+        index_w.delete(&key).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    // newer level, with tombstones for 300..400
+    let mut index_w = index.to_writer().unwrap();
+    for key in 300..400 {
+        index_w.delete(&key).unwrap();
+    }
+    mem::drop(index_w);
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+
+    let levels: Vec<usize> = {
+        let inn = index.as_inner().unwrap();
+        let iter = inn.disks.iter().enumerate();
+        iter.filter_map(|(l, d)| if d.is_active() { Some(l) } else { None })
+            .collect()
+    };
+    assert_eq!(levels.len(), 2);
+
+    assert!(index.compact_range(200..400, Cutoff::Mono).is_err());
+
+    // tombstones in the newer level shadow values in the older level,
+    // hence they are not purged.
+    let cutoff = Cutoff::new_tombstone(Bound::Unbounded);
+    let res = index.compact_range(200..400, cutoff).unwrap();
+    assert_eq!(res, vec![(levels[0], 0), (levels[1], 100)]);
+
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..1000 {
+        match index_r.get(&key) {
+            Ok(entry) if (300..400).contains(&key) => assert!(entry.is_deleted()),
+            Ok(entry) => assert_eq!(entry.to_native_value(), Some(key * 10)),
+            Err(Error::KeyNotFound) => assert!((200..300).contains(&key)),
+            Err(err) => panic!("unexpected {:?}", err),
+        }
+    }
+
+    // nothing left to purge.
+    let res = index.compact_range(200..400, cutoff).unwrap();
+    assert_eq!(res, vec![(levels[0], 0), (levels[1], 0)]);
+
+    mem::drop(index_r);
+    index.validate().unwrap();
+}
+
 #[test]
 fn test_dgm_cutoffs() {
     let seed: u128 = {
@@ -544,6 +1375,14 @@ fn test_dgm_cutoffs() {
         disk_ratio: 0.5,
         commit_interval: None,
         compact_interval: None,
+        cache_limit: None,
+        cache_policy: Default::default(),
+        compaction: Default::default(),
+        rate_limit: None,
+        write_mode: WriteMode::ReadBeforeWrite,
+        wal_dir: None,
+        wal_fsync: false,
+        wal_batch_size: Config::WAL_BATCH_SIZE,
     };
 
     let dir = {
@@ -626,6 +1465,32 @@ fn test_dgm_cutoffs() {
     index.validate().unwrap();
 }
 
+// fixture for dgm tests, return lsm config with auto commit and
+// compaction disabled, directory `name` under temp-dir, mvcc factory and
+// robt factory.
+fn new_fixture<B>(name: &str) -> (Config, ffi::OsString, MvccFactory, RobtFactory<i64, i64, B>) {
+    let mut config: Config = Default::default();
+    config.set_lsm(true).unwrap();
+    config.commit_interval = None;
+    config.compact_interval = None;
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    (config, dir, mem_factory, new_disk_factory())
+}
+
+// robt factory with deltas and values persisted in value-log.
+fn new_disk_factory<B>() -> RobtFactory<i64, i64, B> {
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+    robt::robt_factory::<i64, i64, B>(config)
+}
+
 fn verify_read(
     key_max: i64,
     ref_index: &mut mvcc::Mvcc<i64, i64>,
@@ -792,3 +1657,44 @@ fn random_low_high(rng: &mut SmallRng) -> (Bound<i64>, Bound<i64>) {
     //println!("low_high {:?} {:?}", low, high);
     (low, high)
 }
+
+fn check_stats(
+    validate: &Stats<mvcc::Stats, robt::Stats>,
+    stats: &Stats<mvcc::Stats, robt::Stats>,
+) {
+    assert_eq!(validate.name, stats.name);
+    assert_eq!(validate.to_levels(), stats.to_levels());
+    assert_eq!(validate.mem_footprint, stats.mem_footprint);
+    assert_eq!(validate.disk_footprint, stats.disk_footprint);
+    assert_eq!(validate.n_commits, stats.n_commits);
+    assert_eq!(validate.n_compacts, stats.n_compacts);
+    assert_eq!(validate.n_high_compacts, stats.n_high_compacts);
+    assert_eq!(validate.m0.entries, stats.m0.entries);
+
+    let mut footprint = 0;
+    for (x, y) in validate.disks.iter().zip(stats.disks.iter()) {
+        assert_eq!(x.level, y.level);
+        assert_eq!(x.footprint, y.footprint);
+        assert_eq!((x.from_seqno, x.to_seqno), (y.from_seqno, y.to_seqno));
+        assert!(y.from_seqno <= y.to_seqno, "{}", y);
+        assert_eq!(x.stats.n_count, y.stats.n_count);
+        footprint += y.footprint;
+    }
+    assert_eq!(footprint, stats.disk_footprint);
+    for w in stats.disks.windows(2) {
+        assert!(w[0].level < w[1].level, "{} {}", w[0].level, w[1].level);
+        assert_eq!(w[0].from_seqno, w[1].to_seqno);
+    }
+
+    if stats.n_commits > 0 {
+        assert!(stats.n_committed_bytes > 0, "{}", stats);
+        assert!(stats.n_written_bytes > 0, "{}", stats);
+        assert!(stats.to_write_amplification() > 0.0, "{}", stats);
+    }
+    let n_levels = format!(r#""n_levels": {}"#, stats.to_levels());
+    assert!(stats.to_json().contains(&n_levels));
+
+    assert_eq!(stats.cache_footprint == 0, stats.n_cache_entries == 0);
+    assert!(stats.cache_footprint <= 16 * 1000, "{}", stats);
+    assert!(stats.to_cache_hit_ratio() <= 1.0, "{}", stats);
+}
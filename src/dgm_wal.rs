@@ -0,0 +1,245 @@
+//! Module `dgm_wal` integrate [Wal] with [Dgm][crate::dgm::Dgm] index.
+//!
+//! Write operations on Dgm writer handles are appended to the log before
+//! applying them on the m0 snapshot, using the seqno assigned by the log.
+//! Once a memory snapshot is committed to disk, journals upto the
+//! committed seqno are purged. While opening a Dgm index, operations
+//! newer than the latest disk level are replayed on the m0 snapshot.
+//! Committing entries into m0, via [Index::commit][crate::core::Index::commit],
+//! is not allowed, since the log cannot preserve their seqno.
+//! Operations are flushed into the log in batches, refer to
+//! [Config::set_wal_batch_size][crate::dgm::Config::set_wal_batch_size].
+//!
+//! Log is type-erased, so that Dgm's type need not carry the constraints
+//! required by [Wal].
+
+use std::{
+    borrow::Borrow,
+    ffi,
+    hash::Hash,
+    ops::Bound,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    core::{Diff, Entry, Replay, Result, Serialize, WalWriter},
+    dlog::{self, Dlog},
+    error::Error,
+    hasher::Crc32,
+    wal::{self, Wal},
+};
+
+// Writes are serialized across writer handles, refer to DgmWal::seqlock,
+// hence a single shard is sufficient.
+const NSHARDS: usize = 1;
+
+/// Write-ahead-log for Dgm index.
+pub(crate) struct DgmWal<K, V> {
+    log: Box<dyn Log<K, V>>,
+    // serialize log-append and m0-apply, so that m0 sees mutations
+    // in seqno order.
+    seqlock: Arc<Mutex<()>>,
+}
+
+impl<K, V> DgmWal<K, V>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Serialize,
+    V: 'static + Send + Clone + Default + Serialize + Diff,
+{
+    /// Create a new log under `dir`, purging existing journals for `name`.
+    pub(crate) fn create(
+        dir: &ffi::OsStr,
+        name: &str,
+        fsync: bool,
+        batch_size: usize,
+    ) -> Result<DgmWal<K, V>> {
+        let dl = Dlog::<wal::State, wal::Op<K, V>>::create(
+            dir.to_os_string(),
+            name.to_string(),
+            NSHARDS,
+            dlog::JOURNAL_LIMIT,
+            batch_size,
+            fsync,
+        )?;
+        Ok(DgmWal {
+            log: Box::new(Wal::from_dlog(dl, Crc32)),
+            seqlock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Load an existing log under `dir`, and replay all operations
+    /// greater-than `seqno` on `db`. Return the log along with the number
+    /// of operations replayed.
+    pub(crate) fn load<P>(
+        dir: &ffi::OsStr,
+        name: &str,
+        fsync: bool,
+        batch_size: usize,
+        db: &mut P,
+        seqno: u64,
+    ) -> Result<(DgmWal<K, V>, usize)>
+    where
+        P: Replay<K, V>,
+    {
+        let dl = Dlog::<wal::State, wal::Op<K, V>>::load(
+            dir.to_os_string(),
+            name.to_string(),
+            NSHARDS,
+            dlog::JOURNAL_LIMIT,
+            batch_size,
+            fsync,
+        )?;
+        let (wal, n_ops) = Wal::from_dlog_replay(dl, Crc32, db, seqno)?;
+        let wal = DgmWal {
+            log: Box::new(wal),
+            seqlock: Arc::new(Mutex::new(())),
+        };
+        Ok((wal, n_ops))
+    }
+}
+
+impl<K, V> DgmWal<K, V> {
+    /// Create a new writer handle.
+    pub(crate) fn to_writer(&mut self) -> Result<DgmWalWriter<K, V>> {
+        Ok(DgmWalWriter {
+            w: self.log.to_writer()?,
+            seqlock: Arc::clone(&self.seqlock),
+        })
+    }
+
+    /// Purge journals whose entries are all before `before`.
+    pub(crate) fn purge_till(&mut self, before: Bound<u64>) -> Result<()> {
+        self.log.purge_till(before)
+    }
+
+    /// Close the log, journals are left on disk.
+    pub(crate) fn close(self) -> Result<()> {
+        self.log.close()
+    }
+
+    /// Purge the log along with its journals.
+    pub(crate) fn purge(self) -> Result<()> {
+        self.log.purge()
+    }
+}
+
+/// Writer handle for Dgm's write-ahead-log.
+pub(crate) struct DgmWalWriter<K, V> {
+    w: Box<dyn Append<K, V>>,
+    seqlock: Arc<Mutex<()>>,
+}
+
+impl<K, V> DgmWalWriter<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Append set operation into the log and apply it on `w`.
+    pub(crate) fn set<W>(&mut self, w: &mut W, key: K, value: V) -> Result<Option<Entry<K, V>>>
+    where
+        W: WalWriter<K, V>,
+    {
+        let _guard = self.as_seqlock()?;
+        let seqno = self.w.set(key.clone(), value.clone())?;
+        w.set_index(key, value, seqno)
+    }
+
+    /// Append set operation into the log and apply it on `w`, only if
+    /// `validate` succeeds. Validation is serialized with other writes,
+    /// hence compare-and-set is atomic. Return the entry returned by
+    /// `validate`.
+    pub(crate) fn set_cas<W, F>(
+        &mut self,
+        w: &mut W,
+        key: K,
+        value: V,
+        validate: F,
+    ) -> Result<Option<Entry<K, V>>>
+    where
+        W: WalWriter<K, V>,
+        F: FnOnce(&K) -> Result<Option<Entry<K, V>>>,
+    {
+        let _guard = self.as_seqlock()?;
+        let old = validate(&key)?;
+        let seqno = self.w.set(key.clone(), value.clone())?;
+        w.set_index(key, value, seqno)?;
+        Ok(old)
+    }
+
+    /// Append delete operation into the log and apply it on `w`.
+    pub(crate) fn delete<W, Q>(&mut self, w: &mut W, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        W: WalWriter<K, V>,
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let _guard = self.as_seqlock()?;
+        let seqno = self.w.delete(key.to_owned())?;
+        w.delete_index(key, seqno)
+    }
+
+    fn as_seqlock(&self) -> Result<MutexGuard<()>> {
+        match self.seqlock.lock() {
+            Ok(guard) => Ok(guard),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+// type-erased Wal.
+trait Log<K, V>: Send {
+    fn to_writer(&mut self) -> Result<Box<dyn Append<K, V>>>;
+
+    fn purge_till(&mut self, before: Bound<u64>) -> Result<()>;
+
+    fn close(self: Box<Self>) -> Result<()>;
+
+    fn purge(self: Box<Self>) -> Result<()>;
+}
+
+// type-erased Wal writer.
+trait Append<K, V>: Send {
+    fn set(&mut self, key: K, value: V) -> Result<u64>;
+
+    fn delete(&mut self, key: K) -> Result<u64>;
+}
+
+impl<K, V> Log<K, V> for Wal<K, V, Crc32>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Serialize,
+    V: 'static + Send + Clone + Default + Serialize,
+{
+    fn to_writer(&mut self) -> Result<Box<dyn Append<K, V>>> {
+        Ok(Box::new(Wal::to_writer(self)?))
+    }
+
+    fn purge_till(&mut self, before: Bound<u64>) -> Result<()> {
+        Wal::purge_till(self, before)?;
+        Ok(())
+    }
+
+    fn close(self: Box<Self>) -> Result<()> {
+        let mut wal = *self;
+        Wal::close(&mut wal)?;
+        Ok(())
+    }
+
+    fn purge(self: Box<Self>) -> Result<()> {
+        Wal::purge(*self)?;
+        Ok(())
+    }
+}
+
+impl<K, V> Append<K, V> for wal::Writer<K, V, Crc32>
+where
+    K: 'static + Send + Clone + Default + Hash + Serialize,
+    V: 'static + Send + Default + Serialize,
+{
+    fn set(&mut self, key: K, value: V) -> Result<u64> {
+        wal::Writer::set(self, key, value)
+    }
+
+    fn delete(&mut self, key: K) -> Result<u64> {
+        wal::Writer::delete(self, &key)
+    }
+}
@@ -11,6 +11,14 @@
 //! * Entries are appended into a journal file and automatically rotated
 //!   when `journal_limit` is exceeded.
 //! * Durability guarantee is controlled via `fsync` parameter.
+//! * Every flushed batch carries a crc32 checksum. While loading, a torn
+//!   or corrupted batch at the tail of a shard's latest journal is
+//!   truncated, refer to [Dlog::to_dropped]. Corrupted batch followed by
+//!   valid data fails the load with `InvalidFile`.
+//! * Checksummed batches are identified by a versioned batch-marker.
+//!   Journals written by older versions, without checksum, can be loaded
+//!   as is, and new batches are appended in the current format, no
+//!   migration is required.
 //!
 //! **Shards**:
 //!
@@ -162,7 +170,12 @@ where
 
         dlog_seqno.store(last_seqno, SeqCst);
 
-        debug!(target: "dlog  ", "load existing dlog from {:?}/{}", dir, name);
+        let n_dropped: usize = shards.iter().map(|s| s.to_dropped()).sum();
+        debug!(
+            target: "dlog  ",
+            "load existing dlog from {:?}/{}, dropped {} entries",
+            dir, name, n_dropped
+        );
 
         Ok(Dlog {
             dir,
@@ -173,6 +186,17 @@ where
         })
     }
 
+    /// Return the number of entries dropped while loading this [`Dlog`].
+    /// Entries are dropped when the tail batch of a shard's latest journal
+    /// is torn or corrupted, say due to a power failure. Such journals
+    /// are truncated back to their last good batch.
+    pub fn to_dropped(&self) -> usize {
+        self.shards.iter().map(|shard| shard.to_dropped()).sum()
+    }
+
+    /// Mark journals, whose entries are all before `before`, as cold.
+    /// Cold journals are not required by the application any more, and
+    /// can be purged via [Dlog::purge_cold].
     pub fn set_deep_freeze(&mut self, before: Bound<u64>) -> Result<()> {
         let shards: Vec<Shard<S, T>> = self.shards.drain(..).collect();
         for shard in shards.into_iter() {
@@ -181,6 +205,16 @@ where
 
         Ok(())
     }
+
+    /// Purge all cold journals, refer to [Dlog::set_deep_freeze]. Return
+    /// the number of journals purged.
+    pub fn purge_cold(&mut self) -> Result<usize> {
+        let mut n = 0;
+        for shard in self.shards.iter_mut() {
+            n += shard.purge_cold()?;
+        }
+        Ok(n)
+    }
 }
 
 pub(crate) enum OpRequest<T> {
@@ -1,3 +1,4 @@
+use crc::crc32;
 use lazy_static::lazy_static;
 
 use std::{
@@ -99,6 +100,10 @@ impl<S, T> Batch<S, T> {
         }
     }
 
+    pub(crate) fn new_active(state: S, entries: Vec<DEntry<T>>) -> Batch<S, T> {
+        Batch::Active { state, entries }
+    }
+
     pub(crate) fn new_refer(
         fpos: u64,
         length: usize,
@@ -156,6 +161,13 @@ impl<S, T> Batch<S, T> {
         }
     }
 
+    pub(crate) fn to_fpos(&self) -> Option<u64> {
+        match self {
+            Batch::Refer { fpos, .. } => Some(*fpos),
+            Batch::Active { .. } => None,
+        }
+    }
+
     pub(crate) fn into_entries(self) -> Result<Vec<DEntry<T>>> {
         match self {
             Batch::Active { entries, .. } => Ok(entries),
@@ -163,6 +175,13 @@ impl<S, T> Batch<S, T> {
         }
     }
 
+    pub(crate) fn into_state_entries(self) -> Result<(S, Vec<DEntry<T>>)> {
+        match self {
+            Batch::Active { state, entries } => Ok((state, entries)),
+            Batch::Refer { .. } => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
     pub(crate) fn into_active(mut self, fd: &mut fs::File) -> Result<Batch<S, T>>
     where
         S: Default + Serialize,
@@ -214,12 +233,19 @@ impl<S, T> Batch<S, T> {
 // +--------------------------------+-------------------------------+
 // |                              entries                           |
 // +--------------------------------+-------------------------------+
-// |                         DLOG_BATCH_MARKER                      |
+// |                             checksum                           |
+// +----------------------------------------------------------------+
+// |                       DLOG_BATCH_MARKER_V2                     |
 // +----------------------------------------------------------------+
 // |                              length                            |
 // +----------------------------------------------------------------+
 //
 // NOTE: `length` value includes 8-byte length-prefix and 8-byte length-suffix.
+// NOTE: `checksum` is crc32-ieee, stored in 64-bit, computed over all bytes
+// from `start_seqno` till the end of `entries`.
+// NOTE: batches written by older versions end with DLOG_BATCH_MARKER and
+// carry no checksum. They are loaded as is, without verification, and
+// journals containing them can be mixed with newer batches.
 impl<S, T> Batch<S, T>
 where
     S: Serialize,
@@ -252,8 +278,15 @@ where
                     n += entry.encode(buf)?;
                 }
 
-                buf.extend_from_slice(DLOG_BATCH_MARKER.as_ref());
-                n += DLOG_BATCH_MARKER.len();
+                let checksum: u64 = {
+                    let m = buf.len() - (n - 8);
+                    crc32::checksum_ieee(&buf[m..]).into()
+                };
+                buf.extend_from_slice(&checksum.to_be_bytes());
+                n += 8;
+
+                buf.extend_from_slice(DLOG_BATCH_MARKER_V2.as_ref());
+                n += DLOG_BATCH_MARKER_V2.len();
 
                 n += 8; // suffix length
 
@@ -267,6 +300,18 @@ where
         }
     }
 
+    // decode batch header, return (length, start_seqno, last_seqno).
+    // Unlike decode_refer, the batch is not validated.
+    pub(crate) fn decode_header(buf: &[u8]) -> Result<(usize, u64, u64)> {
+        check_remaining!(buf, 24, "dlog-batch-header")?;
+
+        let length = convert_at!(u64::from_be_bytes(array_at!(buf[..8])?))?;
+        let start_seqno = u64::from_be_bytes(array_at!(buf[8..16])?);
+        let last_seqno = u64::from_be_bytes(array_at!(buf[16..24])?);
+
+        Ok((length, start_seqno, last_seqno))
+    }
+
     pub(crate) fn decode_refer(&mut self, buf: &[u8], fpos: u64) -> Result<usize> {
         check_remaining!(buf, 24, "dlog-batch-refer-hdr")?;
 
@@ -295,7 +340,7 @@ where
         let mut n = 24;
 
         let mut state: S = Default::default();
-        n += state.decode(buf)?;
+        n += state.decode(&buf[n..])?;
 
         let nentries = u64::from_be_bytes(array_at!(buf[n..n + 8])?);
         n += 8;
@@ -316,24 +361,64 @@ where
         Ok(length)
     }
 
+    // validate the batch's length, marker and checksum, if batch is
+    // of current format.
     fn validate(buf: &[u8]) -> Result<usize> {
-        let (a, z): (usize, usize) = {
-            let n = convert_at!(u64::from_be_bytes(array_at!(buf[..8])?))?;
-            (
-                n,
-                convert_at!(u64::from_be_bytes(array_at!(buf[n - 8..n])?))?,
-            )
-        };
+        check_remaining!(buf, 8, "dlog-batch-length")?;
+
+        let a: usize = convert_at!(u64::from_be_bytes(array_at!(buf[..8])?))?;
+        if a < Self::min_length() {
+            return err_at!(InvalidFile, msg: format!("batch length {}", a));
+        }
+        check_remaining!(buf, a, "dlog-batch")?;
+
+        let z: usize = convert_at!(u64::from_be_bytes(array_at!(buf[a - 8..a])?))?;
         if a != z {
-            return err_at!(Fatal, msg: format!("{} != {}", a, z));
+            return err_at!(InvalidFile, msg: format!("{} != {}", a, z));
+        }
+
+        let n = a - 8;
+        let v2 = DLOG_BATCH_MARKER_V2.len();
+        if a >= (Self::min_length() + 8 + v2 - DLOG_BATCH_MARKER.len())
+            && DLOG_BATCH_MARKER_V2.as_slice() == &buf[n - v2..n]
+        {
+            let m = n - v2;
+            let checksum = u64::from_be_bytes(array_at!(buf[m - 8..m])?);
+            let computed: u64 = crc32::checksum_ieee(&buf[8..m - 8]).into();
+            if checksum != computed {
+                let msg = format!("checksum {:x} != {:x}", checksum, computed);
+                return err_at!(InvalidFile, msg: msg);
+            }
+            return Ok(a);
         }
 
-        let (m, n) = (a - 8 - DLOG_BATCH_MARKER.len(), a - 8);
-        if DLOG_BATCH_MARKER.as_slice() == &buf[m..n] {
-            Ok(a)
-        } else {
-            err_at!(Fatal, msg: format!("{:?}", &buf[m..n]))
+        let m = n - DLOG_BATCH_MARKER.len();
+        if DLOG_BATCH_MARKER.as_slice() != &buf[m..n] {
+            return err_at!(InvalidFile, msg: format!("{:?}", &buf[m..n]));
         }
+
+        Ok(a)
+    }
+
+    // smallest possible batch, with empty state and no entries, in older
+    // format without checksum.
+    pub(crate) fn min_length() -> usize {
+        8 + 16 + 8 + DLOG_BATCH_MARKER.len() + 8
+    }
+
+    // Return the number of entries in a, possibly torn, batch. This is
+    // a best effort count, entries themselves are not validated.
+    pub(crate) fn peek_nentries(buf: &[u8]) -> Result<usize>
+    where
+        S: Default,
+    {
+        check_remaining!(buf, 24, "dlog-batch-peek-hdr")?;
+
+        let mut state: S = Default::default();
+        let n = 24 + state.decode(&buf[24..])?;
+        check_remaining!(buf, n + 8, "dlog-batch-peek-nentries")?;
+
+        convert_at!(u64::from_be_bytes(array_at!(buf[n..n + 8])?))
     }
 }
 
@@ -372,6 +457,16 @@ impl<T> DEntry<T> {
         DEntry { seqno, op }
     }
 
+    #[inline]
+    pub(crate) fn to_seqno(&self) -> u64 {
+        self.seqno
+    }
+
+    #[inline]
+    pub(crate) fn as_op(&self) -> &T {
+        &self.op
+    }
+
     #[inline]
     pub(crate) fn into_seqno_op(self) -> (u64, T) {
         (self.seqno, self.op)
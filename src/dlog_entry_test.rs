@@ -75,7 +75,7 @@ fn test_batch2() {
 
     let mut buf = vec![];
     let length = batch.encode_active(&mut buf).unwrap();
-    assert_eq!(length, 4099);
+    assert_eq!(length, 4107);
 
     let file = {
         let mut dir = std::env::temp_dir();
@@ -96,11 +96,11 @@ fn test_batch2() {
 
     let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
     let n = batch.decode_refer(&buf, 0).unwrap();
-    assert_eq!(n, 4099);
+    assert_eq!(n, 4107);
     match batch {
         Batch::Refer {
             fpos: 0,
-            length: 4099,
+            length: 4107,
             start_seqno: 1,
             last_seqno: 100,
         } => (),
@@ -113,3 +113,60 @@ fn test_batch2() {
         _ => unreachable!(),
     }
 }
+
+#[test]
+fn test_batch_checksum() {
+    use crate::wal;
+
+    let batch = {
+        let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
+        for i in 0..100 {
+            let op = wal::Op::new_set(10, 20);
+            batch.add_entry(DEntry::new(i + 1, op)).unwrap();
+        }
+        batch
+    };
+
+    let mut buf = vec![];
+    let length = batch.encode_active(&mut buf).unwrap();
+    assert_eq!(
+        Batch::<wal::State, wal::Op<i64, i64>>::peek_nentries(&buf).unwrap(),
+        100
+    );
+
+    // flip a bit in one of the entries.
+    let mut corrupt = buf.clone();
+    corrupt[length / 2] ^= 0x10;
+    let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
+    match batch.decode_refer(&corrupt, 0) {
+        Err(Error::InvalidFile(_)) => (),
+        Err(err) => panic!("unexpected err {:?}", err),
+        Ok(_) => panic!("expected checksum failure"),
+    }
+
+    // torn batch.
+    let torn = &buf[..length - 10];
+    let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
+    assert!(batch.decode_refer(torn, 0).is_err());
+    assert_eq!(
+        Batch::<wal::State, wal::Op<i64, i64>>::peek_nentries(torn).unwrap(),
+        100
+    );
+    assert!(Batch::<wal::State, wal::Op<i64, i64>>::peek_nentries(&buf[..20]).is_err());
+
+    // batch in older format, without checksum.
+    let legacy = {
+        let m = length - 8 - DLOG_BATCH_MARKER_V2.len() - 8;
+        let mut legacy = buf[..m].to_vec();
+        legacy.extend_from_slice(DLOG_BATCH_MARKER.as_ref());
+        let n: u64 = (legacy.len() + 8) as u64;
+        legacy[..8].copy_from_slice(&n.to_be_bytes());
+        legacy.extend_from_slice(&n.to_be_bytes());
+        legacy
+    };
+    let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
+    assert_eq!(batch.decode_refer(&legacy, 0).unwrap(), legacy.len());
+    let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
+    assert_eq!(batch.decode_active(&legacy).unwrap(), legacy.len());
+    assert_eq!(batch.into_entries().unwrap().len(), 100);
+}
@@ -1,10 +1,9 @@
-use log::debug;
+use log::{debug, warn};
 
 use std::{
-    cmp,
     convert::{TryFrom, TryInto},
     ffi, fmt, fs,
-    io::{self, Read, Seek, Write},
+    io::{self, Read, Write},
     mem,
     ops::Bound,
     path, result,
@@ -50,6 +49,12 @@ impl From<(String, String, usize, usize)> for JournalFile {
     }
 }
 
+impl From<ffi::OsString> for JournalFile {
+    fn from(fname: ffi::OsString) -> JournalFile {
+        JournalFile(fname)
+    }
+}
+
 impl From<JournalFile> for ffi::OsString {
     fn from(jf: JournalFile) -> ffi::OsString {
         jf.0
@@ -118,6 +123,8 @@ pub(crate) struct Shard<S, T> {
     dlog_seqno: Arc<AtomicU64>,
     journals: Vec<Journal<S, T>>,
     active: Journal<S, T>,
+    // number of entries dropped from a torn/corrupted tail, while loading.
+    n_dropped: usize,
 }
 
 impl<S, T> Shard<S, T>
@@ -163,6 +170,7 @@ where
             dlog_seqno: seqno,
             journals: vec![],
             active,
+            n_dropped: 0,
         })
     }
 
@@ -183,15 +191,34 @@ where
         for item in err_at!(IoError, fs::read_dir(&dir))? {
             let file_name = err_at!(IoError, item)?.file_name();
             let (n, id) = (name.clone(), shard_id);
-            match Journal::<S, T>::new_archive(dir.clone(), n, id, file_name) {
+            match Journal::<S, T>::new_cold(dir.clone(), n, id, file_name) {
                 Some(journal) => journals.push(journal),
                 None => (),
             }
         }
 
+        journals.sort_by(|x, y| x.num.cmp(&y.num));
+
+        // Only the latest journal can have a torn batch, since journals are
+        // append-only and older journals are never written after rotation.
+        let (journals, n_dropped) = {
+            let mut n_dropped = 0;
+            let mut archives = vec![];
+            let n = journals.len();
+            for (i, journal) in journals.into_iter().enumerate() {
+                if (i + 1) == n {
+                    let (journal, m) = journal.into_repaired_archive()?;
+                    n_dropped = m;
+                    archives.push(journal);
+                } else {
+                    archives.push(journal.into_archive()?);
+                }
+            }
+            (archives, n_dropped)
+        };
+
         let idx = seqno.load(SeqCst);
 
-        journals.sort_by(|x, y| x.num.cmp(&y.num));
         let last_seqno = {
             let mut iter = journals.iter().rev();
             loop {
@@ -230,6 +257,7 @@ where
                 dlog_seqno: seqno,
                 journals,
                 active,
+                n_dropped,
             },
         ))
     }
@@ -238,14 +266,21 @@ where
         let mut last_seqno = 0;
         let mut journals = vec![];
         for journal in self.journals.into_iter() {
+            if journal.is_cold() {
+                journals.push(journal);
+                continue;
+            }
             let seqno = match journal.to_last_seqno()? {
                 Some(seqno) => seqno,
-                None => break,
+                None => {
+                    journals.push(journal);
+                    continue;
+                }
             };
-            assert!(seqno <= last_seqno, "fatal {} > {}", seqno, last_seqno);
+            assert!(seqno >= last_seqno, "fatal {} < {}", seqno, last_seqno);
             let ok = match before {
-                Bound::Included(before) => last_seqno <= before,
-                Bound::Excluded(before) => last_seqno < before,
+                Bound::Included(before) => seqno <= before,
+                Bound::Excluded(before) => seqno < before,
                 Bound::Unbounded => true,
             };
             if ok {
@@ -268,9 +303,20 @@ where
             dlog_seqno: self.dlog_seqno,
             journals,
             active: self.active,
+            n_dropped: self.n_dropped,
         })
     }
 
+    // purge all cold journals, return the number of journals purged.
+    pub(crate) fn purge_cold(&mut self) -> Result<usize> {
+        let mut n = 0;
+        for journal in self.journals.drain_filter(|j| j.is_cold()) {
+            journal.purge()?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
     pub(crate) fn close(self) -> Result<()> {
         debug!(
             target: "dlogsd",
@@ -297,6 +343,20 @@ where
 
 // shards are monotonically increasing number from 1 to N
 impl<S, T> Shard<S, T> {
+    /// Return number of entries dropped from torn/corrupted batches
+    /// while loading this shard.
+    #[inline]
+    pub(crate) fn to_dropped(&self) -> usize {
+        self.n_dropped
+    }
+
+    #[inline]
+    pub(crate) fn to_journals(&self) -> Vec<&Journal<S, T>> {
+        let mut journals: Vec<&Journal<S, T>> = self.journals.iter().collect();
+        journals.push(&self.active);
+        journals
+    }
+
     #[inline]
     pub(crate) fn into_journals(mut self) -> Vec<Journal<S, T>> {
         self.journals.push(self.active);
@@ -416,6 +476,92 @@ where
         Ok(before)
     }
 
+    /// Append a batch of `entries`, along with its `state`, to the active
+    /// journal and flush them to disk. Unlike operations sent to the shard
+    /// thread, seqno for each entry is supplied by the caller, and must be
+    /// greater than the seqno of entries already in this shard.
+    pub(crate) fn append_batch(&mut self, state: S, entries: Vec<DEntry<T>>) -> Result<()>
+    where
+        S: DlogState<T>,
+    {
+        let seqno = match entries.last() {
+            Some(entry) => entry.to_seqno(),
+            None => return Ok(()),
+        };
+
+        let mut batch = Batch::new_active(state, vec![]);
+        for entry in entries.into_iter() {
+            batch.add_entry(entry)?;
+        }
+        self.active.set_active(batch)?;
+        match self.active.flush1(self.journal_limit, self.fsync)? {
+            None => (),
+            Some((buffer, batch)) => {
+                self.rotate_journal()?;
+                self.active.flush2(&buffer, batch, self.fsync)?;
+            }
+        }
+        self.dlog_seqno.store(seqno + 1, SeqCst);
+
+        Ok(())
+    }
+
+    /// Remove all entries after `seqno` from this shard. Journals whose
+    /// entries are all after `seqno` are purged, and the batch holding
+    /// `seqno` is rewritten with entries upto `seqno`.
+    pub(crate) fn truncate_after(&mut self, seqno: u64) -> Result<()>
+    where
+        S: DlogState<T>,
+    {
+        let mut off = None;
+        for (i, journal) in self.journals.iter().enumerate() {
+            if journal.is_cold() {
+                continue;
+            }
+            match journal.to_last_seqno()? {
+                Some(last_seqno) if last_seqno > seqno => {
+                    off = Some(i);
+                    break;
+                }
+                _ => (),
+            }
+        }
+
+        let partial = match off {
+            Some(off) => {
+                let mut journals: Vec<Journal<S, T>> = self.journals.drain(off..).collect();
+                let mut journal = journals.remove(0);
+                let partial = journal.truncate_after(seqno)?;
+                for journal in journals.into_iter() {
+                    journal.purge()?;
+                }
+
+                let num = journal.num + 1;
+                self.journals.push(journal);
+
+                err_at!(IoError, fs::remove_file(&self.active.to_file_path()))?;
+                let (d, n, i) = (self.dir.clone(), self.name.clone(), self.shard_id);
+                self.active = Journal::<S, T>::new_active(d, n, i, num)?;
+                partial
+            }
+            None => self.active.truncate_after(seqno)?,
+        };
+
+        match partial {
+            Some((state, entries)) => self.append_batch(state, entries)?,
+            None => (),
+        }
+        self.dlog_seqno.store(seqno + 1, SeqCst);
+
+        debug!(
+            target: "dlogsd",
+            "shard:{} {:?}/{} truncated after seqno:{}",
+            self.shard_id, self.dir, self.name, seqno
+        );
+
+        Ok(())
+    }
+
     fn rotate_journal(&mut self) -> Result<()>
     where
         S: DlogState<T>,
@@ -526,65 +672,87 @@ where
         })
     }
 
-    fn new_archive(
-        dir: ffi::OsString,
-        name: String,
-        shard_id: usize,
-        fname: ffi::OsString,
-    ) -> Option<Journal<S, T>>
-    where
-        S: DlogState<T>,
-    {
-        let (nm, _typ, id, num): (String, String, usize, usize) =
-            TryFrom::try_from(JournalFile(fname.clone())).ok()?;
-
-        if nm != name || id != shard_id {
-            return None;
-        }
-
-        let file_path = {
-            let mut fp = path::PathBuf::new();
-            fp.push(&dir);
-            fp.push(fname);
-            fp.into_os_string()
-        };
+    // load batch references from journal file. If `repair` is true, a
+    // torn or corrupted batch at the tail of the file, that is the batch
+    // extends till the end of the file, shall be truncated. Corrupted
+    // batch followed by more data is not repaired. Return loaded batches
+    // and number of entries dropped.
+    fn load_batches(file_path: &ffi::OsStr, repair: bool) -> Result<(Vec<Batch<S, T>>, usize)> {
+        let fd = util::open_file_r(file_path)?;
+        let till = err_at!(IoError, fd.metadata())?.len();
+        let mut reader = io::BufReader::with_capacity(DLOG_BLOCK_SIZE, fd);
 
         let mut batches = vec![];
-        let mut fd = util::open_file_r(&file_path).ok()?;
-        let mut fpos = 0_usize;
-        let till: usize = convert_at!(fd.metadata().ok()?.len()).ok()?;
-
-        while fpos < till {
-            let n = cmp::min(DLOG_BLOCK_SIZE, till - fpos) as u64;
-            let fpos_u64: u64 = convert_at!(fpos).ok()?;
-            let block = read_file!(&mut fd, fpos_u64, n, "journal corrupted").ok()?;
-
-            let mut m = 0_usize;
-            while m < block.len() {
-                let mut batch: Batch<S, T> = Batch::default_active();
-                m += batch
-                    .decode_refer(&block[m..], convert_at!((fpos + m)).ok()?)
-                    .ok()?;
-                batches.push(batch);
+        let mut fpos = 0_u64;
+
+        // (buf, is_tail, reason)
+        let bad = loop {
+            if fpos >= till {
+                break None;
             }
-            fpos += block.len();
-        }
 
-        debug!(
-            target: "dlogjn",
-            "Opening archive journal {:?}, loaded {} batches",
-            file_path, batches.len()
-        );
+            let mut buf = vec![0; 8];
+            if (till - fpos) < 8 {
+                buf.truncate(convert_at!((till - fpos))?);
+                err_at!(IoError, reader.read_exact(&mut buf))?;
+                break Some((buf, true, "torn-length".to_string()));
+            }
+            err_at!(IoError, reader.read_exact(&mut buf))?;
+
+            let length = u64::from_be_bytes(array_at!(buf[..8])?);
+            let min_length: u64 = convert_at!(Batch::<S, T>::min_length())?;
+            let n = if length < min_length {
+                // a zero filled tail, left behind by a crash, is torn.
+                let mut rest = vec![];
+                err_at!(IoError, reader.read_to_end(&mut rest))?;
+                let is_tail = buf.iter().chain(rest.iter()).all(|b| *b == 0);
+                break Some((buf, is_tail, format!("bad-length {}", length)));
+            } else if length > (till - fpos) {
+                till - fpos
+            } else {
+                length
+            };
+            buf.resize(convert_at!(n)?, 0);
+            err_at!(IoError, reader.read_exact(&mut buf[8..]))?;
+
+            let mut batch: Batch<S, T> = Batch::default_active();
+            match batch.decode_refer(&buf, fpos) {
+                Ok(_) => {
+                    batches.push(batch);
+                    fpos += n;
+                }
+                Err(err) => {
+                    let is_tail = (fpos + n) == till;
+                    break Some((buf, is_tail, format!("{:?}", err)));
+                }
+            }
+        };
 
-        Some(Journal {
-            num: num,
-            file_path: file_path.clone(),
+        let n_dropped = match bad {
+            None => 0,
+            Some((buf, true, reason)) if repair => {
+                // header of a torn batch might not be readable.
+                let n_dropped = Batch::<S, T>::peek_nentries(&buf).unwrap_or(0);
+                warn!(
+                    target: "dlogjn",
+                    "{:?} truncated at {}/{}, dropped {} entries, {}",
+                    file_path, fpos, till, n_dropped, reason
+                );
+                let fd = {
+                    let mut opts = fs::OpenOptions::new();
+                    err_at!(IoError, opts.write(true).open(file_path))?
+                };
+                err_at!(IoError, fd.set_len(fpos))?;
+                err_at!(IoError, fd.sync_all())?;
+                n_dropped
+            }
+            Some((_, _, reason)) => {
+                let msg = format!("{:?} corrupted at {}, {}", file_path, fpos, reason);
+                err_at!(InvalidFile, msg: msg)?
+            }
+        };
 
-            inner: InnerJournal::Archive {
-                file_path: file_path.clone(),
-                batches,
-            },
-        })
+        Ok((batches, n_dropped))
     }
 
     // don't load the batches. use this only for purging the journal.
@@ -643,17 +811,42 @@ where
                 Ok(self)
             }
             Cold { file_path } => {
-                let (dir, fname) = {
-                    let fp = path::Path::new(&file_path);
-                    let fname = fp.file_name().unwrap().to_os_string();
-                    let dir = fp.parent().unwrap().as_os_str().to_os_string();
-                    (dir, fname)
-                };
+                let (batches, _) = Self::load_batches(&file_path, false)?;
 
-                let (name, _, shard_id, _): (String, String, usize, usize) =
-                    TryFrom::try_from(JournalFile(fname.clone())).unwrap();
+                debug!(
+                    target: "dlogjn",
+                    "Opening archive journal {:?}, loaded {} batches",
+                    file_path, batches.len()
+                );
 
-                Ok(Self::new_archive(dir, name, shard_id, fname).unwrap())
+                self.inner = Archive { file_path, batches };
+                Ok(self)
+            }
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
+    // same as into_archive, except that torn/corrupted batches at the
+    // tail of the journal shall be truncated. Return the archived journal
+    // and number of entries dropped.
+    fn into_repaired_archive(mut self) -> Result<(Self, usize)>
+    where
+        S: DlogState<T>,
+    {
+        use InnerJournal::{Archive, Cold};
+
+        match self.inner {
+            Cold { file_path } => {
+                let (batches, n_dropped) = Self::load_batches(&file_path, true)?;
+
+                debug!(
+                    target: "dlogjn",
+                    "Opening archive journal {:?}, loaded {} batches",
+                    file_path, batches.len()
+                );
+
+                self.inner = Archive { file_path, batches };
+                Ok((self, n_dropped))
             }
             _ => err_at!(Fatal, msg: format!("unreachable")),
         }
@@ -716,6 +909,26 @@ impl<S, T> Journal<S, T> {
         Ok(batches)
     }
 
+    pub(crate) fn to_batches(&self) -> Result<Vec<Batch<S, T>>>
+    where
+        S: Clone,
+        T: Clone,
+    {
+        let batches = match &self.inner {
+            InnerJournal::Active {
+                batches, active, ..
+            } => {
+                let mut batches = batches.clone();
+                batches.push(active.clone());
+                batches
+            }
+            InnerJournal::Archive { batches, .. } => batches.clone(),
+            _ => err_at!(Fatal, msg: format!("unreachable"))?,
+        };
+
+        Ok(batches)
+    }
+
     pub(crate) fn add_entry(&mut self, entry: DEntry<T>) -> Result<()>
     where
         S: DlogState<T>,
@@ -726,6 +939,16 @@ impl<S, T> Journal<S, T> {
         }
     }
 
+    fn set_active(&mut self, batch: Batch<S, T>) -> Result<()> {
+        match &mut self.inner {
+            InnerJournal::Active { active, .. } if active.len()? == 0 => {
+                *active = batch;
+                Ok(())
+            }
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
     pub(crate) fn into_cold(mut self) -> Result<Self> {
         use InnerJournal::{Active, Archive, Cold};
 
@@ -743,6 +966,65 @@ where
     S: Default + Serialize,
     T: Serialize,
 {
+    // truncate entries after `seqno`. If a batch has to be cut in the
+    // middle, return its state and entries upto `seqno`, so that they can
+    // be appended back as a fresh batch.
+    fn truncate_after(&mut self, seqno: u64) -> Result<Option<(S, Vec<DEntry<T>>)>>
+    where
+        T: Default,
+    {
+        let (file_path, batches) = match &mut self.inner {
+            InnerJournal::Active {
+                file_path,
+                batches,
+                active,
+                ..
+            } => {
+                if let Batch::Active { entries, .. } = active {
+                    entries.retain(|e| e.to_seqno() <= seqno);
+                }
+                Ok((file_path, batches))
+            }
+            InnerJournal::Archive { file_path, batches } => Ok((file_path, batches)),
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }?;
+
+        let off = batches
+            .iter()
+            .position(|b| b.to_last_seqno().map_or(false, |s| s > seqno));
+        let batch = match off {
+            Some(off) => batches.drain(off..).next().unwrap(),
+            None => return Ok(None),
+        };
+        let fpos = match batch.to_fpos() {
+            Some(fpos) => Ok(fpos),
+            None => err_at!(Fatal, msg: format!("unreachable")),
+        }?;
+
+        let (state, mut entries) = {
+            let mut fd = util::open_file_r(file_path)?;
+            batch.into_active(&mut fd)?.into_state_entries()?
+        };
+        entries.retain(|e| e.to_seqno() <= seqno);
+
+        let fd = {
+            let mut opts = fs::OpenOptions::new();
+            err_at!(IoError, opts.write(true).open(&file_path))?
+        };
+        err_at!(IoError, fd.set_len(fpos))?;
+        err_at!(IoError, fd.sync_all())?;
+
+        debug!(
+            target: "dlogjn",
+            "{:?} truncated at {} after seqno:{}", file_path, fpos, seqno
+        );
+
+        match entries.len() {
+            0 => Ok(None),
+            _ => Ok(Some((state, entries))),
+        }
+    }
+
     // periodically flush journal entries from memory to disk.
     fn flush1(
         &mut self,
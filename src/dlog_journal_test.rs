@@ -1,6 +1,9 @@
 use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
 
-use std::sync::atomic::{AtomicU64, Ordering::SeqCst};
+use std::{
+    io::Seek,
+    sync::atomic::{AtomicU64, Ordering::SeqCst},
+};
 
 use super::*;
 use crate::wal;
@@ -217,3 +220,235 @@ fn test_shard() {
         shard.purge().unwrap();
     }
 }
+
+#[test]
+fn test_shard_torn_batch() {
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(std::env::temp_dir());
+        dir.push("test-shard-torn-batch");
+        dir.into_os_string()
+    };
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "torn".to_string();
+    let shard_id = 1;
+
+    let dlog_seqno = Arc::new(AtomicU64::new(1));
+    let shard = Shard::<wal::State, wal::Op<i64, i64>>::create(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        1_000_000_000,
+        1000,
+        true,
+    )
+    .unwrap();
+
+    let file_path = shard.active.to_file_path();
+    let mut journal = shard.into_journals().remove(0);
+    for i in 0..10 {
+        for j in 0..100 {
+            let op = wal::Op::<i64, i64>::new_set(i * 100 + j, i);
+            let seqno = (i * 100 + j) as u64 + 1;
+            journal.add_entry(DEntry::new(seqno, op)).unwrap();
+        }
+        assert_eq!(journal.flush1(1_000_000_000, true).unwrap().is_none(), true);
+    }
+
+    // simulate a torn write by chopping off the tail of the last batch.
+    let len = fs::metadata(&file_path).unwrap().len();
+    {
+        let fd = fs::OpenOptions::new().write(true).open(&file_path).unwrap();
+        fd.set_len(len - 10).unwrap();
+    }
+
+    let (last_seqno, shard) = Shard::<wal::State, wal::Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        1_000_000_000,
+        1000,
+        true,
+    )
+    .unwrap();
+    assert_eq!(last_seqno, 900);
+    assert_eq!(shard.to_dropped(), 100);
+
+    let mut journals = shard.into_journals();
+    let journal = journals.remove(0);
+    assert_eq!(journal.to_last_seqno().unwrap(), Some(900));
+    assert!(fs::metadata(&file_path).unwrap().len() < len);
+
+    // zero filled tail is truncated, without dropping any entries.
+    let len = fs::metadata(&file_path).unwrap().len();
+    {
+        let mut fd = fs::OpenOptions::new()
+            .append(true)
+            .open(&file_path)
+            .unwrap();
+        fd.write(&[0; 20]).unwrap();
+    }
+    let (last_seqno, shard) = Shard::<wal::State, wal::Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        1_000_000_000,
+        1000,
+        true,
+    )
+    .unwrap();
+    assert_eq!(last_seqno, 900);
+    assert_eq!(shard.to_dropped(), 0);
+    assert_eq!(fs::metadata(&file_path).unwrap().len(), len);
+    mem::drop(shard);
+
+    // corrupt a byte in the middle of the latest journal, batches after
+    // the corrupted batch are valid, so loading should fail without
+    // truncating the journal.
+    {
+        let mut fd = fs::OpenOptions::new().write(true).open(&file_path).unwrap();
+        fd.seek(io::SeekFrom::Start(100)).unwrap();
+        fd.write(&[0xAB, 0xCD, 0xEF]).unwrap();
+    }
+    match Shard::<wal::State, wal::Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        1_000_000_000,
+        1000,
+        true,
+    ) {
+        Err(Error::InvalidFile(_)) => (),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected corrupted journal"),
+    }
+    assert_eq!(fs::metadata(&file_path).unwrap().len(), len);
+
+    // corrupt a byte in the middle of the first batch, that is not the
+    // tail batch any more, so loading should fail.
+    {
+        let mut fd = fs::OpenOptions::new().write(true).open(&file_path).unwrap();
+        fd.seek(io::SeekFrom::Start(100)).unwrap();
+        fd.write(&[0xAB, 0xCD, 0xEF]).unwrap();
+    }
+    journals.into_iter().for_each(|j| j.purge().unwrap());
+    let journal_file: JournalFile = (name.clone(), "wal".to_string(), shard_id, 3).into();
+    let next_file = {
+        let mut fp = path::PathBuf::new();
+        fp.push(&dir);
+        fp.push(ffi::OsString::from(journal_file));
+        fp
+    };
+    fs::File::create(&next_file).unwrap();
+
+    match Shard::<wal::State, wal::Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        1_000_000_000,
+        1000,
+        true,
+    ) {
+        Err(Error::InvalidFile(_)) => (),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected corrupted journal"),
+    }
+
+    fs::remove_file(&file_path).unwrap();
+    fs::remove_file(&next_file).unwrap();
+}
+
+#[test]
+fn test_shard_truncate() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed:{}", seed);
+
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(std::env::temp_dir());
+        dir.push("test-shard-truncate");
+        dir.into_os_string()
+    };
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "truncate".to_string();
+    let (shard_id, journal_limit) = (1, 10_000);
+
+    let dlog_seqno = Arc::new(AtomicU64::new(1));
+    let mut shard = Shard::<wal::State, wal::Op<i64, i64>>::create(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        journal_limit,
+        1,
+        false,
+    )
+    .unwrap();
+
+    let mut ref_entries = vec![];
+    let mut seqno = 1;
+    for _ in 0..100 {
+        let mut entries = vec![];
+        for _ in 0..((rng.gen::<u64>() % 20) + 1) {
+            let op = wal::Op::<i64, i64>::new_set(seqno as i64, rng.gen());
+            entries.push(DEntry::new(seqno, op));
+            seqno += 1;
+        }
+        ref_entries.extend(entries.clone());
+        shard.append_batch(wal::State, entries).unwrap();
+    }
+    assert!(shard.journals.len() > 1);
+    assert_eq!(dlog_seqno.load(SeqCst), seqno);
+
+    let read_entries = |shard: &Shard<wal::State, wal::Op<i64, i64>>| {
+        let mut entries = vec![];
+        for journal in shard.to_journals().into_iter() {
+            let mut fd = util::open_file_r(&journal.to_file_path()).unwrap();
+            for batch in journal.to_batches().unwrap().into_iter() {
+                let batch = batch.into_active(&mut fd).unwrap();
+                entries.extend(batch.into_entries().unwrap());
+            }
+        }
+        entries
+    };
+
+    // truncate in the middle of the active journal and archives.
+    for _ in 0..3 {
+        let last_seqno = ref_entries.len() as u64;
+        let seqno = rng.gen::<u64>() % last_seqno;
+        println!("truncate after {}/{}", seqno, last_seqno);
+        shard.truncate_after(seqno).unwrap();
+        ref_entries.truncate(seqno as usize);
+        assert_eq!(dlog_seqno.load(SeqCst), seqno + 1);
+        assert_eq!(read_entries(&shard), ref_entries);
+
+        let op = wal::Op::<i64, i64>::new_set(0, 0);
+        let entry = DEntry::new(seqno + 1, op);
+        ref_entries.push(entry.clone());
+        shard.append_batch(wal::State, vec![entry]).unwrap();
+        assert_eq!(read_entries(&shard), ref_entries);
+    }
+
+    let (last_seqno, shard) = Shard::<wal::State, wal::Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        shard_id,
+        Arc::clone(&dlog_seqno),
+        journal_limit,
+        1,
+        false,
+    )
+    .unwrap();
+    assert_eq!(last_seqno, ref_entries.len() as u64);
+    assert_eq!(read_entries(&shard), ref_entries);
+
+    shard.purge().unwrap();
+}
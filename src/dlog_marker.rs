@@ -1,6 +1,12 @@
 lazy_static! {
+    // marker for batches without checksum, older format.
     static ref DLOG_BATCH_MARKER: Vec<u8> = {
         let marker = "செய்வன திருந்தச் செய்";
         marker.as_bytes().to_vec()
     };
+    // marker for batches with checksum, current format.
+    static ref DLOG_BATCH_MARKER_V2: Vec<u8> = {
+        let marker = "செய்வன திருந்தச் செய் v2";
+        marker.as_bytes().to_vec()
+    };
 }
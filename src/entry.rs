@@ -17,7 +17,7 @@ use crate::{
     scans,
     wal::Wal,
 };
-use crate::{error::Error, vlog};
+use crate::{error::Error, robt::Compression, vlog};
 
 #[derive(Clone)]
 pub(crate) struct Delta<V>
@@ -529,6 +529,15 @@ where
             .collect();
         Some(self)
     }
+
+    // return whether purging with `cutoff` shall drop this entry, or
+    // some of its older versions.
+    pub(crate) fn is_purgeable(&self, cutoff: Cutoff) -> bool {
+        match self.clone().purge(cutoff) {
+            Some(entry) => entry.deltas.len() < self.deltas.len(),
+            None => true,
+        }
+    }
 }
 
 impl<K, V> Entry<K, V>
@@ -691,12 +700,12 @@ where
     V: Default + Clone + Diff + Serialize,
     <V as Diff>::D: Default + Serialize,
 {
-    pub(crate) fn fetch_value(&mut self, fd: &mut fs::File) -> Result<()> {
+    pub(crate) fn fetch_value(&mut self, fd: &mut fs::File, cmp: Compression) -> Result<()> {
         Ok(match &self.value {
             Value::U { value, seqno, .. } => match value.to_reference() {
                 Some((fpos, len, _seqno)) => {
                     self.value =
-                        Value::new_upsert(Box::new(vlog::fetch_value(fpos, len, fd)?), *seqno);
+                        Value::new_upsert(Box::new(vlog::fetch_value(fpos, len, fd, cmp)?), *seqno);
                 }
                 _ => (),
             },
@@ -704,14 +713,14 @@ where
         })
     }
 
-    pub(crate) fn fetch_deltas(&mut self, fd: &mut fs::File) -> Result<()> {
+    pub(crate) fn fetch_deltas(&mut self, fd: &mut fs::File, cmp: Compression) -> Result<()> {
         for delta in self.deltas.iter_mut() {
             match delta.data {
                 InnerDelta::U {
                     delta: vlog::Delta::Reference { fpos, length, .. },
                     seqno,
                 } => {
-                    *delta = Delta::new_upsert(vlog::fetch_delta(fpos, length, fd)?, seqno);
+                    *delta = Delta::new_upsert(vlog::fetch_delta(fpos, length, fd, cmp)?, seqno);
                 }
                 _ => (),
             }
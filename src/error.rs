@@ -59,6 +59,8 @@ pub enum Error {
     DiffSizeExceeded(usize),
     /// Return list of files that needs to be purged.
     PurgeFiles(Vec<ffi::OsString>),
+    /// Checksum mismatch for disk block or entry, in file at file-position.
+    Corrupted(ffi::OsString, u64),
 
     #[doc(hidden)]
     // internal error, given key is less than the entire data set.
@@ -135,12 +137,14 @@ macro_rules! array_at {
 
 impl PartialEq for Error {
     fn eq(&self, other: &Error) -> bool {
+        use Error::Corrupted;
         use Error::InvalidCAS;
         use Error::InvalidFile;
 
         match (self, other) {
             (InvalidCAS(x), InvalidCAS(y)) => x == y,
             (InvalidFile(s1), InvalidFile(s2)) => s1 == s2,
+            (Corrupted(f1, x), Corrupted(f2, y)) => f1 == f2 && x == y,
             _ => false,
         }
     }
@@ -0,0 +1,125 @@
+//! Module `hasher` implement hashers that can be used with [Bloom]
+//! implementations to compute key digests.
+//!
+//! Bitmaps are generic over the hasher, and identity of the hasher is
+//! persisted along with the bitmap, refer to [HasherId]. Default hasher
+//! is [Crc32], which is compatible with bitmaps persisted by older
+//! versions. Use `benches/hasher_bench.rs` to compare hashers.
+//!
+//! Note that hashers are fed by the key's [Hash] implementation, and std
+//! implementations for integers, `usize` and `isize` feed native-endian
+//! and native-width bytes. Hence digests, and the bitmaps persisted using
+//! them, are portable only across platforms of same endianness and
+//! pointer width.
+//!
+//! [Bloom]: crate::core::Bloom
+//! [HasherId]: crate::core::HasherId
+
+use crc::crc32::{self, Hasher32};
+
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use crate::core::HasherId;
+
+/// Build [Crc32Hasher], using IEEE polynomial.
+#[derive(Clone, Default)]
+pub struct Crc32;
+
+impl BuildHasher for Crc32 {
+    type Hasher = Crc32Hasher;
+
+    fn build_hasher(&self) -> Crc32Hasher {
+        Crc32Hasher {
+            digest: crc32::Digest::new(crc32::IEEE),
+        }
+    }
+}
+
+impl HasherId for Crc32 {
+    fn to_hasher_id() -> String {
+        "crc32".to_string()
+    }
+}
+
+/// Hasher computing 32-bit crc.
+pub struct Crc32Hasher {
+    digest: crc32::Digest,
+}
+
+impl Hasher for Crc32Hasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        Hasher32::write(&mut self.digest, bytes)
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.digest.sum32() as u64
+    }
+}
+
+/// Build [Fnv1aHasher].
+#[derive(Clone, Default)]
+pub struct Fnv1a;
+
+impl BuildHasher for Fnv1a {
+    type Hasher = Fnv1aHasher;
+
+    fn build_hasher(&self) -> Fnv1aHasher {
+        Fnv1aHasher {
+            hash: Fnv1aHasher::OFFSET_BASIS,
+        }
+    }
+}
+
+impl HasherId for Fnv1a {
+    fn to_hasher_id() -> String {
+        "fnv1a64".to_string()
+    }
+}
+
+/// Hasher computing 64-bit [FNV-1a][fnv] hash, fast for short keys.
+///
+/// [fnv]: https://en.wikipedia.org/wiki/Fowler-Noll-Vo_hash_function
+pub struct Fnv1aHasher {
+    hash: u64,
+}
+
+impl Fnv1aHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+}
+
+impl Hasher for Fnv1aHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes.iter() {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Compute 32-bit digest for `element` using hasher built from `builder`.
+/// 64-bit hash is folded into 32-bit digest, which is a no-op for
+/// 32-bit hashers like [Crc32].
+#[inline]
+pub(crate) fn to_digest32<H, Q>(builder: &H, element: &Q) -> u32
+where
+    H: BuildHasher,
+    Q: ?Sized + Hash,
+{
+    let mut hasher = builder.build_hasher();
+    element.hash(&mut hasher);
+    let hash = hasher.finish();
+    ((hash >> 32) ^ hash) as u32
+}
+
+#[cfg(test)]
+#[path = "hasher_test.rs"]
+mod hasher_test;
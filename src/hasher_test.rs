@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn test_crc32_hasher() {
+    // digests must match with that of older bitmaps, that were computed
+    // using crc32::Digest directly.
+    for key in 0..1000_u64 {
+        let mut digest = crc32::Digest::new(crc32::IEEE);
+        key.hash(&mut digest);
+        assert_eq!(to_digest32(&Crc32, &key), digest.sum32());
+    }
+    assert_eq!(Crc32::to_hasher_id(), "crc32".to_string());
+}
+
+#[test]
+fn test_fnv1a_hasher() {
+    // reference values for 64-bit FNV-1a.
+    let mut hasher = Fnv1a.build_hasher();
+    hasher.write(b"");
+    assert_eq!(hasher.finish(), 0xcbf2_9ce4_8422_2325);
+    let mut hasher = Fnv1a.build_hasher();
+    hasher.write(b"a");
+    assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    let mut hasher = Fnv1a.build_hasher();
+    hasher.write(b"foobar");
+    assert_eq!(hasher.finish(), 0x8594_4171_f739_67e8);
+
+    assert_eq!(to_digest32(&Fnv1a, "foobar"), to_digest32(&Fnv1a, "foobar"));
+    assert_ne!(to_digest32(&Fnv1a, "foobar"), to_digest32(&Fnv1a, "fooba"));
+    assert_eq!(Fnv1a::to_hasher_id(), "fnv1a64".to_string());
+}
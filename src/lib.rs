@@ -12,7 +12,7 @@
 //! * Concurrent reads, with single concurrent write.
 //! * Concurrent writes (_Work in progress_).
 //! * Version control, centralised.
-//! * Version control, distributed (_Work in progress_).
+//! * Version control, distributed, using raft consensus.
 //! * Log Structured Merge for multi-level indexing.
 //!
 //! **Key**, each data shall be indexed using an associated key. A key
@@ -115,24 +115,35 @@ pub mod wal;
 
 // raft
 pub mod raft_log;
+mod raft_node;
+mod raft_transport;
 
 // mem index
 pub mod llrb;
 mod llrb_node;
 pub mod mvcc;
 pub mod shllrb;
+pub mod skiplist;
 // disk index
+pub mod backup;
 pub mod dgm;
+mod dgm_cache;
+mod dgm_compact;
+mod dgm_wal;
 pub mod nodisk;
 pub mod robt;
+mod robt_cache;
 mod robt_entry;
 mod robt_index;
+mod robt_limiter;
 pub mod shrobt;
-// pub mod backup; TODO
 
 // bloom filters.
+pub mod bloom;
 pub mod croaring;
+pub mod hasher;
 pub mod nobitmap;
+pub mod xorfilter;
 
 pub mod rdms;
 pub use crate::rdms::Rdms;
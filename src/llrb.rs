@@ -35,7 +35,8 @@ use log::{debug, error, info, trace, warn};
 use std::{
     borrow::Borrow,
     cmp::{self, Ord, Ordering},
-    convert::TryFrom,
+    collections::BinaryHeap,
+    convert::{TryFrom, TryInto},
     ffi, fmt,
     hash::Hash,
     marker, mem,
@@ -47,7 +48,7 @@ use std::{
 #[allow(unused_imports)]
 use crate::{
     core::{CommitIter, Replay, Result, ScanEntry, ScanIter, Value, WalWriter},
-    core::{CommitIterator, Cutoff, ToJson, Validate, WriteIndexFactory, Writer},
+    core::{CommitIterator, Cutoff, ToJson, ToStats, Validate, WriteIndexFactory, Writer},
     core::{Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
     error::Error,
     llrb_node::Node,
@@ -69,6 +70,7 @@ pub struct LlrbFactory {
     lsm: bool,
     sticky: bool,
     spin: bool,
+    mem_budget: Option<usize>,
 }
 
 /// Create a new [LlrbFactory] with initial set of configuration.
@@ -83,6 +85,7 @@ pub fn llrb_factory(lsm: bool) -> LlrbFactory {
         lsm,
         sticky: false,
         spin: true,
+        mem_budget: None,
     }
 }
 
@@ -111,10 +114,19 @@ impl LlrbFactory {
         Ok(self)
     }
 
+    /// Create all Llrb instances with a memory budget, refer to
+    /// Llrb::set_mem_budget() for more details. Creating lsm or sticky
+    /// instances with a memory budget shall fail.
+    /// _Default: None_.
+    pub fn set_mem_budget(&mut self, budget: usize) -> Result<&mut Self> {
+        self.mem_budget = Some(budget);
+        Ok(self)
+    }
+
     fn to_config_string(&self) -> String {
         format!(
-            "llrb = {{ lsm = {}, sticky = {}, spin = {} }}",
-            self.lsm, self.sticky, self.spin
+            "llrb = {{ lsm = {}, sticky = {}, spin = {}, mem_budget = {:?} }}",
+            self.lsm, self.sticky, self.spin, self.mem_budget
         )
     }
 }
@@ -137,6 +149,9 @@ where
             Llrb::new(name)
         };
         index.set_sticky(self.sticky)?.set_spinlatch(self.spin)?;
+        if let Some(budget) = self.mem_budget {
+            index.set_mem_budget(budget)?;
+        }
 
         debug!(
             target: "llrbfc",
@@ -159,12 +174,14 @@ where
     lsm: bool,
     sticky: bool,
     spin: bool,
+    mem_budget: Option<usize>,
 
     root: Option<Box<Node<K, V>>>,
     seqno: u64,
     metadata: Vec<u8>,
     n_count: usize,   // number entries index.
     n_deleted: usize, // number of entries marked deleted.
+    n_evicted: usize, // number of entries evicted for memory budget.
     latch: RWSpinlock,
     key_footprint: isize,
     tree_footprint: isize,
@@ -254,12 +271,14 @@ where
             lsm: false,
             sticky: false,
             spin: true,
+            mem_budget: None,
 
             root: None,
             seqno: Default::default(),
             metadata: Default::default(),
             n_count: Default::default(),
             n_deleted: Default::default(),
+            n_evicted: Default::default(),
             latch: RWSpinlock::new(),
             key_footprint: Default::default(),
             tree_footprint: Default::default(),
@@ -281,12 +300,14 @@ where
             lsm: true,
             sticky: false,
             spin: true,
+            mem_budget: None,
 
             root: None,
             seqno: Default::default(),
             metadata: Default::default(),
             n_count: Default::default(),
             n_deleted: Default::default(),
+            n_evicted: Default::default(),
             latch: RWSpinlock::new(),
             key_footprint: Default::default(),
             tree_footprint: Default::default(),
@@ -317,7 +338,9 @@ where
     /// deleted and but its value shall be removed.
     pub fn set_sticky(&mut self, sticky: bool) -> Result<&mut Self> {
         let n = self.multi_rw();
-        if n == 0 {
+        if sticky && self.mem_budget.is_some() {
+            err_at!(InvalidInput, msg: format!("sticky with mem_budget"))
+        } else if n == 0 {
             self.sticky = sticky;
             Ok(self)
         } else {
@@ -358,12 +381,14 @@ where
             lsm: self.lsm,
             sticky: self.sticky,
             spin: self.spin,
+            mem_budget: self.mem_budget,
 
             root: self.root.clone(),
             seqno: self.seqno,
             metadata: Default::default(),
             n_count: self.n_count,
             n_deleted: self.n_deleted,
+            n_evicted: Default::default(),
             latch: RWSpinlock::new(),
             key_footprint: self.key_footprint,
             tree_footprint: self.tree_footprint,
@@ -430,6 +455,83 @@ where
         Ok((one, two))
     }
 
+    /// Merge two indexes into a single index, inverse of [Llrb::split].
+    /// All keys in index `one` shall sort before keys in index `two`.
+    /// Merge is done in linear time, preserving the lsm versions of each
+    /// entry and the seqno of both indexes.
+    pub fn merge(one: Llrb<K, V>, two: Llrb<K, V>, name: String) -> Result<Box<Llrb<K, V>>> {
+        let n = one.multi_rw() + two.multi_rw();
+        if n > 0 {
+            return err_at!(APIMisuse, msg: format!("active-handles:{}", n));
+        } else if one.lsm != two.lsm {
+            let msg = format!("lsm mismatch {} {}", one.name, two.name);
+            return err_at!(InvalidInput, msg: msg);
+        }
+        let (root1, root2) = (
+            one.root.as_ref().map(Deref::deref),
+            two.root.as_ref().map(Deref::deref),
+        );
+        if !is_adjacent(root1, root2) {
+            let msg = format!("overlapping keys {} {}", one.name, two.name);
+            return err_at!(InvalidInput, msg: msg);
+        }
+
+        let mut index = if one.lsm {
+            Llrb::new_lsm(&name)
+        } else {
+            Llrb::new(&name)
+        };
+        index.set_sticky(one.sticky)?.set_spinlatch(one.spin)?;
+
+        let (name1, name2) = (one.name.clone(), two.name.clone());
+        let debris = merge_debris(one.squash()?, two.squash()?)?;
+        index.root = debris.root;
+        index.seqno = debris.seqno;
+        index.n_count = debris.n_count;
+        index.n_deleted = debris.n_deleted;
+        index.key_footprint = debris.key_footprint;
+        index.tree_footprint = debris.tree_footprint;
+
+        debug!(
+            target: "llrb  ",
+            "{}, merged {} and {} -> (seqno:{} len:{})",
+            index.name, name1, name2, index.seqno, index.n_count
+        );
+
+        Ok(index)
+    }
+
+    /// Build a new index, in linear time, from entries in sort order.
+    /// In lsm mode, entries are loaded as is, along with their versions,
+    /// hence `iter` shall be created with versions. Otherwise, older
+    /// versions are dropped and deleted entries are skipped. Index seqno
+    /// is set to the maximum seqno across all entries.
+    pub fn from_sorted_iter<S>(name: S, lsm: bool, iter: IndexIter<K, V>) -> Result<Box<Llrb<K, V>>>
+    where
+        S: AsRef<str>,
+    {
+        let mut index = if lsm {
+            Llrb::new_lsm(name)
+        } else {
+            Llrb::new(name)
+        };
+
+        let debris = sorted_debris(iter, lsm)?;
+        index.root = debris.root;
+        index.seqno = debris.seqno;
+        index.n_count = debris.n_count;
+        index.n_deleted = debris.n_deleted;
+        index.key_footprint = debris.key_footprint;
+        index.tree_footprint = debris.tree_footprint;
+
+        debug!(
+            target: "llrb  ", "{}, from sorted iter seqno:{} len:{}",
+            index.name, index.seqno, index.n_count
+        );
+
+        Ok(index)
+    }
+
     fn do_split(
         node: Option<Box<Node<K, V>>>,
         index: &mut Llrb<K, V>,
@@ -495,6 +597,7 @@ where
         let mut stats = Stats::new(&self.name);
         stats.entries = self.len();
         stats.n_deleted = self.n_deleted;
+        stats.n_evicted = self.n_evicted;
         stats.node_size = mem::size_of::<Node<K, V>>();
         stats.key_footprint = self.key_footprint;
         stats.tree_footprint = self.tree_footprint;
@@ -673,6 +776,7 @@ where
                 self.set_index_entry(entry?)?;
                 count += 1;
             }
+            self.evict()?;
             count
         };
 
@@ -823,6 +927,7 @@ where
                 return err_at!(Fatal, msg: format!("call-the-programmer"));
             }
         }
+        self.evict()?;
         Ok((seqno, old_entry))
     }
 
@@ -882,6 +987,7 @@ where
 
                 root.set_black();
                 self.root = Some(root);
+                self.evict()?;
                 Ok((self.seqno, Ok(old_entry)))
             }
             _ => err_at!(Fatal, msg: format!("call-the-programmer")),
@@ -926,7 +1032,7 @@ where
             self.seqno = seqno;
             self.tree_footprint += res.size;
 
-            return match res.old_entry {
+            let res = match res.old_entry {
                 None => {
                     self.key_footprint += key_footprint;
                     self.n_count += 1;
@@ -940,6 +1046,8 @@ where
                     Ok((seqno, Ok(Some(entry))))
                 }
             };
+            self.evict()?;
+            return res;
         } else {
             // in non-lsm mode remove the entry from the tree.
             let res = match Llrb::do_delete(self.root.take(), key)? {
@@ -1508,6 +1616,70 @@ where
     }
 }
 
+/// Memory budget for Llrb index.
+impl<K, V> Llrb<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Configure a memory budget, in bytes, for this index. When the
+    /// index footprint exceeds the budget, entries with the oldest seqno
+    /// are evicted, along with all their versions, until the footprint
+    /// falls below 90% of the budget. Evicted entries are removed from
+    /// the index, hence memory budget is applicable only for non-lsm and
+    /// non-sticky index, return error otherwise.
+    pub fn set_mem_budget(&mut self, budget: usize) -> Result<&mut Self> {
+        if self.lsm || self.sticky {
+            let msg = format!("mem_budget for lsm:{} sticky:{}", self.lsm, self.sticky);
+            err_at!(InvalidInput, msg: msg)?
+        }
+        {
+            let _latch = self.latch.acquire_write(self.spin);
+            self.mem_budget = Some(budget);
+            self.evict()?;
+        }
+        Ok(self)
+    }
+
+    // caller hold a write latch.
+    fn evict(&self) -> Result<usize> {
+        let budget: isize = match self.mem_budget {
+            Some(budget) => convert_at!(budget)?,
+            None => return Ok(0),
+        };
+        if self.tree_footprint <= budget {
+            return Ok(0);
+        }
+
+        let low_water = (budget / 10) * 9;
+        let mut n = 0;
+        while self.tree_footprint > low_water && self.n_count > 0 {
+            let k = to_eviction_count(self.tree_footprint, self.n_count, low_water)?;
+            let root = self.root.as_ref().map(Deref::deref);
+            for (_seqno, key) in to_eviction_order(root, k).into_iter() {
+                if self.tree_footprint <= low_water {
+                    break;
+                }
+                self.delete_index_entry(key)?;
+                n += 1;
+            }
+        }
+
+        let mself = unsafe {
+            // caller hold a write latch.
+            (self as *const Self as *mut Self).as_mut().unwrap()
+        };
+        mself.n_evicted += n;
+
+        debug!(
+            target: "llrb  ", "{}, evicted {} entries, footprint:{}",
+            self.name, n, self.tree_footprint
+        );
+
+        Ok(n)
+    }
+}
+
 /// Read operations on Llrb index.
 impl<K, V> Reader<K, V> for Llrb<K, V>
 where
@@ -1821,6 +1993,26 @@ where
     }
 }
 
+impl<K, V> ToStats<Stats> for Box<Llrb<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn to_stats(&self) -> Result<Stats> {
+        self.as_ref().to_stats()
+    }
+}
+
+impl<K, V> ToStats<Stats> for Llrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn to_stats(&self) -> Result<Stats> {
+        Llrb::to_stats(self)
+    }
+}
+
 impl<K, V> Validate<Stats> for Box<Llrb<K, V>>
 where
     K: Clone + Ord + fmt::Debug,
@@ -1871,6 +2063,7 @@ where
         let mut stats = Stats::new(&self.name);
         stats.entries = self.len();
         stats.n_deleted = self.n_deleted;
+        stats.n_evicted = self.n_evicted;
         stats.node_size = mem::size_of::<Node<K, V>>();
         stats.key_footprint = self.key_footprint;
         stats.tree_footprint = self.tree_footprint;
@@ -2405,6 +2598,7 @@ pub struct Stats {
     pub name: String,
     pub entries: usize,
     pub n_deleted: usize,
+    pub n_evicted: usize,
     pub node_size: usize,
     pub key_footprint: isize,
     pub tree_footprint: isize,
@@ -2419,6 +2613,7 @@ impl Stats {
             name: name.to_string(),
             entries: Default::default(),
             n_deleted: Default::default(),
+            n_evicted: Default::default(),
             node_size: Default::default(),
             key_footprint: Default::default(),
             tree_footprint: Default::default(),
@@ -2451,6 +2646,7 @@ impl Stats {
             name: Default::default(),
             entries: self.entries + other.entries,
             n_deleted: self.n_deleted + other.n_deleted,
+            n_evicted: self.n_evicted + other.n_evicted,
             node_size: self.node_size,
             key_footprint: self.key_footprint + other.key_footprint,
             tree_footprint: self.tree_footprint + other.tree_footprint,
@@ -2474,8 +2670,8 @@ impl fmt::Display for Stats {
         )?;
         write!(
             f,
-            "llrb = {{ key_footprint={}, tree_footprint={} }}\n",
-            self.key_footprint, self.tree_footprint,
+            "llrb = {{ n_evicted={}, key_footprint={}, tree_footprint={} }}\n",
+            self.n_evicted, self.key_footprint, self.tree_footprint,
         )?;
         write!(f, "llrb.rw_latch = {}\n", self.rw_latch)?;
         write!(f, "llrb.depths = {}", d)
@@ -2489,6 +2685,7 @@ impl ToJson for Stats {
         format!(
             concat!(
                 r#"{{ ""llrb": {{ "name": {}, "entries": {:X}, "n_deleted": {}",
+                r#""n_evicted": {}, "#,
                 r#""key_footprint": {}, "tree_footprint": {}, "#,
                 r#""node_size": {}, "#,
                 r#""rw_latch": {}, "blacks": {}, "depths": {} }} }}"#,
@@ -2496,6 +2693,7 @@ impl ToJson for Stats {
             self.name,
             self.entries,
             self.n_deleted,
+            self.n_evicted,
             self.key_footprint,
             self.tree_footprint,
             self.node_size,
@@ -153,6 +153,224 @@ where
     Ok(ss_l)
 }
 
+// Return whether all keys in tree `one` sort before keys in tree `two`.
+fn is_adjacent<K, V>(one: Option<&Node<K, V>>, two: Option<&Node<K, V>>) -> bool
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    let (mut one, mut two) = match (one, two) {
+        (Some(one), Some(two)) => (one, two),
+        _ => return true,
+    };
+    while let Some(nref) = one.as_right_deref() {
+        one = nref;
+    }
+    while let Some(nref) = two.as_left_deref() {
+        two = nref;
+    }
+    one.as_key().lt(two.as_key())
+}
+
+// Detach nodes from the tree rooted at `node`, and collect them in sort
+// order.
+fn flatten_tree<K, V>(node: Option<Box<Node<K, V>>>, acc: &mut Vec<Box<Node<K, V>>>)
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    if let Some(mut node) = node {
+        flatten_tree(node.left.take(), acc);
+        let right = node.right.take();
+        acc.push(node);
+        flatten_tree(right, acc);
+    }
+}
+
+// Return the black-height of a balanced tree holding `n` entries.
+fn tree_height(n: usize) -> usize {
+    let mut h = 0;
+    while n >= (1 << (h + 1)) - 1 {
+        h += 1;
+    }
+    h
+}
+
+// Build a balanced tree, in linear time, from `n` detached nodes supplied
+// in sort order. The tree is shaped as a 2-3 tree of black-height `h`,
+// where every 3-node is a black node with a red left child, holding
+// anywhere between 2^h-1 and 3^h-1 entries.
+fn build_tree<K, V, I>(nodes: &mut I, n: usize, h: usize) -> Option<Box<Node<K, V>>>
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+    I: Iterator<Item = Box<Node<K, V>>>,
+{
+    if n == 0 {
+        return None;
+    }
+
+    // maximum entries that a child tree can hold.
+    let max = 3_usize.saturating_pow((h - 1) as u32) - 1;
+    if (n - 1) <= max.saturating_mul(2) {
+        // 2-node
+        let n_left = (n - 1) / 2;
+        let left = build_tree(nodes, n_left, h - 1);
+        let mut node = nodes.next().unwrap();
+        node.left = left;
+        node.right = build_tree(nodes, n - 1 - n_left, h - 1);
+        node.set_black();
+        Some(node)
+    } else {
+        // 3-node
+        let (n1, n2) = ((n - 2) / 3, (n - 1) / 3);
+        let left = build_tree(nodes, n1, h - 1);
+        let mut red = nodes.next().unwrap();
+        red.left = left;
+        red.right = build_tree(nodes, n2, h - 1);
+        red.set_red();
+        let mut node = nodes.next().unwrap();
+        node.left = Some(red);
+        node.right = build_tree(nodes, n - 2 - n1 - n2, h - 1);
+        node.set_black();
+        Some(node)
+    }
+}
+
+// Build a balanced tree from detached nodes in sort order, and compute
+// its book-keeping from the nodes.
+fn nodes_to_debris<K, V>(nodes: Vec<Box<Node<K, V>>>, seqno: u64) -> Result<SquashDebris<K, V>>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    let mut debris = SquashDebris {
+        root: None,
+        seqno,
+        n_count: 0,
+        n_deleted: 0,
+        key_footprint: 0,
+        tree_footprint: 0,
+    };
+    for node in nodes.iter() {
+        debris.seqno = cmp::max(debris.seqno, node.to_seqno());
+        debris.n_count += 1;
+        if node.is_deleted() {
+            debris.n_deleted += 1;
+        }
+        debris.key_footprint += util::key_footprint(node.as_key())?;
+        debris.tree_footprint += node.footprint()?;
+    }
+
+    let (n, h) = (debris.n_count, tree_height(debris.n_count));
+    debris.root = build_tree(&mut nodes.into_iter(), n, h);
+    Ok(debris)
+}
+
+// Merge two squashed trees, where all keys in `one` sort before keys in
+// `two`, into a single balanced tree. Book-keeping is recomputed from
+// the nodes and validated against the book-keeping of both the trees.
+fn merge_debris<K, V>(
+    one: SquashDebris<K, V>,
+    two: SquashDebris<K, V>,
+) -> Result<SquashDebris<K, V>>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    let seqno = cmp::max(one.seqno, two.seqno);
+    let mut nodes = Vec::with_capacity(one.n_count + two.n_count);
+    flatten_tree(one.root, &mut nodes);
+    flatten_tree(two.root, &mut nodes);
+    let debris = nodes_to_debris(nodes, seqno)?;
+
+    // validation
+    assert_eq!(debris.seqno, seqno);
+    assert_eq!(debris.n_count, one.n_count + two.n_count);
+    assert_eq!(debris.n_deleted, one.n_deleted + two.n_deleted);
+    let key_footprint = one.key_footprint + two.key_footprint;
+    assert_eq!(debris.key_footprint, key_footprint);
+    let tree_footprint = one.tree_footprint + two.tree_footprint;
+    assert_eq!(debris.tree_footprint, tree_footprint);
+
+    Ok(debris)
+}
+
+// Build a balanced tree from entries in sort order. For lsm, entries
+// are loaded as is, along with their versions. Otherwise older versions
+// are dropped and deleted entries are skipped, seqno of skipped entries
+// still count towards the tree's seqno.
+fn sorted_debris<K, V>(iter: IndexIter<K, V>, lsm: bool) -> Result<SquashDebris<K, V>>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    let (mut nodes, mut seqno): (Vec<Box<Node<K, V>>>, u64) = (vec![], 0);
+    let mut last_key: Option<K> = None;
+    for (i, entry) in iter.enumerate() {
+        let mut entry = entry?;
+        match &last_key {
+            Some(key) if key.ge(entry.as_key()) => {
+                let msg = format!("entry {} not in sort order", i);
+                return err_at!(InvalidInput, msg: msg);
+            }
+            _ => last_key = Some(entry.to_key()),
+        }
+        seqno = cmp::max(seqno, entry.to_seqno());
+        if !lsm {
+            if entry.is_deleted() {
+                continue;
+            }
+            entry.set_deltas(vec![]);
+        }
+
+        let mut node: Box<Node<K, V>> = Box::new(From::from(entry));
+        node.dirty = false;
+        nodes.push(node);
+    }
+    nodes_to_debris(nodes, seqno)
+}
+
+// Return the seqno and key of `k` entries with the oldest seqno, from
+// the tree rooted at `node`, sorted by seqno, oldest first. Used to pick
+// entries for eviction, a bounded max-heap keeps this O(n log k) in time
+// and O(k) in space.
+fn to_eviction_order<K, V>(node: Option<&Node<K, V>>, k: usize) -> Vec<(u64, K)>
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    fn do_collect<K, V>(node: Option<&Node<K, V>>, k: usize, heap: &mut BinaryHeap<(u64, K)>)
+    where
+        K: Ord + Clone,
+        V: Clone + Diff,
+    {
+        if let Some(node) = node {
+            do_collect(node.as_left_deref(), k, heap);
+            let seqno = node.to_seqno();
+            if heap.len() < k {
+                heap.push((seqno, node.to_key()));
+            } else if heap.peek().map_or(false, |(top, _)| seqno < *top) {
+                heap.pop();
+                heap.push((seqno, node.to_key()));
+            }
+            do_collect(node.as_right_deref(), k, heap);
+        }
+    }
+
+    let mut heap = BinaryHeap::with_capacity(k);
+    do_collect(node, k, &mut heap);
+    heap.into_sorted_vec()
+}
+
+// Estimate the number of entries to evict, for the tree footprint to
+// fall to `low_water`, based on the average footprint of an entry.
+fn to_eviction_count(tree_footprint: isize, n_count: usize, low_water: isize) -> Result<usize> {
+    let n_count: isize = convert_at!(n_count)?;
+    let avg = cmp::max(tree_footprint / cmp::max(n_count, 1), 1);
+    convert_at!(((tree_footprint - low_water) / avg) + 1)
+}
+
 /// Iterator type, to do full table scan, for both [Llrb] and [Mvcc] index.
 ///
 /// A full table scan using this type is optimal when used with concurrent
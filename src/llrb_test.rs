@@ -1214,6 +1214,206 @@ fn test_split() {
     }
 }
 
+#[test]
+fn test_merge() {
+    let seed: u128 = random();
+    println!("seed:{}", seed,);
+    for i in 0..50 {
+        let seed = seed + (i * 10);
+        let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+        let lsm: bool = rng.gen();
+        let sticky: bool = rng.gen();
+
+        let mut llrb: Box<Llrb<i64, i64>> = if lsm {
+            Llrb::new_lsm("test-llrb")
+        } else {
+            Llrb::new("test-llrb")
+        };
+        llrb.set_sticky(sticky).unwrap();
+
+        let n_ops = match rng.gen::<u8>() % 10 {
+            0 => 0,
+            1 => 1,
+            _ => i64::abs(rng.gen::<i64>() % 60_000),
+        };
+        let key_max = 20_000;
+        random_llrb(n_ops, key_max, seed, &mut llrb);
+        println!(
+            "index-config: lsm:{} sticky:{} nops:{} key_max:{}",
+            lsm, sticky, n_ops, key_max,
+        );
+
+        let mut refllrb = llrb.clone();
+        let (first, second) = llrb
+            .split("first".to_string(), "second".to_string())
+            .unwrap();
+        if first.len() > 0 && second.len() > 0 {
+            let (one, two) = (first.clone(), second.clone());
+            match Llrb::merge(*two, *one, "merged".to_string()) {
+                Err(Error::InvalidInput(_)) => (),
+                _ => unreachable!(),
+            }
+        }
+
+        let mut llrb = Llrb::merge(*first, *second, "merged".to_string()).unwrap();
+        assert_eq!(llrb.to_name().unwrap(), "merged".to_string());
+        assert_eq!(llrb.is_lsm(), lsm);
+        assert_eq!(llrb.to_seqno().unwrap(), refllrb.to_seqno().unwrap());
+        assert_eq!(llrb.len(), refllrb.len());
+        assert_eq!(llrb.footprint().unwrap(), refllrb.footprint().unwrap());
+        let (stats, refstats) = (llrb.validate().unwrap(), refllrb.validate().unwrap());
+        assert_eq!(stats.n_deleted, refstats.n_deleted);
+        assert_eq!(stats.key_footprint, refstats.key_footprint);
+
+        let mut iter = llrb.iter().unwrap();
+        let mut refiter = refllrb.iter().unwrap();
+        loop {
+            match (iter.next(), refiter.next()) {
+                (Some(Ok(lentry)), Some(Ok(mentry))) => {
+                    check_node1(&lentry, &mentry);
+                }
+                (None, None) => break,
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_from_sorted_iter() {
+    let seed: u128 = random();
+    println!("seed:{}", seed,);
+    for i in 0..20 {
+        let seed = seed + (i * 10);
+        let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+        let lsm: bool = rng.gen();
+        let mut llrb: Box<Llrb<i64, i64>> = if lsm {
+            Llrb::new_lsm("test-llrb")
+        } else {
+            Llrb::new("test-llrb")
+        };
+
+        let n_ops = match rng.gen::<u8>() % 10 {
+            0 => 0,
+            1 => 1,
+            _ => i64::abs(rng.gen::<i64>() % 60_000),
+        };
+        let key_max = 20_000;
+        random_llrb(n_ops, key_max, seed, &mut llrb);
+        println!(
+            "index-config: lsm:{} nops:{} key_max:{}",
+            lsm, n_ops, key_max
+        );
+
+        if llrb.len() > 1 {
+            let iter = llrb
+                .reverse_with_versions((Bound::<i64>::Unbounded, Bound::<i64>::Unbounded))
+                .unwrap();
+            match Llrb::from_sorted_iter("loaded", lsm, iter) {
+                Err(Error::InvalidInput(_)) => (),
+                _ => unreachable!(),
+            }
+        }
+
+        let iter = llrb.iter_with_versions().unwrap();
+        let mut index = Llrb::from_sorted_iter("loaded", lsm, iter).unwrap();
+        assert_eq!(index.to_name().unwrap(), "loaded".to_string());
+        assert_eq!(index.is_lsm(), lsm);
+        assert!(index.to_seqno().unwrap() <= llrb.to_seqno().unwrap());
+        assert_eq!(index.len(), llrb.len());
+        assert_eq!(index.footprint().unwrap(), llrb.footprint().unwrap());
+        let (stats, refstats) = (index.validate().unwrap(), llrb.validate().unwrap());
+        assert_eq!(stats.n_deleted, refstats.n_deleted);
+        assert_eq!(stats.key_footprint, refstats.key_footprint);
+
+        let mut iter = index.iter_with_versions().unwrap();
+        let mut refiter = llrb.iter_with_versions().unwrap();
+        loop {
+            match (iter.next(), refiter.next()) {
+                (Some(Ok(entry)), Some(Ok(refentry))) => {
+                    check_node1(&entry, &refentry);
+                }
+                (None, None) => break,
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_from_sorted_iter_non_lsm() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let n_ops = i64::abs(rng.gen::<i64>() % 60_000);
+    random_llrb(n_ops, 20_000 /*key_max*/, seed, &mut llrb);
+
+    // load lsm entries, along with their versions, into non-lsm index.
+    let iter = llrb.iter_with_versions().unwrap();
+    let mut index = Llrb::from_sorted_iter("loaded", false /*lsm*/, iter).unwrap();
+    assert_eq!(index.is_lsm(), false);
+    assert!(index.to_seqno().unwrap() <= llrb.to_seqno().unwrap());
+    let stats = index.validate().unwrap();
+    assert_eq!(stats.n_deleted, 0);
+
+    let mut n_count = 0;
+    for refentry in llrb.iter_with_versions().unwrap() {
+        let refentry = refentry.unwrap();
+        match index.get(refentry.as_key()) {
+            Ok(entry) => {
+                assert!(!refentry.is_deleted());
+                assert_eq!(entry.to_native_value(), refentry.to_native_value());
+                assert_eq!(entry.to_seqno(), refentry.to_seqno());
+                assert_eq!(entry.as_deltas().len(), 0);
+                n_count += 1;
+            }
+            Err(Error::KeyNotFound) => assert!(refentry.is_deleted()),
+            Err(err) => panic!("unexpected {:?}", err),
+        }
+    }
+    assert_eq!(index.len(), n_count);
+}
+
+#[test]
+fn test_mem_budget() {
+    let budget = 100 * 1024;
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    llrb.set_mem_budget(budget).unwrap();
+
+    let n = 10_000;
+    for key in 0..n {
+        llrb.set(key, key * 10).unwrap();
+        assert!(llrb.footprint().unwrap() <= (budget as isize));
+    }
+
+    let stats = llrb.validate().unwrap();
+    assert!(stats.n_evicted > 0);
+    assert_eq!(stats.entries + stats.n_evicted, n as usize);
+
+    // only the oldest entries are evicted.
+    let keys: Vec<i64> = llrb.iter().unwrap().map(|e| e.unwrap().to_key()).collect();
+    let start = n - (keys.len() as i64);
+    assert_eq!(keys, (start..n).collect::<Vec<i64>>());
+
+    // memory budget is not applicable for lsm and sticky index.
+    assert!(llrb.set_sticky(true).is_err());
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb-lsm");
+    match llrb.set_mem_budget(budget) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res.map(|_| ())),
+    }
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb-sticky");
+    llrb.set_sticky(true).unwrap();
+    match llrb.set_mem_budget(budget) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res.map(|_| ())),
+    }
+}
+
 #[test]
 fn test_commit1() {
     let mut index1: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-index1");
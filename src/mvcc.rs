@@ -37,6 +37,7 @@ use log::{debug, error, info, warn};
 use std::{
     borrow::Borrow,
     cmp::{self, Ord, Ordering},
+    collections::BinaryHeap,
     convert::{self, TryFrom, TryInto},
     ffi, fmt,
     fmt::Debug,
@@ -53,7 +54,7 @@ use std::{
 
 use crate::{
     core::{CommitIter, Cutoff, Result, ScanEntry, ScanIter, Value, WalWriter},
-    core::{CommitIterator, ToJson, Validate, WriteIndexFactory, Writer},
+    core::{CommitIterator, ToJson, ToStats, Validate, WriteIndexFactory, Writer},
     core::{Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
     error::Error,
     llrb::Llrb,
@@ -77,6 +78,7 @@ pub struct MvccFactory {
     lsm: bool,
     sticky: bool,
     spin: bool,
+    mem_budget: Option<usize>,
 }
 
 /// Create a new [MvccFactory] with initial set of configuration.
@@ -94,6 +96,7 @@ pub fn mvcc_factory(lsm: bool) -> MvccFactory {
         lsm,
         sticky: false,
         spin: true,
+        mem_budget: None,
     }
 }
 
@@ -112,10 +115,18 @@ impl MvccFactory {
         Ok(self)
     }
 
+    /// Create all Mvcc instances with a memory budget, refer to
+    /// Mvcc::set_mem_budget() for more details. Creating lsm or sticky
+    /// instances with a memory budget shall fail.
+    pub fn set_mem_budget(&mut self, budget: usize) -> Result<&mut Self> {
+        self.mem_budget = Some(budget);
+        Ok(self)
+    }
+
     fn to_config_string(&self) -> String {
         format!(
-            "mvcc = {{ lsm = {}, sticky = {}, spin = {} }}",
-            self.lsm, self.sticky, self.spin
+            "mvcc = {{ lsm = {}, sticky = {}, spin = {}, mem_budget = {:?} }}",
+            self.lsm, self.sticky, self.spin, self.mem_budget
         )
     }
 }
@@ -140,6 +151,9 @@ where
             index
         };
         index.set_spinlatch(self.spin)?;
+        if let Some(budget) = self.mem_budget {
+            index.set_mem_budget(budget)?;
+        }
 
         info!(
             target: "mvccfc",
@@ -164,6 +178,7 @@ where
     lsm: bool,
     sticky: bool,
     spin: bool,
+    mem_budget: Option<usize>,
 
     snapshot: OuterSnapshot<K, V>,
     latch: RWSpinlock,
@@ -171,6 +186,7 @@ where
     tree_footprint: isize,
     n_deleted: usize,
     n_reclaimed: usize,
+    n_evicted: usize,
     readers: Arc<u32>,
     writers: Arc<u32>,
 }
@@ -289,6 +305,7 @@ where
             lsm: false,
             sticky: false,
             spin: true,
+            mem_budget: None,
 
             snapshot: OuterSnapshot::new(),
             latch: RWSpinlock::new(),
@@ -296,6 +313,7 @@ where
             tree_footprint: Default::default(),
             n_deleted: Default::default(),
             n_reclaimed: Default::default(),
+            n_evicted: Default::default(),
             readers: Arc::new(0xC0FFEE),
             writers: Arc::new(0xC0FFEE),
         })
@@ -310,6 +328,7 @@ where
             lsm: true,
             sticky: false,
             spin: true,
+            mem_budget: None,
 
             snapshot: OuterSnapshot::new(),
             latch: RWSpinlock::new(),
@@ -317,6 +336,7 @@ where
             tree_footprint: Default::default(),
             n_deleted: Default::default(),
             n_reclaimed: Default::default(),
+            n_evicted: Default::default(),
             readers: Arc::new(0xC0FFEE),
             writers: Arc::new(0xC0FFEE),
         })
@@ -344,7 +364,9 @@ where
     /// deleted and but its value shall be removed.
     pub fn set_sticky(&mut self, sticky: bool) -> Result<&mut Self> {
         let n = self.multi_rw();
-        if n == 0 {
+        if sticky && self.mem_budget.is_some() {
+            err_at!(InvalidInput, msg: format!("sticky with mem_budget"))
+        } else if n == 0 {
             self.sticky = sticky;
             Ok(self)
         } else {
@@ -401,6 +423,7 @@ where
             lsm: self.lsm,
             sticky: self.sticky,
             spin: self.spin,
+            mem_budget: self.mem_budget,
 
             snapshot: OuterSnapshot::new(),
             latch: RWSpinlock::new(),
@@ -408,6 +431,7 @@ where
             tree_footprint: self.tree_footprint,
             n_deleted: self.n_deleted,
             n_reclaimed: Default::default(),
+            n_evicted: Default::default(),
             readers: Arc::new(0xC0FFEE),
             writers: Arc::new(0xC0FFEE),
         });
@@ -429,6 +453,99 @@ where
 
         Ok(cloned)
     }
+
+    /// Build a new index, in linear time, from entries in sort order,
+    /// refer to [Llrb::from_sorted_iter].
+    pub fn from_sorted_iter<S>(name: S, lsm: bool, iter: IndexIter<K, V>) -> Result<Box<Mvcc<K, V>>>
+    where
+        S: AsRef<str>,
+        K: Footprint,
+        V: Footprint,
+    {
+        let mut index = if lsm {
+            Mvcc::new_lsm(name)
+        } else {
+            Mvcc::new(name)
+        };
+
+        let debris = sorted_debris(iter, lsm)?;
+        index
+            .snapshot
+            .n_nodes
+            .store(convert_at!(debris.n_count)?, SeqCst);
+        index.key_footprint = debris.key_footprint;
+        index.tree_footprint = debris.tree_footprint;
+        index.n_deleted = debris.n_deleted;
+        index.snapshot.shift_snapshot(
+            debris.root,
+            debris.seqno,
+            debris.n_count,
+            vec![], /*reclaim*/
+        );
+
+        debug!(
+            target: "mvcc  ", "{}, from sorted iter seqno:{} len:{}",
+            index.name, debris.seqno, debris.n_count
+        );
+
+        Ok(index)
+    }
+
+    /// Merge two indexes into a single index, refer to [Llrb::merge].
+    /// All keys in index `one` shall sort before keys in index `two`.
+    pub fn merge(one: Mvcc<K, V>, two: Mvcc<K, V>, name: String) -> Result<Box<Mvcc<K, V>>>
+    where
+        K: Footprint,
+        V: Footprint,
+    {
+        let n = one.multi_rw() + two.multi_rw();
+        if n > 0 {
+            return err_at!(APIMisuse, msg: format!("active-handles:{}", n));
+        } else if one.lsm != two.lsm {
+            let msg = format!("lsm mismatch {} {}", one.name, two.name);
+            return err_at!(InvalidInput, msg: msg);
+        }
+        let ok = {
+            let s1 = OuterSnapshot::clone(&one.snapshot);
+            let s2 = OuterSnapshot::clone(&two.snapshot);
+            is_adjacent(s1.as_root(), s2.as_root())
+        };
+        if !ok {
+            let msg = format!("overlapping keys {} {}", one.name, two.name);
+            return err_at!(InvalidInput, msg: msg);
+        }
+
+        let mut index = if one.lsm {
+            Mvcc::new_lsm(&name)
+        } else {
+            Mvcc::new(&name)
+        };
+        index.set_sticky(one.sticky)?.set_spinlatch(one.spin)?;
+
+        let (name1, name2) = (one.name.clone(), two.name.clone());
+        let debris = merge_debris(one.squash()?, two.squash()?)?;
+        index
+            .snapshot
+            .n_nodes
+            .store(convert_at!(debris.n_count)?, SeqCst);
+        index.key_footprint = debris.key_footprint;
+        index.tree_footprint = debris.tree_footprint;
+        index.n_deleted = debris.n_deleted;
+        index.snapshot.shift_snapshot(
+            debris.root,
+            debris.seqno,
+            debris.n_count,
+            vec![], /*reclaim*/
+        );
+
+        debug!(
+            target: "mvcc  ",
+            "{}, merged {} and {} -> (seqno:{} len:{})",
+            index.name, name1, name2, debris.seqno, debris.n_count
+        );
+
+        Ok(index)
+    }
 }
 
 /// Maintanence API.
@@ -475,11 +592,29 @@ where
         stats.tree_footprint = self.tree_footprint;
         stats.n_deleted = self.n_deleted;
         stats.n_reclaimed = self.n_reclaimed;
+        stats.n_evicted = self.n_evicted;
         stats.rw_latch = self.latch.to_stats()?;
         stats.snapshot_latch = self.snapshot.ulatch.to_stats()?;
         Ok(stats)
     }
 
+    /// Return a read handle pinned to the latest snapshot of this index.
+    /// Reads on the handle see the index as of [MvccSnapshot::to_seqno],
+    /// while writers continue to mutate the index. Nodes reclaimed by
+    /// subsequent writes are held back until all clones of the handle are
+    /// dropped. Handles shall be dropped before dropping the index.
+    pub fn to_snapshot(&self) -> Result<MvccSnapshot<K, V>> {
+        let snapshot = OuterSnapshot::clone(&self.snapshot);
+        debug!(
+            target: "mvcc  ", "{}, new snapshot at seqno {}", self.name, snapshot.seqno
+        );
+        Ok(MvccSnapshot {
+            name: self.name.clone(),
+            _refn: Arc::clone(&self.readers),
+            snapshot,
+        })
+    }
+
     fn multi_rw(&self) -> usize {
         Arc::strong_count(&self.readers) + Arc::strong_count(&self.writers) - 2
     }
@@ -646,6 +781,7 @@ where
                 self.set_index_entry(entry?)?;
                 count += 1;
             }
+            self.evict()?;
             count
         };
 
@@ -772,6 +908,7 @@ where
                 return err_at!(Fatal, msg: format!("call-the-programmer"));
             }
         }
+        self.evict()?;
         Ok((seqno, old_entry))
     }
 
@@ -842,6 +979,7 @@ where
         // TODO: can we optimize this for no-op cases (err cases) ?
         self.n_reclaimed += rclm.len();
         self.snapshot.shift_snapshot(root, seqno, n_count, rclm);
+        self.evict()?;
         Ok((seqno, entry))
     }
 
@@ -931,6 +1069,7 @@ where
 
         self.n_reclaimed += rclm.len();
         self.snapshot.shift_snapshot(root, seqno, n_count, rclm);
+        self.evict()?;
         Ok((seqno, Ok(old_entry)))
     }
 }
@@ -1636,6 +1775,70 @@ where
     }
 }
 
+/// Memory budget for Mvcc index.
+impl<K, V> Mvcc<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Configure a memory budget, in bytes, for this index, refer to
+    /// [Llrb::set_mem_budget] for details. Return error for lsm and
+    /// sticky index.
+    pub fn set_mem_budget(&mut self, budget: usize) -> Result<&mut Self> {
+        if self.lsm || self.sticky {
+            let msg = format!("mem_budget for lsm:{} sticky:{}", self.lsm, self.sticky);
+            err_at!(InvalidInput, msg: msg)?
+        }
+        {
+            let _w = self.latch.acquire_write(self.spin);
+            self.mem_budget = Some(budget);
+            self.evict()?;
+        }
+        Ok(self)
+    }
+
+    // caller hold a write latch.
+    fn evict(&self) -> Result<usize> {
+        let budget: isize = match self.mem_budget {
+            Some(budget) => convert_at!(budget)?,
+            None => return Ok(0),
+        };
+        if self.tree_footprint <= budget {
+            return Ok(0);
+        }
+
+        let low_water = (budget / 10) * 9;
+        let mut n = 0;
+        while self.tree_footprint > low_water && self.len() > 0 {
+            let keys = {
+                let k = to_eviction_count(self.tree_footprint, self.len(), low_water)?;
+                let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
+                to_eviction_order(snapshot.as_root(), k)
+            };
+            for (_seqno, key) in keys.into_iter() {
+                if self.tree_footprint <= low_water {
+                    break;
+                }
+                self.delete_index_entry(key)?;
+                n += 1;
+            }
+        }
+
+        let mself = unsafe {
+            // caller hold a write latch.
+            (self as *const Self as *mut Self).as_mut().unwrap()
+        };
+        mself.n_evicted += n;
+
+        debug!(
+            target: "mvcc  ", "{}, evicted {} entries, footprint:{}",
+            self.name, n, self.tree_footprint
+        );
+
+        Ok(n)
+    }
+}
+
 /// Read operations on Mvcc instance.
 impl<K, V> Reader<K, V> for Mvcc<K, V>
 where
@@ -1654,18 +1857,7 @@ where
     }
 
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
-        let mut iter = Box::new(Iter {
-            _latch: Default::default(),
-            _arc: OuterSnapshot::clone(&self.snapshot),
-            paths: Default::default(),
-        });
-        let root = iter
-            ._arc
-            .as_ref()
-            .root_duplicate()
-            .map(|n| Box::leak(n) as &Node<K, V>);
-        iter.paths = Some(build_iter(IFlag::Left, root, vec![])?);
-        Ok(iter)
+        Snapshot::iter(OuterSnapshot::clone(&self.snapshot))
     }
 
     fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
@@ -1674,24 +1866,7 @@ where
         R: 'a + Clone + RangeBounds<Q>,
         Q: 'a + Ord + ?Sized,
     {
-        let mut r = Box::new(Range {
-            _latch: Default::default(),
-            _arc: OuterSnapshot::clone(&self.snapshot),
-            range,
-            paths: Default::default(),
-            high: marker::PhantomData,
-        });
-        let root = r
-            ._arc
-            .as_ref()
-            .root_duplicate()
-            .map(|n| Box::leak(n) as &Node<K, V>);
-        r.paths = match r.range.start_bound() {
-            Bound::Unbounded => Some(build_iter(IFlag::Left, root, vec![])?),
-            Bound::Included(low) => Some(find_start(root, low, true, vec![])),
-            Bound::Excluded(low) => Some(find_start(root, low, false, vec![])),
-        };
-        Ok(r)
+        Snapshot::range(OuterSnapshot::clone(&self.snapshot), range)
     }
 
     fn reverse<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
@@ -1700,24 +1875,7 @@ where
         R: 'a + Clone + RangeBounds<Q>,
         Q: 'a + Ord + ?Sized,
     {
-        let mut r = Box::new(Reverse {
-            _latch: Default::default(),
-            _arc: OuterSnapshot::clone(&self.snapshot),
-            range,
-            paths: Default::default(),
-            low: marker::PhantomData,
-        });
-        let root = r
-            ._arc
-            .as_ref()
-            .root_duplicate()
-            .map(|n| Box::leak(n) as &Node<K, V>);
-        r.paths = match r.range.end_bound() {
-            Bound::Unbounded => Some(build_iter(IFlag::Right, root, vec![])?),
-            Bound::Included(high) => Some(find_end(root, high, true, vec![])),
-            Bound::Excluded(high) => Some(find_end(root, high, false, vec![])),
-        };
-        Ok(r)
+        Snapshot::reverse(OuterSnapshot::clone(&self.snapshot), range)
     }
 
     /// Short circuited to get().
@@ -1936,6 +2094,26 @@ where
     }
 }
 
+impl<K, V> ToStats<Stats> for Box<Mvcc<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn to_stats(&self) -> Result<Stats> {
+        self.as_ref().to_stats()
+    }
+}
+
+impl<K, V> ToStats<Stats> for Mvcc<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn to_stats(&self) -> Result<Stats> {
+        Mvcc::to_stats(self)
+    }
+}
+
 impl<K, V> Validate<Stats> for Box<Mvcc<K, V>>
 where
     K: Clone + Ord + Debug,
@@ -1991,6 +2169,7 @@ where
         stats.tree_footprint = self.tree_footprint;
         stats.n_deleted = self.n_deleted;
         stats.n_reclaimed = self.n_reclaimed;
+        stats.n_evicted = self.n_evicted;
         stats.node_size = mem::size_of::<Node<K, V>>();
         stats.rw_latch = self.latch.to_stats()?;
         stats.snapshot_latch = self.snapshot.ulatch.to_stats()?;
@@ -2371,6 +2550,79 @@ where
     fn as_root(&self) -> Option<&Node<K, V>> {
         self.root.as_ref().map(Deref::deref)
     }
+
+    fn iter<'a>(snapshot: Arc<Snapshot<K, V>>) -> Result<IndexIter<'a, K, V>>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let mut iter = Box::new(Iter {
+            _latch: Default::default(),
+            _arc: snapshot,
+            paths: Default::default(),
+        });
+        let root = iter
+            ._arc
+            .as_ref()
+            .root_duplicate()
+            .map(|n| Box::leak(n) as &Node<K, V>);
+        iter.paths = Some(build_iter(IFlag::Left, root, vec![])?);
+        Ok(iter)
+    }
+
+    fn range<'a, R, Q>(snapshot: Arc<Snapshot<K, V>>, range: R) -> Result<IndexIter<'a, K, V>>
+    where
+        K: 'a + Borrow<Q>,
+        V: 'a,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let mut r = Box::new(Range {
+            _latch: Default::default(),
+            _arc: snapshot,
+            range,
+            paths: Default::default(),
+            high: marker::PhantomData,
+        });
+        let root = r
+            ._arc
+            .as_ref()
+            .root_duplicate()
+            .map(|n| Box::leak(n) as &Node<K, V>);
+        r.paths = match r.range.start_bound() {
+            Bound::Unbounded => Some(build_iter(IFlag::Left, root, vec![])?),
+            Bound::Included(low) => Some(find_start(root, low, true, vec![])),
+            Bound::Excluded(low) => Some(find_start(root, low, false, vec![])),
+        };
+        Ok(r)
+    }
+
+    fn reverse<'a, R, Q>(snapshot: Arc<Snapshot<K, V>>, range: R) -> Result<IndexIter<'a, K, V>>
+    where
+        K: 'a + Borrow<Q>,
+        V: 'a,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let mut r = Box::new(Reverse {
+            _latch: Default::default(),
+            _arc: snapshot,
+            range,
+            paths: Default::default(),
+            low: marker::PhantomData,
+        });
+        let root = r
+            ._arc
+            .as_ref()
+            .root_duplicate()
+            .map(|n| Box::leak(n) as &Node<K, V>);
+        r.paths = match r.range.end_bound() {
+            Bound::Unbounded => Some(build_iter(IFlag::Right, root, vec![])?),
+            Bound::Included(high) => Some(find_end(root, high, true, vec![])),
+            Bound::Excluded(high) => Some(find_end(root, high, false, vec![])),
+        };
+        Ok(r)
+    }
 }
 
 impl<K, V> Drop for Snapshot<K, V>
@@ -2634,6 +2886,139 @@ where
     }
 }
 
+/// Point-in-time read handle into [Mvcc] index, refer to
+/// [Mvcc::to_snapshot].
+pub struct MvccSnapshot<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    name: String,
+    _refn: Arc<u32>,
+    snapshot: Arc<Snapshot<K, V>>,
+}
+
+impl<K, V> Clone for MvccSnapshot<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn clone(&self) -> Self {
+        MvccSnapshot {
+            name: self.name.clone(),
+            _refn: Arc::clone(&self._refn),
+            snapshot: Arc::clone(&self.snapshot),
+        }
+    }
+}
+
+impl<K, V> MvccSnapshot<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Return the name of the index this snapshot was taken from.
+    pub fn to_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Return the index seqno as of this snapshot.
+    pub fn to_seqno(&self) -> u64 {
+        self.snapshot.seqno
+    }
+
+    /// Return number of entries in the index as of this snapshot.
+    pub fn len(&self) -> usize {
+        self.snapshot.n_count
+    }
+
+    /// Return whether the index was empty as of this snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Read operations against the pinned snapshot.
+impl<K, V> Reader<K, V> for MvccSnapshot<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Get ``key`` as of this snapshot.
+    fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        get(self.snapshot.as_root(), key)
+    }
+
+    /// Iterate over all entries as of this snapshot.
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        Snapshot::iter(Arc::clone(&self.snapshot))
+    }
+
+    /// Iterate from lower bound to upper bound.
+    fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        Snapshot::range(Arc::clone(&self.snapshot), range)
+    }
+
+    /// Iterate from upper bound to lower bound.
+    fn reverse<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        Snapshot::reverse(Arc::clone(&self.snapshot), range)
+    }
+
+    /// Short circuited to get().
+    fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.get(key)
+    }
+
+    /// Short circuited to iter().
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        self.iter()
+    }
+
+    /// Short circuited to range().
+    fn range_with_versions<'a, R, Q>(
+        &'a mut self, // reader cannot be shared
+        r: R,
+    ) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.range(r)
+    }
+
+    /// Short circuited to reverse()
+    fn reverse_with_versions<'a, R, Q>(
+        &'a mut self, // reader cannot be shared
+        r: R,
+    ) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reverse(r)
+    }
+}
+
 /// Write handle into [Mvcc] index.
 pub struct MvccWriter<K, V>
 where
@@ -2839,6 +3224,7 @@ pub struct Stats {
     pub entries: usize,
     pub n_deleted: usize,
     pub n_reclaimed: usize,
+    pub n_evicted: usize,
     pub node_size: usize,
     pub key_footprint: isize,
     pub tree_footprint: isize,
@@ -2855,6 +3241,7 @@ impl Stats {
             entries: Default::default(),
             n_deleted: Default::default(),
             n_reclaimed: Default::default(),
+            n_evicted: Default::default(),
             node_size: Default::default(),
             key_footprint: Default::default(),
             tree_footprint: Default::default(),
@@ -2879,8 +3266,8 @@ impl fmt::Display for Stats {
         )?;
         write!(
             f,
-            "mvcc = {{ n_reclaimed={}, key_footprint={}, tree_footprint={} }}\n",
-            self.n_reclaimed, self.key_footprint, self.tree_footprint,
+            "mvcc = {{ n_reclaimed={}, n_evicted={}, key_footprint={}, tree_footprint={} }}\n",
+            self.n_reclaimed, self.n_evicted, self.key_footprint, self.tree_footprint,
         )?;
         write!(f, "mvcc.rw_latch = {}\n", self.rw_latch)?;
         write!(f, "mvcc.snap_latch = {}\n", self.snapshot_latch)?;
@@ -2896,7 +3283,7 @@ impl ToJson for Stats {
         format!(
             concat!(
                 r#"{{ ""mvcc": {{ "name": {}, "entries": {:X}, "#,
-                r#""n_deleted": {}, "n_reclaimed": {}, "#,
+                r#""n_deleted": {}, "n_reclaimed": {}, "n_evicted": {}, "#,
                 r#""key_footprint": {}, "tree_footprint": {}, "#,
                 r#""node_size": {}, "rw_latch": {}, "#,
                 r#""snap_latch": {}, "blacks": {}, "depths": {} }} }}"#,
@@ -2905,6 +3292,7 @@ impl ToJson for Stats {
             self.entries,
             self.n_deleted,
             self.n_reclaimed,
+            self.n_evicted,
             self.key_footprint,
             self.tree_footprint,
             self.node_size,
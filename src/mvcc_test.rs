@@ -494,6 +494,74 @@ fn test_range() {
     }
 }
 
+#[test]
+fn test_snapshot() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed {}", seed);
+
+    let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
+    for key in 0..1000 {
+        mvcc.set(key, rng.gen::<i64>()).unwrap();
+    }
+
+    let to_item = |e: Entry<i64, i64>| (e.to_key(), e.to_native_value(), e.to_seqno());
+    let mut snap = mvcc.to_snapshot().unwrap();
+    let refs: Vec<(i64, Option<i64>, u64)> =
+        mvcc.iter().unwrap().map(|e| to_item(e.unwrap())).collect();
+    let (seqno, n_count) = (mvcc.to_seqno().unwrap(), mvcc.len());
+
+    for _ in 0..5000 {
+        let key = rng.gen::<i64>() % 2000;
+        match rng.gen::<u8>() % 3 {
+            0 | 1 => {
+                mvcc.set(key, rng.gen::<i64>()).unwrap();
+            }
+            _ => {
+                mvcc.delete(&key).ok();
+            }
+        }
+    }
+    assert!(mvcc.to_seqno().unwrap() > seqno);
+
+    let mut clone = snap.clone();
+    for s in [&mut snap, &mut clone].iter_mut() {
+        assert_eq!(s.to_seqno(), seqno);
+        assert_eq!(s.len(), n_count);
+        assert_eq!(s.to_name(), "test-mvcc".to_string());
+
+        for (key, value, seqno) in refs.iter() {
+            let item = to_item(s.get(key).unwrap());
+            assert_eq!(item, (*key, *value, *seqno));
+        }
+        assert!(s.get(&1500).is_err());
+
+        let items: Vec<(i64, Option<i64>, u64)> =
+            s.iter().unwrap().map(|e| to_item(e.unwrap())).collect();
+        assert_eq!(items, refs);
+
+        let items: Vec<(i64, Option<i64>, u64)> = s
+            .range(100..200)
+            .unwrap()
+            .map(|e| to_item(e.unwrap()))
+            .collect();
+        assert_eq!(items, refs[100..200].to_vec());
+
+        let items: Vec<(i64, Option<i64>, u64)> = s
+            .reverse(100..=200)
+            .unwrap()
+            .map(|e| to_item(e.unwrap()))
+            .collect();
+        let mut ref_items = refs[100..=200].to_vec();
+        ref_items.reverse();
+        assert_eq!(items, ref_items);
+    }
+
+    std::mem::drop(clone);
+    std::mem::drop(snap);
+    assert!(mvcc.validate().is_ok());
+}
+
 #[test]
 fn test_crud() {
     let size = 1000;
@@ -820,6 +888,163 @@ fn test_pw_scan() {
     assert!(index.validate().is_ok());
 }
 
+#[test]
+fn test_merge() {
+    let seed: u128 = random();
+    println!("seed:{}", seed,);
+    for i in 0..20 {
+        let seed = seed + (i * 10);
+        let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+        let lsm: bool = rng.gen();
+        let mut mvcc: Box<Mvcc<i64, i64>> = if lsm {
+            Mvcc::new_lsm("test-mvcc")
+        } else {
+            Mvcc::new("test-mvcc")
+        };
+
+        let n_ops = match rng.gen::<u8>() % 10 {
+            0 => 0,
+            1 => 1,
+            _ => i64::abs(rng.gen::<i64>() % 60_000),
+        };
+        let key_max = 20_000;
+        random_mvcc(n_ops, key_max, seed, &mut mvcc);
+        println!(
+            "index-config: lsm:{} nops:{} key_max:{}",
+            lsm, n_ops, key_max
+        );
+
+        let mut refmvcc = mvcc.clone().unwrap();
+        let llrb: Box<Llrb<i64, i64>> = TryFrom::try_from(*mvcc).unwrap();
+        let (first, second) = llrb
+            .split("first".to_string(), "second".to_string())
+            .unwrap();
+        let first: Box<Mvcc<i64, i64>> = TryFrom::try_from(*first).unwrap();
+        let second: Box<Mvcc<i64, i64>> = TryFrom::try_from(*second).unwrap();
+
+        let mut mvcc = Mvcc::merge(*first, *second, "merged".to_string()).unwrap();
+        assert_eq!(mvcc.to_name().unwrap(), "merged".to_string());
+        assert_eq!(mvcc.is_lsm(), lsm);
+        assert_eq!(mvcc.to_seqno().unwrap(), refmvcc.to_seqno().unwrap());
+        assert_eq!(mvcc.len(), refmvcc.len());
+        assert_eq!(mvcc.footprint().unwrap(), refmvcc.footprint().unwrap());
+        let (stats, refstats) = (mvcc.validate().unwrap(), refmvcc.validate().unwrap());
+        assert_eq!(stats.n_deleted, refstats.n_deleted);
+        assert_eq!(stats.key_footprint, refstats.key_footprint);
+
+        let mut iter = mvcc.iter().unwrap();
+        let mut refiter = refmvcc.iter().unwrap();
+        loop {
+            match (iter.next(), refiter.next()) {
+                (Some(Ok(entry)), Some(Ok(refentry))) => {
+                    check_node1(&entry, &refentry);
+                }
+                (None, None) => break,
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_from_sorted_iter() {
+    let seed: u128 = random();
+    println!("seed:{}", seed,);
+    for i in 0..20 {
+        let seed = seed + (i * 10);
+        let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+        let lsm: bool = rng.gen();
+        let mut mvcc: Box<Mvcc<i64, i64>> = if lsm {
+            Mvcc::new_lsm("test-mvcc")
+        } else {
+            Mvcc::new("test-mvcc")
+        };
+
+        let n_ops = match rng.gen::<u8>() % 10 {
+            0 => 0,
+            1 => 1,
+            _ => i64::abs(rng.gen::<i64>() % 60_000),
+        };
+        let key_max = 20_000;
+        random_mvcc(n_ops, key_max, seed, &mut mvcc);
+        println!(
+            "index-config: lsm:{} nops:{} key_max:{}",
+            lsm, n_ops, key_max
+        );
+
+        if mvcc.len() > 1 {
+            let iter = mvcc
+                .reverse_with_versions((Bound::<i64>::Unbounded, Bound::<i64>::Unbounded))
+                .unwrap();
+            match Mvcc::from_sorted_iter("loaded", lsm, iter) {
+                Err(Error::InvalidInput(_)) => (),
+                _ => unreachable!(),
+            }
+        }
+
+        let iter = mvcc.iter_with_versions().unwrap();
+        let mut index = Mvcc::from_sorted_iter("loaded", lsm, iter).unwrap();
+        assert_eq!(index.to_name().unwrap(), "loaded".to_string());
+        assert_eq!(index.is_lsm(), lsm);
+        assert!(index.to_seqno().unwrap() <= mvcc.to_seqno().unwrap());
+        assert_eq!(index.len(), mvcc.len());
+        assert_eq!(index.footprint().unwrap(), mvcc.footprint().unwrap());
+        let (stats, refstats) = (index.validate().unwrap(), mvcc.validate().unwrap());
+        assert_eq!(stats.n_deleted, refstats.n_deleted);
+        assert_eq!(stats.key_footprint, refstats.key_footprint);
+
+        let mut iter = index.iter_with_versions().unwrap();
+        let mut refiter = mvcc.iter_with_versions().unwrap();
+        loop {
+            match (iter.next(), refiter.next()) {
+                (Some(Ok(entry)), Some(Ok(refentry))) => {
+                    check_node1(&entry, &refentry);
+                }
+                (None, None) => break,
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mem_budget() {
+    let budget = 100 * 1024;
+    let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
+    mvcc.set_mem_budget(budget).unwrap();
+
+    let n = 10_000;
+    for key in 0..n {
+        mvcc.set(key, key * 10).unwrap();
+        assert!(mvcc.footprint().unwrap() <= (budget as isize));
+    }
+
+    let stats = mvcc.validate().unwrap();
+    assert!(stats.n_evicted > 0);
+    assert_eq!(stats.entries + stats.n_evicted, n as usize);
+
+    // only the oldest entries are evicted.
+    let keys: Vec<i64> = mvcc.iter().unwrap().map(|e| e.unwrap().to_key()).collect();
+    let start = n - (keys.len() as i64);
+    assert_eq!(keys, (start..n).collect::<Vec<i64>>());
+
+    // memory budget is not applicable for lsm and sticky index.
+    assert!(mvcc.set_sticky(true).is_err());
+    let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc-lsm");
+    match mvcc.set_mem_budget(budget) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res.map(|_| ())),
+    }
+    let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc-sticky");
+    mvcc.set_sticky(true).unwrap();
+    match mvcc.set_mem_budget(budget) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res.map(|_| ())),
+    }
+}
+
 #[test]
 fn test_commit1() {
     let mut index1: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-index1");
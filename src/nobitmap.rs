@@ -31,8 +31,8 @@ impl Bloom for NoBitmap {
     }
 
     #[inline]
-    fn to_vec(&self) -> Vec<u8> {
-        vec![]
+    fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(vec![])
     }
 
     #[inline]
@@ -44,4 +44,9 @@ impl Bloom for NoBitmap {
     fn or(&self, _other: &NoBitmap) -> Result<NoBitmap> {
         Ok(NoBitmap)
     }
+
+    #[inline]
+    fn to_hasher_id() -> String {
+        "".to_string() // keys are not hashed.
+    }
 }
@@ -1,7 +1,52 @@
-//! [Work in progress]
+//! Module `raft_log` implement [Raft][raft-link] consensus for
+//! replicating write operations across a cluster of nodes.
+//!
+//! Replicated log for each [Raft] node is managed as a single-shard
+//! [Dlog] instance, where index-seqno of each entry is its raft index.
+//! Entries are persisted in batches, and every batch carries the raft
+//! term in which its entries were created, along with the commit-index
+//! and persisted-index known at the time of appending the batch. Current
+//! term and the node voted for, in the current term, are persisted in a
+//! separate state file before responding to peers.
+//!
+//! Following are the features in [Raft]:
+//!
+//! * Leader election with randomized election timeout.
+//! * Log replication via AppendEntries, with conflicting entries
+//!   truncated from follower's log.
+//! * Commit-index advancement, once an entry from leader's current
+//!   term is replicated on majority of nodes.
+//! * Committed entries can be applied on any index implementing
+//!   [Replay] trait, refer to [Raft::apply].
+//! * Membership changes via joint consensus, refer to [Raft::add_voter]
+//!   and [Raft::remove_voter]. Configuration entries take effect as soon
+//!   as they are added to the log, and only one change can be in
+//!   progress at any time.
+//! * Log compaction, application can supply a [Snapshot] of its index,
+//!   typically a [robt][crate::robt] file, via [Raft::set_snapshot].
+//!   Journals holding entries upto the snapshot are purged, and
+//!   followers lagging behind the snapshot are sent the snapshot file
+//!   in chunks, refer to [Raft::take_installed].
+//!
+//! **Transport**:
+//!
+//! Nodes exchange [Message] with each other via [Transport] trait, which
+//! can be implemented over any network layer. [ChannelNetwork] is an
+//! in-process implementation over channels, useful for running a cluster
+//! of nodes within a single process.
+//!
+//! **Driving a node**:
+//!
+//! [Raft] does not spawn threads, application is expected to call
+//! [Raft::tick] periodically, which drains incoming messages and
+//! advances election and heartbeat timers.
+//!
+//! [raft-link]: https://raft.github.io/
 
 use std::{convert::TryInto, fmt, result};
 
+#[allow(unused_imports)] // for documentation
+use crate::{core::Replay, dlog::Dlog};
 use crate::{
     core::{Result, Serialize},
     dlog,
@@ -9,6 +54,9 @@ use crate::{
     error::Error,
 };
 
+pub use crate::raft_node::{Config, LogEntry, Message, Raft, Role, Snapshot};
+pub use crate::raft_transport::{ChannelNetwork, ChannelTransport, Transport};
+
 // term value when not using consensus
 const NIL_TERM: u64 = 0;
 
@@ -29,11 +77,61 @@ pub(crate) struct State {
     persisted: u64,
     // List of participating nodes.
     config: Vec<String>,
+    // List of participating nodes in the older configuration, while
+    // transitioning to `config` via joint consensus. Empty otherwise.
+    old_config: Vec<String>,
     // Votedfor is the leader's address in which this batch
     // was created.
     votedfor: String,
 }
 
+impl State {
+    pub(crate) fn new(
+        term: u64,
+        committed: u64,
+        persisted: u64,
+        (config, old_config): (Vec<String>, Vec<String>),
+        votedfor: Option<String>,
+    ) -> State {
+        State {
+            term,
+            committed,
+            persisted,
+            config,
+            old_config,
+            votedfor: votedfor.unwrap_or(DEFAULT_NODE.to_string()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_term(&self) -> u64 {
+        self.term
+    }
+
+    #[inline]
+    pub(crate) fn to_committed(&self) -> u64 {
+        self.committed
+    }
+
+    #[inline]
+    pub(crate) fn to_persisted(&self) -> u64 {
+        self.persisted
+    }
+
+    #[inline]
+    pub(crate) fn to_config(&self) -> (Vec<String>, Vec<String>) {
+        (self.config.clone(), self.old_config.clone())
+    }
+
+    #[inline]
+    pub(crate) fn to_votedfor(&self) -> Option<String> {
+        match self.votedfor.as_str() {
+            DEFAULT_NODE => None,
+            votedfor => Some(votedfor.to_string()),
+        }
+    }
+}
+
 impl Default for State {
     fn default() -> Self {
         State {
@@ -41,6 +139,7 @@ impl Default for State {
             committed: Default::default(),
             persisted: Default::default(),
             config: Default::default(),
+            old_config: Default::default(),
             votedfor: DEFAULT_NODE.to_string(),
         }
     }
@@ -54,9 +153,21 @@ where
     type Key = K;
     type Val = V;
 
-    // TODO: add test cases for this.
-    fn on_add_entry(&mut self, _entry: &DEntry<Op<K, V>>) -> () {
-        todo!()
+    // Term, commit-index and persisted-index for a batch are set by the
+    // raft node while creating the batch. Configuration changes take
+    // effect as soon as they are added to the log.
+    fn on_add_entry(&mut self, entry: &DEntry<Op<K, V>>) -> () {
+        match entry.as_op() {
+            Op::JointConfig { old, new } => {
+                self.config = new.clone();
+                self.old_config = old.clone();
+            }
+            Op::Config { nodes } => {
+                self.config = nodes.clone();
+                self.old_config = vec![];
+            }
+            _ => (),
+        }
     }
 
     fn to_type(&self) -> String {
@@ -71,18 +182,8 @@ impl Serialize for State {
         buf.extend_from_slice(&self.persisted.to_be_bytes());
         let mut n = 24;
 
-        let count: u16 = convert_at!(self.config.len())?;
-        buf.extend_from_slice(&count.to_be_bytes());
-        n += 2;
-        for cnf in self.config.iter() {
-            let b = cnf.as_bytes();
-            {
-                let len: u16 = convert_at!(b.len())?;
-                buf.extend_from_slice(&len.to_be_bytes());
-            }
-            buf.extend_from_slice(b);
-            n += 2 + b.len();
-        }
+        n += encode_names(buf, &self.config)?;
+        n += encode_names(buf, &self.old_config)?;
 
         let b = self.votedfor.as_bytes();
         let len: u16 = convert_at!(b.len())?;
@@ -102,22 +203,12 @@ impl Serialize for State {
         self.persisted = u64::from_be_bytes(array_at!(buf[16..24])?);
         let mut n = 24;
 
-        let count = u16::from_be_bytes(array_at!(buf[n..n + 2])?);
-        self.config = Vec::with_capacity(convert_at!(count)?);
-        n += 2;
-
-        for _i in 0..count {
-            check_remaining!(buf, n + 2, "raft-batch-config")?;
-
-            let m: usize = convert_at!(u16::from_be_bytes(array_at!(buf[n..n + 2])?))?;
-            n += 2;
-
-            check_remaining!(buf, n + m, "raft-batch-config")?;
-
-            let s = err_at!(InvalidInput, from_utf8(&buf[n..n + m]))?;
-            self.config.push(s.to_string());
-            n += m;
-        }
+        let (config, m) = decode_names(&buf[n..], "raft-batch-config")?;
+        self.config = config;
+        n += m;
+        let (old_config, m) = decode_names(&buf[n..], "raft-batch-old-config")?;
+        self.old_config = old_config;
+        n += m;
 
         check_remaining!(buf, n + 2, "raft-batch-votedfor")?;
 
@@ -137,6 +228,55 @@ impl Serialize for State {
     }
 }
 
+// +----------------------------------------------------------------+
+// |  count  |  len  |  name  |  len  |  name  | ...                |
+// +----------------------------------------------------------------+
+//
+// count: 16-bit, number of names.
+// len:   16-bit, length of name that follows.
+//
+pub(crate) fn encode_names(buf: &mut Vec<u8>, names: &Vec<String>) -> Result<usize> {
+    let count: u16 = convert_at!(names.len())?;
+    buf.extend_from_slice(&count.to_be_bytes());
+    let mut n = 2;
+
+    for name in names.iter() {
+        let b = name.as_bytes();
+        {
+            let len: u16 = convert_at!(b.len())?;
+            buf.extend_from_slice(&len.to_be_bytes());
+        }
+        buf.extend_from_slice(b);
+        n += 2 + b.len();
+    }
+
+    Ok(n)
+}
+
+pub(crate) fn decode_names(buf: &[u8], msg: &str) -> Result<(Vec<String>, usize)> {
+    use std::str::from_utf8;
+
+    check_remaining!(buf, 2, msg)?;
+    let count = u16::from_be_bytes(array_at!(buf[..2])?);
+    let mut names = Vec::with_capacity(convert_at!(count)?);
+    let mut n = 2;
+
+    for _i in 0..count {
+        check_remaining!(buf, n + 2, msg)?;
+
+        let m: usize = convert_at!(u16::from_be_bytes(array_at!(buf[n..n + 2])?))?;
+        n += 2;
+
+        check_remaining!(buf, n + m, msg)?;
+
+        let s = err_at!(InvalidInput, from_utf8(&buf[n..n + m]))?;
+        names.push(s.to_string());
+        n += m;
+    }
+
+    Ok((names, n))
+}
+
 #[derive(PartialEq, Debug)]
 enum OpType {
     // Data operations
@@ -144,7 +284,8 @@ enum OpType {
     SetCAS,
     Delete,
     // Config operations
-    // TBD
+    JointConfig,
+    Config,
 }
 
 impl From<u64> for OpType {
@@ -153,13 +294,16 @@ impl From<u64> for OpType {
             1 => OpType::Set,
             2 => OpType::SetCAS,
             3 => OpType::Delete,
+            4 => OpType::JointConfig,
+            5 => OpType::Config,
             _ => unreachable!(),
         }
     }
 }
 
+/// Write operations replicated via [Raft] log.
 #[derive(Clone)]
-pub(crate) enum Op<K, V>
+pub enum Op<K, V>
 where
     K: Default + Serialize,
     V: Default + Serialize,
@@ -168,6 +312,9 @@ where
     Set { key: K, value: V },
     SetCAS { key: K, value: V, cas: u64 },
     Delete { key: K },
+    // Config operations
+    JointConfig { old: Vec<String>, new: Vec<String> },
+    Config { nodes: Vec<String> },
 }
 
 impl<K, V> Default for Op<K, V>
@@ -208,6 +355,10 @@ where
                 },
             ) => key.eq(k) && value.eq(v) && cas.eq(c),
             (Op::Delete { key }, Op::Delete { key: k }) => key == k,
+            (Op::JointConfig { old, new }, Op::JointConfig { old: o, new: n }) => {
+                old == o && new == n
+            }
+            (Op::Config { nodes }, Op::Config { nodes: n }) => nodes == n,
             _ => false,
         }
     }
@@ -233,6 +384,12 @@ where
             Op::Delete { key } => {
                 write!(f, "Op::Set< key: {:?}>", key)?;
             }
+            Op::JointConfig { old, new } => {
+                write!(f, "Op::JointConfig<old:{:?} new:{:?}>", old, new)?;
+            }
+            Op::Config { nodes } => {
+                write!(f, "Op::Config<nodes:{:?}>", nodes)?;
+            }
         }
         Ok(())
     }
@@ -243,18 +400,29 @@ where
     K: Default + Serialize,
     V: Default + Serialize,
 {
-    pub(crate) fn new_set(key: K, value: V) -> Op<K, V> {
+    /// Create a new set operation.
+    pub fn new_set(key: K, value: V) -> Op<K, V> {
         Op::Set { key, value }
     }
 
-    pub(crate) fn new_set_cas(key: K, value: V, cas: u64) -> Op<K, V> {
+    /// Create a new set-cas operation.
+    pub fn new_set_cas(key: K, value: V, cas: u64) -> Op<K, V> {
         Op::SetCAS { cas, key, value }
     }
 
-    pub(crate) fn new_delete(key: K) -> Op<K, V> {
+    /// Create a new delete operation.
+    pub fn new_delete(key: K) -> Op<K, V> {
         Op::Delete { key }
     }
 
+    pub(crate) fn new_joint_config(old: Vec<String>, new: Vec<String>) -> Op<K, V> {
+        Op::JointConfig { old, new }
+    }
+
+    pub(crate) fn new_config(nodes: Vec<String>) -> Op<K, V> {
+        Op::Config { nodes }
+    }
+
     fn op_type(buf: &[u8]) -> Result<OpType> {
         check_remaining!(buf, 8, "raft-op-type")?;
         let hdr1 = u64::from_be_bytes(array_at!(buf[..8])?);
@@ -281,6 +449,14 @@ where
                 let n = Self::encode_delete(buf, key)?;
                 n
             }
+            Op::JointConfig { old, new } => {
+                let n = Self::encode_joint_config(buf, old, new)?;
+                n
+            }
+            Op::Config { nodes } => {
+                let n = Self::encode_config(buf, nodes)?;
+                n
+            }
         })
     }
 
@@ -290,12 +466,16 @@ where
             OpType::Set => Op::new_set(key, Default::default()),
             OpType::SetCAS => Op::new_set_cas(key, Default::default(), Default::default()),
             OpType::Delete => Op::new_delete(key),
+            OpType::JointConfig => Op::new_joint_config(vec![], vec![]),
+            OpType::Config => Op::new_config(vec![]),
         };
 
         match self {
             Op::Set { key, value } => Self::decode_set(buf, key, value),
             Op::SetCAS { key, value, cas } => Self::decode_set_cas(buf, key, value, cas),
             Op::Delete { key } => Self::decode_delete(buf, key),
+            Op::JointConfig { old, new } => Self::decode_joint_config(buf, old, new),
+            Op::Config { nodes } => Self::decode_config(buf, nodes),
         }
     }
 }
@@ -484,6 +664,74 @@ where
     }
 }
 
-//#[cfg(test)]
-//#[path = "raft_log_test.rs"]
-//mod raft_log_test;
+// +--------------------------------+-------------------------------+
+// | reserved |         op-type     |          reserved             |
+// +--------------------------------+-------------------------------+
+// |                         old-node-names                         |
+// +----------------------------------------------------------------+
+// |                         new-node-names                         |
+// +----------------------------------------------------------------+
+//
+// reserved: bits 63, 62, 61, 60, 59, 58, 57, 56
+// op-type:  24-bit
+//
+// For config operation, there is only one list of node-names.
+impl<K, V> Op<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn encode_joint_config(
+        buf: &mut Vec<u8>,
+        old: &Vec<String>,
+        new: &Vec<String>,
+    ) -> Result<usize> {
+        let hdr1: u64 = (OpType::JointConfig as u64) << 32;
+        buf.extend_from_slice(&hdr1.to_be_bytes());
+        let mut n = 8;
+
+        n += encode_names(buf, old)?;
+        n += encode_names(buf, new)?;
+
+        Ok(n)
+    }
+
+    fn decode_joint_config(
+        buf: &[u8],
+        old: &mut Vec<String>,
+        new: &mut Vec<String>,
+    ) -> Result<usize> {
+        let mut n = 8;
+        check_remaining!(buf, n, "raft-op-joint-config-hdr1")?;
+
+        let (names, m) = decode_names(&buf[n..], "raft-op-joint-config-old")?;
+        *old = names;
+        n += m;
+        let (names, m) = decode_names(&buf[n..], "raft-op-joint-config-new")?;
+        *new = names;
+        n += m;
+
+        Ok(n)
+    }
+
+    fn encode_config(buf: &mut Vec<u8>, nodes: &Vec<String>) -> Result<usize> {
+        let hdr1: u64 = (OpType::Config as u64) << 32;
+        buf.extend_from_slice(&hdr1.to_be_bytes());
+
+        Ok(8 + encode_names(buf, nodes)?)
+    }
+
+    fn decode_config(buf: &[u8], nodes: &mut Vec<String>) -> Result<usize> {
+        let n = 8;
+        check_remaining!(buf, n, "raft-op-config-hdr1")?;
+
+        let (names, m) = decode_names(&buf[n..], "raft-op-config-nodes")?;
+        *nodes = names;
+
+        Ok(n + m)
+    }
+}
+
+#[cfg(test)]
+#[path = "raft_log_test.rs"]
+mod raft_log_test;
@@ -7,6 +7,7 @@ fn test_state() {
     assert_eq!(state.committed, 0);
     assert_eq!(state.persisted, 0);
     assert_eq!(state.config, vec![]);
+    assert_eq!(state.old_config, vec![]);
     assert_eq!(state.votedfor, DEFAULT_NODE.to_string());
 
     let state = State {
@@ -14,19 +15,32 @@ fn test_state() {
         committed: 0x2341,
         persisted: 0x3211,
         config: vec!["node1".to_string(), "node2".to_string()],
+        old_config: vec!["node1".to_string()],
         votedfor: DEFAULT_NODE.to_string(),
     };
 
     let mut buf = vec![];
-    assert_eq!(state.encode(&mut buf), 0);
+    let n = state.encode(&mut buf).unwrap();
+    assert_eq!(n, buf.len());
     let mut dec_state: State = Default::default();
-    assert_eq!(dec_state.decode(&buf), state);
+    assert_eq!(dec_state.decode(&buf).unwrap(), n);
+    assert!(dec_state == state);
 
     assert_eq!(dec_state.term, state.term);
     assert_eq!(dec_state.committed, state.committed);
     assert_eq!(dec_state.persisted, state.persisted);
     assert_eq!(dec_state.config, state.config);
+    assert_eq!(dec_state.old_config, state.old_config);
     assert_eq!(dec_state.votedfor, state.votedfor);
+    assert_eq!(dec_state.to_votedfor(), None);
+
+    let config = (vec!["node1".to_string()], vec![]);
+    let state = State::new(10, 9, 8, config.clone(), Some("node1".to_string()));
+    assert_eq!(state.to_term(), 10);
+    assert_eq!(state.to_committed(), 9);
+    assert_eq!(state.to_persisted(), 8);
+    assert_eq!(state.to_config(), config);
+    assert_eq!(state.to_votedfor(), Some("node1".to_string()));
 }
 
 #[test]
@@ -37,6 +51,10 @@ fn test_op_type() {
     assert_eq!(op_type, OpType::SetCAS);
     let op_type: OpType = From::from(3_u64);
     assert_eq!(op_type, OpType::Delete);
+    let op_type: OpType = From::from(4_u64);
+    assert_eq!(op_type, OpType::JointConfig);
+    let op_type: OpType = From::from(5_u64);
+    assert_eq!(op_type, OpType::Config);
 }
 
 #[test]
@@ -79,4 +97,54 @@ fn test_op() {
         Op::Delete { key: 34 } => (),
         _ => unreachable!(),
     }
+
+    let (old, new) = (
+        vec!["node1".to_string(), "node2".to_string()],
+        vec!["node2".to_string(), "node3".to_string()],
+    );
+    let op: Op<i32, i32> = Op::new_joint_config(old.clone(), new.clone());
+    out.resize(0, 0);
+    op.encode(&mut out).unwrap();
+    assert_eq!(Op::<i32, i32>::op_type(&out).unwrap(), OpType::JointConfig);
+    let n = res.decode(&out).expect("op-joint-config decode failed");
+    assert_eq!(n, out.len());
+    assert_eq!(
+        res,
+        Op::JointConfig {
+            old,
+            new: new.clone()
+        }
+    );
+
+    let op: Op<i32, i32> = Op::new_config(new.clone());
+    out.resize(0, 0);
+    op.encode(&mut out).unwrap();
+    assert_eq!(Op::<i32, i32>::op_type(&out).unwrap(), OpType::Config);
+    let n = res.decode(&out).expect("op-config decode failed");
+    assert_eq!(n, out.len());
+    assert_eq!(res, Op::Config { nodes: new });
+}
+
+#[test]
+fn test_state_config() {
+    use crate::dlog::DlogState;
+
+    let mut state = State::new(1, 0, 0, (vec!["n1".to_string()], vec![]), None);
+    let old = vec!["n1".to_string()];
+    let new = vec!["n1".to_string(), "n2".to_string()];
+
+    let entry = DEntry::new(1, Op::<i32, i32>::new_set(10, 10));
+    state.on_add_entry(&entry);
+    assert_eq!(state.to_config(), (old.clone(), vec![]));
+
+    let entry = DEntry::new(
+        2,
+        Op::<i32, i32>::new_joint_config(old.clone(), new.clone()),
+    );
+    state.on_add_entry(&entry);
+    assert_eq!(state.to_config(), (new.clone(), old.clone()));
+
+    let entry = DEntry::new(3, Op::<i32, i32>::new_config(new.clone()));
+    state.on_add_entry(&entry);
+    assert_eq!(state.to_config(), (new, vec![]));
 }
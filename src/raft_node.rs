@@ -0,0 +1,1651 @@
+use log::{debug, info};
+
+use std::{
+    cmp,
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    convert::TryInto,
+    ffi, fmt, fs,
+    hash::{BuildHasher, Hasher},
+    io::{self, Read, Seek, Write},
+    ops::Bound,
+    path, result,
+};
+
+use crate::{
+    core::{Diff, Replay, Result, Serialize},
+    dlog::Dlog,
+    dlog_entry::DEntry,
+    error::Error,
+    raft_log::{decode_names, encode_names, Op, State},
+    raft_transport::Transport,
+    util,
+};
+
+/// Configuration for a [Raft] node.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Directory where raft log and state files are persisted.
+    pub(crate) dir: ffi::OsString,
+    /// Unique name of this node in the cluster. Also used as the name
+    /// for raft log.
+    pub(crate) name: String,
+    /// List of all voting nodes in the cluster, including this node.
+    /// A node joining an existing cluster shall start with an empty
+    /// list, and learn its configuration from the leader.
+    pub(crate) nodes: Vec<String>,
+    /// Election timeout, in ticks, randomized between `election_ticks`
+    /// and 2 * `election_ticks`. Default: Config::ELECTION_TICKS
+    pub(crate) election_ticks: usize,
+    /// Leader sends heartbeat every `heartbeat_ticks`, should be less
+    /// than `election_ticks`. Default: Config::HEARTBEAT_TICKS
+    pub(crate) heartbeat_ticks: usize,
+    /// Maximum number of entries sent in a single AppendEntries message.
+    /// Default: Config::MAX_ENTRIES
+    pub(crate) max_entries: usize,
+    /// Maximum number of bytes sent in a single InstallSnapshot message.
+    /// Default: Config::SNAPSHOT_CHUNK
+    pub(crate) snapshot_chunk: usize,
+    /// Limit for journal file size. Default: dlog::JOURNAL_LIMIT
+    pub(crate) journal_limit: usize,
+    /// Fsync batches and state to disk, before responding to peers.
+    /// Default: true
+    pub(crate) fsync: bool,
+}
+
+impl Config {
+    /// Default election timeout, in ticks.
+    pub const ELECTION_TICKS: usize = 10;
+    /// Default heartbeat interval, in ticks.
+    pub const HEARTBEAT_TICKS: usize = 2;
+    /// Default value for maximum entries in a single AppendEntries.
+    pub const MAX_ENTRIES: usize = 1000;
+    /// Default value for maximum bytes in a single InstallSnapshot.
+    pub const SNAPSHOT_CHUNK: usize = 1024 * 1024;
+
+    /// New configuration for node `name`, part of cluster `nodes`.
+    pub fn new(dir: &ffi::OsStr, name: &str, nodes: Vec<String>) -> Config {
+        Config {
+            dir: dir.to_os_string(),
+            name: name.to_string(),
+            nodes,
+            election_ticks: Self::ELECTION_TICKS,
+            heartbeat_ticks: Self::HEARTBEAT_TICKS,
+            max_entries: Self::MAX_ENTRIES,
+            snapshot_chunk: Self::SNAPSHOT_CHUNK,
+            journal_limit: crate::dlog::JOURNAL_LIMIT,
+            fsync: true,
+        }
+    }
+
+    /// Configure election timeout and heartbeat interval, in ticks.
+    pub fn set_ticks(&mut self, election: usize, heartbeat: usize) -> Result<&mut Self> {
+        if heartbeat == 0 || heartbeat >= election {
+            let msg = format!("heartbeat:{} election:{}", heartbeat, election);
+            return err_at!(InvalidInput, msg: msg);
+        }
+        self.election_ticks = election;
+        self.heartbeat_ticks = heartbeat;
+        Ok(self)
+    }
+
+    /// Configure maximum number of entries in a single AppendEntries.
+    pub fn set_max_entries(&mut self, max_entries: usize) -> Result<&mut Self> {
+        self.max_entries = cmp::max(max_entries, 1);
+        Ok(self)
+    }
+
+    /// Configure maximum number of bytes in a single InstallSnapshot.
+    pub fn set_snapshot_chunk(&mut self, chunk: usize) -> Result<&mut Self> {
+        self.snapshot_chunk = cmp::max(chunk, 1);
+        Ok(self)
+    }
+
+    /// Configure journal limit and fsync for raft log.
+    pub fn set_journal(&mut self, journal_limit: usize, fsync: bool) -> Result<&mut Self> {
+        self.journal_limit = journal_limit;
+        self.fsync = fsync;
+        Ok(self)
+    }
+
+    fn to_state_file(&self) -> ffi::OsString {
+        let mut fpath = path::PathBuf::new();
+        fpath.push(&self.dir);
+        fpath.push(format!("{}-raft-state.dat", self.name));
+        fpath.into_os_string()
+    }
+
+    fn to_snapshot_file(&self) -> ffi::OsString {
+        let mut fpath = path::PathBuf::new();
+        fpath.push(&self.dir);
+        fpath.push(format!("{}-raft-snapshot.dat", self.name));
+        fpath.into_os_string()
+    }
+
+    // snapshots received from leader are stored under this directory.
+    fn to_snapshot_dir(&self) -> ffi::OsString {
+        let mut fpath = path::PathBuf::new();
+        fpath.push(&self.dir);
+        fpath.push(format!("{}-raft-snapshot", self.name));
+        fpath.into_os_string()
+    }
+
+    // return path to `file_name` under the snapshot directory. File name
+    // is supplied by leader, and shall be a plain file name, without
+    // separators or relative components.
+    fn to_snapshot_file(&self, file_name: &ffi::OsStr) -> Result<ffi::OsString> {
+        let name = path::Path::new(file_name);
+        let file_name = match name
+            .components()
+            .collect::<Vec<path::Component>>()
+            .as_slice()
+        {
+            [path::Component::Normal(_)] => match name.file_name() {
+                Some(f) if f == file_name => f,
+                _ => err_at!(InvalidInput, msg: format!("snapshot {:?}", file_name))?,
+            },
+            _ => err_at!(InvalidInput, msg: format!("snapshot {:?}", file_name))?,
+        };
+        let fname = file_name.to_string_lossy();
+        if fname.contains('/') || fname.contains('\\') || fname.contains("..") {
+            err_at!(InvalidInput, msg: format!("snapshot {:?}", file_name))?
+        }
+
+        let dir = path::PathBuf::from(self.to_snapshot_dir());
+        let file = dir.join(file_name);
+        match file.parent() {
+            Some(parent) if parent == dir.as_path() => Ok(file.into_os_string()),
+            _ => err_at!(InvalidInput, msg: format!("snapshot {:?}", file_name)),
+        }
+    }
+}
+
+/// Role played by a [Raft] node in its current term.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// Single entry in the replicated log.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LogEntry<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    index: u64,
+    term: u64,
+    op: Op<K, V>,
+}
+
+impl<K, V> LogEntry<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn new(index: u64, term: u64, op: Op<K, V>) -> LogEntry<K, V> {
+        LogEntry { index, term, op }
+    }
+
+    /// Return the raft index for this entry.
+    pub fn to_index(&self) -> u64 {
+        self.index
+    }
+
+    /// Return the term in which this entry was created.
+    pub fn to_term(&self) -> u64 {
+        self.term
+    }
+
+    /// Return the write operation.
+    pub fn as_op(&self) -> &Op<K, V> {
+        &self.op
+    }
+}
+
+/// Snapshot of replicated state, upto `index`.
+///
+/// Snapshot is an index file, typically a [robt][crate::robt] index,
+/// built by the application from applied entries. Raft treats the file
+/// as opaque bytes, while shipping it to followers.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Snapshot {
+    file: ffi::OsString,
+    index: u64,
+    term: u64,
+    config: Vec<String>,
+    old_config: Vec<String>,
+}
+
+impl Snapshot {
+    /// Return the location of snapshot file.
+    pub fn to_file(&self) -> ffi::OsString {
+        self.file.clone()
+    }
+
+    /// Return the raft index of the last entry included in snapshot.
+    pub fn to_index(&self) -> u64 {
+        self.index
+    }
+
+    /// Return the term of the last entry included in snapshot.
+    pub fn to_term(&self) -> u64 {
+        self.term
+    }
+}
+
+impl Serialize for Snapshot {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        buf.extend_from_slice(&self.index.to_be_bytes());
+        buf.extend_from_slice(&self.term.to_be_bytes());
+        let mut n = 16;
+
+        n += encode_names(buf, &self.config)?;
+        n += encode_names(buf, &self.old_config)?;
+
+        let file = match self.file.to_str() {
+            Some(file) => file.to_string(),
+            None => err_at!(InvalidInput, msg: format!("{:?}", self.file))?,
+        };
+        n += encode_names(buf, &vec![file])?;
+
+        Ok(n)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        check_remaining!(buf, 16, "raft-snapshot")?;
+        self.index = u64::from_be_bytes(array_at!(buf[0..8])?);
+        self.term = u64::from_be_bytes(array_at!(buf[8..16])?);
+        let mut n = 16;
+
+        let (config, m) = decode_names(&buf[n..], "raft-snapshot-config")?;
+        self.config = config;
+        n += m;
+        let (old_config, m) = decode_names(&buf[n..], "raft-snapshot-old-config")?;
+        self.old_config = old_config;
+        n += m;
+        let (mut files, m) = decode_names(&buf[n..], "raft-snapshot-file")?;
+        self.file = match files.pop() {
+            Some(file) => file.into(),
+            None => err_at!(InvalidFile, msg: format!("missing snapshot file"))?,
+        };
+        n += m;
+
+        Ok(n)
+    }
+}
+
+/// Messages exchanged between [Raft] nodes.
+#[derive(Clone, Debug)]
+pub enum Message<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    /// Sent by candidates to gather votes.
+    RequestVote {
+        term: u64,
+        from: String,
+        last_index: u64,
+        last_term: u64,
+    },
+    /// Response to RequestVote.
+    Vote {
+        term: u64,
+        from: String,
+        granted: bool,
+    },
+    /// Sent by leader to replicate entries, also used as heartbeat.
+    AppendEntries {
+        term: u64,
+        from: String,
+        prev_index: u64,
+        prev_term: u64,
+        entries: Vec<LogEntry<K, V>>,
+        committed: u64,
+    },
+    /// Response to AppendEntries. On success `index` is the last index
+    /// matching with leader's log, on failure `index` is a hint for the
+    /// leader to retry from `index + 1`.
+    AppendResult {
+        term: u64,
+        from: String,
+        success: bool,
+        index: u64,
+    },
+    /// Sent by leader to ship its snapshot, in chunks, to a follower
+    /// whose next entry is already compacted away from leader's log.
+    /// `data` is the chunk of snapshot file starting from `offset`.
+    InstallSnapshot {
+        term: u64,
+        from: String,
+        index: u64,
+        last_term: u64,
+        config: Vec<String>,
+        old_config: Vec<String>,
+        file_name: String,
+        offset: u64,
+        data: Vec<u8>,
+        done: bool,
+    },
+    /// Response to InstallSnapshot. `offset` is the number of bytes
+    /// received so far, and `done` is true once the snapshot for
+    /// `index` is installed by the follower.
+    SnapshotResult {
+        term: u64,
+        from: String,
+        index: u64,
+        offset: u64,
+        done: bool,
+    },
+}
+
+impl<K, V> Message<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn to_term(&self) -> u64 {
+        match self {
+            Message::RequestVote { term, .. } => *term,
+            Message::Vote { term, .. } => *term,
+            Message::AppendEntries { term, .. } => *term,
+            Message::AppendResult { term, .. } => *term,
+            Message::InstallSnapshot { term, .. } => *term,
+            Message::SnapshotResult { term, .. } => *term,
+        }
+    }
+}
+
+/// Raft node, replicating write operations across a cluster of nodes.
+///
+/// Refer to [module][crate::raft_log] documentation for details.
+pub struct Raft<K, V, T>
+where
+    K: Clone + Default + Serialize,
+    V: Clone + Default + Serialize,
+    T: Transport<K, V>,
+{
+    config: Config,
+    transport: T,
+    dlog: Dlog<State, Op<K, V>>,
+
+    // persisted state.
+    term: u64,
+    votedfor: Option<String>,
+    log: Vec<LogEntry<K, V>>,
+    // log starts after the latest snapshot, if any.
+    snapshot: Option<Snapshot>,
+    // membership, as of the latest configuration entry in the log,
+    // old_voters is non-empty while in joint consensus.
+    voters: Vec<String>,
+    old_voters: Vec<String>,
+    config_index: u64,
+    // volatile state.
+    role: Role,
+    leader: Option<String>,
+    committed: u64,
+    applied: u64,
+    // snapshot installed from leader, yet to be taken by application.
+    installed: Option<Snapshot>,
+    // snapshot being received from leader, (index, file, received-bytes).
+    receiving: Option<(u64, ffi::OsString, u64)>,
+    // candidate state.
+    votes: HashSet<String>,
+    // leader state.
+    next_index: HashMap<String, u64>,
+    match_index: HashMap<String, u64>,
+    snapshot_offsets: HashMap<String, u64>,
+    // timers, in ticks.
+    elapsed: usize,
+    timeout: usize,
+}
+
+impl<K, V, T> fmt::Debug for Raft<K, V, T>
+where
+    K: Clone + Default + Serialize,
+    V: Clone + Default + Serialize,
+    T: Transport<K, V>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(
+            f,
+            "Raft<{},{:?},term:{},committed:{}>",
+            self.config.name, self.role, self.term, self.committed
+        )
+    }
+}
+
+impl<K, V, T> Raft<K, V, T>
+where
+    K: Clone + Default + Serialize,
+    V: Clone + Default + Serialize,
+    T: Transport<K, V>,
+{
+    /// Create a new raft node, any existing log and state for this node,
+    /// under the configured directory, shall be purged.
+    pub fn create(config: Config, transport: T) -> Result<Raft<K, V, T>> {
+        let dl = Dlog::<State, Op<K, V>>::create(
+            config.dir.clone(),
+            config.name.clone(),
+            1, /*nshards*/
+            config.journal_limit,
+            1, /*batch_size*/
+            config.fsync,
+        )?;
+        fs::remove_file(&config.to_snapshot_file()).ok();
+        fs::remove_dir_all(&config.to_snapshot_dir()).ok();
+
+        let mut raft = Raft::new(config, transport, dl);
+        raft.persist_state()?;
+
+        info!(target: "raft  ", "{:?}, created", raft);
+
+        Ok(raft)
+    }
+
+    /// Load an existing raft node, from its persisted log, state and
+    /// snapshot. Entries upto the snapshot are treated as applied.
+    pub fn load(config: Config, transport: T) -> Result<Raft<K, V, T>> {
+        let dl = Dlog::<State, Op<K, V>>::load(
+            config.dir.clone(),
+            config.name.clone(),
+            1, /*nshards*/
+            config.journal_limit,
+            1, /*batch_size*/
+            config.fsync,
+        )?;
+
+        let state = {
+            let state_file = config.to_state_file();
+            let buf = err_at!(IoError, fs::read(&state_file))?;
+            let mut state: State = Default::default();
+            state.decode(&buf)?;
+            state
+        };
+        let snapshot = match fs::read(&config.to_snapshot_file()) {
+            Ok(buf) => {
+                let mut snapshot: Snapshot = Default::default();
+                snapshot.decode(&buf)?;
+                Some(snapshot)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => err_at!(IoError, Err(err))?,
+        };
+
+        let mut raft = Raft::new(config, transport, dl);
+        raft.term = state.to_term();
+        raft.votedfor = state.to_votedfor();
+        raft.committed = state.to_committed();
+        if let Some(snapshot) = snapshot {
+            raft.committed = cmp::max(raft.committed, snapshot.index);
+            raft.applied = snapshot.index;
+            raft.snapshot = Some(snapshot);
+        }
+
+        let offset = raft.to_offset().0;
+        for journal in raft.dlog.shards[0].to_journals().into_iter() {
+            let mut fd = util::open_file_r(&journal.to_file_path())?;
+            for batch in journal.to_batches()?.into_iter() {
+                let (state, entries) = batch.into_active(&mut fd)?.into_state_entries()?;
+                for entry in entries.into_iter() {
+                    let (index, op) = entry.into_seqno_op();
+                    // entries upto snapshot are already compacted.
+                    if index > offset {
+                        raft.log.push(LogEntry::new(index, state.to_term(), op));
+                    }
+                }
+                raft.term = cmp::max(raft.term, state.to_term());
+                raft.committed = cmp::max(raft.committed, state.to_committed());
+            }
+        }
+        raft.committed = cmp::min(raft.committed, raft.to_last_index());
+        raft.reload_membership();
+
+        info!(
+            target: "raft  ",
+            "{:?}, loaded {} entries after snapshot:{}, dropped {}",
+            raft, raft.log.len(), offset, raft.dlog.shards[0].to_dropped()
+        );
+
+        Ok(raft)
+    }
+
+    fn new(config: Config, transport: T, dlog: Dlog<State, Op<K, V>>) -> Self {
+        let timeout = election_timeout(config.election_ticks);
+        let voters = config.nodes.clone();
+        Raft {
+            config,
+            transport,
+            dlog,
+
+            term: 0,
+            votedfor: None,
+            log: vec![],
+            snapshot: None,
+            voters,
+            old_voters: vec![],
+            config_index: 0,
+
+            role: Role::Follower,
+            leader: None,
+            committed: 0,
+            applied: 0,
+            installed: None,
+            receiving: None,
+
+            votes: HashSet::new(),
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            snapshot_offsets: HashMap::new(),
+
+            elapsed: 0,
+            timeout,
+        }
+    }
+
+    /// Close this node, persisted log, state and snapshot are left on disk.
+    pub fn close(self) -> Result<()> {
+        info!(target: "raft  ", "{:?}, closed", self);
+        for shard in self.dlog.shards.into_iter() {
+            shard.close()?;
+        }
+        Ok(())
+    }
+
+    /// Close this node and purge its persisted log, state and snapshots
+    /// received from leader. Snapshot files supplied by application via
+    /// [Raft::set_snapshot] are left untouched.
+    pub fn purge(self) -> Result<()> {
+        err_at!(IoError, fs::remove_file(&self.config.to_state_file()))?;
+        fs::remove_file(&self.config.to_snapshot_file()).ok();
+        fs::remove_dir_all(&self.config.to_snapshot_dir()).ok();
+        info!(target: "raft  ", "{:?}, purged", self);
+        for shard in self.dlog.shards.into_iter() {
+            shard.purge()?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, T> Raft<K, V, T>
+where
+    K: Clone + Default + Serialize,
+    V: Clone + Default + Serialize,
+    T: Transport<K, V>,
+{
+    /// Return name of this node.
+    pub fn to_name(&self) -> String {
+        self.config.name.clone()
+    }
+
+    /// Return the role played by this node in its current term.
+    pub fn to_role(&self) -> Role {
+        self.role
+    }
+
+    /// Return whether this node is the leader for its current term.
+    pub fn is_leader(&self) -> bool {
+        self.role == Role::Leader
+    }
+
+    /// Return the current term.
+    pub fn to_term(&self) -> u64 {
+        self.term
+    }
+
+    /// Return the leader for current term, if known.
+    pub fn to_leader(&self) -> Option<String> {
+        self.leader.clone()
+    }
+
+    /// Return the list of voting nodes, as of the latest configuration
+    /// entry in the log.
+    pub fn to_voters(&self) -> Vec<String> {
+        self.voters.clone()
+    }
+
+    /// Return whether cluster is transitioning between configurations,
+    /// requiring majority from both old and new configuration.
+    pub fn is_joint_consensus(&self) -> bool {
+        !self.old_voters.is_empty()
+    }
+
+    /// Return the index upto which entries are committed.
+    pub fn to_committed(&self) -> u64 {
+        self.committed
+    }
+
+    /// Return the index upto which entries are applied.
+    pub fn to_applied(&self) -> u64 {
+        self.applied
+    }
+
+    /// Return the index upto which entries are persisted in the latest
+    /// snapshot.
+    pub fn to_persisted(&self) -> u64 {
+        self.to_offset().0
+    }
+
+    /// Return the latest snapshot, if any.
+    pub fn to_snapshot(&self) -> Option<Snapshot> {
+        self.snapshot.clone()
+    }
+
+    /// Return the index of the last entry in the log.
+    pub fn to_last_index(&self) -> u64 {
+        self.log.last().map_or(self.to_offset().0, |e| e.index)
+    }
+
+    /// Return entries from log, starting from `index`. Entries compacted
+    /// into snapshot are not returned.
+    pub fn to_entries(&self, index: u64) -> Vec<LogEntry<K, V>> {
+        self.entries_from(index, self.log.len())
+    }
+
+    /// Application shall call this method, once all entries upto `index`
+    /// are applied and durably persisted into snapshot `file`. Entries
+    /// upto `index` are compacted away from the log, and journals holding
+    /// them are purged. Followers lagging behind `index` shall be sent
+    /// the snapshot file, refer to [Raft::take_installed].
+    pub fn set_snapshot(&mut self, file: ffi::OsString, index: u64) -> Result<()> {
+        if index > self.applied {
+            let msg = format!("snapshot:{} > applied:{}", index, self.applied);
+            return err_at!(InvalidInput, msg: msg);
+        } else if index <= self.to_offset().0 {
+            return Ok(());
+        }
+
+        let term = match self.term_at(index) {
+            Some(term) => term,
+            None => err_at!(Fatal, msg: format!("missing index {}", index))?,
+        };
+        let (config, old_config, _) = self.config_at(index);
+        let snapshot = Snapshot {
+            file,
+            index,
+            term,
+            config,
+            old_config,
+        };
+        self.compact(snapshot)
+    }
+
+    /// Return the snapshot installed from leader, if any. Application
+    /// shall load its index from the snapshot file before applying
+    /// further entries, refer to [Raft::apply].
+    pub fn take_installed(&mut self) -> Option<Snapshot> {
+        self.installed.take()
+    }
+
+    /// Propose a write operation to the cluster. Only the leader can
+    /// accept proposals. Return the index assigned to the operation.
+    /// Proposed operation is durable only after it is committed, refer
+    /// to [Raft::to_committed].
+    pub fn propose(&mut self, op: Op<K, V>) -> Result<u64> {
+        match &op {
+            Op::JointConfig { .. } | Op::Config { .. } => {
+                let msg = format!("use add_voter/remove_voter for {:?}", op);
+                err_at!(InvalidInput, msg: msg)
+            }
+            _ => self.propose_op(op),
+        }
+    }
+
+    /// Add node `name` as a voter to the cluster. Only the leader can
+    /// accept membership changes, and only one change can be in
+    /// progress at any time. Return the index of the configuration entry.
+    ///
+    /// Cluster transitions to the new configuration via joint consensus,
+    /// once the joint configuration is committed, leader shall propose
+    /// the new configuration.
+    pub fn add_voter(&mut self, name: &str) -> Result<u64> {
+        let mut voters = self.voters.clone();
+        if !voters.iter().any(|n| n == name) {
+            voters.push(name.to_string());
+        }
+        self.change_config(voters)
+    }
+
+    /// Remove voter `name` from the cluster, refer to [Raft::add_voter]
+    /// for details. If leader is removed, it shall step down once the new
+    /// configuration is committed.
+    pub fn remove_voter(&mut self, name: &str) -> Result<u64> {
+        let voters: Vec<String> = {
+            let iter = self.voters.iter().filter(|n| n.as_str() != name);
+            iter.cloned().collect()
+        };
+        if voters.is_empty() {
+            return err_at!(InvalidInput, msg: format!("can't remove last voter"));
+        }
+        self.change_config(voters)
+    }
+
+    /// Apply committed entries, that are not yet applied, on `db`.
+    /// Return number of entries applied.
+    pub fn apply<P>(&mut self, db: &mut P) -> Result<usize>
+    where
+        K: Ord,
+        V: Diff,
+        P: Replay<K, V>,
+    {
+        if let Some(snapshot) = &self.installed {
+            let msg = format!("take installed snapshot {}", snapshot.index);
+            return err_at!(APIMisuse, msg: msg);
+        }
+
+        let offset = self.to_offset().0;
+        let mut n = 0;
+        while self.applied < self.committed {
+            let index = self.applied + 1;
+            let off: usize = convert_at!((index - offset - 1))?;
+            let res = match self.log[off].op.clone() {
+                Op::Set { key, value } => db.set_index(key, value, index).map(|_| ()),
+                Op::SetCAS { key, value, cas } => {
+                    db.set_cas_index(key, value, cas, index).map(|_| ())
+                }
+                Op::Delete { key } => db.delete_index(key, index).map(|_| ()),
+                Op::JointConfig { .. } | Op::Config { .. } => Ok(()),
+            };
+            match res {
+                // cas failures are deterministic across nodes.
+                Ok(_) | Err(Error::InvalidCAS(_)) => (),
+                Err(err) => return Err(err),
+            }
+            self.applied = index;
+            n += 1;
+        }
+
+        Ok(n)
+    }
+
+    /// Drive this node, shall be called periodically by the application.
+    /// Drain all incoming messages from transport and advance election
+    /// and heartbeat timers by one tick.
+    pub fn tick(&mut self) -> Result<()> {
+        while let Some(msg) = self.transport.try_recv()? {
+            self.step(msg)?;
+        }
+
+        self.elapsed += 1;
+        match self.role {
+            Role::Leader if self.elapsed >= self.config.heartbeat_ticks => {
+                self.elapsed = 0;
+                for peer in self.to_peers().into_iter() {
+                    self.send_append(&peer)?;
+                }
+            }
+            Role::Leader => (),
+            // only voters can campaign for leadership.
+            _ if self.elapsed >= self.timeout && self.is_voter() => self.campaign()?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Handle a single message received from a peer node.
+    pub fn step(&mut self, msg: Message<K, V>) -> Result<()> {
+        // disregard vote requests while leader is active, nodes removed
+        // from the cluster shall not disrupt the cluster.
+        if let Message::RequestVote { .. } = &msg {
+            if self.leader.is_some() && self.elapsed < self.config.election_ticks {
+                return Ok(());
+            }
+        }
+
+        if msg.to_term() > self.term {
+            self.become_follower(msg.to_term())?;
+        }
+
+        match msg {
+            Message::RequestVote {
+                term,
+                from,
+                last_index,
+                last_term,
+            } => self.handle_request_vote(term, from, last_index, last_term),
+            Message::Vote {
+                term,
+                from,
+                granted,
+            } => self.handle_vote(term, from, granted),
+            Message::AppendEntries {
+                term,
+                from,
+                prev_index,
+                prev_term,
+                entries,
+                committed,
+            } => self.handle_append(term, from, prev_index, prev_term, entries, committed),
+            Message::AppendResult {
+                term,
+                from,
+                success,
+                index,
+            } => self.handle_append_result(term, from, success, index),
+            Message::InstallSnapshot {
+                term,
+                from,
+                index,
+                last_term,
+                config,
+                old_config,
+                file_name,
+                offset,
+                data,
+                done,
+            } => {
+                let snapshot = Snapshot {
+                    file: file_name.into(),
+                    index,
+                    term: last_term,
+                    config,
+                    old_config,
+                };
+                self.handle_install_snapshot(term, from, snapshot, offset, data, done)
+            }
+            Message::SnapshotResult {
+                term,
+                from,
+                index,
+                offset,
+                done,
+            } => self.handle_snapshot_result(term, from, index, offset, done),
+        }
+    }
+}
+
+impl<K, V, T> Raft<K, V, T>
+where
+    K: Clone + Default + Serialize,
+    V: Clone + Default + Serialize,
+    T: Transport<K, V>,
+{
+    fn become_follower(&mut self, term: u64) -> Result<()> {
+        debug!(
+            target: "raft  ",
+            "{:?}, step down to follower, term:{}", self, term
+        );
+
+        self.role = Role::Follower;
+        self.leader = None;
+        self.votes.clear();
+        if term > self.term {
+            self.term = term;
+            self.votedfor = None;
+            self.persist_state()?;
+        }
+        Ok(())
+    }
+
+    fn become_leader(&mut self) -> Result<()> {
+        self.role = Role::Leader;
+        self.leader = Some(self.config.name.clone());
+        self.votes.clear();
+        self.elapsed = 0;
+
+        let next_index = self.to_last_index() + 1;
+        self.next_index.clear();
+        self.match_index.clear();
+        self.snapshot_offsets.clear();
+        for peer in self.to_peers().into_iter() {
+            self.next_index.insert(peer.clone(), next_index);
+            self.match_index.insert(peer, 0);
+        }
+
+        info!(target: "raft  ", "{:?}, became leader", self);
+
+        for peer in self.to_peers().into_iter() {
+            self.send_append(&peer)?;
+        }
+        Ok(())
+    }
+
+    fn campaign(&mut self) -> Result<()> {
+        self.term += 1;
+        self.role = Role::Candidate;
+        self.leader = None;
+        self.votedfor = Some(self.config.name.clone());
+        self.persist_state()?;
+
+        self.elapsed = 0;
+        self.timeout = election_timeout(self.config.election_ticks);
+        self.votes.clear();
+        self.votes.insert(self.config.name.clone());
+
+        debug!(target: "raft  ", "{:?}, campaign for leadership", self);
+
+        if self.has_quorum(|n| self.votes.contains(n)) {
+            return self.become_leader();
+        }
+
+        let (last_index, last_term) = (self.to_last_index(), self.to_last_term());
+        for peer in self.to_peers().into_iter() {
+            let msg = Message::RequestVote {
+                term: self.term,
+                from: self.config.name.clone(),
+                last_index,
+                last_term,
+            };
+            self.transport.send(&peer, msg)?;
+        }
+        Ok(())
+    }
+
+    fn handle_request_vote(
+        &mut self,
+        term: u64,
+        from: String,
+        last_index: u64,
+        last_term: u64,
+    ) -> Result<()> {
+        let granted = if term < self.term {
+            false
+        } else {
+            let ok = match &self.votedfor {
+                Some(votedfor) => votedfor == &from,
+                None => true,
+            };
+            let (my_index, my_term) = (self.to_last_index(), self.to_last_term());
+            let uptodate = last_term > my_term || (last_term == my_term && last_index >= my_index);
+            ok && uptodate
+        };
+
+        if granted {
+            self.votedfor = Some(from.clone());
+            self.persist_state()?;
+            self.elapsed = 0;
+        }
+
+        let msg = Message::Vote {
+            term: self.term,
+            from: self.config.name.clone(),
+            granted,
+        };
+        self.transport.send(&from, msg)
+    }
+
+    fn handle_vote(&mut self, term: u64, from: String, granted: bool) -> Result<()> {
+        if self.role != Role::Candidate || term != self.term || !granted {
+            return Ok(());
+        }
+
+        self.votes.insert(from);
+        if self.has_quorum(|n| self.votes.contains(n)) {
+            self.become_leader()?;
+        }
+        Ok(())
+    }
+
+    fn handle_append(
+        &mut self,
+        term: u64,
+        from: String,
+        prev_index: u64,
+        prev_term: u64,
+        entries: Vec<LogEntry<K, V>>,
+        committed: u64,
+    ) -> Result<()> {
+        if term < self.term {
+            let msg = Message::AppendResult {
+                term: self.term,
+                from: self.config.name.clone(),
+                success: false,
+                index: self.to_last_index(),
+            };
+            return self.transport.send(&from, msg);
+        }
+
+        // there can be only one leader for a term.
+        self.role = Role::Follower;
+        self.leader = Some(from.clone());
+        self.elapsed = 0;
+
+        // entries upto snapshot are committed, hence match with leader.
+        let (offset, offset_term) = self.to_offset();
+        let (prev_index, prev_term, entries) = if prev_index < offset {
+            let entries = entries.into_iter().filter(|e| e.index > offset);
+            (offset, offset_term, entries.collect())
+        } else {
+            (prev_index, prev_term, entries)
+        };
+
+        let (success, index) = match self.term_at(prev_index) {
+            Some(t) if t == prev_term => {
+                let index = prev_index + (entries.len() as u64);
+                self.merge_entries(entries)?;
+                if committed > self.committed {
+                    self.committed = cmp::min(committed, index);
+                }
+                (true, index)
+            }
+            Some(t) => {
+                // skip all entries from the conflicting term.
+                let mut index = prev_index - 1;
+                while index > self.committed && self.term_at(index) == Some(t) {
+                    index -= 1;
+                }
+                (false, index)
+            }
+            None => (false, self.to_last_index()),
+        };
+
+        let msg = Message::AppendResult {
+            term: self.term,
+            from: self.config.name.clone(),
+            success,
+            index,
+        };
+        self.transport.send(&from, msg)
+    }
+
+    fn handle_append_result(
+        &mut self,
+        term: u64,
+        from: String,
+        success: bool,
+        index: u64,
+    ) -> Result<()> {
+        if self.role != Role::Leader || term != self.term {
+            return Ok(());
+        }
+
+        let (next_index, match_index) = {
+            let next_index = self.next_index.get(&from).cloned().unwrap_or(1);
+            let match_index = self.match_index.get(&from).cloned().unwrap_or(0);
+            (next_index, match_index)
+        };
+
+        if success {
+            let match_index = cmp::max(match_index, index);
+            self.match_index.insert(from.clone(), match_index);
+            self.next_index
+                .insert(from.clone(), cmp::max(next_index, match_index + 1));
+            self.advance_commit()?;
+
+            if self.role == Role::Leader && match_index < self.to_last_index() {
+                self.send_append(&from)?;
+            }
+        } else {
+            let next_index = cmp::min(next_index.saturating_sub(1), index + 1);
+            let next_index = cmp::max(next_index, match_index + 1);
+            self.next_index.insert(from.clone(), next_index);
+            self.send_append(&from)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_install_snapshot(
+        &mut self,
+        term: u64,
+        from: String,
+        snapshot: Snapshot,
+        offset: u64,
+        data: Vec<u8>,
+        done: bool,
+    ) -> Result<()> {
+        let index = snapshot.index;
+        if term < self.term {
+            return self.send_snapshot_result(&from, index, 0, false);
+        }
+
+        // there can be only one leader for a term.
+        self.role = Role::Follower;
+        self.leader = Some(from.clone());
+        self.elapsed = 0;
+
+        // entries upto index are already committed in this node.
+        if index <= self.to_offset().0 || index <= self.committed {
+            return self.send_snapshot_result(&from, index, offset, true);
+        }
+
+        let file = self.config.to_snapshot_file(&snapshot.file)?;
+        let (tmp_file, received) = match &self.receiving {
+            Some((n, tmp_file, received)) if *n == index => (tmp_file.clone(), *received),
+            _ => {
+                let mut tmp_file = ffi::OsString::from(&file);
+                tmp_file.push(format!("-{}.tmp", index));
+                (tmp_file, 0)
+            }
+        };
+        // duplicate or out-of-order chunk.
+        if offset != received {
+            return self.send_snapshot_result(&from, index, received, false);
+        }
+
+        let mut fd = if offset == 0 {
+            // discard partially received snapshot, if any.
+            if let Some((_, old_file, _)) = self.receiving.take() {
+                fs::remove_file(&old_file).ok();
+            }
+            err_at!(IoError, fs::create_dir_all(&self.config.to_snapshot_dir()))?;
+            fs::remove_file(&tmp_file).ok();
+            util::create_file_a(tmp_file.clone())?
+        } else {
+            util::open_file_w(&tmp_file)?
+        };
+        write_file!(fd, &data, tmp_file.clone(), "raft-snapshot")?;
+        let received = received + (data.len() as u64);
+
+        if !done {
+            self.receiving = Some((index, tmp_file, received));
+            return self.send_snapshot_result(&from, index, received, false);
+        }
+
+        if self.config.fsync {
+            err_at!(IoError, fd.sync_all())?;
+        }
+        err_at!(IoError, fs::rename(&tmp_file, &file))?;
+        self.receiving = None;
+
+        self.install_snapshot(Snapshot { file, ..snapshot })?;
+        self.send_snapshot_result(&from, index, received, true)
+    }
+
+    fn handle_snapshot_result(
+        &mut self,
+        term: u64,
+        from: String,
+        index: u64,
+        offset: u64,
+        done: bool,
+    ) -> Result<()> {
+        if self.role != Role::Leader || term != self.term {
+            return Ok(());
+        }
+
+        if done {
+            self.snapshot_offsets.remove(&from);
+            let match_index = {
+                let match_index = self.match_index.get(&from).cloned().unwrap_or(0);
+                cmp::max(match_index, index)
+            };
+            self.match_index.insert(from.clone(), match_index);
+            self.next_index.insert(from.clone(), match_index + 1);
+            self.advance_commit()?;
+
+            if self.role == Role::Leader && match_index < self.to_last_index() {
+                self.send_append(&from)?;
+            }
+        } else if index == self.to_offset().0 {
+            // send next chunk only on progress, duplicate results are
+            // ignored, retries are driven by heartbeat.
+            let current = self.snapshot_offsets.get(&from).cloned().unwrap_or(0);
+            if offset != current {
+                self.snapshot_offsets.insert(from.clone(), offset);
+                self.send_snapshot(&from)?;
+            }
+        } else {
+            // leader has moved on to a newer snapshot, start afresh.
+            self.snapshot_offsets.insert(from.clone(), 0);
+            self.send_append(&from)?;
+        }
+
+        Ok(())
+    }
+
+    fn send_append(&mut self, peer: &str) -> Result<()> {
+        let next_index = self.next_index.get(peer).cloned().unwrap_or(1);
+        if next_index <= self.to_offset().0 {
+            return self.send_snapshot(peer);
+        }
+
+        let prev_index = next_index - 1;
+        let prev_term = match self.term_at(prev_index) {
+            Some(term) => term,
+            None => err_at!(Fatal, msg: format!("missing index {}", prev_index))?,
+        };
+
+        let msg = Message::AppendEntries {
+            term: self.term,
+            from: self.config.name.clone(),
+            prev_index,
+            prev_term,
+            entries: self.entries_from(next_index, self.config.max_entries),
+            committed: self.committed,
+        };
+        self.transport.send(peer, msg)
+    }
+
+    fn send_snapshot(&mut self, peer: &str) -> Result<()> {
+        let snapshot = match &self.snapshot {
+            Some(snapshot) => snapshot.clone(),
+            None => err_at!(Fatal, msg: format!("missing snapshot for {}", peer))?,
+        };
+        let offset = self.snapshot_offsets.get(peer).cloned().unwrap_or(0);
+
+        let (data, done) = {
+            let mut fd = util::open_file_r(&snapshot.file)?;
+            let len = err_at!(IoError, fd.metadata())?.len();
+            let n = cmp::min(
+                len.saturating_sub(offset),
+                self.config.snapshot_chunk as u64,
+            );
+            let data = read_file!(fd, offset, n, "raft-snapshot")?;
+            (data, (offset + n) >= len)
+        };
+        let file_name = match path::Path::new(&snapshot.file).file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => err_at!(InvalidFile, msg: format!("{:?}", snapshot.file))?,
+        };
+
+        let msg = Message::InstallSnapshot {
+            term: self.term,
+            from: self.config.name.clone(),
+            index: snapshot.index,
+            last_term: snapshot.term,
+            config: snapshot.config,
+            old_config: snapshot.old_config,
+            file_name,
+            offset,
+            data,
+            done,
+        };
+        self.transport.send(peer, msg)
+    }
+
+    fn send_snapshot_result(
+        &mut self,
+        to: &str,
+        index: u64,
+        offset: u64,
+        done: bool,
+    ) -> Result<()> {
+        let msg = Message::SnapshotResult {
+            term: self.term,
+            from: self.config.name.clone(),
+            index,
+            offset,
+            done,
+        };
+        self.transport.send(to, msg)
+    }
+
+    // leader can commit an index, only when an entry from its current
+    // term is replicated on majority of nodes. While in joint consensus,
+    // majority is required from both old and new configuration.
+    fn advance_commit(&mut self) -> Result<()> {
+        let last_index = self.to_last_index();
+        let match_of = |name: &str| -> u64 {
+            if name == self.config.name {
+                last_index
+            } else {
+                self.match_index.get(name).cloned().unwrap_or(0)
+            }
+        };
+
+        let mut indexes: Vec<u64> = self
+            .to_peers()
+            .iter()
+            .map(|p| match_of(p.as_str()))
+            .collect();
+        indexes.push(last_index);
+        indexes.sort_by(|x, y| y.cmp(x));
+        indexes.dedup();
+
+        let mut committed = self.committed;
+        for index in indexes.into_iter() {
+            if index <= committed {
+                break;
+            } else if self.has_quorum(|n| match_of(n) >= index) {
+                if self.term_at(index) == Some(self.term) {
+                    committed = index;
+                }
+                break;
+            }
+        }
+
+        if committed > self.committed {
+            debug!(
+                target: "raft  ",
+                "{:?}, commit index {} -> {}", self, self.committed, committed
+            );
+            self.committed = committed;
+            self.on_commit()?;
+        }
+        Ok(())
+    }
+
+    // on committing the joint configuration, leader shall propose the
+    // new configuration. On committing the new configuration, leader
+    // steps down if it is not part of it.
+    fn on_commit(&mut self) -> Result<()> {
+        if self.role != Role::Leader || self.config_index > self.committed {
+            return Ok(());
+        }
+
+        if !self.old_voters.is_empty() {
+            let op = Op::new_config(self.voters.clone());
+            self.propose_op(op)?;
+        } else if !self.voters.contains(&self.config.name) {
+            info!(target: "raft  ", "{:?}, removed from cluster", self);
+            self.become_follower(self.term)?;
+        }
+        Ok(())
+    }
+
+    fn propose_op(&mut self, op: Op<K, V>) -> Result<u64> {
+        if self.role != Role::Leader {
+            let msg = format!(
+                "{} not a leader, leader:{:?}",
+                self.config.name, self.leader
+            );
+            return err_at!(APIMisuse, msg: msg);
+        }
+
+        let index = self.to_last_index() + 1;
+        self.append_entries(vec![LogEntry::new(index, self.term, op)])?;
+        self.advance_commit()?;
+
+        for peer in self.to_peers().into_iter() {
+            // don't flood peers that are catching up, newly added peers
+            // shall catch up from heartbeat.
+            if self.next_index.get(&peer) == Some(&index) {
+                self.send_append(&peer)?;
+            }
+        }
+
+        Ok(index)
+    }
+
+    fn change_config(&mut self, voters: Vec<String>) -> Result<u64> {
+        if self.role == Role::Leader && self.is_config_pending() {
+            let msg = format!("config change in progress at {}", self.config_index);
+            return err_at!(APIMisuse, msg: msg);
+        }
+
+        let op = Op::new_joint_config(self.voters.clone(), voters);
+        self.propose_op(op)
+    }
+
+    fn is_config_pending(&self) -> bool {
+        !self.old_voters.is_empty() || self.config_index > self.committed
+    }
+
+    // merge entries from leader into local log, truncating conflicting
+    // entries if any.
+    fn merge_entries(&mut self, entries: Vec<LogEntry<K, V>>) -> Result<()> {
+        let mut fresh = vec![];
+        for entry in entries.into_iter() {
+            if fresh.len() > 0 {
+                fresh.push(entry);
+                continue;
+            }
+            match self.term_at(entry.index) {
+                Some(term) if term == entry.term => (),
+                Some(_) if entry.index <= self.committed => {
+                    let msg = format!("conflict at committed index {}", entry.index);
+                    err_at!(Fatal, msg: msg)?
+                }
+                Some(_) => {
+                    self.truncate_after(entry.index - 1)?;
+                    fresh.push(entry);
+                }
+                None => fresh.push(entry),
+            }
+        }
+
+        self.append_entries(fresh)
+    }
+
+    // append entries to log, entries are persisted in batches, one
+    // batch for each term. Configuration entries take effect as soon
+    // as they are appended.
+    fn append_entries(&mut self, entries: Vec<LogEntry<K, V>>) -> Result<()> {
+        let mut iter = entries.into_iter().peekable();
+        while let Some(entry) = iter.next() {
+            let term = entry.term;
+            let mut batch = vec![entry];
+            loop {
+                match iter.peek() {
+                    Some(entry) if entry.term == term => (),
+                    _ => break,
+                }
+                batch.push(iter.next().unwrap());
+            }
+
+            let state = State::new(
+                term,
+                self.committed,
+                self.to_offset().0,
+                (self.voters.clone(), self.old_voters.clone()),
+                self.leader.clone(),
+            );
+            let dentries = batch
+                .iter()
+                .map(|e| DEntry::new(e.index, e.op.clone()))
+                .collect();
+            self.dlog.shards[0].append_batch(state, dentries)?;
+            for entry in batch.into_iter() {
+                self.set_membership(&entry);
+                self.log.push(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn truncate_after(&mut self, index: u64) -> Result<()> {
+        debug!(
+            target: "raft  ",
+            "{:?}, truncate log after index:{}", self, index
+        );
+
+        self.dlog.shards[0].truncate_after(index)?;
+        self.log
+            .truncate(convert_at!((index - self.to_offset().0))?);
+        self.reload_membership();
+        Ok(())
+    }
+
+    // install snapshot received from leader, log entries following the
+    // snapshot are retained if they match with the snapshot.
+    fn install_snapshot(&mut self, snapshot: Snapshot) -> Result<()> {
+        let index = snapshot.index;
+        match self.term_at(index) {
+            Some(term) if term == snapshot.term => (),
+            _ => {
+                if self.to_last_index() > index {
+                    self.dlog.shards[0].truncate_after(index)?;
+                }
+                self.log.clear();
+            }
+        }
+
+        info!(
+            target: "raft  ",
+            "{:?}, install snapshot {:?} at index:{}", self, snapshot.file, index
+        );
+
+        self.committed = cmp::max(self.committed, index);
+        if self.applied < index {
+            self.applied = index;
+            self.installed = Some(snapshot.clone());
+        }
+        self.compact(snapshot)?;
+        self.reload_membership();
+
+        Ok(())
+    }
+
+    // compact log entries upto snapshot and purge journals holding them.
+    fn compact(&mut self, snapshot: Snapshot) -> Result<()> {
+        let index = snapshot.index;
+        self.persist_snapshot(&snapshot)?;
+
+        let n = self.log.iter().take_while(|e| e.index <= index).count();
+        self.log.drain(..n);
+
+        // remove older snapshot, if it was received from leader.
+        if let Some(old) = self.snapshot.take() {
+            let received = path::Path::new(&old.file).starts_with(&self.config.to_snapshot_dir());
+            if received && old.file != snapshot.file {
+                fs::remove_file(&old.file).ok();
+            }
+        }
+        self.snapshot = Some(snapshot);
+        self.persist_state()?;
+
+        self.dlog.set_deep_freeze(Bound::Included(index))?;
+        let n = self.dlog.purge_cold()?;
+
+        debug!(
+            target: "raft  ",
+            "{:?}, compacted upto index:{}, purged {} journals", self, index, n
+        );
+
+        Ok(())
+    }
+
+    fn persist_state(&self) -> Result<()> {
+        let state = State::new(
+            self.term,
+            self.committed,
+            self.to_offset().0,
+            (self.voters.clone(), self.old_voters.clone()),
+            self.votedfor.clone(),
+        );
+        let mut buf = vec![];
+        state.encode(&mut buf)?;
+
+        self.write_atomic(self.config.to_state_file(), &buf)
+    }
+
+    fn persist_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let mut buf = vec![];
+        snapshot.encode(&mut buf)?;
+
+        self.write_atomic(self.config.to_snapshot_file(), &buf)
+    }
+
+    // write into a temporary file and atomically rename it.
+    fn write_atomic(&self, file: ffi::OsString, buf: &[u8]) -> Result<()> {
+        let tmp_file = {
+            let mut tmp_file = file.clone();
+            tmp_file.push(".tmp");
+            tmp_file
+        };
+        let mut fd = util::create_file_a(tmp_file.clone())?;
+        write_file!(fd, buf, tmp_file.clone(), "raft-state")?;
+        if self.config.fsync {
+            err_at!(IoError, fd.sync_all())?;
+        }
+        err_at!(IoError, fs::rename(&tmp_file, &file))?;
+
+        Ok(())
+    }
+
+    fn set_membership(&mut self, entry: &LogEntry<K, V>) {
+        match &entry.op {
+            Op::JointConfig { old, new } => {
+                self.voters = new.clone();
+                self.old_voters = old.clone();
+            }
+            Op::Config { nodes } => {
+                self.voters = nodes.clone();
+                self.old_voters = vec![];
+            }
+            _ => return,
+        }
+        self.config_index = entry.index;
+
+        debug!(
+            target: "raft  ",
+            "{:?}, config voters:{:?} old_voters:{:?}", self, self.voters, self.old_voters
+        );
+    }
+
+    // membership, as of the latest configuration entry in the log.
+    fn reload_membership(&mut self) {
+        let (voters, old_voters, config_index) = self.config_at(self.to_last_index());
+        self.voters = voters;
+        self.old_voters = old_voters;
+        self.config_index = config_index;
+    }
+
+    // return (voters, old_voters, config_index) as of `index`.
+    fn config_at(&self, index: u64) -> (Vec<String>, Vec<String>, u64) {
+        for entry in self.log.iter().rev().filter(|e| e.index <= index) {
+            match &entry.op {
+                Op::JointConfig { old, new } => return (new.clone(), old.clone(), entry.index),
+                Op::Config { nodes } => return (nodes.clone(), vec![], entry.index),
+                _ => (),
+            }
+        }
+
+        match &self.snapshot {
+            Some(s) => (s.config.clone(), s.old_config.clone(), s.index),
+            None => (self.config.nodes.clone(), vec![], 0),
+        }
+    }
+
+    fn to_peers(&self) -> Vec<String> {
+        let mut peers: Vec<String> = {
+            let iter = self.voters.iter().chain(self.old_voters.iter());
+            let iter = iter.filter(|n| n.as_str() != self.config.name);
+            iter.cloned().collect()
+        };
+        peers.sort();
+        peers.dedup();
+        peers
+    }
+
+    fn is_voter(&self) -> bool {
+        let name = &self.config.name;
+        self.voters.contains(name) || self.old_voters.contains(name)
+    }
+
+    // majority from voters, and from old_voters while in joint consensus.
+    fn has_quorum<F>(&self, f: F) -> bool
+    where
+        F: Fn(&str) -> bool,
+    {
+        let is_majority = |nodes: &Vec<String>| -> bool {
+            let n = nodes.iter().filter(|n| f(n.as_str())).count();
+            n > (nodes.len() / 2)
+        };
+        is_majority(&self.voters) && (self.old_voters.is_empty() || is_majority(&self.old_voters))
+    }
+
+    fn to_offset(&self) -> (u64, u64) {
+        self.snapshot.as_ref().map_or((0, 0), |s| (s.index, s.term))
+    }
+
+    fn to_last_term(&self) -> u64 {
+        self.log.last().map_or(self.to_offset().1, |e| e.term)
+    }
+
+    fn term_at(&self, index: u64) -> Option<u64> {
+        let (offset, offset_term) = self.to_offset();
+        match index {
+            index if index == offset => Some(offset_term),
+            index if index < offset || index > self.to_last_index() => None,
+            index => {
+                let off: usize = convert_at!(index - offset - 1).ok()?;
+                self.log.get(off).map(|entry| entry.term)
+            }
+        }
+    }
+
+    fn entries_from(&self, index: u64, max: usize) -> Vec<LogEntry<K, V>> {
+        let offset = self.to_offset().0;
+        let start = cmp::max(index, offset + 1) - offset - 1;
+        let start = cmp::min(start as usize, self.log.len());
+        let end = cmp::min(start + max, self.log.len());
+        self.log[start..end].to_vec()
+    }
+}
+
+// randomized election timeout between `ticks` and 2 * `ticks`.
+fn election_timeout(ticks: usize) -> usize {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(ticks);
+    ticks + ((hasher.finish() as usize) % cmp::max(ticks, 1))
+}
+
+#[cfg(test)]
+#[path = "raft_node_test.rs"]
+mod raft_node_test;
@@ -0,0 +1,442 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use std::{ffi, fs, path};
+
+use super::*;
+use crate::{
+    core::Reader,
+    llrb::Llrb,
+    nobitmap::NoBitmap,
+    raft_log::{ChannelNetwork, ChannelTransport},
+    robt,
+};
+
+type Node = Raft<i64, i64, ChannelTransport<i64, i64>>;
+
+#[test]
+fn test_raft_cluster() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed:{}", seed);
+
+    for n in [3, 5].iter() {
+        let name = format!("cluster{}", n);
+        let network = ChannelNetwork::new();
+        let mut nodes = make_cluster(&name, *n, &network);
+
+        let leader = wait_leader(&mut nodes, None);
+        println!("n:{} leader:{}", n, nodes[leader].to_name());
+        assert_eq!(nodes[leader].propose(Op::new_set(1, 1)).unwrap(), 1);
+
+        let mut last_index = 1;
+        for i in 0..2000 {
+            let key = (rng.gen::<u64>() % 100) as i64;
+            let op = match rng.gen::<u8>() % 3 {
+                0 | 1 => Op::new_set(key, i),
+                2 => Op::new_delete(key),
+                _ => unreachable!(),
+            };
+            last_index = nodes[leader].propose(op).unwrap();
+            if rng.gen::<u8>() % 10 == 0 {
+                tick_all(&mut nodes, 1);
+            }
+        }
+        assert_eq!(last_index, 2001);
+
+        // followers can't accept proposals.
+        let follower = (leader + 1) % nodes.len();
+        match nodes[follower].propose(Op::new_set(1, 1)) {
+            Err(Error::APIMisuse(_)) => (),
+            res => panic!("unexpected {:?}", res.map(|_| ())),
+        }
+
+        wait_commit(&mut nodes, last_index, None);
+        check_nodes(&nodes, last_index);
+        check_apply(&mut nodes);
+
+        nodes.into_iter().for_each(|node| node.purge().unwrap());
+    }
+}
+
+#[test]
+fn test_raft_failover() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed:{}", seed);
+
+    let network = ChannelNetwork::new();
+    let mut nodes = make_cluster("failover", 5, &network);
+
+    let old = wait_leader(&mut nodes, None);
+    for i in 0..100 {
+        nodes[old].propose(Op::new_set(i, i)).unwrap();
+    }
+    wait_commit(&mut nodes, 100, None);
+    let old_term = nodes[old].to_term();
+
+    // isolate the leader, and let it accept proposals that can never
+    // be committed.
+    network.isolate(&nodes[old].to_name()).unwrap();
+    for i in 0..50 {
+        nodes[old].propose(Op::new_set(i, -i)).unwrap();
+    }
+
+    let leader = wait_leader(&mut nodes, Some(old));
+    assert!(nodes[leader].to_term() > old_term);
+    assert!(nodes[old].is_leader());
+    assert_eq!(nodes[old].to_committed(), 100);
+
+    let mut last_index = 0;
+    for _ in 0..200 {
+        let key = (rng.gen::<u64>() % 100) as i64;
+        last_index = nodes[leader].propose(Op::new_set(key, key * 10)).unwrap();
+    }
+    wait_commit(&mut nodes, last_index, Some(old));
+
+    // heal the old leader, it shall step down and its conflicting
+    // entries shall be truncated.
+    network.heal(&nodes[old].to_name()).unwrap();
+    wait_commit(&mut nodes, last_index, None);
+    assert!(!nodes[old].is_leader());
+    assert_eq!(nodes[old].to_leader(), Some(nodes[leader].to_name()));
+    check_nodes(&nodes, last_index);
+
+    // reload a follower from disk.
+    let follower = (leader + 1) % nodes.len();
+    let term = nodes[follower].to_term();
+    let entries = nodes[follower].to_entries(1);
+    let node = nodes.remove(follower);
+    let config = node.config.clone();
+    node.close().unwrap();
+
+    let transport = network.join(&config.name).unwrap();
+    let node: Node = Raft::load(config, transport).unwrap();
+    assert_eq!(node.to_term(), term);
+    assert_eq!(node.to_entries(1), entries);
+    assert_eq!(node.to_role(), Role::Follower);
+    nodes.insert(follower, node);
+
+    let index = nodes[leader].propose(Op::new_delete(10)).unwrap();
+    wait_commit(&mut nodes, index, None);
+    check_nodes(&nodes, index);
+    check_apply(&mut nodes);
+
+    nodes.into_iter().for_each(|node| node.purge().unwrap());
+}
+
+#[test]
+fn test_raft_membership() {
+    let network = ChannelNetwork::new();
+    let mut nodes = make_cluster("membership", 3, &network);
+
+    let leader = wait_leader(&mut nodes, None);
+    for i in 0..100 {
+        nodes[leader].propose(Op::new_set(i, i)).unwrap();
+    }
+    wait_commit(&mut nodes, 100, None);
+
+    // new nodes start without any configuration, and shall learn the
+    // same from leader.
+    let dir = nodes[leader].config.dir.clone();
+    for i in 3..5 {
+        let name = format!("membership-node{}", i);
+        nodes.push(make_node(&dir, &name, vec![], &network));
+
+        let index = nodes[leader].add_voter(&name).unwrap();
+        assert!(nodes[leader].is_joint_consensus());
+        // only one change can be in progress.
+        match nodes[leader].add_voter("membership-nodex") {
+            Err(Error::APIMisuse(_)) => (),
+            res => panic!("unexpected {:?}", res),
+        }
+        // joint configuration is followed by the new configuration.
+        wait_commit(&mut nodes, index + 1, None);
+        assert!(!nodes[leader].is_joint_consensus());
+        assert_eq!(nodes[leader].to_voters().len(), i + 1);
+        assert_eq!(nodes[i].to_voters(), nodes[leader].to_voters());
+    }
+
+    match nodes[leader].propose(Op::new_config(vec![])) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    // remove the leader, it shall step down once the new configuration
+    // is committed.
+    let old = leader;
+    let old_name = nodes[old].to_name();
+    let index = nodes[old].remove_voter(&old_name).unwrap();
+    let leader = wait_leader(&mut nodes, Some(old));
+    assert!(!nodes[old].is_leader());
+    assert!(nodes[old].to_committed() >= index + 1);
+    assert!(!nodes[leader].to_voters().contains(&old_name));
+    assert_eq!(nodes[leader].to_voters().len(), 4);
+
+    let leader_name = nodes[leader].to_name();
+    nodes.remove(old).purge().unwrap();
+    let leader = nodes.iter().position(|n| n.to_name() == leader_name);
+    let leader = leader.unwrap();
+
+    let mut last_index = 0;
+    for i in 0..100 {
+        last_index = nodes[leader].propose(Op::new_set(i, i * 10)).unwrap();
+    }
+    wait_commit(&mut nodes, last_index, None);
+    check_nodes(&nodes, last_index);
+    check_apply(&mut nodes);
+
+    nodes.into_iter().for_each(|node| node.purge().unwrap());
+}
+
+#[test]
+fn test_raft_snapshot() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed:{}", seed);
+
+    let network = ChannelNetwork::new();
+    let mut nodes = make_cluster("snapshot", 3, &network);
+
+    let leader = wait_leader(&mut nodes, None);
+    let lagging = (leader + 1) % nodes.len();
+
+    // isolate a follower, and let leader move ahead.
+    network.isolate(&nodes[lagging].to_name()).unwrap();
+    let mut last_index = 0;
+    for i in 0..1000 {
+        let key = (rng.gen::<u64>() % 100) as i64;
+        last_index = nodes[leader].propose(Op::new_set(key, i)).unwrap();
+    }
+    wait_commit(&mut nodes, last_index, Some(lagging));
+
+    // leader applies entries and persists its index as snapshot.
+    let mut ref_index: Box<Llrb<i64, i64>> = Llrb::new(&nodes[leader].to_name());
+    nodes[leader].apply(ref_index.as_mut()).unwrap();
+    let applied = nodes[leader].to_applied();
+    assert_eq!(applied, last_index);
+
+    let file = build_snapshot(&nodes[leader], ref_index.as_mut(), "snapshot1");
+    match nodes[leader].set_snapshot(file.clone(), applied + 1) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+    nodes[leader].set_snapshot(file.clone(), applied).unwrap();
+    assert_eq!(nodes[leader].to_persisted(), applied);
+    assert_eq!(nodes[leader].to_entries(1), vec![]);
+    assert_eq!(nodes[leader].to_last_index(), last_index);
+    // journals holding compacted entries are purged.
+    assert!(nodes[leader].dlog.shards[0].to_journals().len() <= 1);
+
+    for i in 0..100 {
+        last_index = nodes[leader].propose(Op::new_set(i, -i)).unwrap();
+    }
+
+    // lagging follower shall catch up via snapshot.
+    network.heal(&nodes[lagging].to_name()).unwrap();
+    wait_commit(&mut nodes, last_index, None);
+
+    let snapshot = nodes[lagging].take_installed().unwrap();
+    assert_eq!(snapshot.to_index(), applied);
+    assert_eq!(nodes[lagging].to_persisted(), applied);
+    assert_eq!(nodes[lagging].to_applied(), applied);
+    assert_eq!(nodes[lagging].take_installed(), None);
+
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new(&nodes[lagging].to_name());
+    {
+        let file = snapshot.to_file();
+        let dir = path::Path::new(&file).parent().unwrap().as_os_str();
+        let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(dir, "snapshot1").unwrap();
+        for entry in snap.iter().unwrap() {
+            let entry = entry.unwrap();
+            let (key, value) = (entry.to_key(), entry.to_native_value().unwrap());
+            index.set_index(key, value, entry.to_seqno()).unwrap();
+        }
+    }
+    nodes[lagging].apply(index.as_mut()).unwrap();
+    nodes[leader].apply(ref_index.as_mut()).unwrap();
+    compare_index(ref_index.as_mut(), index.as_mut());
+
+    // reload the lagging follower from disk.
+    let entries = nodes[lagging].to_entries(1);
+    let node = nodes.remove(lagging);
+    let config = node.config.clone();
+    node.close().unwrap();
+
+    let transport = network.join(&config.name).unwrap();
+    let node: Node = Raft::load(config, transport).unwrap();
+    assert_eq!(node.to_persisted(), applied);
+    assert_eq!(node.to_applied(), applied);
+    assert_eq!(node.to_snapshot(), Some(snapshot));
+    assert_eq!(node.to_entries(1), entries);
+    nodes.insert(lagging, node);
+
+    let index = nodes[leader].propose(Op::new_set(1, 1)).unwrap();
+    wait_commit(&mut nodes, index, None);
+
+    nodes.into_iter().for_each(|node| node.purge().unwrap());
+}
+
+fn make_cluster(name: &str, n: usize, network: &ChannelNetwork<i64, i64>) -> Vec<Node> {
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(std::env::temp_dir());
+        dir.push(format!("test-raft-{}", name));
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    println!("dir:{:?}", dir);
+
+    let names: Vec<String> = (0..n).map(|i| format!("{}-node{}", name, i)).collect();
+    let mut nodes = vec![];
+    for name in names.iter() {
+        nodes.push(make_node(&dir, name, names.clone(), network));
+    }
+    nodes
+}
+
+#[test]
+fn test_snapshot_file() {
+    let dir = std::env::temp_dir().into_os_string();
+    let config = Config::new(&dir, "test-snapshot-file", vec![]);
+    let snapshot_dir = path::PathBuf::from(config.to_snapshot_dir());
+
+    let file = config
+        .to_snapshot_file("app-snapshot.dat".as_ref())
+        .unwrap();
+    assert_eq!(
+        path::Path::new(&file).parent(),
+        Some(snapshot_dir.as_path())
+    );
+
+    let names = vec![
+        "",
+        ".",
+        "..",
+        "../app-snapshot.dat",
+        "../../etc/passwd",
+        "/etc/passwd",
+        "app/snapshot.dat",
+        "app\\..\\snapshot.dat",
+    ];
+    for name in names.into_iter() {
+        match config.to_snapshot_file(name.as_ref()) {
+            Err(Error::InvalidInput(_)) => (),
+            res => panic!("unexpected {:?} for {:?}", res, name),
+        }
+    }
+}
+
+fn make_node(
+    dir: &ffi::OsStr,
+    name: &str,
+    names: Vec<String>,
+    network: &ChannelNetwork<i64, i64>,
+) -> Node {
+    let mut config = Config::new(dir, name, names);
+    config.set_journal(10_000, false).unwrap();
+    config.set_max_entries(100).unwrap();
+    config.set_snapshot_chunk(1024).unwrap();
+    let transport = network.join(name).unwrap();
+    Raft::create(config, transport).unwrap()
+}
+
+// build a robt snapshot from `index`, return the index file.
+fn build_snapshot(node: &Node, index: &mut Llrb<i64, i64>, name: &str) -> ffi::OsString {
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(&node.config.dir);
+        dir.push(format!("{}-app", node.to_name()));
+        dir.into_os_string()
+    };
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.set_delta(None, false).unwrap();
+    config.set_value_log(None, false).unwrap();
+    let b = robt::Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(index.iter().unwrap(), vec![]).unwrap();
+
+    let mut file = path::PathBuf::new();
+    file.push(&dir);
+    file.push(format!("{}.indx", name));
+    file.into_os_string()
+}
+
+fn tick_all(nodes: &mut Vec<Node>, ticks: usize) {
+    for _ in 0..ticks {
+        for node in nodes.iter_mut() {
+            node.tick().unwrap();
+        }
+    }
+}
+
+fn wait_leader(nodes: &mut Vec<Node>, skip: Option<usize>) -> usize {
+    for _ in 0..10_000 {
+        tick_all(nodes, 1);
+        let leaders: Vec<usize> = nodes
+            .iter()
+            .enumerate()
+            .filter(|(i, node)| Some(*i) != skip && node.is_leader())
+            .map(|(i, _)| i)
+            .collect();
+        if leaders.len() == 1 {
+            return leaders[0];
+        }
+    }
+    panic!("leader not elected");
+}
+
+fn wait_commit(nodes: &mut Vec<Node>, index: u64, skip: Option<usize>) {
+    for _ in 0..10_000 {
+        tick_all(nodes, 1);
+        let ok = nodes
+            .iter()
+            .enumerate()
+            .all(|(i, node)| Some(i) == skip || node.to_committed() >= index);
+        if ok {
+            return;
+        }
+    }
+    panic!("index {} not committed", index);
+}
+
+fn check_nodes(nodes: &Vec<Node>, index: u64) {
+    let ref_entries = nodes[0].to_entries(1);
+    assert_eq!(ref_entries.len() as u64, index);
+    for (i, entry) in ref_entries.iter().enumerate() {
+        assert_eq!(entry.to_index(), (i as u64) + 1);
+    }
+    for node in nodes.iter() {
+        assert_eq!(node.to_entries(1), ref_entries);
+    }
+}
+
+fn check_apply(nodes: &mut Vec<Node>) {
+    let mut indexes = vec![];
+    for node in nodes.iter_mut() {
+        let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm(&node.to_name());
+        assert_eq!(node.to_applied(), 0);
+        let n = node.apply(index.as_mut()).unwrap();
+        assert_eq!(node.to_applied(), node.to_committed());
+        assert_eq!(n as u64, node.to_committed());
+        indexes.push(index);
+    }
+
+    let mut ref_index = indexes.remove(0);
+    for mut index in indexes.into_iter() {
+        compare_index(ref_index.as_mut(), index.as_mut());
+    }
+}
+
+fn compare_index(ref_index: &mut Llrb<i64, i64>, index: &mut Llrb<i64, i64>) {
+    let mut iter = index.iter().unwrap();
+    for ref_entry in ref_index.iter().unwrap() {
+        let ref_entry = ref_entry.unwrap();
+        let entry = iter.next().unwrap().unwrap();
+        assert_eq!(entry.to_key(), ref_entry.to_key());
+        assert_eq!(entry.to_native_value(), ref_entry.to_native_value());
+        assert_eq!(entry.to_seqno(), ref_entry.to_seqno());
+    }
+    assert!(iter.next().is_none());
+}
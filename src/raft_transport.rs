@@ -0,0 +1,151 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    core::{Result, Serialize},
+    error::Error,
+    raft_node::Message,
+};
+
+/// Transport to exchange [Message] between [Raft][crate::raft_log::Raft]
+/// nodes.
+///
+/// Delivery is best effort, messages can be dropped, delayed or
+/// duplicated by the transport, and raft protocol shall recover from
+/// the same.
+pub trait Transport<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    /// Send `msg` to peer node `to`.
+    fn send(&mut self, to: &str, msg: Message<K, V>) -> Result<()>;
+
+    /// Return the next message sent to this node, if any. Shall not block.
+    fn try_recv(&mut self) -> Result<Option<Message<K, V>>>;
+}
+
+/// In-process network, where raft nodes exchange messages over channels.
+///
+/// Each node shall [join][ChannelNetwork::join] the network to get its
+/// [ChannelTransport]. Nodes can be isolated from, and healed back into,
+/// the network to simulate network partitions.
+pub struct ChannelNetwork<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    inner: Arc<Mutex<Network<K, V>>>,
+}
+
+struct Network<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    nodes: HashMap<String, mpsc::Sender<Message<K, V>>>,
+    isolated: HashSet<String>,
+}
+
+impl<K, V> Clone for ChannelNetwork<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn clone(&self) -> Self {
+        ChannelNetwork {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<K, V> ChannelNetwork<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    /// Create a new network, without any nodes.
+    pub fn new() -> ChannelNetwork<K, V> {
+        let network = Network {
+            nodes: HashMap::new(),
+            isolated: HashSet::new(),
+        };
+        ChannelNetwork {
+            inner: Arc::new(Mutex::new(network)),
+        }
+    }
+
+    /// Join node `name` to this network, and return the transport to be
+    /// used by that node. Joining again with the same name shall replace
+    /// the older transport.
+    pub fn join(&self, name: &str) -> Result<ChannelTransport<K, V>> {
+        let (tx, rx) = mpsc::channel();
+        self.as_network()?.nodes.insert(name.to_string(), tx);
+
+        Ok(ChannelTransport {
+            name: name.to_string(),
+            network: self.clone(),
+            rx,
+        })
+    }
+
+    /// Isolate node `name` from the rest of the network, all messages
+    /// to and from `name` shall be dropped.
+    pub fn isolate(&self, name: &str) -> Result<()> {
+        self.as_network()?.isolated.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Heal node `name` back into the network.
+    pub fn heal(&self, name: &str) -> Result<()> {
+        self.as_network()?.isolated.remove(name);
+        Ok(())
+    }
+
+    fn as_network(&self) -> Result<MutexGuard<Network<K, V>>> {
+        match self.inner.lock() {
+            Ok(network) => Ok(network),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+/// Transport for a single node in [ChannelNetwork].
+pub struct ChannelTransport<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    name: String,
+    network: ChannelNetwork<K, V>,
+    rx: mpsc::Receiver<Message<K, V>>,
+}
+
+impl<K, V> Transport<K, V> for ChannelTransport<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn send(&mut self, to: &str, msg: Message<K, V>) -> Result<()> {
+        let network = self.network.as_network()?;
+        if network.isolated.contains(&self.name) || network.isolated.contains(to) {
+            return Ok(());
+        }
+        match network.nodes.get(to) {
+            // receiving node might have gone away, drop the message.
+            Some(tx) => tx.send(msg).ok(),
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn try_recv(&mut self) -> Result<Option<Message<K, V>>> {
+        match self.rx.try_recv() {
+            Ok(msg) => Ok(Some(msg)),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(err) => err_at!(IPCFail, msg: format!("{}", err)),
+        }
+    }
+}
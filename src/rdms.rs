@@ -2,18 +2,32 @@
 //!
 //! [Rdms] can be composed using underlying components and mechanisms defined
 //! in [core] module.
+//!
+//! **Durability**:
+//!
+//! [Rdms::with_wal] compose any [Index] with a [Wal] instance, to create
+//! a [Durable] index. Writes are first appended to the write-ahead-log and
+//! then applied on the index, using the sequence-number assigned by the log.
+//! While opening a durable index, mutations in the log that are newer than
+//! the index's seqno are automatically replayed.
 
 use std::{
-    fmt, marker,
+    borrow::Borrow,
+    cmp, fmt,
+    hash::{BuildHasher, Hash},
+    marker,
+    ops::Bound,
     sync::{self, Arc, MutexGuard},
 };
 
 #[allow(unused_imports)]
 use crate::core;
 use crate::{
-    core::{CommitIter, CommitIterator, Diff, Footprint, Index},
-    core::{Cutoff, Result, Validate},
+    core::{CommitIter, CommitIterator, Diff, Entry, Footprint, Index},
+    core::{Cutoff, Replay, Result, Serialize, Validate, WalWriter, Writer},
+    dlog::Dlog,
     error::Error,
+    wal::{self, Wal},
 };
 
 /// Index type, composable index type. Check module documentation for
@@ -174,3 +188,270 @@ where
         index.validate()
     }
 }
+
+impl<K, V, I> Rdms<K, V, I>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Serialize + Footprint,
+    V: 'static + Send + Clone + Default + Serialize + Diff + Footprint,
+    I: Index<K, V>,
+    <I as Index<K, V>>::W: WalWriter<K, V>,
+{
+    /// Create a new [Durable] instance, identified by `name`, composing the
+    /// underlying `index` with a write-ahead-log loaded from `dl`.
+    ///
+    /// All mutations in the log that are greater-than index's seqno are
+    /// replayed on the index before returning.
+    pub fn with_wal<S, H>(
+        name: S,
+        index: I,
+        dl: Dlog<wal::State, wal::Op<K, V>>,
+        hash_builder: H,
+    ) -> Result<Box<Durable<K, V, I, H>>>
+    where
+        S: AsRef<str>,
+        H: Clone + BuildHasher,
+    {
+        let mut rdms = Rdms::new(name, index)?;
+
+        let seqno = rdms.to_seqno()?;
+        let (mut wal, n_ops) = {
+            let mut db = ReplayWriter(rdms.to_writer()?);
+            Wal::from_dlog_replay(dl, hash_builder, &mut db, seqno)?
+        };
+        // writers apply mutations in seqno order, while replay is done
+        // shard by shard, fix up the index seqno.
+        let last_seqno = wal.to_seqno() - 1;
+        if n_ops > 0 && last_seqno > rdms.to_seqno()? {
+            rdms.set_seqno(last_seqno)?;
+        }
+
+        Ok(Box::new(Durable {
+            rdms,
+            wal,
+            seqlock: Arc::new(sync::Mutex::new(())),
+            persisted: false,
+        }))
+    }
+}
+
+// Adapt index writer for replaying write-ahead-log.
+pub(crate) struct ReplayWriter<W>(pub(crate) W);
+
+impl<K, V, W> Replay<K, V> for ReplayWriter<W>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    W: WalWriter<K, V>,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<()> {
+        self.0.set_index(key, value, seqno)?;
+        Ok(())
+    }
+
+    fn set_cas_index(&mut self, key: K, value: V, cas: u64, seqno: u64) -> Result<()> {
+        // a failed CAS operation was already reported to the application,
+        // when it was originally applied.
+        match self.0.set_cas_index(key, value, cas, seqno) {
+            Ok(_) | Err(Error::InvalidCAS(_)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn delete_index(&mut self, key: K, seqno: u64) -> Result<()> {
+        self.0.delete_index(&key, seqno)?;
+        Ok(())
+    }
+}
+
+/// Durable index type, composing a [Wal] with an [Index]. Created using
+/// [Rdms::with_wal].
+pub struct Durable<K, V, I, H>
+where
+    K: 'static + Send + Clone + Default + Ord + Serialize,
+    V: 'static + Send + Clone + Default + Serialize + Diff,
+    I: Index<K, V>,
+    H: Clone + BuildHasher,
+{
+    rdms: Box<Rdms<K, V, I>>,
+    wal: Wal<K, V, H>,
+    // serialize log-append and index-apply, so that the index sees
+    // mutations in seqno order.
+    seqlock: Arc<sync::Mutex<()>>,
+    // whether index's commit persist all its entries onto disk.
+    persisted: bool,
+}
+
+impl<K, V, I, H> Durable<K, V, I, H>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Serialize + Footprint,
+    V: 'static + Send + Clone + Default + Serialize + Diff + Footprint,
+    I: Index<K, V>,
+    H: Clone + BuildHasher,
+{
+    /// Set this to true if the underlying index's commit persist all its
+    /// entries onto disk. Default is false, and write-ahead-log journals
+    /// shall not be purged on commit, for memory-only index the log is the
+    /// only durable copy of its entries. Refer [Durable::commit] for
+    /// the journals purged on commit.
+    pub fn set_persisted(&mut self, persisted: bool) -> &mut Self {
+        self.persisted = persisted;
+        self
+    }
+
+    /// Close this instance, both the write-ahead-log and the index.
+    /// Persisted data (in disk) is not cleared, use `purge()` for that.
+    pub fn close(mut self) -> Result<()> {
+        self.wal.close()?;
+        self.rdms.close()
+    }
+
+    /// Purge this instance along with the write-ahead-log and the index.
+    pub fn purge(self) -> Result<()> {
+        self.wal.purge()?;
+        self.rdms.purge()
+    }
+
+    pub fn to_name(&self) -> Result<String> {
+        self.rdms.to_name()
+    }
+
+    pub fn to_metadata(&self) -> Result<Vec<u8>> {
+        self.rdms.to_metadata()
+    }
+
+    pub fn to_seqno(&self) -> Result<u64> {
+        self.rdms.to_seqno()
+    }
+
+    pub fn to_reader(&mut self) -> Result<<I as Index<K, V>>::R> {
+        self.rdms.to_reader()
+    }
+
+    /// Create a new writer handle. Every mutation is appended to the
+    /// write-ahead-log before applying it on the index.
+    pub fn to_writer(&mut self) -> Result<DurableWriter<K, V, <I as Index<K, V>>::W, H>>
+    where
+        <I as Index<K, V>>::W: WalWriter<K, V>,
+    {
+        Ok(DurableWriter {
+            seqlock: Arc::clone(&self.seqlock),
+            wal: self.wal.to_writer()?,
+            w: self.rdms.to_writer()?,
+        })
+    }
+
+    /// Commit entries from iterator into the index. If index is marked
+    /// as persisted, refer [Durable::set_persisted], once the commit is
+    /// successful, mutations upto the index's seqno, at the time of
+    /// this call, and within the upper bound of the scanner's seqno range,
+    /// are treated as durable and their write-ahead-log journals are
+    /// purged. Scanner is expected to cover all mutations below its upper
+    /// bound, that are not yet persisted by earlier commits.
+    pub fn commit<C, F>(&mut self, scanner: CommitIter<K, V, C>, metacb: F) -> Result<()>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        let seqno = {
+            let _guard = self.as_seqlock()?;
+            self.rdms.to_seqno()?
+        };
+        // purge only what the commit has persisted.
+        let till = match scanner.to_within().1 {
+            Bound::Included(till) => Some(cmp::min(seqno, till)),
+            Bound::Excluded(0) => None,
+            Bound::Excluded(till) => Some(cmp::min(seqno, till - 1)),
+            Bound::Unbounded => Some(seqno),
+        };
+        self.rdms.commit(scanner, metacb)?;
+        match till {
+            Some(till) if self.persisted => self.wal.purge_till(Bound::Included(till))?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    pub fn compact(&mut self, cutoff: Cutoff) -> Result<usize> {
+        self.rdms.compact(cutoff)
+    }
+
+    fn as_seqlock(&self) -> Result<MutexGuard<()>> {
+        match self.seqlock.lock() {
+            Ok(guard) => Ok(guard),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+impl<K, V, I, H, T> Validate<T> for Durable<K, V, I, H>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Serialize + Footprint + fmt::Debug,
+    V: 'static + Send + Clone + Default + Serialize + Diff + Footprint,
+    I: Index<K, V> + Validate<T>,
+    H: Clone + BuildHasher,
+    T: fmt::Display,
+{
+    fn validate(&mut self) -> Result<T> {
+        self.rdms.validate()
+    }
+}
+
+/// Writer handle into [Durable] index.
+pub struct DurableWriter<K, V, W, H>
+where
+    K: 'static + Send + Default + Hash + Serialize,
+    V: 'static + Send + Default + Serialize,
+    H: BuildHasher,
+{
+    seqlock: Arc<sync::Mutex<()>>,
+    wal: wal::Writer<K, V, H>,
+    w: W,
+}
+
+impl<K, V, W, H> DurableWriter<K, V, W, H>
+where
+    K: 'static + Send + Default + Hash + Serialize,
+    V: 'static + Send + Default + Serialize,
+    H: BuildHasher,
+{
+    fn as_seqlock(seqlock: &Arc<sync::Mutex<()>>) -> Result<MutexGuard<()>> {
+        match seqlock.lock() {
+            Ok(guard) => Ok(guard),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+impl<K, V, W, H> Writer<K, V> for DurableWriter<K, V, W, H>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Serialize,
+    V: 'static + Send + Clone + Default + Serialize + Diff,
+    W: WalWriter<K, V>,
+    H: BuildHasher,
+{
+    fn set(&mut self, key: K, value: V) -> Result<Option<Entry<K, V>>> {
+        let _guard = Self::as_seqlock(&self.seqlock)?;
+        let seqno = self.wal.set(key.clone(), value.clone())?;
+        self.w.set_index(key, value, seqno)
+    }
+
+    fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
+        let _guard = Self::as_seqlock(&self.seqlock)?;
+        let seqno = self.wal.set_cas(key.clone(), value.clone(), cas)?;
+        self.w.set_cas_index(key, value, cas, seqno)
+    }
+
+    fn delete<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let _guard = Self::as_seqlock(&self.seqlock)?;
+        let seqno = self.wal.delete(key)?;
+        self.w.delete_index(key, seqno)
+    }
+}
+
+#[cfg(test)]
+#[path = "rdms_test.rs"]
+mod rdms_test;
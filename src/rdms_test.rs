@@ -0,0 +1,223 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use std::{collections::hash_map::RandomState, convert, ffi, fs, path};
+
+use super::*;
+use crate::{
+    core::{Reader, Writer},
+    llrb::Llrb,
+};
+
+#[test]
+fn test_rdms_with_wal() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-rdms-with-wal");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let (nshards, journal_limit, batch_size) = (2, 10_000, 10);
+    let fsync: bool = rng.gen();
+    println!("seed:{} dir:{:?} fsync:{}", seed, dir, fsync);
+
+    let mut ref_index: Box<Llrb<i64, i64>> = Llrb::new_lsm("ref-rdms-with-wal");
+
+    let mut index = {
+        let dl = Dlog::<wal::State, wal::Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            fsync,
+        )
+        .unwrap();
+        let index: Box<Llrb<i64, i64>> = Llrb::new_lsm("rdms-with-wal");
+        Rdms::with_wal(&name, index, dl, RandomState::new()).unwrap()
+    };
+
+    {
+        let mut w = index.to_writer().unwrap();
+        for _ in 0..10_000 {
+            let key = (rng.gen::<u64>() % 1000) as i64;
+            match rng.gen::<u8>() % 3 {
+                0 | 1 => {
+                    let value: i64 = rng.gen();
+                    w.set(key, value).unwrap();
+                    ref_index.set(key, value).unwrap();
+                }
+                2 => {
+                    w.delete(&key).unwrap();
+                    ref_index.delete(&key).unwrap();
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+    assert_eq!(index.to_seqno().unwrap(), 10_000);
+    index.close().unwrap();
+
+    // reopen with an empty index, entries shall be replayed from wal.
+    let mut index = {
+        let dl = Dlog::<wal::State, wal::Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            fsync,
+        )
+        .unwrap();
+        let index: Box<Llrb<i64, i64>> = Llrb::new_lsm("rdms-with-wal");
+        Rdms::with_wal(&name, index, dl, RandomState::new()).unwrap()
+    };
+    assert_eq!(index.to_seqno().unwrap(), 10_000);
+
+    {
+        let mut r = index.to_reader().unwrap();
+        let mut iter = r.iter().unwrap();
+        for ref_entry in ref_index.iter().unwrap() {
+            let ref_entry = ref_entry.unwrap();
+            let entry = iter.next().unwrap().unwrap();
+            assert_eq!(entry.to_key(), ref_entry.to_key());
+            assert_eq!(entry.to_native_value(), ref_entry.to_native_value());
+            assert_eq!(entry.to_seqno(), ref_entry.to_seqno());
+            assert_eq!(entry.is_deleted(), ref_entry.is_deleted());
+        }
+        assert!(iter.next().is_none());
+    }
+
+    // new writes shall continue from the replayed seqno.
+    {
+        let mut w = index.to_writer().unwrap();
+        w.set(1001, 1001).unwrap();
+    }
+    assert_eq!(index.to_seqno().unwrap(), 10_001);
+
+    index.purge().unwrap();
+}
+
+#[test]
+fn test_rdms_commit_purge() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-rdms-commit-purge");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let (nshards, journal_limit, batch_size) = (2, 1_000, 10);
+    println!("seed:{} dir:{:?}", seed, dir);
+
+    let mut ref_index: Box<Llrb<i64, i64>> = Llrb::new_lsm("ref-rdms-commit-purge");
+    // shadow index, receiving the same set of mutations as the durable
+    // index, stands for an index that persisted its entries onto disk.
+    let mut disk_index: Box<Llrb<i64, i64>> = Llrb::new_lsm("rdms-commit-purge");
+
+    let mut index = {
+        let dl = Dlog::<wal::State, wal::Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false,
+        )
+        .unwrap();
+        let index: Box<Llrb<i64, i64>> = Llrb::new_lsm("rdms-commit-purge");
+        Rdms::with_wal(&name, index, dl, RandomState::new()).unwrap()
+    };
+
+    {
+        let mut w = index.to_writer().unwrap();
+        for _ in 0..1_000 {
+            let (key, value) = ((rng.gen::<u64>() % 100) as i64, rng.gen::<i64>());
+            w.set(key, value).unwrap();
+            ref_index.set(key, value).unwrap();
+            disk_index.set(key, value).unwrap();
+        }
+    }
+
+    // memory-only index, commit shall not purge the log.
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let (seqno, _) = index.wal.iter_from(0).unwrap().next().unwrap().unwrap();
+    assert_eq!(seqno, 1);
+
+    // persisted index, commit shall purge the log only upto the
+    // scanner's upper bound.
+    index.set_persisted(true);
+    let entries: Vec<Result<Entry<i64, i64>>> = vec![];
+    let within = (Bound::<u64>::Unbounded, Bound::<u64>::Excluded(501));
+    index
+        .commit(
+            CommitIter::new(entries.into_iter(), within),
+            convert::identity,
+        )
+        .unwrap();
+    let (seqno, _) = index.wal.iter_from(0).unwrap().next().unwrap().unwrap();
+    assert!(seqno <= 501, "{}", seqno);
+
+    // commit shall purge the log upto index's seqno.
+    index
+        .commit(CommitIter::new_empty(), convert::identity)
+        .unwrap();
+    let (seqno, _) = index.wal.iter_from(0).unwrap().next().unwrap().unwrap();
+    assert!(seqno > 1, "{}", seqno);
+
+    {
+        let mut w = index.to_writer().unwrap();
+        for _ in 0..100 {
+            let (key, value) = ((rng.gen::<u64>() % 100) as i64, rng.gen::<i64>());
+            w.set(key, value).unwrap();
+            ref_index.set(key, value).unwrap();
+        }
+    }
+    index.close().unwrap();
+
+    // reopen with the persisted index, rest shall be replayed from wal.
+    let mut index = {
+        let dl = Dlog::<wal::State, wal::Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false,
+        )
+        .unwrap();
+        Rdms::with_wal(&name, disk_index, dl, RandomState::new()).unwrap()
+    };
+    assert_eq!(index.to_seqno().unwrap(), 1_100);
+
+    {
+        let mut r = index.to_reader().unwrap();
+        let mut iter = r.iter().unwrap();
+        for ref_entry in ref_index.iter().unwrap() {
+            let ref_entry = ref_entry.unwrap();
+            let entry = iter.next().unwrap().unwrap();
+            assert_eq!(entry.to_key(), ref_entry.to_key());
+            assert_eq!(entry.to_native_value(), ref_entry.to_native_value());
+            assert_eq!(entry.to_seqno(), ref_entry.to_seqno());
+        }
+        assert!(iter.next().is_none());
+    }
+
+    index.purge().unwrap();
+}
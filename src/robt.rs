@@ -36,7 +36,21 @@
 //! Total length of `metadata-blocks` can be computed based on
 //! `marker-length`, `stats-length`, `app-metadata-length`, `bitmap-length`.
 //!
+//! Btree-blocks and value-log entries can optionally be compressed,
+//! refer to [Compression] for details.
+//!
+//! Btree-blocks and value-log entries carry a crc32 checksum, verified
+//! on every read. A mismatch is reported as `Error::Corrupted` along with
+//! the file and file-position. Use `Snapshot::verify()` to scan the whole
+//! snapshot for corrupted blocks.
+//!
+//! Decoded btree-blocks can be cached in a shared, size bounded,
+//! [BlockCache], refer to [Config::set_block_cache] and
+//! [Robt::set_block_cache] for details.
+//!
 //! [Config]: crate::robt::Config
+//! [Compression]: crate::robt::Compression
+//! [BlockCache]: crate::robt::BlockCache
 //!
 
 use fs2::FileExt;
@@ -61,16 +75,20 @@ use std::{
 #[allow(unused_imports)] // for documentation
 use crate::rdms::Rdms;
 use crate::{
-    core::Cutoff,
-    core::{self, Bloom, CommitIterator, Index, Serialize, ToJson, Validate},
+    core::{self, Bloom, CommitIterator, HasherId, Index, Serialize, ToJson, ToStats, Validate},
+    core::{CompactRange, Cutoff, IndexFiles},
     core::{Diff, DiskIndexFactory, Entry, Footprint, IndexIter, Reader, Result},
     error::Error,
+    hasher::Crc32,
     panic::Panic,
     robt_entry::MEntry,
-    robt_index::{MBlock, ZBlock},
+    robt_index::{self, MBlock, ZBlock},
     scans, thread as rt, util,
 };
 
+pub use crate::robt_cache::BlockCache;
+pub use crate::robt_limiter::RateLimiter;
+
 include!("robt_marker.rs");
 
 pub(crate) trait Flusher {
@@ -276,12 +294,23 @@ where
             "{}, open from {:?} ...", name, dir,
         );
 
-        Robt::open(dir, name)
+        let mut index = Robt::open(dir, name)?;
+        if let Some(cache) = &self.config.block_cache {
+            index.set_block_cache(cache.clone());
+        }
+        if let Some(limiter) = &self.config.rate_limiter {
+            index.set_rate_limiter(limiter.clone());
+        }
+        Ok(index)
     }
 
     fn to_type(&self) -> String {
         "robt".to_string()
     }
+
+    fn set_rate_limiter(&mut self, limiter: RateLimiter) {
+        self.config.rate_limiter = Some(limiter);
+    }
 }
 
 /// Index type, immutable, durable, fully-packed and lockless reads.
@@ -294,6 +323,8 @@ where
 {
     inner: sync::Mutex<InnerRobt<K, V, B>>,
     purger: Option<rt::Thread<ffi::OsString, (), ()>>,
+    block_cache: Option<BlockCache>,
+    rate_limiter: Option<RateLimiter>,
 }
 
 enum InnerRobt<K, V, B>
@@ -382,6 +413,8 @@ where
         Robt {
             inner: sync::Mutex::new(inner.clone()),
             purger: Some(purger),
+            block_cache: self.block_cache.clone(),
+            rate_limiter: self.rate_limiter.clone(),
         }
     }
 }
@@ -428,6 +461,8 @@ where
 {
     pub fn new(dir: &ffi::OsStr, name: &str, mut config: Config) -> Result<Robt<K, V, B>> {
         config.name = name.to_string();
+        let block_cache = config.block_cache.clone();
+        let rate_limiter = config.rate_limiter.clone();
 
         let inner = InnerRobt::Build {
             dir: dir.to_os_string(),
@@ -453,6 +488,8 @@ where
         Ok(Robt {
             inner: sync::Mutex::new(inner),
             purger: Some(purger),
+            block_cache,
+            rate_limiter,
         })
     }
 
@@ -487,6 +524,8 @@ where
         Ok(Robt {
             inner: sync::Mutex::new(inner),
             purger: Some(purger),
+            block_cache: None,
+            rate_limiter: None,
         })
     }
 
@@ -502,9 +541,24 @@ where
         Ok(Robt {
             inner: sync::Mutex::new(inner.deref().clone()),
             purger: Some(purger),
+            block_cache: self.block_cache.clone(),
+            rate_limiter: self.rate_limiter.clone(),
         })
     }
 
+    /// Cache decoded z-blocks and m-blocks, read by snapshots of this
+    /// index, in `cache`. Same cache can be shared by several indexes.
+    pub fn set_block_cache(&mut self, cache: BlockCache) {
+        self.block_cache = Some(cache);
+    }
+
+    /// Throttle disk writes, by subsequent commits and compactions on
+    /// this index, using `limiter`. Same limiter can be shared by several
+    /// indexes.
+    pub fn set_rate_limiter(&mut self, limiter: RateLimiter) {
+        self.rate_limiter = Some(limiter);
+    }
+
     pub fn purge_files(&self, files: Vec<ffi::OsString>) -> Result<()> {
         for file in files.into_iter() {
             // verify that requested files to be purged belong to this Robt
@@ -531,6 +585,7 @@ where
             };
 
             if nm == name && ver < version {
+                self.invalidate_cache(&file)?;
                 self.purger.as_ref().unwrap().post(file)?;
                 Ok(())
             } else {
@@ -639,6 +694,17 @@ where
         }
     }
 
+    // drop cached blocks of an index file that is about to be purged.
+    fn invalidate_cache(&self, file: &ffi::OsStr) -> Result<()> {
+        match &self.block_cache {
+            Some(cache) => {
+                cache.invalidate(file)?;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
     fn do_close(&mut self) -> Result<()> {
         match self.purger.take() {
             Some(purger) => purger.close_wait()?,
@@ -647,6 +713,221 @@ where
         Ok(())
     }
 
+    // return (n_count, n_purged) after compaction.
+    fn do_compact<F>(
+        &mut self,
+        range: (Bound<K>, Bound<K>),
+        cutoff: Cutoff,
+        mut filter: F,
+    ) -> Result<(usize, usize)>
+    where
+        K: Default + Hash + Footprint,
+        V: Default + Footprint,
+        <V as Diff>::D: Default,
+        F: FnMut(&Entry<K, V>) -> Result<bool>,
+    {
+        let mut inner = self.as_inner()?;
+        let (new_inner, (count, n_purged)) = match inner.deref() {
+            InnerRobt::Build {
+                dir, name, config, ..
+            } => {
+                error!(
+                    target: "robt  ",
+                    "{}, cannot compact in build state ...", name
+                );
+
+                (
+                    InnerRobt::Build {
+                        dir: dir.clone(),
+                        name: name.clone(),
+                        config: config.clone(),
+                        _phantom_key: marker::PhantomData,
+                        _phantom_val: marker::PhantomData,
+                    },
+                    (0, 0),
+                )
+            }
+            InnerRobt::Snapshot {
+                dir,
+                name,
+                config,
+                meta,
+                ..
+            } => {
+                let skip = match &range {
+                    (Bound::Unbounded, Bound::Unbounded) => {
+                        // skip compaction if cutoff is empty and the previous
+                        // build started from a clean vlog file.
+                        let old = Snapshot::<K, V, B>::open(dir, &name.0)?;
+                        let stats = old.to_stats()?;
+                        cutoff.is_empty() && stats.n_abytes == 0
+                    }
+                    range => {
+                        // skip compaction if nothing to purge within range.
+                        let mut old = Snapshot::<K, V, B>::open(dir, &name.0)?;
+                        let mut skip = true;
+                        for entry in old.range_with_versions(range.clone())? {
+                            let entry = entry?;
+                            if entry.is_purgeable(cutoff) && filter(&entry)? {
+                                skip = false;
+                                break;
+                            }
+                        }
+                        skip
+                    }
+                };
+                if skip {
+                    return Ok((0, 0));
+                }
+
+                let (name, snapshot, meta_block_bytes, n_purged) = {
+                    let mut old = Snapshot::<K, V, B>::open(dir, &name.0)?;
+                    let old_seqno: u64 = old.to_seqno()?;
+                    let meta = match &meta[2] {
+                        MetaItem::AppMetadata(data) => data.clone(),
+                        _ => err_at!(Fatal, msg: format!("unreachable"))?,
+                    };
+                    let (old_name, name) = (name.clone(), name.clone().next());
+
+                    let (meta_block_bytes, n_purged) = match &range {
+                        (Bound::Unbounded, Bound::Unbounded) => {
+                            let comp_iter = {
+                                let (iter, range) = (old.iter_with_versions()?, range.clone());
+                                scans::RangeCompactScan::new(iter, range, cutoff, &mut filter)
+                            };
+                            let conf = {
+                                let mut conf = config.clone();
+                                conf.vlog_file = None; // use a new vlog file.
+                                conf.rate_limiter = self.rate_limiter.clone();
+                                conf
+                            };
+                            let b = Builder::<K, V, B>::initial(dir, &name.0, conf)?;
+                            let res = Self::build_compact(b, comp_iter, old_seqno, meta)?;
+
+                            // purge old snapshot's value-log file.
+                            if let Some((file, _)) = &old.valog_fd {
+                                self.purger.as_ref().unwrap().post(file.clone())?;
+                            }
+                            res
+                        }
+                        range => {
+                            // rebuild only those partitions that overlap the
+                            // range, rest of the partitions are copied with
+                            // their values and deltas referring into the old
+                            // value-log file, which is appended to.
+                            let (low, high) = {
+                                let partitions = old.to_partitions()?;
+                                let mut iter = partitions
+                                    .into_iter()
+                                    .filter(|p| util::is_overlapping(p, range));
+                                match iter.next() {
+                                    Some((low, high)) => {
+                                        (low, iter.last().map(|(_, high)| high).unwrap_or(high))
+                                    }
+                                    None => return Ok((0, 0)),
+                                }
+                            };
+                            let iter = {
+                                let mut iters: Vec<IndexIter<K, V>> = vec![];
+                                if let Bound::Included(lk) = &low {
+                                    let before = (Bound::Unbounded, Bound::Excluded(lk.clone()));
+                                    let snap = Snapshot::<K, V, B>::open(dir, &old_name.0)?;
+                                    iters.push(Box::new(snap.into_shallow_scan(before)?));
+                                }
+                                let after = util::high_key_to_low_key(&high);
+                                let snap = Snapshot::<K, V, B>::open(dir, &old_name.0)?;
+                                iters.push(Box::new(snap.into_range_scan((low, high))?));
+                                if let Bound::Included(_) = &after {
+                                    let after = (after, Bound::Unbounded);
+                                    let snap = Snapshot::<K, V, B>::open(dir, &old_name.0)?;
+                                    iters.push(Box::new(snap.into_shallow_scan(after)?));
+                                }
+                                iters.into_iter().flatten()
+                            };
+                            let comp_iter = {
+                                let range = range.clone();
+                                scans::RangeCompactScan::new(iter, range, cutoff, &mut filter)
+                            };
+                            let conf = {
+                                let mut conf = config.clone();
+                                conf.rate_limiter = self.rate_limiter.clone();
+                                conf
+                            };
+                            let b = Builder::<K, V, B>::incremental(dir, &name.0, conf)?;
+                            Self::build_compact(b, comp_iter, old_seqno, meta)?
+                        }
+                    };
+                    let snapshot = Snapshot::<K, V, B>::open(dir, &name.0)?;
+
+                    // purge old snapshot's index file.
+                    self.invalidate_cache(old.index_fd.as_file())?;
+                    self.purger.as_ref().unwrap().post(old.index_fd.to_file())?;
+
+                    (name, snapshot, meta_block_bytes, n_purged)
+                };
+
+                let stats = snapshot.to_stats()?;
+                let footprint = snapshot.footprint()?;
+
+                let index_file = snapshot.index_fd.to_file();
+                let vlog_file = snapshot
+                    .valog_fd
+                    .as_ref()
+                    .map(|(vf, _)| vf.clone())
+                    .unwrap_or(ffi::OsString::new());
+                debug!(
+                    target: "robt  ",
+                    "{:?}/{}, compacted to index_file:{:?} vlog_file:{:?} footprint:{} wrote:{}",
+                    dir, name, index_file, vlog_file, footprint,
+                    stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes
+                );
+
+                (
+                    InnerRobt::Snapshot {
+                        dir: dir.clone(),
+                        name: name.clone(),
+                        footprint,
+                        meta: snapshot.meta.clone(),
+                        config: snapshot.config.clone(),
+                        stats: stats.clone(),
+                        bitmap: Arc::clone(&snapshot.bitmap),
+                    },
+                    (stats.n_count, n_purged),
+                )
+            }
+        };
+        *inner = new_inner;
+        Ok((convert_at!(count)?, n_purged))
+    }
+
+    // build compacted snapshot using builder `b`, return
+    // (meta_block_bytes, n_purged).
+    fn build_compact<I, F>(
+        mut b: Builder<K, V, B>,
+        comp_iter: scans::RangeCompactScan<K, V, I, F>,
+        old_seqno: u64,
+        meta: Vec<u8>,
+    ) -> Result<(usize, usize)>
+    where
+        K: Default,
+        V: Default,
+        <V as Diff>::D: Default,
+        I: Iterator<Item = Result<Entry<K, V>>>,
+        F: FnMut(&Entry<K, V>) -> Result<bool>,
+    {
+        let (root, bitmap, n_purged): (u64, B, usize) = {
+            let mut bditer = {
+                let btiter = scans::BitmappedScan::new(comp_iter);
+                BuildScan::new(btiter, old_seqno)
+            };
+            let root = b.build_tree(&mut bditer)?;
+            let btiter = bditer.update_stats(&mut b.stats)?;
+            let (comp_iter, bitmap) = btiter.close()?;
+            (root, bitmap, comp_iter.to_purged())
+        };
+        Ok((b.build_finish(meta, bitmap, root)?, n_purged))
+    }
+
     pub fn to_partitions(&mut self) -> Result<Vec<(Bound<K>, Bound<K>)>>
     where
         K: Default + Hash + Footprint,
@@ -697,6 +978,23 @@ where
     }
 }
 
+impl<K, V, B> ToStats<Stats> for Robt<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+    <V as Diff>::D: Serialize,
+    B: Bloom,
+{
+    /// Return statistics from the latest snapshot. If index is yet to be
+    /// built, return statistics with only _build-configuration_.
+    fn to_stats(&self) -> Result<Stats> {
+        match self.as_inner()?.deref() {
+            InnerRobt::Snapshot { stats, .. } => Ok(stats.clone()),
+            InnerRobt::Build { config, .. } => Ok(config.clone().into()),
+        }
+    }
+}
+
 impl<K, V, B> Index<K, V> for Robt<K, V, B>
 where
     K: Default + Clone + Ord + Hash + Footprint + Serialize,
@@ -750,6 +1048,9 @@ where
                 //println!("Robt.to_reader() {:?} {}", dir, name);
                 let mut snapshot = Snapshot::open(dir, &name.0)?;
                 snapshot.set_bitmap(Arc::clone(bitmap));
+                if let Some(cache) = &self.block_cache {
+                    snapshot.set_block_cache(cache.clone());
+                }
                 Ok(snapshot)
             }
             InnerRobt::Build { .. } => err_at!(UnInitialized, msg: format!("Robt.to_reader()")),
@@ -771,7 +1072,8 @@ where
                 dir, name, config, ..
             } => {
                 let (snapshot, meta_block_bytes) = {
-                    let config = config.clone();
+                    let mut config = config.clone();
+                    config.rate_limiter = self.rate_limiter.clone();
                     let b = Builder::<K, V, B>::initial(dir, &name.0, config)?;
                     let meta_block_bytes = b.build(scanner.scan()?, metacb(vec![]))?;
 
@@ -828,11 +1130,11 @@ where
 
                     let (name, mut b) = {
                         let name = name.clone().next();
+                        let mut config = config.clone();
+                        config.rate_limiter = self.rate_limiter.clone();
                         let b = Builder::<K, V, B>::incremental(
                             //
-                            dir,
-                            &name.0,
-                            config.clone(),
+                            dir, &name.0, config,
                         )?;
                         (name, b)
                     };
@@ -859,6 +1161,7 @@ where
                     let snapshot = Snapshot::<K, V, B>::open(dir, &name.0)?;
 
                     // purge old snapshot's index file.
+                    self.invalidate_cache(old.index_fd.as_file())?;
                     self.purger.as_ref().unwrap().post(old.index_fd.to_file())?;
 
                     (name, snapshot, meta_block_bytes)
@@ -896,123 +1199,9 @@ where
     }
 
     fn compact(&mut self, cutoff: Cutoff) -> Result<usize> {
-        let mut inner = self.as_inner()?;
-        let (new_inner, count) = match inner.deref() {
-            InnerRobt::Build {
-                dir, name, config, ..
-            } => {
-                error!(
-                    target: "robt  ",
-                    "{}, cannot compact in build state ...", name
-                );
-
-                (
-                    InnerRobt::Build {
-                        dir: dir.clone(),
-                        name: name.clone(),
-                        config: config.clone(),
-                        _phantom_key: marker::PhantomData,
-                        _phantom_val: marker::PhantomData,
-                    },
-                    0,
-                )
-            }
-            InnerRobt::Snapshot {
-                dir,
-                name,
-                config,
-                meta,
-                ..
-            } => {
-                {
-                    // skip compaction if cutoff is empty and the previous
-                    // build started from a clean vlog file.
-                    let old = Snapshot::<K, V, B>::open(dir, &name.0)?;
-                    let stats = old.to_stats()?;
-                    if cutoff.is_empty() && stats.n_abytes == 0 {
-                        return Ok(0);
-                    }
-                }
-
-                let (name, snapshot, meta_block_bytes) = {
-                    let mut old = Snapshot::<K, V, B>::open(dir, &name.0)?;
-                    let old_seqno: u64 = old.to_seqno()?;
-
-                    let comp_iter = {
-                        let iter = old.iter_with_versions()?;
-                        scans::CompactScan::new(iter, cutoff)
-                    };
-
-                    let name = name.clone().next();
-                    let (meta_block_bytes, snapshot) = {
-                        let conf = {
-                            let mut conf = config.clone();
-                            conf.vlog_file = None; // use a new vlog file.
-                            conf
-                        };
-                        let meta = match &meta[2] {
-                            MetaItem::AppMetadata(data) => data.clone(),
-                            _ => err_at!(Fatal, msg: format!("unreachable"))?,
-                        };
-                        let mut b = Builder::<K, V, B>::initial(dir, &name.0, conf)?;
-                        // let mbbytes = b.build(comp_iter, meta)?;
-
-                        let (root, bitmap): (u64, B) = {
-                            let mut bditer = {
-                                let btiter = scans::BitmappedScan::new(comp_iter);
-                                BuildScan::new(btiter, old_seqno)
-                            };
-                            let root = b.build_tree(&mut bditer)?;
-                            let btiter = bditer.update_stats(&mut b.stats)?;
-                            let (_, bitmap) = btiter.close()?;
-                            (root, bitmap)
-                        };
-                        let mbbytes = b.build_finish(meta, bitmap, root)?;
-
-                        (mbbytes, Snapshot::<K, V, B>::open(dir, &name.0)?)
-                    };
-
-                    // purge old snapshots file(s).
-                    self.purger.as_ref().unwrap().post(old.index_fd.to_file())?;
-                    if let Some((file, _)) = &old.valog_fd {
-                        self.purger.as_ref().unwrap().post(file.clone())?;
-                    }
-
-                    (name, snapshot, meta_block_bytes)
-                };
-
-                let stats = snapshot.to_stats()?;
-                let footprint = snapshot.footprint()?;
-
-                let index_file = snapshot.index_fd.to_file();
-                let vlog_file = snapshot
-                    .valog_fd
-                    .as_ref()
-                    .map(|(vf, _)| vf.clone())
-                    .unwrap_or(ffi::OsString::new());
-                debug!(
-                    target: "robt  ",
-                    "{:?}/{}, compacted to index_file:{:?} vlog_file:{:?} footprint:{} wrote:{}",
-                    dir, name, index_file, vlog_file, footprint,
-                    stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes
-                );
-
-                (
-                    InnerRobt::Snapshot {
-                        dir: dir.clone(),
-                        name: name.clone(),
-                        footprint,
-                        meta: snapshot.meta.clone(),
-                        config: snapshot.config.clone(),
-                        stats: stats.clone(),
-                        bitmap: Arc::clone(&snapshot.bitmap),
-                    },
-                    stats.n_count,
-                )
-            }
-        };
-        *inner = new_inner;
-        Ok(convert_at!(count)?)
+        let range = (Bound::Unbounded, Bound::Unbounded);
+        let (count, _) = self.do_compact(range, cutoff, |_| Ok(true))?;
+        Ok(count)
     }
 
     fn close(mut self) -> Result<()> {
@@ -1032,7 +1221,10 @@ where
         self.do_close()?;
         let (res, dir, name) = match self.as_inner()?.deref() {
             InnerRobt::Snapshot { dir, name, .. } => {
-                let snapshot = Snapshot::<K, V, B>::open(&dir, &name.0)?;
+                let mut snapshot = Snapshot::<K, V, B>::open(&dir, &name.0)?;
+                if let Some(cache) = &self.block_cache {
+                    snapshot.set_block_cache(cache.clone());
+                }
                 (snapshot.purge(), dir.clone(), name.clone())
             }
             InnerRobt::Build { dir, name, .. } => {
@@ -1047,6 +1239,56 @@ where
     }
 }
 
+impl<K, V, B> CompactRange<K, V> for Robt<K, V, B>
+where
+    K: Default + Clone + Ord + Hash + Footprint + Serialize,
+    V: Default + Clone + Diff + Footprint + Serialize,
+    <V as Diff>::D: Default + Serialize,
+    B: Bloom,
+{
+    fn compact_range<R, F>(&mut self, range: R, cutoff: Cutoff, filter: F) -> Result<usize>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&Entry<K, V>) -> Result<bool>,
+    {
+        let range = util::to_owned_range(&range);
+        let (_, n_purged) = self.do_compact(range, cutoff, filter)?;
+        Ok(n_purged)
+    }
+}
+
+impl<K, V, B> IndexFiles for Robt<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+    <V as Diff>::D: Serialize,
+    B: Bloom,
+{
+    fn to_files(&self) -> Result<Vec<ffi::OsString>> {
+        let inner = self.as_inner()?;
+        match inner.deref() {
+            InnerRobt::Build { .. } => Ok(vec![]),
+            InnerRobt::Snapshot {
+                dir, name, config, ..
+            } => {
+                let mut files = vec![Config::stitch_index_file(dir, &name.0)];
+                // value log is always located in index directory.
+                if let Some(vfile) = &config.vlog_file {
+                    match path::Path::new(vfile).file_name() {
+                        Some(vfile) => {
+                            let mut vpath = path::PathBuf::from(dir);
+                            vpath.push(vfile);
+                            files.push(vpath.into_os_string());
+                        }
+                        None => err_at!(InvalidFile, msg: format!("{:?}", vfile))?,
+                    }
+                }
+                Ok(files)
+            }
+        }
+    }
+}
+
 impl<K, V, B> CommitIterator<K, V> for Robt<K, V, B>
 where
     K: Default + Clone + Ord + Serialize,
@@ -1149,6 +1391,78 @@ where
     }
 }
 
+/// Compression codec, for [Robt] index blocks and value-log entries.
+///
+/// With compression enabled, z-blocks and m-blocks are individually
+/// compressed and written without padding, each compressed block is
+/// prefixed with a 4-byte header carrying its on-disk length. Values and
+/// deltas persisted in value-log file are compressed per entry, so that
+/// they can still be fetched by their file-position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// Blocks and value-log entries are stored as is.
+    None,
+    /// Fast LZ compression using [snappy](https://github.com/google/snappy).
+    Snappy,
+}
+
+impl Default for Compression {
+    fn default() -> Compression {
+        Compression::None
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Snappy => write!(f, "snappy"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Compression> {
+        match s {
+            "none" => Ok(Compression::None),
+            "snappy" => Ok(Compression::Snappy),
+            _ => err_at!(InvalidInput, msg: format!("compression {}", s)),
+        }
+    }
+}
+
+impl Compression {
+    pub(crate) fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Snappy => {
+                let mut enc = snap::raw::Encoder::new();
+                err_at!(Fatal, enc.compress_vec(data))
+            }
+        }
+    }
+
+    pub(crate) fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Snappy => {
+                let mut dec = snap::raw::Decoder::new();
+                err_at!(DecodeFail, dec.decompress_vec(data))
+            }
+        }
+    }
+
+    // return the uncompressed length of compressed `data`.
+    pub(crate) fn decompress_len(&self, data: &[u8]) -> Result<usize> {
+        match self {
+            Compression::None => Ok(data.len()),
+            Compression::Snappy => err_at!(DecodeFail, snap::raw::decompress_len(data)),
+        }
+    }
+}
+
 /// Configuration type, for Read Only BTree.
 #[derive(Clone)]
 pub struct Config {
@@ -1178,6 +1492,18 @@ pub struct Config {
     pub(crate) value_in_vlog: bool,
     /// Flush queue size. Default: Config::FLUSH_QUEUE_SIZE
     pub(crate) flush_queue_size: usize,
+    /// Compression codec for z-blocks, m-blocks and value-log entries.
+    /// Default: Compression::None
+    pub(crate) compression: Compression,
+    /// Add crc32 checksum to z-blocks and m-blocks, and verify them while
+    /// reading. Value-log entries always carry a checksum. Default: true
+    pub(crate) checksum: bool,
+    /// Shared cache for decoded z-blocks and m-blocks, not persisted
+    /// along with the index. Default: None
+    pub(crate) block_cache: Option<BlockCache>,
+    /// Shared limiter throttling bytes flushed to disk, not persisted
+    /// along with the index. Default: None
+    pub(crate) rate_limiter: Option<RateLimiter>,
 }
 
 impl Default for Config {
@@ -1188,6 +1514,8 @@ impl Default for Config {
     /// * LSM entries are preserved.
     /// * Deltas are persisted in default value-log-file.
     /// * Main index is persisted in default index-file.
+    /// * Blocks are not compressed.
+    /// * Blocks are check-summed.
     fn default() -> Config {
         let dir: &ffi::OsStr = "not/a/path".as_ref();
         Config {
@@ -1200,6 +1528,10 @@ impl Default for Config {
             vlog_file: Default::default(),
             value_in_vlog: false,
             flush_queue_size: Self::FLUSH_QUEUE_SIZE,
+            compression: Compression::None,
+            checksum: true,
+            block_cache: None,
+            rate_limiter: None,
         }
     }
 }
@@ -1258,6 +1590,32 @@ impl Config {
         self.flush_queue_size = size;
         Ok(self)
     }
+
+    /// Compress z-blocks, m-blocks and value-log entries using the
+    /// supplied codec. Compressed blocks are not padded to their block
+    /// size, hence can't be updated in place.
+    pub fn set_compression(&mut self, compression: Compression) -> Result<&mut Self> {
+        self.compression = compression;
+        Ok(self)
+    }
+
+    /// Cache decoded z-blocks and m-blocks in `cache`. Same cache can be
+    /// shared by several indexes. Applicable to indexes created using
+    /// this configuration, for indexes opened from disk use
+    /// [Robt::set_block_cache].
+    pub fn set_block_cache(&mut self, cache: BlockCache) -> Result<&mut Self> {
+        self.block_cache = Some(cache);
+        Ok(self)
+    }
+
+    /// Throttle bytes flushed to disk, while building the index, using
+    /// `limiter`. Same limiter can be shared by several indexes.
+    /// Applicable to indexes created using this configuration, for
+    /// indexes opened from disk use [Robt::set_rate_limiter].
+    pub fn set_rate_limiter(&mut self, limiter: RateLimiter) -> Result<&mut Self> {
+        self.rate_limiter = Some(limiter);
+        Ok(self)
+    }
 }
 
 impl fmt::Display for Config {
@@ -1269,7 +1627,7 @@ impl fmt::Display for Config {
 
         let (z, m, v) = (self.z_blocksize, self.m_blocksize, self.v_blocksize);
         let dok = self.delta_ok;
-        let fqs = self.flush_queue_size;
+        let (fqs, cmp) = (self.flush_queue_size, self.compression);
 
         write!(
             f,
@@ -1277,9 +1635,9 @@ impl fmt::Display for Config {
                 "robt.name = {}\n",
                 "robt.config.blocksize = {{ z={}, m={}, v={} }}\n",
                 "robt.config = {{ delta_ok={}, value_in_vlog={} vlog_file={} }}\n",
-                "robt.config = {{ flush_queue_size={} }}",
+                "robt.config = {{ flush_queue_size={}, compression={}, checksum={} }}",
             ),
-            self.name, z, m, v, dok, self.value_in_vlog, vlog_file, fqs,
+            self.name, z, m, v, dok, self.value_in_vlog, vlog_file, fqs, cmp, self.checksum,
         )
     }
 }
@@ -1297,6 +1655,8 @@ impl ToJson for Config {
                 self.vlog_file.as_ref().map_or(null, |f| f.clone()),
             ),
             format!(r#""flush_queue_size": {}"#, self.flush_queue_size,),
+            format!(r#""compression": "{}""#, self.compression),
+            format!(r#""checksum": {}"#, self.checksum),
         ];
         format!(
             r#"{{ "robt": {{ "name": "{}", "config": {{ {} }} }}"#,
@@ -1319,6 +1679,10 @@ impl From<Stats> for Config {
             vlog_file: stats.vlog_file,
             value_in_vlog: stats.value_in_vlog,
             flush_queue_size: stats.flush_queue_size,
+            compression: stats.compression,
+            checksum: stats.checksum,
+            block_cache: None,
+            rate_limiter: None,
         }
     }
 }
@@ -1510,10 +1874,16 @@ pub fn read_meta_items(
         Ok((meta_items, convert_at!(meta_block_bytes)?))
     } else {
         let at: u64 = convert_at!(stats.m_blocksize)?;
-        let at = m - meta_block_bytes - at;
-        if at == root {
+        let ok = match stats.compression {
+            Compression::None => (m - meta_block_bytes - at) == root,
+            // root block is not padded, and can be stored uncompressed
+            // with its 4-byte header and optional 4-byte checksum.
+            _ => root < (m - meta_block_bytes) && (m - meta_block_bytes - root) <= (at + 8),
+        };
+        if ok {
             Ok((meta_items, convert_at!(meta_block_bytes)?))
         } else {
+            let at = m - meta_block_bytes - at;
             err_at!(InvalidFile, msg: format!("root:{}, found:{}", at, root))
         }
     }
@@ -1565,6 +1935,12 @@ pub struct Stats {
     pub value_in_vlog: bool,
     /// Flush queue size. Default: Config::FLUSH_QUEUE_SIZE
     pub flush_queue_size: usize,
+    /// Part of _build-configuration_, specifies the codec used to
+    /// compress blocks and value-log entries.
+    pub compression: Compression,
+    /// Part of _build-configuration_, specifies whether z-blocks and
+    /// m-blocks carry a checksum.
+    pub checksum: bool,
 
     /// Number of entries indexed.
     pub n_count: u64,
@@ -1584,6 +1960,15 @@ pub struct Stats {
     pub m_bytes: usize,
     /// Total disk footprint for values and deltas.
     pub v_bytes: usize,
+    /// Uncompressed size of all leaf-nodes, same as `z_bytes` when
+    /// compression is disabled.
+    pub z_raw_bytes: usize,
+    /// Uncompressed size of all intermediate-nodes, same as `m_bytes`
+    /// when compression is disabled.
+    pub m_raw_bytes: usize,
+    /// Uncompressed size of values and deltas, same as `v_bytes` when
+    /// compression is disabled.
+    pub v_raw_bytes: usize,
     /// Total disk size wasted in padding leaf-nodes and intermediate-nodes.
     pub padding: usize,
     /// Older size of value-log file, applicable only in compact build.
@@ -1592,6 +1977,17 @@ pub struct Stats {
     pub mem_bitmap: usize,
     /// Number of entries in bitmap.
     pub n_bitmap: usize,
+    /// Identity of hasher used to compute key digests for bitmap, refer
+    /// to [HasherId]. Empty if bitmap does not hash its keys.
+    pub hasher: String,
+    /// Number of block lookups served from block cache. Counters are
+    /// maintained by the [BlockCache], and shared by all indexes using
+    /// the same cache.
+    pub n_cache_hits: usize,
+    /// Number of block lookups missed in block cache.
+    pub n_cache_misses: usize,
+    /// Number of blocks evicted from block cache.
+    pub n_cache_evictions: usize,
 
     /// Time take to build this btree.
     pub build_time: u64,
@@ -1610,6 +2006,8 @@ impl Stats {
             vlog_file: None,
             value_in_vlog: other.value_in_vlog,
             flush_queue_size: other.flush_queue_size,
+            compression: other.compression,
+            checksum: other.checksum,
 
             n_count: self.n_count + other.n_count,
             n_deleted: self.n_deleted + other.n_deleted,
@@ -1620,10 +2018,18 @@ impl Stats {
             z_bytes: self.z_bytes + other.z_bytes,
             m_bytes: self.m_bytes + other.m_bytes,
             v_bytes: self.v_bytes + other.v_bytes,
+            z_raw_bytes: self.z_raw_bytes + other.z_raw_bytes,
+            m_raw_bytes: self.m_raw_bytes + other.m_raw_bytes,
+            v_raw_bytes: self.v_raw_bytes + other.v_raw_bytes,
             padding: self.padding + other.padding,
             n_abytes: self.n_abytes + other.n_abytes,
             mem_bitmap: self.mem_bitmap + other.mem_bitmap,
             n_bitmap: self.n_bitmap + other.n_bitmap,
+            hasher: other.hasher.clone(),
+            // block cache is typically shared across shards and levels.
+            n_cache_hits: cmp::max(self.n_cache_hits, other.n_cache_hits),
+            n_cache_misses: cmp::max(self.n_cache_misses, other.n_cache_misses),
+            n_cache_evictions: cmp::max(self.n_cache_evictions, other.n_cache_evictions),
 
             build_time: other.build_time,
             epoch: other.epoch,
@@ -1651,8 +2057,19 @@ impl fmt::Display for Stats {
         )?;
         write!(
             f,
-            "robt.stats = {{ mem_bitmap={}, n_bitmap={}, }}\n",
-            self.mem_bitmap, self.n_bitmap,
+            "robt.stats = {{ compression={}, z_raw_bytes={}, m_raw_bytes={}, v_raw_bytes={} }}\n",
+            self.compression, self.z_raw_bytes, self.m_raw_bytes, self.v_raw_bytes,
+        )?;
+        write!(f, "robt.stats = {{ checksum={} }}\n", self.checksum)?;
+        write!(
+            f,
+            "robt.stats = {{ mem_bitmap={}, n_bitmap={}, hasher={:?} }}\n",
+            self.mem_bitmap, self.n_bitmap, self.hasher,
+        )?;
+        write!(
+            f,
+            "robt.stats = {{ n_cache_hits={}, n_cache_misses={}, n_cache_evictions={} }}\n",
+            self.n_cache_hits, self.n_cache_misses, self.n_cache_evictions,
         )?;
         let bt = time::Duration::from_nanos(self.build_time);
         write!(
@@ -1678,6 +2095,8 @@ impl ToJson for Stats {
             format!(r#""vlog_file": {}"#, vlog_file),
             format!(r#""value_in_vlog": {}"#, self.value_in_vlog),
             format!(r#""flush_queue_size": {}"#, self.flush_queue_size),
+            format!(r#""compression": "{}""#, self.compression),
+            format!(r#""checksum": {}"#, self.checksum),
             format!(r#""seqno": {}"#, self.seqno),
             format!(r#""n_count": {}"#, self.n_count),
             format!(r#""n_deleted": {}"#, self.n_deleted),
@@ -1687,8 +2106,15 @@ impl ToJson for Stats {
             format!(r#""z_bytes": {}"#, self.z_bytes),
             format!(r#""m_bytes": {}"#, self.m_bytes),
             format!(r#""v_bytes": {}"#, self.v_bytes),
+            format!(r#""z_raw_bytes": {}"#, self.z_raw_bytes),
+            format!(r#""m_raw_bytes": {}"#, self.m_raw_bytes),
+            format!(r#""v_raw_bytes": {}"#, self.v_raw_bytes),
             format!(r#""mem_bitmap": {}"#, self.mem_bitmap),
             format!(r#""n_bitmap": {}"#, self.n_bitmap),
+            format!(r#""hasher": "{}""#, self.hasher),
+            format!(r#""n_cache_hits": {}"#, self.n_cache_hits),
+            format!(r#""n_cache_misses": {}"#, self.n_cache_misses),
+            format!(r#""n_cache_evictions": {}"#, self.n_cache_evictions),
             format!(r#""padding": {}"#, self.padding),
             format!(r#""n_abytes": {}"#, self.n_abytes),
             format!(r#""build_time": {}"#, self.build_time),
@@ -1709,6 +2135,8 @@ impl From<Config> for Stats {
             vlog_file: config.vlog_file,
             value_in_vlog: config.value_in_vlog,
             flush_queue_size: config.flush_queue_size,
+            compression: config.compression,
+            checksum: config.checksum,
 
             n_count: Default::default(),
             n_deleted: Default::default(),
@@ -1719,8 +2147,15 @@ impl From<Config> for Stats {
             z_bytes: Default::default(),
             v_bytes: Default::default(),
             m_bytes: Default::default(),
+            z_raw_bytes: Default::default(),
+            m_raw_bytes: Default::default(),
+            v_raw_bytes: Default::default(),
             mem_bitmap: Default::default(),
             n_bitmap: Default::default(),
+            hasher: Default::default(),
+            n_cache_hits: Default::default(),
+            n_cache_misses: Default::default(),
+            n_cache_evictions: Default::default(),
             padding: Default::default(),
             n_abytes: Default::default(),
 
@@ -1768,6 +2203,26 @@ impl FromStr for Stats {
                 None => err_at!(InvalidInput, msg: format!("key:{}", key)),
             }
         };
+        // older index files don't carry compression details.
+        let to_usize_or = |key: &str, dflt: usize| -> Result<usize> {
+            match js.get(key) {
+                Ok(_) => to_usize(key),
+                Err(_) => Ok(dflt),
+            }
+        };
+        let compression = match js.get("/compression") {
+            Ok(_) => to_string("/compression")?.parse()?,
+            Err(_) => Compression::None,
+        };
+        let checksum = match js.get("/checksum") {
+            Ok(_) => to_bool("/checksum")?,
+            Err(_) => false,
+        };
+        // older index files were built using crc32 hasher.
+        let hasher = match js.get("/hasher") {
+            Ok(_) => to_string("/hasher")?,
+            Err(_) => Crc32::to_hasher_id(),
+        };
         let vlog_file = {
             match err_at!(InvalidInput, js.get("/vlog_file"))?.as_str() {
                 Some(s) if s.len() == 0 => None,
@@ -1789,6 +2244,8 @@ impl FromStr for Stats {
             vlog_file: vlog_file,
             value_in_vlog: to_bool("/value_in_vlog")?,
             flush_queue_size: to_usize("/flush_queue_size")?,
+            compression,
+            checksum,
             // statitics fields.
             n_count: to_u64("/n_count")?,
             n_deleted: to_usize("/n_deleted")?,
@@ -1799,8 +2256,15 @@ impl FromStr for Stats {
             z_bytes: to_usize("/z_bytes")?,
             v_bytes: to_usize("/v_bytes")?,
             m_bytes: to_usize("/m_bytes")?,
+            z_raw_bytes: to_usize_or("/z_raw_bytes", to_usize("/z_bytes")?)?,
+            m_raw_bytes: to_usize_or("/m_raw_bytes", to_usize("/m_bytes")?)?,
+            v_raw_bytes: to_usize_or("/v_raw_bytes", to_usize("/v_bytes")?)?,
             mem_bitmap: to_usize("/mem_bitmap")?,
             n_bitmap: to_usize("/n_bitmap")?,
+            hasher,
+            n_cache_hits: to_usize_or("/n_cache_hits", 0)?,
+            n_cache_misses: to_usize_or("/n_cache_misses", 0)?,
+            n_cache_evictions: to_usize_or("/n_cache_evictions", 0)?,
             padding: to_usize("/padding")?,
             n_abytes: to_usize("/n_abytes")?,
 
@@ -1849,9 +2313,10 @@ where
 
         let iflusher = {
             let ifile = Config::stitch_index_file(dir, name);
+            let limiter = config.rate_limiter.clone();
             rt::Thread::new_sync(
                 format!("robt-index-flusher-{}", name),
-                move |rx| move || thread_flush(ifile, create, rx),
+                move |rx| move || thread_flush(ifile, create, limiter, rx),
                 config.flush_queue_size,
             )
         };
@@ -1866,9 +2331,10 @@ where
         let vflusher = match &config.vlog_file {
             Some(vfile) => {
                 let vfile = vfile.clone();
+                let limiter = config.rate_limiter.clone();
                 Some(rt::Thread::new_sync(
                     format!("robt-vlog-flusher-{}", name),
-                    move |rx| move || thread_flush(vfile, create, rx),
+                    move |rx| move || thread_flush(vfile, create, limiter, rx),
                     config.flush_queue_size,
                 ))
             }
@@ -1896,9 +2362,10 @@ where
     ) -> Result<Builder<K, V, B>> {
         let iflusher = {
             let ifile = Config::stitch_index_file(dir, name);
+            let limiter = config.rate_limiter.clone();
             rt::Thread::new_sync(
                 format!("robt-index-flusher-{}", name),
-                move |rx| move || thread_flush(ifile, true /*create*/, rx),
+                move |rx| move || thread_flush(ifile, true /*create*/, limiter, rx),
                 config.flush_queue_size,
             )
         };
@@ -1917,9 +2384,10 @@ where
                 let vfile = vfile.clone();
                 let vf_fpos = err_at!(IoError, fs::metadata(&vfile))?.len();
 
+                let limiter = config.rate_limiter.clone();
                 let t = rt::Thread::new_sync(
                     format!("robt-vlog-flusher-{}", name),
-                    move |rx| move || thread_flush(vfile, create, rx),
+                    move |rx| move || thread_flush(vfile, create, limiter, rx),
                     config.flush_queue_size,
                 );
 
@@ -1984,10 +2452,11 @@ where
     /// Completes the build process, refer to
     /// [build_start][Builder::build_start] for details.
     pub fn build_finish(mut self, app_meta: Vec<u8>, bitmap: B, root: u64) -> Result<usize> {
-        let (n_bitmap, bitmap) = (bitmap.len()?, bitmap.to_vec());
+        let (n_bitmap, bitmap) = (bitmap.len()?, bitmap.to_vec()?);
         let stats: String = {
             self.stats.n_bitmap = n_bitmap;
             self.stats.mem_bitmap = bitmap.len();
+            self.stats.hasher = <B as Bloom>::to_hasher_id();
             self.stats.to_json()
         };
 
@@ -2119,11 +2588,13 @@ where
         }
 
         // flush final set of m-blocks
+        let mut root = None;
         while let Some(mut m) = c.ms.pop() {
             let is_root = m.has_first_key()? && c.ms.len() == 0;
             if is_root {
                 let x = m.finalize(&mut self.stats)?;
                 m.flush(self.iflusher.as_ref())?;
+                root = Some(c.fpos);
                 c.fpos += x;
             } else if m.has_first_key()? {
                 // x is m_blocksize
@@ -2135,8 +2606,10 @@ where
                 c.fpos = res.1
             }
         }
-        let n: u64 = convert_at!(self.config.m_blocksize)?;
-        Ok(c.fpos - n)
+        match root {
+            Some(root) => Ok(root),
+            None => err_at!(Fatal, msg: format!("missing root block")),
+        }
     }
 
     fn insertms(
@@ -2343,6 +2816,7 @@ where
 fn thread_flush(
     file: ffi::OsString, // for debuging purpose
     create: bool,        // if true create a new file
+    limiter: Option<RateLimiter>,
     rx: rt::Rx<Vec<u8>, ()>,
 ) -> Result<(ffi::OsString, u64)> {
     let (mut fd, fpos) = if create {
@@ -2359,6 +2833,9 @@ fn thread_flush(
     for (data, _) in rx {
         // println!("flusher {:?} {} {}", file, fpos, data.len());
         // fpos += data.len();
+        if let Some(limiter) = &limiter {
+            limiter.acquire(data.len())?;
+        }
         let n = write_file!(fd, &data, file.clone(), "robt-thread-flush")?;
         if n != data.len() {
             err_at!(IoError, fd.unlock())?; // <----- read un-lock
@@ -2436,10 +2913,75 @@ impl IndexFile {
         })
     }
 
+    // read z-block or m-block at `fpos`, from block cache if configured,
+    // otherwise from disk.
+    fn read_block(
+        &mut self,
+        fpos: u64,
+        n: usize,
+        config: &Config,
+        msg: &str,
+    ) -> Result<Arc<Vec<u8>>> {
+        match &config.block_cache {
+            Some(cache) => {
+                if let Some(block) = cache.get(self.as_file(), fpos)? {
+                    return Ok(block);
+                }
+                let block = Arc::new(self.read_disk_block(fpos, n, config, msg)?);
+                cache.insert(self.as_file(), fpos, Arc::clone(&block))?;
+                Ok(block)
+            }
+            None => Ok(Arc::new(self.read_disk_block(fpos, n, config, msg)?)),
+        }
+    }
+
+    // read z-block or m-block at `fpos`, for compressed index read the
+    // block header first to learn the on-disk length of the block. If
+    // configured, verify block checksum.
+    fn read_disk_block(
+        &mut self,
+        fpos: u64,
+        n: usize,
+        config: &Config,
+        msg: &str,
+    ) -> Result<Vec<u8>> {
+        let c = if config.checksum {
+            robt_index::CHECKSUM_SIZE
+        } else {
+            0
+        };
+        match config.compression {
+            Compression::None => {
+                let block = self.read_buffer(fpos, n, msg)?;
+                if c > 0 && !robt_index::verify_checksum(&[], &block)? {
+                    return Err(Error::Corrupted(self.to_file(), fpos));
+                }
+                Ok(block)
+            }
+            compression => {
+                let hdr = self.read_buffer(fpos, 4, msg)?;
+                let m = robt_index::to_payload_len(&hdr)?;
+                if m > n {
+                    return Err(Error::Corrupted(self.to_file(), fpos));
+                }
+                let mut payload = self.read_buffer(fpos + 4, m + c, msg)?;
+                if c > 0 && !robt_index::verify_checksum(&hdr, &payload)? {
+                    return Err(Error::Corrupted(self.to_file(), fpos));
+                }
+                payload.truncate(m);
+                robt_index::decompress_block(&hdr, payload, compression)
+            }
+        }
+    }
+
     fn to_file(&self) -> ffi::OsString {
+        self.as_file().to_os_string()
+    }
+
+    fn as_file(&self) -> &ffi::OsStr {
         match self {
-            IndexFile::Block { file, .. } => file.clone(),
-            IndexFile::Mmap { file, .. } => file.clone(),
+            IndexFile::Block { file, .. } => file,
+            IndexFile::Mmap { file, .. } => file,
         }
     }
 
@@ -2528,6 +3070,15 @@ where
         } else {
             err_at!(InvalidFile, msg: format!("{:?}/{}", dir, name))
         }?;
+        // bitmap can be probed only with the hasher it was built with.
+        let hasher = <B as Bloom>::to_hasher_id();
+        if hasher.len() > 0 && hasher != stats.hasher {
+            let msg = format!(
+                "{:?}/{} hasher {:?} != {:?}",
+                dir, name, stats.hasher, hasher
+            );
+            err_at!(InvalidInput, msg: msg)?
+        }
         let bitmap: Arc<B> = if let MetaItem::Bitmap(data) = &mut meta_items[1] {
             let bitmap = <B as Bloom>::from_vec(&data)?;
             data.drain(..);
@@ -2585,13 +3136,20 @@ where
 
     pub fn set_bitmap(&mut self, bitmap: Arc<B>) {
         if cfg!(debug_assertions) {
-            assert_eq!(bitmap.to_vec().len(), self.bitmap.to_vec().len());
-            assert_eq!(bitmap.to_vec(), self.bitmap.to_vec());
+            let (x, y) = (bitmap.to_vec().unwrap(), self.bitmap.to_vec().unwrap());
+            assert_eq!(x.len(), y.len());
+            assert_eq!(x, y);
         };
 
         self.bitmap = bitmap;
     }
 
+    /// Cache decoded z-blocks and m-blocks, read by this snapshot, in
+    /// `cache`.
+    pub fn set_block_cache(&mut self, cache: BlockCache) {
+        self.config.block_cache = Some(cache);
+    }
+
     pub fn is_snapshot(file_name: &ffi::OsStr) -> bool {
         let file_name = file_name.to_os_string();
         let name: Result<Name> = TryFrom::try_from(IndexFileName(file_name));
@@ -2603,6 +3161,10 @@ where
         let vlog_file = self.valog_fd.as_ref().map(|x| x.0.clone());
         let (dir, name) = (self.dir.clone(), self.name.clone());
 
+        if let Some(cache) = &self.config.block_cache {
+            cache.invalidate(&index_file)?;
+        }
+
         mem::drop(self); // IMPORTANT: Close this snapshot first.
 
         match purge_file(index_file.clone(), &mut vec![], &mut vec![]) {
@@ -2702,11 +3264,18 @@ where
 
     /// Return Btree statistics.
     pub fn to_stats(&self) -> Result<Stats> {
-        if let MetaItem::Stats(stats) = &self.meta[3] {
-            Ok(stats.parse()?)
+        let mut stats: Stats = if let MetaItem::Stats(stats) = &self.meta[3] {
+            stats.parse()?
         } else {
-            err_at!(Fatal, msg: format!("{}", self.meta[3]))
+            err_at!(Fatal, msg: format!("{}", self.meta[3]))?
+        };
+        if let Some(cache) = &self.config.block_cache {
+            let (hits, misses, evictions) = cache.to_counts()?;
+            stats.n_cache_hits = hits;
+            stats.n_cache_misses = misses;
+            stats.n_cache_evictions = evictions;
         }
+        Ok(stats)
     }
 
     pub fn to_vlog_path_file(&self) -> Result<Option<String>> {
@@ -2743,7 +3312,23 @@ where
         Ok(Scan::new(self, mzs))
     }
 
-    pub(crate) fn into_range_scan<R>(mut self, range: R) -> Result<ScanRange<K, V, B, R>>
+    pub(crate) fn into_range_scan<R>(self, range: R) -> Result<ScanRange<K, V, B, R>>
+    where
+        R: RangeBounds<K>,
+    {
+        self.do_range_scan(range, false /*shallow*/)
+    }
+
+    // like into_range_scan(), but values and deltas are left as
+    // references into the value-log file, refer to Iter::new_shallow().
+    pub(crate) fn into_shallow_scan<R>(self, range: R) -> Result<ScanRange<K, V, B, R>>
+    where
+        R: RangeBounds<K>,
+    {
+        self.do_range_scan(range, true /*shallow*/)
+    }
+
+    fn do_range_scan<R>(mut self, range: R, shallow: bool) -> Result<ScanRange<K, V, B, R>>
     where
         R: RangeBounds<K>,
     {
@@ -2775,7 +3360,7 @@ where
             }?,
         };
 
-        let mut r = ScanRange::new(self, mzs, range);
+        let mut r = ScanRange::new(self, mzs, range, shallow);
         if skip_one {
             r.next();
         }
@@ -2803,9 +3388,10 @@ where
             Err(Error::EmptyIndex) => return Ok(vec![]),
             Err(err) => Err(err),
         }?;
-        let mblock1 = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock1 = MBlock::<K, V>::new_decode(self.index_fd.read_block(
             fpos,
             m_blocksize,
+            &self.config,
             "partitions, reading root",
         )?)?;
         // println!("robt to_partitions root len {}", mblock1.len());
@@ -2822,9 +3408,10 @@ where
                 partitions.push(range);
                 lk = Bound::Included(hk);
             } else {
-                let mblock2 = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+                let mblock2 = MBlock::<K, V>::new_decode(self.index_fd.read_block(
                     mentry.to_fpos(),
                     m_blocksize,
+                    &self.config,
                     "partitions, reading mblock1",
                 )?)?;
                 // println!("to_partitions level-1 len {}", mblock2.len());
@@ -3005,6 +3592,19 @@ where
         self.do_get(key, versions)
     }
 
+    fn may_contain<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.bitmap.contains(key)
+    }
+
+    fn has_bitmap(&self) -> bool {
+        // NoBitmap, and empty filters, hold no keys.
+        self.bitmap.len().map_or(false, |n| n > 0)
+    }
+
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let mut mzs = vec![];
         match self.to_root() {
@@ -3167,14 +3767,70 @@ where
     V: Default + Clone + Diff + Serialize,
     <V as Diff>::D: Default + Clone + Serialize,
 {
+    /// Verify checksum for every m-block, z-block and value-log entry in
+    /// this snapshot. Return the list of corrupted blocks and entries, as
+    /// (file, file-position), an empty list means the snapshot is clean.
+    pub fn verify(&mut self) -> Result<Vec<(ffi::OsString, u64)>> {
+        let mut corrupted = vec![];
+        let root = match self.to_root() {
+            Ok(root) => Ok(root),
+            Err(Error::EmptyIndex) => return Ok(corrupted),
+            Err(err) => Err(err),
+        }?;
+
+        // verify blocks on disk, bypassing the block cache.
+        let config = {
+            let mut config = self.config.clone();
+            config.block_cache = None;
+            config
+        };
+        let (m_blocksize, z_blocksize) = (config.m_blocksize, config.z_blocksize);
+        let mut stack = vec![(root, false)]; // (fpos, is_zblock)
+        while let Some((fpos, is_zblock)) = stack.pop() {
+            let n = if is_zblock { z_blocksize } else { m_blocksize };
+            let block = match self.index_fd.read_block(fpos, n, &config, "verify") {
+                Ok(block) => block,
+                Err(Error::Corrupted(file, fpos)) => {
+                    corrupted.push((file, fpos));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            if is_zblock {
+                let zblock = ZBlock::<K, V>::new_decode(block)?;
+                for index in 0..zblock.len()? {
+                    let (_, mut entry) = zblock.to_entry(index)?;
+                    match self.fetch(&mut entry, false, true) {
+                        Ok(_) => (),
+                        Err(Error::Corrupted(file, fpos)) => corrupted.push((file, fpos)),
+                        Err(err) => return Err(err),
+                    }
+                }
+            } else {
+                let mblock = MBlock::<K, V>::new_decode(block)?;
+                for index in (0..mblock.len()).rev() {
+                    match mblock.to_entry(index)? {
+                        MEntry::DecM { fpos, .. } => stack.push((fpos, false)),
+                        MEntry::DecZ { fpos, .. } => stack.push((fpos, true)),
+                        _ => err_at!(Fatal, msg: format!("unreachable"))?,
+                    }
+                }
+            }
+        }
+
+        Ok(corrupted)
+    }
+
     /// Return the first entry in index, with only latest value.
     pub fn first(&mut self) -> Result<Entry<K, V>> {
         let zfpos = self.first_zpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_block(
             zfpos,
             z_blocksize,
+            &self.config,
             "first(), reading zblock",
         )?)?;
 
@@ -3188,9 +3844,10 @@ where
         let zfpos = self.first_zpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_block(
             zfpos,
             z_blocksize,
+            &self.config,
             "first(), reading zblock",
         )?)?;
 
@@ -3204,9 +3861,10 @@ where
         let zfpos = self.last_zfpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_block(
             zfpos,
             z_blocksize,
+            &self.config,
             "last(), reading zblock",
         )?)?;
 
@@ -3220,9 +3878,10 @@ where
         let zfpos = self.last_zfpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_block(
             zfpos,
             z_blocksize,
+            &self.config,
             "last(), reading zblock",
         )?)?;
 
@@ -3233,9 +3892,10 @@ where
 
     fn first_zpos(&mut self, fpos: u64) -> Result<u64> {
         let m_blocksize = self.config.m_blocksize;
-        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
             fpos,
             m_blocksize,
+            &self.config,
             "first_zpos, reading mblock",
         )?)?;
 
@@ -3249,9 +3909,10 @@ where
 
     fn last_zfpos(&mut self, fpos: u64) -> Result<u64> {
         let m_blocksize = self.config.m_blocksize;
-        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
             fpos,
             m_blocksize,
+            &self.config,
             "last_zpos, reading mblock",
         )?)?;
 
@@ -3268,9 +3929,10 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
             fpos,
             self.config.m_blocksize,
+            &self.config,
             "get_zpos(), reading mblock",
         )?)?;
         match mblock.get(key, Bound::Unbounded, Bound::Unbounded) {
@@ -3289,9 +3951,10 @@ where
         let zfpos = self.get_zpos(key, self.to_root()?)?;
 
         // println!("do_get {}", zfpos);
-        let zblock: ZBlock<K, V> = ZBlock::new_decode(self.index_fd.read_buffer(
+        let zblock: ZBlock<K, V> = ZBlock::new_decode(self.index_fd.read_block(
             zfpos,
             self.config.z_blocksize,
+            &self.config,
             "do_get(), reading zblock",
         )?)?;
         match zblock.find(key, Bound::Unbounded, Bound::Unbounded) {
@@ -3406,9 +4069,10 @@ where
 
         // println!("build_fwd {} {}", mzs.len(), fpos);
         let zfpos = loop {
-            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
                 fpos,
                 config.m_blocksize,
+                config,
                 "build_fwd(), reading mblock",
             )?)?;
             mzs.push(MZ::M { fpos, index: 0 });
@@ -3421,9 +4085,10 @@ where
         };
         // println!("build_fwd {}", mzs.len());
 
-        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::new_decode(self.index_fd.read_block(
             zfpos,
             config.z_blocksize,
+            config,
             "build_fwd(), reading zblock",
         )?)?;
         mzs.push(MZ::Z { zblock, index: 0 });
@@ -3436,9 +4101,10 @@ where
         match mzs.pop() {
             None => Ok(()),
             Some(MZ::M { fpos, mut index }) => {
-                let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+                let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
                     fpos,
                     config.m_blocksize,
+                    config,
                     "rebuild_fwd(), reading mblock",
                 )?)?;
                 index += 1;
@@ -3446,9 +4112,10 @@ where
                     Ok(MEntry::DecZ { fpos: zfpos, .. }) => {
                         mzs.push(MZ::M { fpos, index });
 
-                        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+                        let zblock = ZBlock::new_decode(self.index_fd.read_block(
                             zfpos,
                             config.z_blocksize,
+                            config,
                             "rebuild_fwd(), reading zblock",
                         )?)?;
                         mzs.push(MZ::Z { zblock, index: 0 });
@@ -3475,9 +4142,10 @@ where
         let config = &self.config;
 
         let zfpos = loop {
-            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
                 fpos,
                 config.m_blocksize,
+                config,
                 "build_rev(), reading mblock",
             )?)?;
             let index = mblock.len() - 1;
@@ -3490,9 +4158,10 @@ where
             fpos = mentry.to_fpos();
         };
 
-        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::new_decode(self.index_fd.read_block(
             zfpos,
             config.z_blocksize,
+            config,
             "build_rev(), reading zblock",
         )?)?;
         let index: isize = convert_at!((zblock.len()? - 1))?;
@@ -3507,9 +4176,10 @@ where
             None => Ok(()),
             Some(MZ::M { index: 0, .. }) => self.rebuild_rev(mzs),
             Some(MZ::M { fpos, mut index }) => {
-                let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+                let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
                     fpos,
                     config.m_blocksize,
+                    config,
                     "rebuild_rev(), reading mblock",
                 )?)?;
                 index -= 1;
@@ -3517,9 +4187,10 @@ where
                     Ok(MEntry::DecZ { fpos: zfpos, .. }) => {
                         mzs.push(MZ::M { fpos, index });
 
-                        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+                        let zblock = ZBlock::new_decode(self.index_fd.read_block(
                             zfpos,
                             config.z_blocksize,
+                            config,
                             "rebuild_rev(), reading zblock",
                         )?)?;
                         let idx: isize = convert_at!((zblock.len()? - 1))?;
@@ -3552,9 +4223,10 @@ where
         let (from_min, to_max) = (Bound::Unbounded, Bound::Unbounded);
 
         let zfpos = loop {
-            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_block(
                 fpos,
                 config.m_blocksize,
+                config,
                 "build(), reading mblock",
             )?)?;
             let mentry = match mblock.find(key, from_min, to_max) {
@@ -3570,9 +4242,10 @@ where
             fpos = mentry.to_fpos();
         };
 
-        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::new_decode(self.index_fd.read_block(
             zfpos,
             config.z_blocksize,
+            config,
             "build(), reading zblock",
         )?)?;
         let (index, entry) = match zblock.find(key, from_min, to_max) {
@@ -3604,19 +4277,22 @@ where
         shallow: bool,  // fetch neither value nor deltas.
         versions: bool, // fetch deltas as well
     ) -> Result<()> {
-        if !shallow {
-            match &mut self.valog_fd {
-                Some((_, fd)) => entry.fetch_value(fd)?,
-                _ => (),
-            }
-        }
-        if versions {
-            match &mut self.valog_fd {
-                Some((_, fd)) => entry.fetch_deltas(fd)?,
-                _ => (),
+        let cmp = self.config.compression;
+        let res = match &mut self.valog_fd {
+            Some((_, fd)) if !shallow => entry.fetch_value(fd, cmp),
+            _ => Ok(()),
+        };
+        let res = match &mut self.valog_fd {
+            Some((_, fd)) if res.is_ok() && versions => entry.fetch_deltas(fd, cmp),
+            _ => res,
+        };
+        match (res, &self.valog_fd) {
+            // tag the corrupted entry with value-log file.
+            (Err(Error::Corrupted(_, fpos)), Some((vlog_file, _))) => {
+                Err(Error::Corrupted(vlog_file.clone(), fpos))
             }
+            (res, _) => res,
         }
-        Ok(())
     }
 }
 
@@ -3689,6 +4365,7 @@ where
     snap: Snapshot<K, V, B>,
     mzs: Vec<MZ<K, V>>,
     range: R,
+    shallow: bool,
 }
 
 impl<K, V, B, R> ScanRange<K, V, B, R>
@@ -3698,8 +4375,13 @@ where
     <V as Diff>::D: Clone + Serialize,
     R: RangeBounds<K>,
 {
-    fn new(snap: Snapshot<K, V, B>, mzs: Vec<MZ<K, V>>, range: R) -> Self {
-        ScanRange { snap, mzs, range }
+    fn new(snap: Snapshot<K, V, B>, mzs: Vec<MZ<K, V>>, range: R, shallow: bool) -> Self {
+        ScanRange {
+            snap,
+            mzs,
+            range,
+            shallow,
+        }
     }
 
     fn till_ok(&self, entry: &Entry<K, V>) -> bool {
@@ -3727,7 +4409,7 @@ where
                 Some(Ok(mut entry)) => {
                     if self.till_ok(&entry) {
                         self.mzs.push(z);
-                        let (shallow, versions) = (false, true);
+                        let (shallow, versions) = (self.shallow, !self.shallow);
                         match self.snap.fetch(&mut entry, shallow, versions) {
                             Ok(()) => Some(Ok(entry)),
                             Err(err) => Some(Err(err)),
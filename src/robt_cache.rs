@@ -0,0 +1,187 @@
+//! Module `robt_cache` implement a size bounded LRU cache for btree
+//! blocks.
+//!
+//! A [BlockCache] can be shared across several [Snapshot][crate::robt::Snapshot]
+//! instances, [ShRobt][crate::shrobt::ShRobt] shards and [Dgm][crate::dgm::Dgm]
+//! levels. Blocks are cached after decompression and checksum
+//! verification, keyed by (index-file, file-position). Least recently used
+//! blocks are evicted when the cached bytes exceed the configured capacity.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{core::Result, error::Error};
+
+/// Shared, size bounded, LRU cache for z-blocks and m-blocks.
+///
+/// Cloning a BlockCache shall return a new handle to the same cache.
+#[derive(Clone)]
+pub struct BlockCache {
+    inner: Arc<Mutex<InnerCache>>,
+}
+
+struct InnerCache {
+    capacity: usize,
+    footprint: usize,
+    tick: u64,
+    // index-file -> file-id
+    files: HashMap<ffi::OsString, u64>,
+    // (file-id, fpos) -> (tick, block)
+    blocks: HashMap<(u64, u64), (u64, Arc<Vec<u8>>)>,
+    // tick -> (file-id, fpos), ordered from least recently used.
+    lru: BTreeMap<u64, (u64, u64)>,
+
+    n_hits: usize,
+    n_misses: usize,
+    n_evictions: usize,
+}
+
+impl BlockCache {
+    /// Create a new cache that can hold upto `capacity` bytes of blocks.
+    pub fn new(capacity: usize) -> BlockCache {
+        let inner = InnerCache {
+            capacity,
+            footprint: 0,
+            tick: 0,
+            files: HashMap::new(),
+            blocks: HashMap::new(),
+            lru: BTreeMap::new(),
+
+            n_hits: 0,
+            n_misses: 0,
+            n_evictions: 0,
+        };
+        BlockCache {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Return the configured capacity, in bytes.
+    pub fn to_capacity(&self) -> Result<usize> {
+        Ok(self.as_inner()?.capacity)
+    }
+
+    /// Return the number of bytes cached.
+    pub fn footprint(&self) -> Result<usize> {
+        Ok(self.as_inner()?.footprint)
+    }
+
+    /// Return the number of blocks cached.
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.as_inner()?.blocks.len())
+    }
+
+    /// Return (hits, misses, evictions) counted so far.
+    pub fn to_counts(&self) -> Result<(usize, usize, usize)> {
+        let inner = self.as_inner()?;
+        Ok((inner.n_hits, inner.n_misses, inner.n_evictions))
+    }
+
+    /// Drop all blocks cached for `file`, return the number of blocks
+    /// dropped. Shall be called before purging an index file.
+    pub fn invalidate(&self, file: &ffi::OsStr) -> Result<usize> {
+        let mut inner = self.as_inner()?;
+        let fid = match inner.files.remove(file) {
+            Some(fid) => fid,
+            None => return Ok(0),
+        };
+
+        let keys: Vec<(u64, u64)> = inner
+            .blocks
+            .keys()
+            .filter(|(id, _)| *id == fid)
+            .cloned()
+            .collect();
+        for key in keys.iter() {
+            if let Some((tick, block)) = inner.blocks.remove(key) {
+                inner.lru.remove(&tick);
+                inner.footprint -= block.len();
+            }
+        }
+        Ok(keys.len())
+    }
+
+    pub(crate) fn get(&self, file: &ffi::OsStr, fpos: u64) -> Result<Option<Arc<Vec<u8>>>> {
+        let mut inner = self.as_inner()?;
+        let key = match inner.files.get(file) {
+            Some(fid) => (*fid, fpos),
+            None => {
+                inner.n_misses += 1;
+                return Ok(None);
+            }
+        };
+
+        inner.tick += 1;
+        let tick = inner.tick;
+        let (old_tick, block) = match inner.blocks.get_mut(&key) {
+            Some((old_tick, block)) => {
+                let old = *old_tick;
+                *old_tick = tick;
+                (old, Arc::clone(block))
+            }
+            None => {
+                inner.n_misses += 1;
+                return Ok(None);
+            }
+        };
+        inner.lru.remove(&old_tick);
+        inner.lru.insert(tick, key);
+        inner.n_hits += 1;
+
+        Ok(Some(block))
+    }
+
+    pub(crate) fn insert(&self, file: &ffi::OsStr, fpos: u64, block: Arc<Vec<u8>>) -> Result<()> {
+        let mut inner = self.as_inner()?;
+        if block.len() > inner.capacity {
+            return Ok(());
+        }
+
+        let fid = match inner.files.get(file) {
+            Some(fid) => *fid,
+            None => {
+                let fid = inner.files.values().max().map_or(0, |fid| fid + 1);
+                inner.files.insert(file.to_os_string(), fid);
+                fid
+            }
+        };
+
+        inner.tick += 1;
+        let (key, tick, n) = ((fid, fpos), inner.tick, block.len());
+        if let Some((old_tick, old)) = inner.blocks.insert(key, (tick, block)) {
+            inner.lru.remove(&old_tick);
+            inner.footprint -= old.len();
+        }
+        inner.lru.insert(tick, key);
+        inner.footprint += n;
+
+        while inner.footprint > inner.capacity {
+            let old_tick = match inner.lru.keys().next() {
+                Some(old_tick) => *old_tick,
+                None => break,
+            };
+            if let Some(key) = inner.lru.remove(&old_tick) {
+                if let Some((_, old)) = inner.blocks.remove(&key) {
+                    inner.footprint -= old.len();
+                    inner.n_evictions += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn as_inner(&self) -> Result<MutexGuard<InnerCache>> {
+        match self.inner.lock() {
+            Ok(inner) => Ok(inner),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "robt_cache_test.rs"]
+mod robt_cache_test;
@@ -0,0 +1,40 @@
+use super::*;
+
+#[test]
+fn test_block_cache() {
+    let cache = BlockCache::new(1000);
+    let (f1, f2): (&ffi::OsStr, &ffi::OsStr) = ("file1".as_ref(), "file2".as_ref());
+
+    assert!(cache.get(f1, 0).unwrap().is_none());
+    cache.insert(f1, 0, Arc::new(vec![1; 400])).unwrap();
+    cache.insert(f1, 400, Arc::new(vec![2; 400])).unwrap();
+    cache.insert(f2, 0, Arc::new(vec![3; 100])).unwrap();
+    assert_eq!(cache.len().unwrap(), 3);
+    assert_eq!(cache.footprint().unwrap(), 900);
+
+    assert_eq!(cache.get(f1, 0).unwrap().unwrap().as_ref(), &vec![1; 400]);
+    assert_eq!(cache.get(f2, 0).unwrap().unwrap().as_ref(), &vec![3; 100]);
+    assert!(cache.get(f2, 400).unwrap().is_none());
+
+    // (f1, 400) is the least recently used block.
+    cache.insert(f2, 100, Arc::new(vec![4; 400])).unwrap();
+    assert!(cache.get(f1, 400).unwrap().is_none());
+    assert_eq!(cache.len().unwrap(), 3);
+    assert_eq!(cache.footprint().unwrap(), 900);
+    assert_eq!(cache.to_counts().unwrap(), (2, 3, 1));
+
+    // blocks larger than capacity are not cached.
+    cache.insert(f1, 800, Arc::new(vec![5; 1001])).unwrap();
+    assert!(cache.get(f1, 800).unwrap().is_none());
+
+    assert_eq!(cache.invalidate(f2).unwrap(), 2);
+    assert_eq!(cache.invalidate(f2).unwrap(), 0);
+    assert!(cache.get(f2, 0).unwrap().is_none());
+    assert_eq!(cache.len().unwrap(), 1);
+    assert_eq!(cache.footprint().unwrap(), 400);
+    assert_eq!(cache.to_capacity().unwrap(), 1000);
+
+    let cache1 = cache.clone();
+    assert_eq!(cache1.get(f1, 0).unwrap().unwrap().as_ref(), &vec![1; 400]);
+    assert_eq!(cache.to_counts().unwrap(), (3, 5, 1));
+}
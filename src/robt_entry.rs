@@ -3,6 +3,7 @@ use std::{convert::TryInto, marker};
 use crate::{
     core::{self, Diff, Result, Serialize},
     error::Error,
+    robt::Compression,
     vlog,
 };
 
@@ -161,7 +162,12 @@ where
     const DLEN_MASK: u64 = 0x0FFFFFFFFFFFFFFF;
     const REFERENCE_FLAG: u64 = 0x8000000000000000;
 
-    fn encode(delta: &core::Delta<V>, leaf: &mut Vec<u8>, blob: &mut Vec<u8>) -> Result<usize> {
+    fn encode(
+        delta: &core::Delta<V>,
+        leaf: &mut Vec<u8>,
+        blob: &mut Vec<u8>,
+        compression: Compression,
+    ) -> Result<usize> {
         match delta.as_ref() {
             core::InnerDelta::U {
                 delta:
@@ -189,7 +195,7 @@ where
                 let mpos: u64 = convert_at!(blob.len())?;
 
                 let (hdr1, n) = {
-                    let n = delta.encode(blob)?;
+                    let n = delta.encode(blob, compression)?;
                     let hdr1: u64 = convert_at!(n)?;
                     let hdr1 = hdr1 | Self::UPSERT_FLAG;
                     (hdr1, n)
@@ -339,12 +345,13 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        compression: Compression,
     ) -> Result<ZEntry<K, V>> {
         let m = leaf.len();
         let (n_deltas, is_vlog) = (entry.to_delta_count(), false);
         let (k, v) = Self::encode_leaf1(entry, n_deltas, is_vlog, leaf)?;
         let doff = leaf.len() - m;
-        let d = ZEntry::encode_deltas(entry, leaf, blob)?;
+        let d = ZEntry::encode_deltas(entry, leaf, blob, compression)?;
         Ok(ZEntry::EncLD {
             doff,
             n_deltas,
@@ -358,9 +365,10 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        compression: Compression,
     ) -> Result<ZEntry<K, V>> {
         let (n_deltas, is_vlog) = (0_usize, true);
-        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob)?;
+        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob, compression)?;
         Ok(ZEntry::EncLV { voff: x, k, v })
     }
 
@@ -368,13 +376,14 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        compression: Compression,
     ) -> Result<ZEntry<K, V>> {
         let m = leaf.len();
         let (n_deltas, is_vlog) = (entry.to_delta_count(), true);
-        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob)?;
+        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob, compression)?;
         // encode deltas
         let doff = leaf.len() - m;
-        let d = ZEntry::encode_deltas(entry, leaf, blob)?;
+        let d = ZEntry::encode_deltas(entry, leaf, blob, compression)?;
         Ok(ZEntry::EncLVD {
             voff: x,
             doff,
@@ -410,6 +419,7 @@ where
         is_vlog: bool,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        compression: Compression,
     ) -> Result<(usize, usize, usize)> {
         // adjust space for header.
         let m = leaf.len();
@@ -418,7 +428,7 @@ where
         let klen = Self::encode_key(entry.as_key(), leaf)?;
         // encode value
         let pos = blob.len();
-        let (fpos, vlen, is_del, seqno) = ZEntry::encode_value_vlog(entry, blob)?;
+        let (fpos, vlen, is_del, seqno) = ZEntry::encode_value_vlog(entry, blob, compression)?;
         let voff = leaf.len() - m;
         if !is_del {
             let fpos: u64 = match fpos {
@@ -492,10 +502,11 @@ where
     fn encode_value_vlog(
         entry: &core::Entry<K, V>,
         buf: &mut Vec<u8>,
+        compression: Compression,
     ) -> Result<(Option<u64>, usize, bool, u64)> {
         match entry.as_value() {
             core::Value::U { value, seqno, .. } => {
-                let (fpos, vlen) = value.encode(buf)?;
+                let (fpos, vlen) = value.encode(buf, compression)?;
                 Ok((fpos, vlen, false, *seqno))
             }
             core::Value::D { seqno } => Ok((None, 0, true, *seqno)),
@@ -506,10 +517,11 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        compression: Compression,
     ) -> Result<usize> {
         let mut n = 0_usize;
         for delta in entry.as_deltas() {
-            n += DiskDelta::encode(delta, leaf, blob)?;
+            n += DiskDelta::encode(delta, leaf, blob, compression)?;
         }
         Ok(n)
     }
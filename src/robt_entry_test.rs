@@ -308,12 +308,10 @@ fn test_zentry_ld() {
         .ok();
 
     let (mut leaf, mut blob): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
-    let ze = ZEntry::<i32, i32>::encode_ld(
-        &entry, &mut leaf, &mut blob, // arguments
-    )
-    .unwrap();
+    let ze =
+        ZEntry::<i32, i32>::encode_ld(&entry, &mut leaf, &mut blob, Compression::None).unwrap();
     let (k, v, d) = ze.to_kvd_stats().unwrap();
-    assert_eq!((k, v, d), (4, 4, 24));
+    assert_eq!((k, v, d), (4, 4, 32));
 
     // leaf.iter().for_each(|x| print!("{:x} ", x));
     let leaf_ref = vec![
@@ -327,22 +325,24 @@ fn test_zentry_ld() {
         0, 0, 0, 0, 0, 0, 0, 0xc, // seqno
         0, 0, 0, 0, 0, 0, 0, 0x0, // fpos
         // upsert seqno 11
-        0x10, 0, 0, 0, 0, 0, 0, 0xc, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0xb, // seqno
         0x00, 0, 0, 0, 0, 0, 0, 0x0, // fpos
         // upsert seqno 10
-        0x10, 0, 0, 0, 0, 0, 0, 0xc, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0xa, // seqno
-        0x00, 0, 0, 0, 0, 0, 0, 0xc, // fpos
+        0x00, 0, 0, 0, 0, 0, 0, 0x10, // fpos
     ];
     assert_eq!(leaf.len(), leaf_ref.len());
     assert_eq!(leaf, leaf_ref);
 
     let blob_ref = vec![
-        0, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
+        0x40, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
         /*             */ 0, 0, 0x4e, 0x20, // payload
-        0, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
+        /*             */ 0xeb, 0xa2, 0x95, 0x1c, // checksum
+        0x40, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
         /*             */ 0, 0, 0x27, 0x10, // payload
+        /*             */ 0x79, 0x44, 0x75, 0x5e, // checksum
     ];
     assert_eq!(blob.len(), blob_ref.len());
     assert_eq!(blob, blob_ref);
@@ -396,13 +396,13 @@ fn test_zentry_ld() {
         0, 0, 0, 0, 0, 0, 0, 0x0c, // seqno
         0, 0, 0, 0, 0, 0, 0, 0x00, // fpos
         // upsert seqno 11
-        0x10, 0, 0, 0, 0, 0, 0, 0x0c, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0x0b, // seqno
         0x00, 0, 0, 0, 0, 0, 0, 0x64, // fpos
         // upsert seqno 10
-        0x10, 0, 0, 0, 0, 0, 0, 0x0c, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0x0a, // seqno
-        0x00, 0, 0, 0, 0, 0, 0, 0x70, // fpos
+        0x00, 0, 0, 0, 0, 0, 0, 0x74, // fpos
     ];
     assert_eq!(leaf.len(), leaf_ref.len());
     assert_eq!(leaf, leaf_ref);
@@ -428,16 +428,14 @@ fn test_zentry_lv() {
         .ok();
 
     let (mut leaf, mut blob): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
-    let ze = ZEntry::<i32, i32>::encode_lv(
-        &entry, &mut leaf, &mut blob, // arguments
-    )
-    .unwrap();
+    let ze =
+        ZEntry::<i32, i32>::encode_lv(&entry, &mut leaf, &mut blob, Compression::None).unwrap();
     let (k, v, d) = ze.to_kvd_stats().unwrap();
-    assert_eq!((4, 12, 0), (k, v, d));
+    assert_eq!((4, 16, 0), (k, v, d));
 
     let leaf_ref = vec![
         0x00, 0, 0, 4, 0, 0, 0x00, 0x00, // klen + n_deltas
-        0x30, 0, 0, 0, 0, 0, 0x00, 0x0c, // vlen
+        0x30, 0, 0, 0, 0, 0, 0x00, 0x10, // vlen
         0x00, 0, 0, 0, 0, 0, 0x00, 0x0d, // seqno
         /*          */ 0, 0, 0x00, 0x64, // key
         0x00, 0, 0, 0, 0, 0, 0x00, 0x00, // value-fpos
@@ -447,8 +445,9 @@ fn test_zentry_lv() {
 
     // blob.iter().for_each(|x| print!("{:x} ", x));
     let blob_ref = vec![
-        0x10, 0, 0, 0x00, 0, 0, 0x00, 0x04, // length
+        0x50, 0, 0, 0x00, 0, 0, 0x00, 0x04, // length
         /*             */ 0, 0, 0x75, 0x30, // payload
+        /*             */ 0xc6, 0xbf, 0x87, 0xbc, // checksum
     ];
     assert_eq!(blob.len(), blob_ref.len());
     assert_eq!(blob, blob_ref);
@@ -466,7 +465,7 @@ fn test_zentry_lv() {
         } if value.is_reference() => {
             let (fpos, length, seqno) = value.to_reference().unwrap();
             assert_eq!(fpos, 0);
-            assert_eq!(length, 12);
+            assert_eq!(length, 16);
             assert_eq!(seqno, 13);
             assert_eq!(*seqno1, 13);
         }
@@ -477,7 +476,7 @@ fn test_zentry_lv() {
     ze.re_encode_fpos(&mut leaf, 200).unwrap();
     let leaf_ref = vec![
         0x00, 0, 0, 4, 0, 0, 0x00, 0x00, // klen + n_deltas
-        0x30, 0, 0, 0, 0, 0, 0x00, 0x0c, // vlen
+        0x30, 0, 0, 0, 0, 0, 0x00, 0x10, // vlen
         0x00, 0, 0, 0, 0, 0, 0x00, 0x0d, // seqno
         /*          */ 0, 0, 0x00, 0x64, // key
         0x00, 0, 0, 0, 0, 0, 0x00, 0xc8, // value-fpos
@@ -504,16 +503,14 @@ fn test_zentry_lvd() {
         .ok();
 
     let (mut leaf, mut blob): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
-    let ze = ZEntry::<i32, i32>::encode_lvd(
-        &entry, &mut leaf, &mut blob, // arguments
-    )
-    .unwrap();
+    let ze =
+        ZEntry::<i32, i32>::encode_lvd(&entry, &mut leaf, &mut blob, Compression::None).unwrap();
     let (k, v, d) = ze.to_kvd_stats().unwrap();
-    assert_eq!((k, v, d), (4, 12, 24));
+    assert_eq!((k, v, d), (4, 16, 32));
 
     let leaf_ref = vec![
         0x00, 0, 0, 4, 0, 0, 0x00, 0x03, // klen + n_deltas
-        0x30, 0, 0, 0, 0, 0, 0x00, 0x0c, // vlen
+        0x30, 0, 0, 0, 0, 0, 0x00, 0x10, // vlen
         0x00, 0, 0, 0, 0, 0, 0x00, 0x0d, // seqno
         /*          */ 0, 0, 0x00, 0x64, // key
         0x00, 0, 0, 0, 0, 0, 0x00, 0x00, // value-fpos
@@ -522,25 +519,28 @@ fn test_zentry_lvd() {
         0, 0, 0, 0, 0, 0, 0, 0xc, // seqno
         0, 0, 0, 0, 0, 0, 0, 0x0, // fpos
         // upsert seqno 11
-        0x10, 0, 0, 0, 0, 0, 0, 0xc, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0xb, // seqno
-        0x00, 0, 0, 0, 0, 0, 0, 0xc, // fpos
+        0x00, 0, 0, 0, 0, 0, 0, 0x10, // fpos
         // upsert seqno 10
-        0x10, 0, 0, 0, 0, 0, 0, 0x0c, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0x0a, // seqno
-        0x00, 0, 0, 0, 0, 0, 0, 0x18, // fpos
+        0x00, 0, 0, 0, 0, 0, 0, 0x20, // fpos
     ];
     assert_eq!(leaf.len(), leaf_ref.len());
     assert_eq!(leaf, leaf_ref);
 
     // blob.iter().for_each(|x| print!("{:x} ", x));
     let blob_ref = vec![
-        0x10, 0, 0, 0x00, 0, 0, 0x00, 0x04, // length
+        0x50, 0, 0, 0x00, 0, 0, 0x00, 0x04, // length
         /*             */ 0, 0, 0x75, 0x30, // payload
-        0, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
+        /*             */ 0xc6, 0xbf, 0x87, 0xbc, // checksum
+        0x40, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
         /*             */ 0, 0, 0x4e, 0x20, // payload
-        0, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
+        /*             */ 0xeb, 0xa2, 0x95, 0x1c, // checksum
+        0x40, 0, 0x00, 0x00, 0, 0, 0x00, 0x04, // length
         /*             */ 0, 0, 0x27, 0x10, // payload
+        /*             */ 0x79, 0x44, 0x75, 0x5e, // checksum
     ];
     assert_eq!(blob.len(), blob_ref.len());
     assert_eq!(blob, blob_ref);
@@ -558,7 +558,7 @@ fn test_zentry_lvd() {
         } if value.is_reference() => {
             let (fpos, length, seqno) = value.to_reference().unwrap();
             assert_eq!(fpos, 0);
-            assert_eq!(length, 12);
+            assert_eq!(length, 16);
             assert_eq!(seqno, 13);
             assert_eq!(*seqno1, 13);
         }
@@ -598,7 +598,7 @@ fn test_zentry_lvd() {
     ze.re_encode_fpos(&mut leaf, 200).unwrap();
     let leaf_ref = vec![
         0x00, 0, 0, 4, 0, 0, 0x00, 0x03, // klen + n_deltas
-        0x30, 0, 0, 0, 0, 0, 0x00, 0x0c, // vlen
+        0x30, 0, 0, 0, 0, 0, 0x00, 0x10, // vlen
         0x00, 0, 0, 0, 0, 0, 0x00, 0x0d, // seqno
         /*          */ 0, 0, 0x00, 0x64, // key
         0x00, 0, 0, 0, 0, 0, 0x00, 0xc8, // value-fpos
@@ -607,13 +607,13 @@ fn test_zentry_lvd() {
         0, 0, 0, 0, 0, 0, 0, 0x0c, // seqno
         0, 0, 0, 0, 0, 0, 0, 0x00, // fpos
         // upsert seqno 11
-        0x10, 0, 0, 0, 0, 0, 0, 0x0c, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0x0b, // seqno
-        0x00, 0, 0, 0, 0, 0, 0, 0xd4, // fpos
+        0x00, 0, 0, 0, 0, 0, 0, 0xd8, // fpos
         // upsert seqno 10
-        0x10, 0, 0, 0, 0, 0, 0, 0x0c, // dlen
+        0x10, 0, 0, 0, 0, 0, 0, 0x10, // dlen
         0x00, 0, 0, 0, 0, 0, 0, 0x0a, // seqno
-        0x00, 0, 0, 0, 0, 0, 0, 0xe0, // fpos
+        0x00, 0, 0, 0, 0, 0, 0, 0xe8, // fpos
     ];
     assert_eq!(leaf.len(), leaf_ref.len());
     assert_eq!(leaf, leaf_ref);
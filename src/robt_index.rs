@@ -1,14 +1,105 @@
 // TODO: flush put blocks into tx channel. Right now we simply unwrap()
 
-use std::{borrow::Borrow, cmp::Ordering, convert::TryInto, marker, ops::Bound};
+use crc::crc32::{self, Hasher32};
+
+use std::{borrow::Borrow, cmp::Ordering, convert::TryInto, marker, ops::Bound, sync::Arc};
 
 use crate::{
     core::{self, Diff, Result, Serialize},
     error::Error,
-    robt::{Config, Flusher, Stats},
+    robt::{Compression, Config, Flusher, Stats},
     robt_entry::{MEntry, ZEntry},
+    vlog,
 };
 
+// Binary format (compressed block):
+//
+// *-----*------------------------------------*
+// |flags|        31-bit payload length       |
+// *-----*------------------------------------*
+// |                 payload                  |
+// *-------------------*----------------------*
+// |     checksum      |
+// *-------------------*
+//
+// Flags:
+// * bit 31 set means payload is stored uncompressed, when compression
+//   can't make it smaller.
+//
+// Compressed blocks are not padded, and payload, after decompression,
+// is same as the un-padded Z-Block or M-Block.
+//
+// Checksum:
+//
+// When configured, every Z-Block and M-Block end with a 32-bit crc32
+// checksum. For uncompressed blocks checksum is computed over the padded
+// block and stored in the last 4 bytes of the block. For compressed
+// blocks checksum is computed over header and payload.
+
+const RAW_BLOCK_FLAG: u32 = 0x80000000;
+
+/// Size of block checksum, in bytes.
+pub(crate) const CHECKSUM_SIZE: usize = 4;
+
+// append crc32 checksum computed over `block`.
+fn append_checksum(block: &mut Vec<u8>) {
+    let crc = crc32::checksum_ieee(block);
+    block.extend_from_slice(&crc.to_be_bytes());
+}
+
+// verify crc32 checksum, stored in the last 4 bytes of `block`, computed
+// over `hdr` and rest of the `block`.
+pub(crate) fn verify_checksum(hdr: &[u8], block: &[u8]) -> Result<bool> {
+    if block.len() < CHECKSUM_SIZE {
+        return Ok(false);
+    }
+    let n = block.len() - CHECKSUM_SIZE;
+    let crc = u32::from_be_bytes(array_at!(block[n..])?);
+    let mut digest = crc32::Digest::new(crc32::IEEE);
+    digest.write(hdr);
+    digest.write(&block[..n]);
+    Ok(crc == digest.sum32())
+}
+
+// compress finalized `block` in place, return the on-disk block length.
+fn compress_block(block: &mut Vec<u8>, compression: Compression, checksum: bool) -> Result<usize> {
+    let data = compression.compress(block)?;
+    let (hdr, data) = if data.len() < block.len() {
+        let hdr: u32 = convert_at!(data.len())?;
+        (hdr, data)
+    } else {
+        let hdr: u32 = convert_at!(block.len())?;
+        (hdr | RAW_BLOCK_FLAG, block.clone())
+    };
+    block.truncate(0);
+    block.extend_from_slice(&hdr.to_be_bytes());
+    block.extend_from_slice(&data);
+    if checksum {
+        append_checksum(block);
+    }
+    Ok(block.len())
+}
+
+// return the payload length of a compressed block, from its header.
+pub(crate) fn to_payload_len(hdr: &[u8]) -> Result<usize> {
+    let hdr = u32::from_be_bytes(array_at!(hdr[..4])?);
+    Ok(convert_at!((hdr & !RAW_BLOCK_FLAG))?)
+}
+
+// decompress block `payload` read from disk, along with its header.
+pub(crate) fn decompress_block(
+    hdr: &[u8],
+    payload: Vec<u8>,
+    compression: Compression,
+) -> Result<Vec<u8>> {
+    let hdr = u32::from_be_bytes(array_at!(hdr[..4])?);
+    if (hdr & RAW_BLOCK_FLAG) == 0 {
+        compression.decompress(&payload)
+    } else {
+        Ok(payload)
+    }
+}
+
 // Binary format (InterMediate-Block prefix):
 //
 // *----------------------*
@@ -33,9 +124,11 @@ pub(crate) enum MBlock<K, V> {
         offsets: Vec<u32>,
         first_key: Option<K>,
         m_blocksize: usize,
+        compression: Compression,
+        checksum: bool,
     },
     Decode {
-        block: Vec<u8>,
+        block: Arc<Vec<u8>>, // shared with block cache
         count: usize,
         offsets: &'static [u8], // point into block
         phantom_val: marker::PhantomData<V>,
@@ -53,6 +146,8 @@ where
             offsets: Default::default(),
             first_key: Default::default(),
             m_blocksize: config.m_blocksize,
+            compression: config.compression,
+            checksum: config.checksum,
         }
     }
 
@@ -101,10 +196,13 @@ where
                 offsets,
                 first_key,
                 m_blocksize,
+                checksum,
+                ..
             } => {
                 let offset = mblock.len();
                 MEntry::new_m(fpos, key).encode(mblock)?;
                 let n = 4 + (offsets.len() + 1) * 4 + mblock.len();
+                let n = if *checksum { n + CHECKSUM_SIZE } else { n };
                 if n < *m_blocksize {
                     offsets.push(convert_at!(offset)?);
                     first_key.get_or_insert_with(|| key.clone());
@@ -126,10 +224,13 @@ where
                 offsets,
                 first_key,
                 m_blocksize,
+                checksum,
+                ..
             } => {
                 let offset = mblock.len();
                 MEntry::new_z(fpos, key).encode(mblock)?;
                 let n = 4 + (offsets.len() + 1) * 4 + mblock.len();
+                let n = if *checksum { n + CHECKSUM_SIZE } else { n };
                 if n < *m_blocksize {
                     offsets.push(convert_at!(offset)?);
                     first_key.get_or_insert_with(|| key.clone());
@@ -149,6 +250,8 @@ where
                 mblock,
                 offsets,
                 m_blocksize,
+                compression,
+                checksum,
                 ..
             } => {
                 let adjust: u32 = {
@@ -167,13 +270,24 @@ where
                     let offset_bytes = (adjust + offset).to_be_bytes();
                     mblock[x..x + 4].copy_from_slice(&offset_bytes);
                 }
-                // update statistics
-                stats.padding += *m_blocksize - mblock.len();
-                stats.m_bytes += *m_blocksize;
-                // align blocks
-                mblock.resize(*m_blocksize, 0);
-
-                Ok(convert_at!((*m_blocksize))?)
+                stats.m_raw_bytes += *m_blocksize;
+                if let Compression::None = compression {
+                    let c = if *checksum { CHECKSUM_SIZE } else { 0 };
+                    // update statistics
+                    stats.padding += *m_blocksize - mblock.len() - c;
+                    stats.m_bytes += *m_blocksize;
+                    // align blocks
+                    mblock.resize(*m_blocksize - c, 0);
+                    if *checksum {
+                        append_checksum(mblock);
+                    }
+
+                    Ok(convert_at!((*m_blocksize))?)
+                } else {
+                    let n = compress_block(mblock, *compression, *checksum)?;
+                    stats.m_bytes += n;
+                    Ok(convert_at!(n)?)
+                }
             }
             MBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
         }
@@ -208,7 +322,7 @@ impl<K, V> MBlock<K, V>
 where
     K: Ord + Serialize,
 {
-    pub(crate) fn new_decode(block: Vec<u8>) -> Result<MBlock<K, V>> {
+    pub(crate) fn new_decode(block: Arc<Vec<u8>>) -> Result<MBlock<K, V>> {
         let count = u32::from_be_bytes(array_at!(block[..4])?);
         let adjust: usize = convert_at!((4 + (count * 4)))?;
         let offsets = &block[4..adjust] as *const [u8];
@@ -404,9 +518,11 @@ where
         z_blocksize: usize,
         value_in_vlog: bool,
         delta_ok: bool,
+        compression: Compression,
+        checksum: bool,
     },
     Decode {
-        block: Vec<u8>,
+        block: Arc<Vec<u8>>, // shared with block cache
         count: usize,
         offsets: &'static [u8],
         phantom_val: marker::PhantomData<V>,
@@ -423,6 +539,8 @@ where
         let z_blocksize = config.z_blocksize;
         let value_in_vlog = config.value_in_vlog;
         let delta_ok = config.delta_ok;
+        let compression = config.compression;
+        let checksum = config.checksum;
 
         ZBlock::Encode {
             leaf: Vec::with_capacity(z_blocksize),
@@ -435,6 +553,8 @@ where
             z_blocksize,
             value_in_vlog,
             delta_ok,
+            compression,
+            checksum,
         }
     }
 
@@ -496,19 +616,23 @@ where
                 z_blocksize,
                 value_in_vlog,
                 delta_ok,
+                compression,
+                checksum,
                 ..
             } => {
                 let (leaf_i, blob_i) = (leaf.len(), blob.len());
+                let cmp = *compression;
                 let de = match (*value_in_vlog, *delta_ok) {
                     (false, false) => DZ::encode_l(entry, leaf)?,
-                    (false, true) => DZ::encode_ld(entry, leaf, blob)?,
-                    (true, false) => DZ::encode_lv(entry, leaf, blob)?,
-                    (true, true) => DZ::encode_lvd(entry, leaf, blob)?,
+                    (false, true) => DZ::encode_ld(entry, leaf, blob, cmp)?,
+                    (true, false) => DZ::encode_lv(entry, leaf, blob, cmp)?,
+                    (true, true) => DZ::encode_lvd(entry, leaf, blob, cmp)?,
                 };
                 let (k, v, d) = de.to_kvd_stats()?;
                 zentries.push(de);
 
                 let n = 4 + ((offsets.len() + 1) * 4) + leaf.len();
+                let n = if *checksum { n + CHECKSUM_SIZE } else { n };
                 if n < *z_blocksize {
                     stats.key_mem += k;
                     stats.val_mem += v;
@@ -536,6 +660,8 @@ where
                 vpos,
                 // configuration
                 z_blocksize,
+                compression,
+                checksum,
                 ..
             } => {
                 let adjust: u32 = {
@@ -557,17 +683,30 @@ where
                     let j: usize = convert_at!((adjust + offset))?;
                     zentries[i].re_encode_fpos(&mut leaf[j..], *vpos)?;
                 }
-                // update statistics
-                stats.padding += *z_blocksize - leaf.len();
-                stats.z_bytes += *z_blocksize;
+                stats.z_raw_bytes += *z_blocksize;
                 stats.v_bytes += blob.len();
-                // align blocks
-                leaf.resize(*z_blocksize, 0);
-
-                Ok((
-                    convert_at!(*z_blocksize)?, // full block
-                    convert_at!(blob.len())?,
-                ))
+                if let Compression::None = compression {
+                    let c = if *checksum { CHECKSUM_SIZE } else { 0 };
+                    stats.v_raw_bytes += blob.len();
+                    // update statistics
+                    stats.padding += *z_blocksize - leaf.len() - c;
+                    stats.z_bytes += *z_blocksize;
+                    // align blocks
+                    leaf.resize(*z_blocksize - c, 0);
+                    if *checksum {
+                        append_checksum(leaf);
+                    }
+
+                    Ok((
+                        convert_at!(*z_blocksize)?, // full block
+                        convert_at!(blob.len())?,
+                    ))
+                } else {
+                    stats.v_raw_bytes += vlog::to_raw_len(blob, *compression)?;
+                    let n = compress_block(leaf, *compression, *checksum)?;
+                    stats.z_bytes += n;
+                    Ok((convert_at!(n)?, convert_at!(blob.len())?))
+                }
             }
             ZBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
         }
@@ -609,7 +748,7 @@ where
     V: Clone + Diff + Serialize,
     <V as Diff>::D: Serialize,
 {
-    pub(crate) fn new_decode(block: Vec<u8>) -> Result<ZBlock<K, V>> {
+    pub(crate) fn new_decode(block: Arc<Vec<u8>>) -> Result<ZBlock<K, V>> {
         let count = u32::from_be_bytes(array_at!(block[..4])?);
         let adjust: usize = convert_at!((4 + (count * 4)))?;
         let offsets = &block[4..adjust] as *const [u8];
@@ -2,6 +2,7 @@ use rand::prelude::random;
 use std::{
     fs,
     io::{self, Read, Seek},
+    sync::Arc,
 };
 
 use super::*;
@@ -52,7 +53,7 @@ fn test_mblock_m() {
 
     let mb = {
         let (mut fd, fpos) = (util::open_file_r(&file).unwrap(), 0);
-        MBlock::<i32, i32>::new_decode(
+        MBlock::<i32, i32>::new_decode(Arc::new(
             read_file!(
                 //
                 &mut fd,
@@ -61,7 +62,7 @@ fn test_mblock_m() {
                 "reading mblock"
             )
             .unwrap(),
-        )
+        ))
         .unwrap()
     };
     assert_eq!(mb.len(), keys.len());
@@ -139,6 +140,47 @@ fn test_mblock_m() {
     }
 }
 
+#[test]
+fn test_mblock_compressed() {
+    let mut config: Config = Default::default();
+    config.set_compression(Compression::Snappy).unwrap();
+    let mut mb = MBlock::<i32, i32>::new_encode(config.clone());
+
+    let mut stats: Stats = Default::default();
+    let mut keys = vec![];
+    for i in 0..100000 {
+        let (key, fpos) = ((i + 1) * 64, (i * 4096) as u64);
+        match mb.insertm(&key, fpos) {
+            Ok(_) => keys.push((key, fpos)),
+            Err(Error::__MBlockOverflow(_n)) => break,
+            _ => unreachable!(),
+        }
+    }
+
+    let m_bytes = mb.finalize(&mut stats).unwrap();
+    let mblock = mb.buffer().unwrap();
+    assert_eq!(m_bytes as usize, mblock.len());
+    assert!(mblock.len() < config.m_blocksize);
+    assert_eq!(stats.m_bytes, mblock.len());
+    assert_eq!(stats.m_raw_bytes, config.m_blocksize);
+    assert_eq!(stats.padding, 0);
+
+    let n = to_payload_len(&mblock[..4]).unwrap();
+    assert_eq!(n + 4 + CHECKSUM_SIZE, mblock.len());
+    assert!(verify_checksum(&mblock[..4], &mblock[4..]).unwrap());
+    let payload = mblock[4..(n + 4)].to_vec();
+    let block = decompress_block(&mblock[..4], payload, Compression::Snappy).unwrap();
+    let mb = MBlock::<i32, i32>::new_decode(Arc::new(block)).unwrap();
+    assert_eq!(mb.len(), keys.len());
+    for (i, (key, fpos)) in keys.into_iter().enumerate() {
+        assert_eq!(mb.to_key(i).unwrap(), key);
+        match mb.to_entry(i).unwrap() {
+            MEntry::DecM { fpos: efpos, .. } => assert_eq!(efpos, fpos),
+            _ => unreachable!(),
+        }
+    }
+}
+
 #[test]
 fn test_mblock_z() {
     let config: Config = Default::default();
@@ -184,7 +226,7 @@ fn test_mblock_z() {
 
     let mb = {
         let (mut fd, fpos) = (util::open_file_r(&file).unwrap(), 0);
-        MBlock::<i32, i32>::new_decode(
+        MBlock::<i32, i32>::new_decode(Arc::new(
             read_file!(
                 //
                 &mut fd,
@@ -193,7 +235,7 @@ fn test_mblock_z() {
                 "reading mblock"
             )
             .unwrap(),
-        )
+        ))
         .unwrap()
     };
     assert_eq!(mb.len(), keys.len());
@@ -319,7 +361,7 @@ fn test_zblock1() {
         let (mut fd, fpos) = (util::open_file_r(&file).unwrap(), 0);
         ZBlock::<i32, i32>::new_decode(
             //
-            read_file!(&mut fd, fpos, zbs, "reading zblock").unwrap(),
+            Arc::new(read_file!(&mut fd, fpos, zbs, "reading zblock").unwrap()),
         )
         .unwrap()
     };
@@ -424,7 +466,7 @@ fn test_zblock2() {
 
     let zb = {
         let (mut fd, fpos) = (util::open_file_r(&file).unwrap(), 0);
-        ZBlock::<i32, i32>::new_decode(
+        ZBlock::<i32, i32>::new_decode(Arc::new(
             read_file!(
                 //
                 &mut fd,
@@ -433,7 +475,7 @@ fn test_zblock2() {
                 "reading zblock"
             )
             .unwrap(),
-        )
+        ))
         .unwrap()
     };
     assert_eq!(zb.len().unwrap(), entries.len());
@@ -546,7 +588,7 @@ fn test_zblock3() {
 
     let zb = {
         let (mut fd, fpos) = (util::open_file_r(&file).unwrap(), 0);
-        ZBlock::<i32, i32>::new_decode(
+        ZBlock::<i32, i32>::new_decode(Arc::new(
             read_file!(
                 //
                 &mut fd,
@@ -555,7 +597,7 @@ fn test_zblock3() {
                 "reading zblock"
             )
             .unwrap(),
-        )
+        ))
         .unwrap()
     };
     assert_eq!(zb.len().unwrap(), entries.len());
@@ -662,7 +704,7 @@ fn test_zblock4() {
 
     let zb = {
         let (mut fd, fpos) = (util::open_file_r(&file).unwrap(), 0);
-        ZBlock::<i32, i32>::new_decode(
+        ZBlock::<i32, i32>::new_decode(Arc::new(
             read_file!(
                 //
                 &mut fd,
@@ -671,7 +713,7 @@ fn test_zblock4() {
                 "reading zblock"
             )
             .unwrap(),
-        )
+        ))
         .unwrap()
     };
     assert_eq!(zb.len().unwrap(), entries.len());
@@ -0,0 +1,125 @@
+//! Module `robt_limiter` implement a token-bucket rate limiter for bytes
+//! flushed to disk by btree [Builder][crate::robt::Builder].
+//!
+//! A [RateLimiter] can be shared across several [Robt][crate::robt::Robt]
+//! instances, typically all the disk levels of a [Dgm][crate::dgm::Dgm]
+//! index, so that background commit and compaction do not starve
+//! foreground reads. The bucket holds upto one second worth of tokens,
+//! and writers borrow from future tokens, sleeping until the borrowed
+//! tokens are refilled. Rate can be adjusted at runtime.
+
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    thread, time,
+};
+
+use crate::{core::Result, error::Error};
+
+/// Shared, token-bucket, rate limiter capping bytes written per second.
+///
+/// Cloning a RateLimiter shall return a new handle to the same limiter.
+#[derive(Clone)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<InnerLimiter>>,
+}
+
+struct InnerLimiter {
+    rate: Option<usize>, // bytes per second, None for unlimited.
+    tokens: f64,         // negative when borrowed from the future.
+    refill: time::Instant,
+
+    n_bytes: usize,
+    throttled: time::Duration,
+}
+
+impl RateLimiter {
+    /// Create a new limiter allowing `rate` bytes per second, if `rate`
+    /// is None or ZERO writes are not throttled.
+    pub fn new(rate: Option<usize>) -> RateLimiter {
+        let rate = rate.filter(|rate| *rate > 0);
+        let inner = InnerLimiter {
+            rate,
+            tokens: rate.unwrap_or(0) as f64,
+            refill: time::Instant::now(),
+
+            n_bytes: 0,
+            throttled: Default::default(),
+        };
+        RateLimiter {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Adjust the rate, in bytes per second, for subsequent writes. If
+    /// `rate` is None or ZERO writes are not throttled.
+    pub fn set_rate(&self, rate: Option<usize>) -> Result<()> {
+        let mut inner = self.as_inner()?;
+        let rate = rate.filter(|rate| *rate > 0);
+        inner.tokens = match (inner.rate, rate) {
+            (Some(_), Some(rate)) => inner.tokens.min(rate as f64),
+            (_, rate) => rate.unwrap_or(0) as f64,
+        };
+        inner.rate = rate;
+        inner.refill = time::Instant::now();
+        Ok(())
+    }
+
+    /// Return the configured rate, in bytes per second.
+    pub fn to_rate(&self) -> Result<Option<usize>> {
+        Ok(self.as_inner()?.rate)
+    }
+
+    /// Return the total number of bytes accounted by this limiter.
+    pub fn to_bytes(&self) -> Result<usize> {
+        Ok(self.as_inner()?.n_bytes)
+    }
+
+    /// Return the total time writers were throttled by this limiter.
+    pub fn to_throttled(&self) -> Result<time::Duration> {
+        Ok(self.as_inner()?.throttled)
+    }
+
+    /// Account `n` bytes about to be written, block the caller until
+    /// the write is within the configured rate. Return the time the
+    /// caller was throttled.
+    pub fn acquire(&self, n: usize) -> Result<time::Duration> {
+        let wait = {
+            let mut inner = self.as_inner()?;
+            inner.n_bytes += n;
+
+            let rate = match inner.rate {
+                Some(rate) => rate as f64,
+                None => return Ok(Default::default()),
+            };
+            let now = time::Instant::now();
+            let elapsed = now.duration_since(inner.refill).as_secs_f64();
+            inner.refill = now;
+            inner.tokens = (inner.tokens + (elapsed * rate)).min(rate);
+            inner.tokens -= n as f64;
+
+            let wait = if inner.tokens < 0.0 {
+                time::Duration::from_secs_f64(-inner.tokens / rate)
+            } else {
+                Default::default()
+            };
+            inner.throttled += wait;
+            wait
+        };
+
+        if wait > time::Duration::default() {
+            thread::sleep(wait);
+        }
+        Ok(wait)
+    }
+
+    fn as_inner(&self) -> Result<MutexGuard<InnerLimiter>> {
+        match self.inner.lock() {
+            Ok(inner) => Ok(inner),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "robt_limiter_test.rs"]
+mod robt_limiter_test;
@@ -0,0 +1,50 @@
+use super::*;
+
+#[test]
+fn test_limiter_unlimited() {
+    for rate in vec![None, Some(0)].into_iter() {
+        let limiter = RateLimiter::new(rate);
+        assert_eq!(limiter.to_rate().unwrap(), None);
+        for _ in 0..100 {
+            let wait = limiter.acquire(1_000_000).unwrap();
+            assert_eq!(wait, time::Duration::default());
+        }
+        assert_eq!(limiter.to_bytes().unwrap(), 100_000_000);
+        assert_eq!(limiter.to_throttled().unwrap(), time::Duration::default());
+    }
+}
+
+#[test]
+fn test_limiter_rate() {
+    let limiter = RateLimiter::new(Some(10_000));
+    assert_eq!(limiter.to_rate().unwrap(), Some(10_000));
+
+    // one second worth of tokens are available to start with.
+    let wait = limiter.acquire(10_000).unwrap();
+    assert!(wait < time::Duration::from_millis(10), "{:?}", wait);
+
+    let start = time::Instant::now();
+    limiter.acquire(5_000).unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= time::Duration::from_millis(400), "{:?}", elapsed);
+
+    let throttled = limiter.to_throttled().unwrap();
+    assert!(
+        throttled >= time::Duration::from_millis(400),
+        "{:?}",
+        throttled
+    );
+    assert_eq!(limiter.to_bytes().unwrap(), 15_000);
+
+    // shared across clones, and adjustable at runtime.
+    let limiter1 = limiter.clone();
+    limiter1.set_rate(None).unwrap();
+    assert_eq!(limiter.to_rate().unwrap(), None);
+    let wait = limiter.acquire(1_000_000).unwrap();
+    assert_eq!(wait, time::Duration::default());
+
+    limiter1.set_rate(Some(1_000_000)).unwrap();
+    let wait = limiter.acquire(1_000_000).unwrap();
+    assert!(wait < time::Duration::from_millis(10), "{:?}", wait);
+    assert_eq!(limiter.to_bytes().unwrap(), 2_015_000);
+}
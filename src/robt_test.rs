@@ -3,11 +3,14 @@ use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
 
 use super::*;
 use crate::{
-    core::{self, Delta, Index, Reader, Writer},
+    bloom::{Bits10, BloomFilter},
+    core::{self, Bloom, Delta, Index, Reader, Writer},
     croaring::CRoaring,
+    hasher::Fnv1a,
     llrb::Llrb,
     nobitmap::NoBitmap,
     robt, scans,
+    xorfilter::Xor8,
 };
 
 #[test]
@@ -129,6 +132,8 @@ fn test_stats() {
         vlog_file: Some(vlog_file.to_os_string()),
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        compression: Compression::Snappy,
+        checksum: true,
 
         n_count: 1000000,
         n_deleted: 100,
@@ -139,8 +144,15 @@ fn test_stats() {
         z_bytes: 256000000,
         v_bytes: 2048000000,
         m_bytes: 256000000,
+        z_raw_bytes: 512000000,
+        m_raw_bytes: 512000000,
+        v_raw_bytes: 4096000000,
         mem_bitmap: 12310000,
         n_bitmap: 1000000,
+        hasher: "crc32".to_string(),
+        n_cache_hits: 1000000,
+        n_cache_misses: 1000000,
+        n_cache_evictions: 1000000,
         padding: 100000000,
         n_abytes: 0,
 
@@ -163,6 +175,10 @@ fn test_stats() {
         vlog_file: Some(vlog_file.to_os_string()),
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        compression: Compression::None,
+        checksum: true,
+        block_cache: None,
+        rate_limiter: None,
     };
     let stats1: Stats = cnf.into();
     let s = stats1.to_json();
@@ -181,6 +197,8 @@ fn test_stats_merge() {
         vlog_file: None,
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        compression: Compression::Snappy,
+        checksum: true,
 
         n_count: 1,
         n_deleted: 1,
@@ -191,8 +209,15 @@ fn test_stats_merge() {
         z_bytes: 1,
         v_bytes: 1,
         m_bytes: 1,
+        z_raw_bytes: 1,
+        m_raw_bytes: 1,
+        v_raw_bytes: 1,
         mem_bitmap: 1,
         n_bitmap: 1,
+        hasher: "crc32".to_string(),
+        n_cache_hits: 1,
+        n_cache_misses: 1,
+        n_cache_evictions: 1,
         padding: 1,
         n_abytes: 2,
 
@@ -208,6 +233,8 @@ fn test_stats_merge() {
         vlog_file: None,
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        compression: Compression::Snappy,
+        checksum: true,
 
         n_count: 2,
         n_deleted: 2,
@@ -218,8 +245,15 @@ fn test_stats_merge() {
         z_bytes: 2,
         v_bytes: 2,
         m_bytes: 2,
+        z_raw_bytes: 2,
+        m_raw_bytes: 2,
+        v_raw_bytes: 2,
         mem_bitmap: 2,
         n_bitmap: 2,
+        hasher: "fnv1a64".to_string(),
+        n_cache_hits: 2,
+        n_cache_misses: 2,
+        n_cache_evictions: 2,
         padding: 2,
         n_abytes: 2,
 
@@ -235,6 +269,8 @@ fn test_stats_merge() {
     assert_eq!(stats.vlog_file, None);
     assert_eq!(stats.value_in_vlog, true);
     assert_eq!(stats.flush_queue_size, Config::FLUSH_QUEUE_SIZE);
+    assert_eq!(stats.compression, Compression::Snappy);
+    assert_eq!(stats.checksum, true);
 
     assert_eq!(stats.n_count, 3);
     assert_eq!(stats.n_deleted, 3);
@@ -245,8 +281,15 @@ fn test_stats_merge() {
     assert_eq!(stats.z_bytes, 3);
     assert_eq!(stats.v_bytes, 3);
     assert_eq!(stats.m_bytes, 3);
+    assert_eq!(stats.z_raw_bytes, 3);
+    assert_eq!(stats.m_raw_bytes, 3);
+    assert_eq!(stats.v_raw_bytes, 3);
     assert_eq!(stats.mem_bitmap, 3);
     assert_eq!(stats.n_bitmap, 3);
+    assert_eq!(stats.hasher, "fnv1a64".to_string());
+    assert_eq!(stats.n_cache_hits, 2);
+    assert_eq!(stats.n_cache_misses, 2);
+    assert_eq!(stats.n_cache_evictions, 2);
     assert_eq!(stats.padding, 3);
     assert_eq!(stats.n_abytes, 4);
     assert_eq!(stats.build_time, 2);
@@ -314,6 +357,10 @@ fn test_config() {
         vlog_file: Some(vlog_file.to_os_string()),
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        compression: Compression::None,
+        checksum: true,
+        block_cache: None,
+        rate_limiter: None,
     };
 
     let stats: Stats = config1.clone().into();
@@ -325,6 +372,8 @@ fn test_config() {
     assert_eq!(config2.vlog_file, config1.vlog_file);
     assert_eq!(config2.value_in_vlog, config1.value_in_vlog);
     assert_eq!(config2.flush_queue_size, Config::FLUSH_QUEUE_SIZE);
+    assert_eq!(config2.compression, config1.compression);
+    assert_eq!(config2.checksum, config1.checksum);
 
     config1
         .set_blocksize(1024 * 8, 1024 * 32, 1024 * 64)
@@ -332,12 +381,14 @@ fn test_config() {
     config1.set_delta(None, false).unwrap();
     config1.set_value_log(None, false).unwrap();
     config1.set_flush_queue_size(1023).unwrap();
+    config1.set_compression(Compression::Snappy).unwrap();
     assert_eq!(config1.z_blocksize, 1024 * 8);
     assert_eq!(config1.v_blocksize, 1024 * 32);
     assert_eq!(config1.m_blocksize, 1024 * 64);
     assert_eq!(config1.delta_ok, false);
     assert_eq!(config1.value_in_vlog, false);
     assert_eq!(config1.flush_queue_size, 1023);
+    assert_eq!(config1.compression, Compression::Snappy);
 
     assert_eq!(Config::compute_root_block(4095), 4096);
     assert_eq!(Config::compute_root_block(4096), 4096);
@@ -618,6 +669,253 @@ fn test_purger() {
     assert_eq!(efiles.len(), 0);
 }
 
+#[test]
+fn test_robt_corrupted() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    let name = "test-robt-corrupted";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-corrupted");
+        dir.into_os_string()
+    };
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    random_llrb(10_000, 2_000, seed, &mut llrb);
+
+    let mut config: robt::Config = Default::default();
+    config.value_in_vlog = true;
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(llrb.iter().unwrap(), vec![]).unwrap();
+
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snap.verify().unwrap(), vec![]);
+    mem::drop(snap);
+
+    let flip_byte = |file: &ffi::OsStr, fpos: u64| {
+        let mut fd = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(file)
+            .unwrap();
+        let mut buf = [0_u8; 1];
+        fd.seek(io::SeekFrom::Start(fpos)).unwrap();
+        fd.read_exact(&mut buf).unwrap();
+        buf[0] = !buf[0];
+        fd.seek(io::SeekFrom::Start(fpos)).unwrap();
+        fd.write_all(&buf).unwrap();
+    };
+
+    // corrupt the first z-block in index file.
+    let index_file = Config::stitch_index_file(&dir, name);
+    flip_byte(&index_file, 100);
+
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snap.verify().unwrap(), vec![(index_file.clone(), 0)]);
+    match snap.first() {
+        Err(Error::Corrupted(file, 0)) => assert_eq!(file, index_file),
+        Err(err) => panic!("{:?}", err),
+        Ok(_) => panic!("expected corrupted index"),
+    }
+    mem::drop(snap);
+
+    // corrupt the last value entry in value-log file.
+    let vlog_file = Config::stitch_vlog_file(&dir, name);
+    let n = fs::metadata(&vlog_file).unwrap().len();
+    flip_byte(&vlog_file, n - 2);
+
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    let corrupted = snap.verify().unwrap();
+    assert_eq!(corrupted.len(), 2, "{:?}", corrupted);
+    assert_eq!(corrupted[0], (index_file, 0));
+    assert_eq!(corrupted[1].0, vlog_file);
+}
+
+#[test]
+fn test_robt_block_cache() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    let name = "test-robt-block-cache";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-block-cache");
+        dir.into_os_string()
+    };
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    random_llrb(10_000, 2_000, seed, &mut llrb);
+    let refs: Vec<Entry<i64, i64>> = llrb.iter().unwrap().map(|e| e.unwrap()).collect();
+
+    let mut config: robt::Config = Default::default();
+    config.set_compression(Compression::Snappy).unwrap();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(llrb.iter().unwrap(), vec![]).unwrap();
+
+    let cache = BlockCache::new(1024 * 1024);
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    snap.set_block_cache(cache.clone());
+    for _ in 0..2 {
+        for entry in refs.iter() {
+            let e = snap.get(entry.as_key()).unwrap();
+            check_entry1(&entry, &e);
+        }
+    }
+    let stats = snap.to_stats().unwrap();
+    assert!(stats.n_cache_hits > refs.len(), "{}", stats);
+    assert_eq!(stats.n_cache_evictions, 0, "{}", stats);
+    assert_eq!(
+        (stats.n_cache_hits, stats.n_cache_misses, stats.n_cache_evictions),
+        cache.to_counts().unwrap()
+    );
+    assert!(cache.len().unwrap() > 0);
+
+    // cache shall not hold more than its capacity.
+    let small = BlockCache::new(2 * Config::ZBLOCKSIZE);
+    snap.set_block_cache(small.clone());
+    for entry in refs.iter() {
+        let e = snap.get(entry.as_key()).unwrap();
+        check_entry1(&entry, &e);
+    }
+    assert!(small.footprint().unwrap() <= 2 * Config::ZBLOCKSIZE);
+    assert!(snap.to_stats().unwrap().n_cache_evictions > 0);
+
+    // purging the snapshot shall invalidate its cached blocks.
+    snap.purge().unwrap();
+    assert_eq!(small.len().unwrap(), 0);
+    assert_eq!(small.footprint().unwrap(), 0);
+}
+
+#[test]
+fn test_robt_rate_limiter() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-rate-limiter");
+        dir.into_os_string()
+    };
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    random_llrb(10_000, 2_000, seed, &mut llrb);
+
+    // unlimited, only account the flushed bytes.
+    let limiter = RateLimiter::new(None);
+    let mut config: robt::Config = Default::default();
+    config.set_rate_limiter(limiter.clone()).unwrap();
+    let name = "test-robt-rate-limiter1";
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(llrb.iter().unwrap(), vec![]).unwrap();
+    let n_bytes = limiter.to_bytes().unwrap();
+    assert!(n_bytes > 0);
+    assert_eq!(limiter.to_throttled().unwrap(), Default::default());
+
+    // same build at half the rate, shall be throttled for about a second.
+    let limiter = RateLimiter::new(Some(n_bytes / 2));
+    let mut config: robt::Config = Default::default();
+    config.set_rate_limiter(limiter.clone()).unwrap();
+    let name = "test-robt-rate-limiter2";
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(llrb.iter().unwrap(), vec![]).unwrap();
+    assert_eq!(limiter.to_bytes().unwrap(), n_bytes);
+    let throttled = limiter.to_throttled().unwrap();
+    assert!(
+        throttled > std::time::Duration::from_millis(500),
+        "{:?}",
+        throttled
+    );
+
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    for entry in llrb.iter().unwrap().map(|e| e.unwrap()) {
+        let e = snap.get(entry.as_key()).unwrap();
+        check_entry1(&entry, &e);
+    }
+}
+
+#[test]
+fn test_robt_bloom_filters() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    run_robt_bloom::<CRoaring>("test-robt-bloom-croaring", seed);
+    run_robt_bloom::<BloomFilter>("test-robt-bloom-filter", seed);
+    run_robt_bloom::<Xor8>("test-robt-bloom-xor8", seed);
+}
+
+#[test]
+fn test_robt_bloom_hasher() {
+    let name = "test-robt-bloom-hasher";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-bloom-hasher");
+        dir.into_os_string()
+    };
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    for key in 0..1000 {
+        llrb.set(key, key * 10).unwrap();
+    }
+
+    type FnvBloom = BloomFilter<Bits10, Fnv1a>;
+    let config: robt::Config = Default::default();
+    let b = Builder::<i64, i64, FnvBloom>::initial(&dir, name, config).unwrap();
+    b.build(llrb.iter().unwrap(), vec![]).unwrap();
+
+    // probing with a different hasher must fail.
+    match robt::Snapshot::<i64, i64, BloomFilter>::open(&dir, name) {
+        Err(Error::InvalidInput(_)) => (),
+        Err(err) => panic!("{:?}", err),
+        Ok(_) => panic!("expected hasher mismatch"),
+    }
+    // bitmap is not probed.
+    let snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snap.to_stats().unwrap().hasher, "fnv1a64".to_string());
+    mem::drop(snap);
+
+    let mut snap = robt::Snapshot::<i64, i64, FnvBloom>::open(&dir, name).unwrap();
+    for key in 0..1000 {
+        assert_eq!(snap.get(&key).unwrap().to_native_value(), Some(key * 10));
+    }
+    match snap.get(&1000) {
+        Err(Error::KeyNotFound) => (),
+        Err(err) => panic!("{:?}", err),
+        Ok(_) => panic!("expected key not found"),
+    }
+    snap.purge().unwrap();
+}
+
+fn run_robt_bloom<B: Bloom>(name: &str, seed: u128) {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-bloom");
+        dir.into_os_string()
+    };
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    random_llrb(10_000, 2_000, seed, &mut llrb);
+    let refs: Vec<Entry<i64, i64>> = llrb.iter().unwrap().map(|e| e.unwrap()).collect();
+
+    let config: robt::Config = Default::default();
+    let b = Builder::<i64, i64, B>::initial(&dir, name, config).unwrap();
+    b.build(llrb.iter().unwrap(), vec![]).unwrap();
+
+    let mut snap = robt::Snapshot::<i64, i64, B>::open(&dir, name).unwrap();
+    for entry in refs.iter() {
+        let e = snap.get(entry.as_key()).unwrap();
+        check_entry1(&entry, &e);
+    }
+    for key in 2_000..3_000_i64 {
+        match snap.get(&key) {
+            Err(Error::KeyNotFound) => (),
+            Err(err) => panic!("{:?}", err),
+            Ok(_) => panic!("expected key not found"),
+        }
+    }
+    snap.purge().unwrap();
+}
+
 #[test]
 fn test_build_scan() {
     let seed: u128 = random();
@@ -848,6 +1146,77 @@ fn test_compact_lsm_cutoff() {
     }
 }
 
+#[test]
+fn test_compact_range() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    let name = "test-compact-range";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let mut index = Robt::<i64, i64, NoBitmap>::new(
+        //
+        &dir, name, config,
+    )
+    .unwrap();
+
+    let (n_ops, key_max) = (30_000_i64, 20_000);
+    random_llrb(n_ops, key_max, seed, &mut mindex);
+    let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+    let scanner = core::CommitIter::new(mindex.as_mut(), within);
+    index.commit(scanner, std::convert::identity).unwrap();
+
+    let ref_entries: Vec<Entry<i64, i64>> = {
+        let mut r = index.to_reader().unwrap();
+        let iter = r.iter_with_versions().unwrap();
+        iter.map(|e| e.unwrap()).collect()
+    };
+
+    // purge tombstones within range, except for keys filtered out.
+    let range = (Bound::Included(5000), Bound::Excluded(10000));
+    let cutoff = Cutoff::new_tombstone(Bound::Unbounded);
+    let is_purged = |e: &Entry<i64, i64>| -> bool {
+        range.contains(&e.to_key()) && e.is_deleted() && e.to_key() % 3 != 0
+    };
+    let filter = |e: &Entry<i64, i64>| Ok(e.to_key() % 3 != 0);
+
+    let version = index.to_version().unwrap();
+    let n_purged = index.compact_range(range.clone(), cutoff, filter).unwrap();
+    let ref_purged = ref_entries.iter().filter(|e| is_purged(e)).count();
+    assert_eq!(n_purged, ref_purged);
+    assert_eq!(index.to_version().unwrap(), version + 1);
+
+    let entries: Vec<Entry<i64, i64>> = {
+        let mut r = index.to_reader().unwrap();
+        let iter = r.iter_with_versions().unwrap();
+        iter.map(|e| e.unwrap()).collect()
+    };
+    let ref_entries: Vec<Entry<i64, i64>> = {
+        let iter = ref_entries.into_iter();
+        iter.filter(|e| !is_purged(e)).collect()
+    };
+    assert_eq!(entries.len(), ref_entries.len());
+    for (e, re) in entries.iter().zip(ref_entries.iter()) {
+        assert_eq!(e.to_key(), re.to_key());
+        assert_eq!(e.to_seqno(), re.to_seqno(), "key:{}", e.to_key());
+        assert_eq!(e.is_deleted(), re.is_deleted(), "key:{}", e.to_key());
+        assert_eq!(e.as_deltas().len(), re.as_deltas().len());
+    }
+
+    // nothing to purge, index shall not be rewritten.
+    let n_purged = index.compact_range(range.clone(), cutoff, filter).unwrap();
+    assert_eq!(n_purged, 0);
+    assert_eq!(index.to_version().unwrap(), version + 1);
+}
+
 #[test]
 fn test_commit_scan() {
     let seed: u128 = random();
@@ -1320,6 +1689,9 @@ fn run_robt_llrb(name: &str, n_ops: u64, key_max: i64, repeat: usize, seed: u128
         let mut config: robt::Config = Default::default();
         config.delta_ok = lsm;
         config.value_in_vlog = rng.gen();
+        if rng.gen::<bool>() {
+            config.set_compression(Compression::Snappy).unwrap();
+        }
         let within = match rng.gen::<u64>() % 100 {
             0..=60 => (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
             61..=70 => (Bound::<u64>::Excluded(1), Bound::<u64>::Unbounded),
@@ -1334,8 +1706,16 @@ fn run_robt_llrb(name: &str, n_ops: u64, key_max: i64, repeat: usize, seed: u128
         let mmap = rng.gen::<bool>();
         println!(
             "seed:{} n_ops:{} lsm:{} sticky:{} delta:{} vlog:{}
-            within:{:?} mmap:{}",
-            seed, n_ops, lsm, sticky, config.delta_ok, config.value_in_vlog, within, mmap,
+            within:{:?} mmap:{} compression:{}",
+            seed,
+            n_ops,
+            lsm,
+            sticky,
+            config.delta_ok,
+            config.value_in_vlog,
+            within,
+            mmap,
+            config.compression,
         );
         let (mut llrb, refs) = llrb_to_refs1(llrb, within.clone(), &config);
         let n_deleted: usize = refs
@@ -1369,6 +1749,20 @@ fn run_robt_llrb(name: &str, n_ops: u64, key_max: i64, repeat: usize, seed: u128
         assert_eq!(stats.v_blocksize, config.v_blocksize);
         assert_eq!(stats.delta_ok, config.delta_ok);
         assert_eq!(stats.value_in_vlog, config.value_in_vlog);
+        assert_eq!(stats.compression, config.compression);
+        match config.compression {
+            Compression::None => {
+                assert_eq!(stats.z_raw_bytes, stats.z_bytes);
+                assert_eq!(stats.m_raw_bytes, stats.m_bytes);
+                assert_eq!(stats.v_raw_bytes, stats.v_bytes);
+            }
+            Compression::Snappy => {
+                assert!(stats.z_raw_bytes >= stats.z_bytes, "{}", stats);
+                assert!(stats.m_raw_bytes >= stats.m_bytes, "{}", stats);
+                assert!(stats.v_raw_bytes >= stats.v_bytes, "{}", stats);
+                assert_eq!(stats.padding, 0);
+            }
+        }
         if lsm || sticky {
             assert_eq!(stats.n_deleted, n_deleted);
         }
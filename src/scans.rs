@@ -66,6 +66,8 @@
 //!   Additionally, can be configured to filter entries within a `seqno` range.
 //! * [BitmappedScan], useful to build a bitmap index for all iterated keys.
 //! * [CompactScan], useful to filter entries that can be compacted in.
+//! * [RangeCompactScan], same as [CompactScan], but compact only entries
+//!   within a key-range.
 //!
 
 use std::{
@@ -499,6 +501,88 @@ where
     }
 }
 
+/// Iterator type, same as [CompactScan], but purge only those entries
+/// whose key fall within a range and are accepted by the filter. Rest of
+/// the entries are passed through as is.
+pub struct RangeCompactScan<K, V, I, F>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&Entry<K, V>) -> Result<bool>,
+{
+    iter: I,
+    range: (Bound<K>, Bound<K>),
+    cutoff: Cutoff,
+    filter: F,
+    n_purged: usize,
+}
+
+impl<K, V, I, F> RangeCompactScan<K, V, I, F>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&Entry<K, V>) -> Result<bool>,
+{
+    pub fn new(
+        iter: I,
+        range: (Bound<K>, Bound<K>),
+        cutoff: Cutoff,
+        filter: F,
+    ) -> RangeCompactScan<K, V, I, F> {
+        RangeCompactScan {
+            iter,
+            range,
+            cutoff,
+            filter,
+            n_purged: 0,
+        }
+    }
+
+    /// Return the number of entries purged so far.
+    pub fn to_purged(&self) -> usize {
+        self.n_purged
+    }
+
+    pub fn close(self) -> Result<I> {
+        Ok(self.iter)
+    }
+}
+
+impl<K, V, I, F> Iterator for RangeCompactScan<K, V, I, F>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&Entry<K, V>) -> Result<bool>,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.iter.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => break Some(Err(err)),
+                None => break None,
+            };
+            if !self.range.contains(entry.as_key()) || !entry.is_purgeable(self.cutoff) {
+                break Some(Ok(entry));
+            }
+            match (self.filter)(&entry) {
+                Ok(true) => (),
+                Ok(false) => break Some(Ok(entry)),
+                Err(err) => break Some(Err(err)),
+            }
+            self.n_purged += 1;
+            match entry.purge(self.cutoff) {
+                Some(entry) => break Some(Ok(entry)),
+                None => (),
+            }
+        }
+    }
+}
+
 /// Iterator type, to convert any iterator, or chain of iterators, into
 /// CommitIterator trait. It can be used within [CommitIter][core::CommitIter].
 ///
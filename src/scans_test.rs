@@ -510,6 +510,62 @@ fn test_compact_scan() {
     }
 }
 
+#[test]
+fn test_range_compact_scan() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let (n_ops, key_max) = (6_000_i64, 2_000);
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(n_ops, key_max, seed, &mut llrb);
+
+    for _i in 0..100 {
+        let cutoff = match rng.gen::<u8>() % 2 {
+            0 => Cutoff::new_lsm(Bound::Included(rng.gen::<u64>() % (n_ops as u64))),
+            _ => Cutoff::new_tombstone(Bound::Included(rng.gen::<u64>() % (n_ops as u64))),
+        };
+        let (low, high) = {
+            let low = rng.gen::<i64>().abs() % key_max;
+            let high = low + (rng.gen::<i64>().abs() % key_max);
+            (Bound::Included(low), Bound::Excluded(high))
+        };
+        let range = (low, high);
+        // filter out odd keys from purging.
+        let filter = |e: &Entry<i64, i64>| Ok(e.to_key() % 2 == 0);
+
+        let mut scanner =
+            RangeCompactScan::new(llrb.iter().unwrap(), range.clone(), cutoff, filter);
+        let es: Vec<Entry<i64, i64>> = scanner.by_ref().map(|e| e.unwrap()).collect();
+
+        let (ref_iter, mut iter) = (llrb.iter().unwrap(), es.iter());
+        let mut n_purged = 0;
+        for ref_entry in ref_iter {
+            let ref_entry = ref_entry.unwrap();
+            let key = ref_entry.to_key();
+            let ref_entry = if range.contains(&key) && key % 2 == 0 {
+                let n = ref_entry.as_deltas().len();
+                match ref_entry.purge(cutoff) {
+                    Some(ref_entry) if ref_entry.as_deltas().len() < n => {
+                        n_purged += 1;
+                        ref_entry
+                    }
+                    Some(ref_entry) => ref_entry,
+                    None => {
+                        n_purged += 1;
+                        continue;
+                    }
+                }
+            } else {
+                ref_entry
+            };
+            let entry = iter.next().unwrap();
+            check_node(entry, &ref_entry);
+        }
+        assert!(iter.next().is_none());
+        assert_eq!(scanner.to_purged(), n_purged, "cutoff:{:?}", cutoff);
+    }
+}
+
 fn check_node(entry: &Entry<i64, i64>, ref_entry: &Entry<i64, i64>) {
     //println!("check_node {} {}", entry.key(), ref_entry.key);
     assert_eq!(entry.to_key(), ref_entry.to_key(), "key");
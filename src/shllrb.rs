@@ -18,7 +18,7 @@ use std::{
 
 use crate::{
     core::{self, CommitIterator, Diff, Entry, Footprint, Index, IndexIter},
-    core::{Cutoff, Reader, Result, Validate, WriteIndexFactory, Writer},
+    core::{Cutoff, Reader, Result, Validate, WalWriter, WriteIndexFactory, Writer},
     error::Error,
     llrb::{Llrb, LlrbReader, LlrbWriter, Stats as LlrbStats},
     scans, thread as rt, util,
@@ -91,6 +91,7 @@ pub struct ShllrbFactory {
     max_shards: usize,
     max_entries: usize,
     interval: time::Duration,
+    mem_budget: Option<usize>,
 }
 
 /// Create a new factory with initial set of configuration.
@@ -111,6 +112,7 @@ pub fn shllrb_factory(lsm: bool, max_shards: usize) -> ShllrbFactory {
         max_shards,
         max_entries: DEFAULT_MAX_ENTRIES,
         interval: SHARD_INTERVAL,
+        mem_budget: None,
     }
 }
 
@@ -119,6 +121,9 @@ impl ShllrbFactory {
     /// If lsm is _true_, this will preserve the entire history of all write
     /// operations applied on the index-shard. _Default: false_.
     pub fn set_lsm(&mut self, lsm: bool) -> Result<&mut Self> {
+        if lsm && self.mem_budget.is_some() {
+            err_at!(InvalidInput, msg: format!("lsm with mem_budget"))?
+        }
         self.lsm = lsm;
         Ok(self)
     }
@@ -135,6 +140,9 @@ impl ShllrbFactory {
     /// for more details. For more detail refer Llrb::set_sticky().
     /// _Default: false_.
     pub fn set_sticky(&mut self, sticky: bool) -> Result<&mut Self> {
+        if sticky && self.mem_budget.is_some() {
+            err_at!(InvalidInput, msg: format!("sticky with mem_budget"))?
+        }
         self.sticky = sticky;
         Ok(self)
     }
@@ -154,6 +162,18 @@ impl ShllrbFactory {
         self.max_entries = max_entries;
         Ok(self)
     }
+
+    /// Set memory budget, in bytes, across all shards. Refer to
+    /// Config::set_mem_budget() for more details. Not applicable for
+    /// lsm and sticky mode. _Default: None_
+    pub fn set_mem_budget(&mut self, budget: usize) -> Result<&mut Self> {
+        if self.lsm || self.sticky {
+            let msg = format!("mem_budget for lsm:{} sticky:{}", self.lsm, self.sticky);
+            err_at!(InvalidInput, msg: msg)?
+        }
+        self.mem_budget = Some(budget);
+        Ok(self)
+    }
 }
 
 impl<K, V> WriteIndexFactory<K, V> for ShllrbFactory
@@ -169,8 +189,7 @@ where
     }
 
     fn new(&self, name: &str) -> Result<Self::I> {
-        let index = ShLlrb::<K, V>::new(name, self.clone().into());
-        Ok(index)
+        ShLlrb::<K, V>::new(name, self.clone().into())
     }
 }
 
@@ -185,6 +204,7 @@ pub struct Config {
     interval: time::Duration,
     max_shards: usize,
     max_entries: usize,
+    mem_budget: Option<usize>,
 }
 
 impl From<ShllrbFactory> for Config {
@@ -196,6 +216,7 @@ impl From<ShllrbFactory> for Config {
             interval: sf.interval,
             max_shards: sf.max_shards,
             max_entries: sf.max_entries,
+            mem_budget: sf.mem_budget,
         }
     }
 }
@@ -203,6 +224,9 @@ impl From<ShllrbFactory> for Config {
 impl Config {
     /// Configure Llrb for LSM, refer to Llrb:new_lsm() for more details.
     pub fn set_lsm(&mut self, lsm: bool) -> Result<&mut Self> {
+        if lsm && self.mem_budget.is_some() {
+            err_at!(InvalidInput, msg: format!("lsm with mem_budget"))?
+        }
         self.lsm = lsm;
         Ok(self)
     }
@@ -210,6 +234,9 @@ impl Config {
     /// Configure Llrb in sticky mode, refer to Llrb::set_sticky() for
     /// more details.
     pub fn set_sticky(&mut self, sticky: bool) -> Result<&mut Self> {
+        if sticky && self.mem_budget.is_some() {
+            err_at!(InvalidInput, msg: format!("sticky with mem_budget"))?
+        }
         self.sticky = sticky;
         Ok(self)
     }
@@ -237,6 +264,19 @@ impl Config {
         self.interval = interval;
         Ok(self)
     }
+
+    /// Configure memory budget, in bytes, across all shards. Budget is
+    /// divided equally between shards and re-distributed every time
+    /// shards are balanced. Not applicable for lsm and sticky mode,
+    /// refer to Llrb::set_mem_budget() for more details on eviction.
+    pub fn set_mem_budget(&mut self, budget: usize) -> Result<&mut Self> {
+        if self.lsm || self.sticky {
+            let msg = format!("mem_budget for lsm:{} sticky:{}", self.lsm, self.sticky);
+            err_at!(InvalidInput, msg: msg)?
+        }
+        self.mem_budget = Some(budget);
+        Ok(self)
+    }
 }
 
 /// Range partitioned index using [Llrb] shards.
@@ -258,6 +298,7 @@ where
     interval: time::Duration,
     max_shards: usize,
     max_entries: usize,
+    mem_budget: Option<usize>,
 
     auto_shard: Option<rt::Thread<String, Result<usize>, ()>>,
     snapshot: Arc<Mutex<Snapshot<K, V>>>,
@@ -507,7 +548,7 @@ where
     V: Clone + Diff + Footprint,
 {
     /// Create a new instance of range-partitioned index using Llrb tree.
-    pub fn new<S: AsRef<str>>(name: S, config: Config) -> Box<ShLlrb<K, V>>
+    pub fn new<S: AsRef<str>>(name: S, config: Config) -> Result<Box<ShLlrb<K, V>>>
     where
         K: 'static + Send,
         V: 'static + Send,
@@ -515,22 +556,30 @@ where
     {
         let name = name.as_ref().to_string();
 
-        let shard = {
+        let mut shards = {
             let shard_name: ShardName = (name.clone(), 0).into();
             let mut llrb = if config.lsm {
                 Llrb::new_lsm(shard_name.to_string())
             } else {
                 Llrb::new(shard_name.to_string())
             };
-            llrb.set_sticky(config.sticky).ok(); // can't be error
-            llrb.set_spinlatch(config.spin).ok(); // can't be error
-            Shard::new_active(llrb, Bound::Unbounded)
+            llrb.set_sticky(config.sticky)?;
+            llrb.set_spinlatch(config.spin)?;
+            vec![Shard::new_active(llrb, Bound::Unbounded)]
         };
+        // memory budget is divided equally between shards, refer to
+        // do_balance().
+        if let Some(budget) = config.mem_budget {
+            let n_shards = shards.len();
+            for shard in shards.iter_mut() {
+                shard.as_mut_index().set_mem_budget(budget / n_shards)?;
+            }
+        }
 
         let snapshot = Arc::new(Mutex::new(Snapshot {
             root_seqno: Arc::new(AtomicU64::new(0)),
             metadata: Default::default(),
-            shards: vec![shard],
+            shards,
             rdrefns: Default::default(),
             wtrefns: Default::default(),
         }));
@@ -543,6 +592,7 @@ where
             interval: config.interval,
             max_shards: config.max_shards,
             max_entries: config.max_entries,
+            mem_budget: config.mem_budget,
 
             auto_shard: None,
             snapshot,
@@ -565,7 +615,7 @@ where
             index.name, index.to_config_string()
         );
 
-        index
+        Ok(index)
     }
 }
 
@@ -645,8 +695,8 @@ where
                 self.interval.as_secs(),
             ),
             format!(
-                "sharded-llrb = {{ max_shards={}, max_entries={} }}",
-                self.max_shards, self.max_entries,
+                "sharded-llrb = {{ max_shards={}, max_entries={}, mem_budget={:?} }}",
+                self.max_shards, self.max_entries, self.mem_budget,
             ),
         ];
         ss.join("\n")
@@ -727,13 +777,20 @@ where
             let name = name.clone();
             Self::try_merging_shards(name, snapshot, config.clone())?
         };
-        let (snapshot, m) = {
+        let (mut snapshot, m) = {
             let name = name.clone();
             Self::try_spliting_shards(name, snapshot, config.clone())?
         };
 
         let new_count = snapshot.shards.len();
 
+        // re-distribute memory budget across the balanced shards.
+        if let Some(budget) = config.mem_budget {
+            for shard in snapshot.shards.iter_mut() {
+                shard.as_mut_index().set_mem_budget(budget / new_count)?;
+            }
+        }
+
         if old_count != new_count {
             debug!(
                 target: "shllrb",
@@ -1758,6 +1815,73 @@ where
     }
 }
 
+/// Create/Update/Delete operations on ShLlrb index, with seqno supplied
+/// by the caller, say a write-ahead-log.
+impl<K, V> WalWriter<K, V> for ShllrbWriter<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<Option<Entry<K, V>>> {
+        loop {
+            let mut writers = self.as_writers()?;
+            match Self::find(&key, writers.as_mut_slice())? {
+                (_, ShardWriter::Active { w, .. }) => {
+                    self.root_seqno.fetch_max(seqno, Ordering::SeqCst);
+                    break Ok(w.set_index(key, value, Some(seqno))?.1);
+                }
+                _ => {
+                    mem::drop(writers);
+                    thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+
+    fn set_cas_index(
+        &mut self,
+        key: K,
+        value: V,
+        cas: u64,
+        seqno: u64,
+    ) -> Result<Option<Entry<K, V>>> {
+        loop {
+            let mut writers = self.as_writers()?;
+            match Self::find(&key, writers.as_mut_slice())? {
+                (_, ShardWriter::Active { w, .. }) => {
+                    self.root_seqno.fetch_max(seqno, Ordering::SeqCst);
+                    break w.set_cas_index(key, value, cas, Some(seqno))?.1;
+                }
+                _ => {
+                    mem::drop(writers);
+                    thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+
+    fn delete_index<Q>(&mut self, key: &Q, seqno: u64) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let keyk: K = key.to_owned();
+        loop {
+            let mut writers = self.as_writers()?;
+            match Self::find(&keyk, writers.as_mut_slice())? {
+                (_, ShardWriter::Active { w, .. }) => {
+                    self.root_seqno.fetch_max(seqno, Ordering::SeqCst);
+                    break w.delete_index(key, Some(seqno))?.1;
+                }
+                _ => {
+                    mem::drop(writers);
+                    thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
 enum Shard<K, V>
 where
     K: Ord + Clone,
@@ -2396,7 +2520,7 @@ where
 
 fn thread_merge<K, V>(
     (c_off, curr): (usize, Shard<K, V>),
-    (o_off, mut other): (usize, Shard<K, V>),
+    (o_off, other): (usize, Shard<K, V>),
 ) -> Result<(usize, usize, Option<Bound<K>>, Shard<K, V>)>
 where
     K: Clone + Ord + Footprint,
@@ -2408,25 +2532,28 @@ where
     };
     let curr_name = curr_index.to_name()?;
     let curr_stats = curr_index.to_stats()?;
+    warn!(target: "shllrb", "{} merging shard\n{}", curr_name, curr_stats);
 
-    let iter = {
-        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
-        core::CommitIter::new(curr_index, within)
+    let (o_index, o_hk) = match other {
+        Shard::Active { index, high_key } => (index, high_key),
+        _ => unreachable!(),
     };
-    warn!(target: "shllrb", "{} commiting shard\n{}", curr_name, curr_stats);
-
-    let other_index = &mut other.as_mut_index();
-    let o_name = other_index.to_name();
-    let o_stats = other_index.to_stats()?;
+    let o_name = o_index.to_name()?;
+    let o_stats = o_index.to_stats()?;
 
-    match other_index.commit(iter, |meta| meta) {
-        Ok(()) if c_off > o_off => {
+    let res = if c_off > o_off {
+        Llrb::merge(*o_index, *curr_index, o_name.clone())
+    } else {
+        Llrb::merge(*curr_index, *o_index, o_name.clone())
+    };
+    match res {
+        Ok(index) if c_off > o_off => {
             debug!(target: "shllrb", "{} left merge\n{}", o_name, o_stats);
-            Ok((c_off, o_off, Some(curr_hk), other))
+            Ok((c_off, o_off, Some(curr_hk), Shard::new_active(index, o_hk)))
         }
-        Ok(()) => {
+        Ok(index) => {
             debug!(target: "shllrb", "{} right merge\n{}", o_name, o_stats);
-            Ok((c_off, o_off, None, other))
+            Ok((c_off, o_off, None, Shard::new_active(index, o_hk)))
         }
         Err(err) => {
             debug!(
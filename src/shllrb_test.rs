@@ -49,11 +49,60 @@ fn test_auto_shard_interval() {
 #[test]
 fn test_len() {
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i32, Empty>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i32, Empty>> = ShLlrb::new("test-shllrb", config).unwrap();
     assert_eq!(index.len().unwrap(), 0);
     assert!(index.validate().is_ok());
 }
 
+#[test]
+fn test_mem_budget() {
+    let budget = 1024 * 1024;
+    let mut config: Config = Default::default();
+    config.set_shard_config(4, 1000).unwrap();
+    config.set_interval(time::Duration::from_secs(100)).unwrap();
+    config.set_mem_budget(budget).unwrap();
+    assert!(config.clone().set_lsm(true).is_err());
+    assert!(config.clone().set_sticky(true).is_err());
+
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
+    let mut w = index.to_writer().unwrap();
+
+    // fits within the budget, before balancing.
+    let n = 4000;
+    for key in 0..n {
+        w.set(key, key * 10).unwrap();
+    }
+    assert_eq!(index.to_stats().unwrap().n_evicted, 0);
+
+    // budget is re-distributed across the balanced shards, and new
+    // entries are evicted from the last shard.
+    index.balance().unwrap();
+    assert!(index.lock_snapshot().unwrap().shards.len() > 1);
+
+    for key in n..(n * 4) {
+        w.set(key, key * 10).unwrap();
+    }
+    {
+        let snapshot = index.lock_snapshot().unwrap();
+        let n_shards = snapshot.shards.len();
+        for shard in snapshot.shards.iter() {
+            let footprint = shard.as_index().footprint().unwrap();
+            assert!(footprint <= ((budget / n_shards) as isize), "{}", footprint);
+        }
+    }
+    let stats = index.to_stats().unwrap();
+    assert!(stats.n_evicted > 0);
+    assert_eq!(stats.entries + stats.n_evicted, (n * 4) as usize);
+    assert!(index.footprint().unwrap() <= (budget as isize));
+
+    let mut config: Config = Default::default();
+    config.set_lsm(true).unwrap();
+    assert!(config.set_mem_budget(budget).is_err());
+    let mut config: Config = Default::default();
+    config.set_sticky(true).unwrap();
+    assert!(config.set_mem_budget(budget).is_err());
+}
+
 #[test]
 fn test_lsm_sticky() {
     let missing_key = 0x123456789;
@@ -79,7 +128,7 @@ fn test_lsm_sticky() {
 
     // without lsm
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut r = index.to_reader().unwrap();
     let key = populate(&mut index);
     match r.get(&key) {
@@ -96,7 +145,7 @@ fn test_lsm_sticky() {
     // without lsm, with sticky
     let mut config: Config = Default::default();
     config.set_sticky(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let key = populate(&mut index);
     let mut r = index.to_reader().unwrap();
     match r.get(&key) {
@@ -129,7 +178,7 @@ fn test_lsm_sticky() {
     // with lsm
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap().set_sticky(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let key = populate(&mut index);
     let mut r = index.to_reader().unwrap();
     match r.get(&key) {
@@ -212,7 +261,7 @@ fn test_n_deleted() {
 #[test]
 fn test_set() {
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(false /*lsm*/, 10);
 
     let mut w = index.to_writer().unwrap();
@@ -267,7 +316,7 @@ fn test_set() {
 fn test_cas_lsm() {
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(true /*lsm*/, 11);
 
     let mut w = index.to_writer().unwrap();
@@ -358,7 +407,7 @@ fn test_cas_lsm() {
 #[test]
 fn test_delete() {
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(false /*lsm*/, 11);
 
     let mut w = index.to_writer().unwrap();
@@ -424,7 +473,7 @@ fn test_delete() {
 #[test]
 fn test_iter() {
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(false /*lsm*/, 10);
 
     let mut w = index.to_writer().unwrap();
@@ -473,7 +522,7 @@ fn test_iter() {
 #[test]
 fn test_range() {
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(false /*lsm*/, 10);
 
     let mut w = index.to_writer().unwrap();
@@ -545,7 +594,7 @@ fn test_range() {
 fn test_crud() {
     let size = 1000;
     let config: Config = Default::default();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(false /*lsm*/, size);
 
     let mut w = index.to_writer().unwrap();
@@ -637,7 +686,7 @@ fn test_crud_lsm() {
     let size = 1000;
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
     let mut refns = RefNodes::new(true /*lsm*/, size as usize);
 
     let mut w = index.to_writer().unwrap();
@@ -734,9 +783,9 @@ fn test_crud_lsm() {
 fn test_commit1() {
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index1: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti1", config.clone());
-    let index2: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti2", config.clone());
-    let mut rindex: Box<ShLlrb<i64, i64>> = ShLlrb::new("tri", config.clone());
+    let mut index1: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti1", config.clone()).unwrap();
+    let index2: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti2", config.clone()).unwrap();
+    let mut rindex: Box<ShLlrb<i64, i64>> = ShLlrb::new("tri", config.clone()).unwrap();
 
     index1
         .commit(
@@ -751,9 +800,9 @@ fn test_commit1() {
 fn test_commit2() {
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index1: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti1", config.clone());
-    let mut index2: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti2", config.clone());
-    let mut rindex: Box<ShLlrb<i64, i64>> = ShLlrb::new("tri", config.clone());
+    let mut index1: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti1", config.clone()).unwrap();
+    let mut index2: Box<ShLlrb<i64, i64>> = ShLlrb::new("ti2", config.clone()).unwrap();
+    let mut rindex: Box<ShLlrb<i64, i64>> = ShLlrb::new("tri", config.clone()).unwrap();
 
     {
         let mut w = index2.to_writer().unwrap();
@@ -786,9 +835,9 @@ fn test_commit3() {
 
         let mut config: Config = Default::default();
         config.set_lsm(lsm).unwrap().set_sticky(sticky).unwrap();
-        let mut index1 = ShLlrb::<i64, i64>::new("ti1", config.clone());
-        let mut index2 = ShLlrb::<i64, i64>::new("ti2", config.clone());
-        let mut rindex = ShLlrb::<i64, i64>::new("tri", config.clone());
+        let mut index1 = ShLlrb::<i64, i64>::new("ti1", config.clone()).unwrap();
+        let mut index2 = ShLlrb::<i64, i64>::new("ti2", config.clone()).unwrap();
+        let mut rindex = ShLlrb::<i64, i64>::new("tri", config.clone()).unwrap();
 
         //  println!("index-config: lsm:{} sticky:{}", lsm, sticky);
 
@@ -913,8 +962,8 @@ fn test_compact() {
 
         let mut config: Config = Default::default();
         config.set_lsm(lsm).unwrap().set_sticky(sticky).unwrap();
-        let mut index = ShLlrb::<i64, i64>::new("ti1", config.clone());
-        let mut rindex = ShLlrb::<i64, i64>::new("tri", config.clone());
+        let mut index = ShLlrb::<i64, i64>::new("ti1", config.clone()).unwrap();
+        let mut rindex = ShLlrb::<i64, i64>::new("tri", config.clone()).unwrap();
 
         let mut w = index.to_writer().unwrap();
         let mut rw = rindex.to_writer().unwrap();
@@ -1034,7 +1083,7 @@ fn test_commit_iterator_scan() {
     let (n_ops, key_max) = (60_000_i64, 20_000);
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
 
     random_index(n_ops, key_max, seed, &mut index);
 
@@ -1081,7 +1130,7 @@ fn test_commit_iterator_scans() {
     let (n_ops, key_max) = (60_000_i64, 20_000);
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
 
     random_index(n_ops, key_max, seed, &mut index);
 
@@ -1137,7 +1186,7 @@ fn test_commit_iterator_range_scans() {
     let (n_ops, key_max) = (128_000_i64, 20_000);
     let mut config: Config = Default::default();
     config.set_lsm(true).unwrap();
-    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config).unwrap();
 
     random_index(n_ops, key_max, seed, &mut index);
 
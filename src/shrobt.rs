@@ -19,7 +19,7 @@ use std::{
 
 use crate::{
     core::{self, Bloom, CommitIter, CommitIterator, Diff, DiskIndexFactory},
-    core::{Cutoff, Validate},
+    core::{CompactRange, Cutoff, Validate},
     core::{Entry, Footprint, Index, IndexIter, Reader, Result, Serialize},
     error::Error,
     lsm,
@@ -249,7 +249,18 @@ where
     }
 
     fn open(&self, dir: &ffi::OsStr, name: &str) -> Result<ShRobt<K, V, B>> {
-        ShRobt::open(dir, name, self.mmap)
+        let mut index = ShRobt::open(dir, name, self.mmap)?;
+        if let Some(cache) = &self.config.block_cache {
+            index.set_block_cache(cache.clone())?;
+        }
+        if let Some(limiter) = &self.config.rate_limiter {
+            index.set_rate_limiter(limiter.clone())?;
+        }
+        Ok(index)
+    }
+
+    fn set_rate_limiter(&mut self, limiter: robt::RateLimiter) {
+        self.config.rate_limiter = Some(limiter);
     }
 }
 
@@ -523,6 +534,24 @@ where
         Ok(self.count)
     }
 
+    /// Cache decoded z-blocks and m-blocks, for all shards, in `cache`.
+    pub fn set_block_cache(&mut self, cache: robt::BlockCache) -> Result<()> {
+        let mut shards = self.as_shards()?;
+        for shard in shards.iter_mut() {
+            shard.as_mut_robt().set_block_cache(cache.clone());
+        }
+        Ok(())
+    }
+
+    /// Throttle disk writes, for all shards, using `limiter`.
+    pub fn set_rate_limiter(&mut self, limiter: robt::RateLimiter) -> Result<()> {
+        let mut shards = self.as_shards()?;
+        for shard in shards.iter_mut() {
+            shard.as_mut_robt().set_rate_limiter(limiter.clone());
+        }
+        Ok(())
+    }
+
     fn to_num_shards(&self) -> Result<usize> {
         Ok(self.as_shards()?.len())
     }
@@ -1097,6 +1126,60 @@ where
     Ok((off, count, index))
 }
 
+impl<K, V, B> CompactRange<K, V> for ShRobt<K, V, B>
+where
+    K: 'static + Send + Default + Clone + Ord + Hash + Footprint + Serialize,
+    V: 'static + Send + Default + Clone + Diff + Footprint + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+    B: 'static + Send + Sync + Bloom,
+{
+    fn compact_range<R, F>(&mut self, range: R, cutoff: Cutoff, mut filter: F) -> Result<usize>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&Entry<K, V>) -> Result<bool>,
+    {
+        // hold on to a reader snapshot of older version, refer to compact().
+        let r = {
+            let (state, _) = self.to_state()?;
+            match state.as_str() {
+                "build" => None,
+                "snapshot" => Some(self.to_reader()?),
+                _ => err_at!(Fatal, msg: format!("unreachable"))?,
+            }
+        };
+
+        let range = util::to_owned_range(&range);
+        let mut shards = self.as_shards()?;
+
+        // shards that don't have anything to purge, within the range,
+        // are not rewritten.
+        let mut n_purged = 0;
+        for shard in shards.iter_mut() {
+            let index = shard.as_mut_robt();
+            n_purged += index.compact_range(range.clone(), cutoff, &mut filter)?;
+        }
+
+        if n_purged > 0 {
+            let indexes: Vec<Robt<K, V, B>> = {
+                let iter = shards.drain(..).map(|shard| shard.into_robt());
+                iter.collect()
+            };
+            robts_to_shards(indexes)?
+                .drain(..)
+                .for_each(|shard| shards.push(shard));
+        }
+
+        mem::drop(r);
+
+        debug!(
+            target: "shrobt", "{:?}/{}, compact range purged {}",
+            self.dir, self.name, n_purged
+        );
+
+        Ok(n_purged)
+    }
+}
+
 impl<K, V, B> Footprint for ShRobt<K, V, B>
 where
     K: 'static + Send + Default + Clone + Ord + Hash + Footprint + Serialize,
@@ -1328,6 +1411,22 @@ where
         reader.snapshot.get(key)
     }
 
+    fn may_contain<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        // probe the bloom filter of the shard holding the key.
+        match self.readers.iter().find(|r| ShardReader::less(key, r)) {
+            Some(reader) => reader.snapshot.may_contain(key),
+            None => true,
+        }
+    }
+
+    fn has_bitmap(&self) -> bool {
+        !self.readers.is_empty() && self.readers.iter().all(|r| r.snapshot.has_bitmap())
+    }
+
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let mut iters = vec![];
         for reader in self.readers.iter_mut() {
@@ -0,0 +1,2217 @@
+//! Module ``skiplist`` implement an in-memory index type, using
+//! _Skip List_ data structure.
+//!
+//! Unlike [Llrb][crate::llrb::Llrb], which serializes all write operations
+//! behind a single latch, [Skiplist] allow concurrent writers. New nodes
+//! are linked into the list using compare-and-swap on their predecessor's
+//! forward pointer, and each node carry its own spin-latch to serialize
+//! mutations on the same key. Readers never block on writers operating on
+//! other keys.
+//!
+//! **[LSM mode]**: Skiplist index can support log-structured-merge while
+//! mutating the list. All mutations for the same key shall be preserved
+//! as delta versions until they are purged.
+//!
+//! Nodes are never unlinked while read/write handles are active. In
+//! non-lsm mode, deleting a key shall remove its entry, while the node
+//! continues to live in the list until the next [compact][Index::compact]
+//! that is invoked without any active handles.
+//!
+//! **Possible ways to configure Skiplist**:
+//!
+//! *spinlatch*, relevant only in multi-threaded context. Calling
+//! _set_spinlatch()_ with _true_ will have the calling thread to spin
+//! while waiting to acquire a node's latch. Calling it with _false_ will
+//! have the calling thread to yield to OS scheduler.
+//!
+//! *seqno*, application can set the beginning sequence number before
+//! ingesting data into the index.
+//!
+//! [skiplist]: https://en.wikipedia.org/wiki/Skip_list
+//! [LSM mode]: https://en.wikipedia.org/wiki/Log-structured_merge-tree
+//!
+
+use log::{debug, error, info, trace, warn};
+
+use std::{
+    borrow::Borrow,
+    cell::{Cell, UnsafeCell},
+    cmp::{self, Ord, Ordering},
+    convert::TryInto,
+    ffi, fmt,
+    hash::Hash,
+    marker, mem,
+    ops::{Bound, RangeBounds},
+    ptr, result,
+    sync::{
+        atomic::{AtomicIsize, AtomicPtr, AtomicU64, AtomicUsize, Ordering::SeqCst},
+        Arc,
+    },
+    thread, time,
+};
+
+use crate::{
+    core::{CommitIter, Replay, Result, ScanEntry, ScanIter, Value, WalWriter},
+    core::{CommitIterator, Cutoff, ToJson, ToStats, Validate, WriteIndexFactory, Writer},
+    core::{Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
+    error::Error,
+    scans,
+    spinlock::RWSpinlock,
+    util,
+};
+
+/// Maximum height of a node's tower.
+const MAX_LEVEL: usize = 32;
+// initial backoff, and the time limit, while waiting for read/write
+// handles to go away, before dropping the index.
+const DROP_BACKOFF: time::Duration = time::Duration::from_millis(1);
+const DROP_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// Factory type, to construct preconfigured [Skiplist] index instances.
+///
+/// By implementing `WriteIndexFactory` trait this can be
+/// used with other, more sophisticated, index implementations.
+pub struct SkiplistFactory {
+    lsm: bool,
+    spin: bool,
+}
+
+/// Create a new [SkiplistFactory] with initial set of configuration.
+///
+/// To know more about other configurations supported by the
+/// SkiplistFactory refer to its ``set_``, methods.
+///
+/// * *lsm*, spawn Skiplist instances in lsm mode, this will preserve the
+///   entire history of all write operations applied on the index.
+pub fn skiplist_factory(lsm: bool) -> SkiplistFactory {
+    SkiplistFactory { lsm, spin: true }
+}
+
+/// Configuration methods.
+impl SkiplistFactory {
+    /// If lsm is _true_, this will preserve the entire history of all write
+    /// operations applied on the index. _Default: false_.
+    pub fn set_lsm(&mut self, lsm: bool) -> Result<&mut Self> {
+        self.lsm = lsm;
+        Ok(self)
+    }
+
+    /// If spin is _true_, calling thread will spin while waiting for the
+    /// latch, otherwise, calling thead will be yielded to OS scheduler.
+    /// _Default: true_.
+    pub fn set_spinlatch(&mut self, spin: bool) -> Result<&mut Self> {
+        self.spin = spin;
+        Ok(self)
+    }
+
+    fn to_config_string(&self) -> String {
+        format!("skiplist = {{ lsm = {}, spin = {} }}", self.lsm, self.spin)
+    }
+}
+
+impl<K, V> WriteIndexFactory<K, V> for SkiplistFactory
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    type I = Box<Skiplist<K, V>>;
+
+    fn to_type(&self) -> String {
+        "skiplist".to_string()
+    }
+
+    fn new(&self, name: &str) -> Result<Self::I> {
+        let mut index = if self.lsm {
+            Skiplist::new_lsm(name)
+        } else {
+            Skiplist::new(name)
+        };
+        index.set_spinlatch(self.spin)?;
+
+        debug!(
+            target: "skipfc",
+            "{}, new skiplist with config {}", name, self.to_config_string()
+        );
+
+        Ok(index)
+    }
+}
+
+// Node in skiplist. Key is immutable once the node is linked into the
+// list, entry is guarded by the node's latch.
+struct Node<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    key: K,
+    latch: RWSpinlock,
+    // None, if entry is not yet populated or removed in non-lsm mode.
+    entry: UnsafeCell<Option<Entry<K, V>>>,
+    // forward pointers, one for each level in this node's tower.
+    next: Box<[AtomicPtr<Node<K, V>>]>,
+}
+
+impl<K, V> Node<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn new(key: K, height: usize) -> Box<Node<K, V>> {
+        let next: Vec<AtomicPtr<Node<K, V>>> = (0..height)
+            .map(|_| AtomicPtr::new(ptr::null_mut()))
+            .collect();
+        Box::new(Node {
+            key,
+            latch: RWSpinlock::new(),
+            entry: UnsafeCell::new(None),
+            next: next.into_boxed_slice(),
+        })
+    }
+
+    // memory overhead for a node, excluding its entry.
+    fn overhead(height: usize) -> Result<isize> {
+        let size = mem::size_of::<Node<K, V>>();
+        let size = size + (height * mem::size_of::<AtomicPtr<Node<K, V>>>());
+        let size: isize = convert_at!(size)?;
+        Ok(size)
+    }
+
+    #[inline]
+    fn as_next(&self, level: usize) -> Option<&Node<K, V>> {
+        as_node(&self.next[level])
+    }
+
+    // clone the entry under read latch.
+    fn to_entry(&self, spin: bool) -> Option<Entry<K, V>> {
+        let _latch = self.latch.acquire_read(spin);
+        unsafe { (*self.entry.get()).clone() }
+    }
+
+    // caller should hold the write latch.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn as_mut_entry(&self) -> &mut Option<Entry<K, V>> {
+        self.entry.get().as_mut().unwrap()
+    }
+}
+
+#[inline]
+fn as_node<'a, K, V>(link: &AtomicPtr<Node<K, V>>) -> Option<&'a Node<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    // nodes are freed only when there are no active handles.
+    unsafe { link.load(SeqCst).as_ref() }
+}
+
+thread_local! {
+    // per-thread state for picking a random tower height.
+    static HEIGHT_SEED: Cell<u64> = Cell::new(new_height_seed());
+}
+
+fn new_height_seed() -> u64 {
+    static SEEDS: AtomicU64 = AtomicU64::new(0);
+    const GOLDEN: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    // xorshift state must be non-zero.
+    SEEDS.fetch_add(GOLDEN, SeqCst).wrapping_add(GOLDEN) | 1
+}
+
+// pick a random tower height, with a branching factor of 4.
+fn random_height() -> usize {
+    HEIGHT_SEED.with(|seed| {
+        let mut x = seed.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        seed.set(x);
+
+        let height = 1 + ((x.trailing_zeros() as usize) / 2);
+        cmp::min(height, MAX_LEVEL)
+    })
+}
+
+// Predecessor towers and successor nodes, at every level, for a key.
+struct Path<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    preds: Vec<&'a [AtomicPtr<Node<K, V>>]>,
+    succs: Vec<*mut Node<K, V>>,
+}
+
+/// Index type, in-memory, using [skip list][skiplist] that allow
+/// concurrent writers.
+///
+/// [skiplist]: https://en.wikipedia.org/wiki/Skip_list
+pub struct Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    name: String,
+    lsm: bool,
+    spin: bool,
+
+    head: Box<[AtomicPtr<Node<K, V>>]>,
+    seqno: AtomicU64,
+    metadata: Vec<u8>,
+    n_count: AtomicUsize,   // number of entries in index.
+    n_deleted: AtomicUsize, // number of entries marked deleted.
+    n_removed: AtomicUsize, // number of linked nodes without an entry.
+    key_footprint: AtomicIsize,
+    tree_footprint: AtomicIsize,
+    readers: Arc<u32>,
+    writers: Arc<u32>,
+
+    phantom_key: marker::PhantomData<K>,
+    phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V> Drop for Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn drop(&mut self) {
+        let (start, mut backoff) = (time::Instant::now(), DROP_BACKOFF);
+        loop {
+            let n = self.multi_rw();
+            if n == 0 {
+                break;
+            } else if start.elapsed() > DROP_TIMEOUT {
+                panic!("{}, dropped before read/write handles {}", self.name, n);
+            }
+            error!(
+                target: "skipl ",
+                "{}, dropped before read/write handles {}", self.name, n
+            );
+            thread::sleep(backoff);
+            backoff = cmp::min(backoff * 2, DROP_TIMEOUT / 10);
+        }
+
+        debug!(target: "skipl ", "{}, dropped", self.name);
+
+        let mut next = self.head[0].load(SeqCst);
+        while !next.is_null() {
+            let node = unsafe { Box::from_raw(next) };
+            next = node.next[0].load(SeqCst);
+        }
+    }
+}
+
+/// Different ways to construct a new Skiplist index.
+impl<K, V> Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Create an empty Skiplist index, identified by `name`.
+    /// Applications can choose unique names.
+    pub fn new<S: AsRef<str>>(name: S) -> Box<Skiplist<K, V>> {
+        Self::new_index(name.as_ref(), false)
+    }
+
+    /// Create a new Skiplist index in lsm mode. In lsm mode, mutations
+    /// are added as log for each key, instead of over-writing previous
+    /// mutation.
+    pub fn new_lsm<S: AsRef<str>>(name: S) -> Box<Skiplist<K, V>> {
+        Self::new_index(name.as_ref(), true)
+    }
+
+    fn new_index(name: &str, lsm: bool) -> Box<Skiplist<K, V>> {
+        let head: Vec<AtomicPtr<Node<K, V>>> = (0..MAX_LEVEL)
+            .map(|_| AtomicPtr::new(ptr::null_mut()))
+            .collect();
+
+        Box::new(Skiplist {
+            name: name.to_string(),
+            lsm,
+            spin: true,
+
+            head: head.into_boxed_slice(),
+            seqno: Default::default(),
+            metadata: Default::default(),
+            n_count: Default::default(),
+            n_deleted: Default::default(),
+            n_removed: Default::default(),
+            key_footprint: Default::default(),
+            tree_footprint: Default::default(),
+            readers: Arc::new(0xC0FFEE),
+            writers: Arc::new(0xC0FFEE),
+
+            phantom_key: marker::PhantomData,
+            phantom_val: marker::PhantomData,
+        })
+    }
+
+    /// Configure behaviour of spin-latch. If `spin` is true, calling
+    /// thread shall spin until a latch is acquired or released, if false
+    /// calling thread will yield to scheduler. Call this api, before
+    /// creating reader and/or writer handles.
+    pub fn set_spinlatch(&mut self, spin: bool) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            self.spin = spin;
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+}
+
+/// Maintanence API.
+impl<K, V> Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Return whether this index support lsm mode.
+    #[inline]
+    pub fn is_lsm(&self) -> bool {
+        self.lsm
+    }
+
+    pub fn is_spin(&self) -> bool {
+        self.spin
+    }
+
+    /// Return number of entries in this index.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n_count.load(SeqCst)
+    }
+
+    /// Return whether this index is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Identify this index. Applications can choose unique names while
+    /// creating Skiplist indices.
+    #[inline]
+    pub fn to_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Return quickly with basic statisics, only entries() method is valid
+    /// with this statisics.
+    pub fn to_stats(&self) -> Result<Stats> {
+        let mut stats = Stats::new(&self.name);
+        stats.entries = self.len();
+        stats.n_deleted = self.n_deleted.load(SeqCst);
+        stats.n_removed = self.n_removed.load(SeqCst);
+        stats.node_size = mem::size_of::<Node<K, V>>();
+        stats.key_footprint = self.key_footprint.load(SeqCst);
+        stats.tree_footprint = self.tree_footprint.load(SeqCst);
+        Ok(stats)
+    }
+
+    fn multi_rw(&self) -> usize {
+        Arc::strong_count(&self.readers) + Arc::strong_count(&self.writers) - 2
+    }
+}
+
+/// Search operations on Skiplist index.
+impl<K, V> Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn new_path(&self) -> Path<K, V> {
+        Path {
+            preds: vec![&self.head[..]; MAX_LEVEL],
+            succs: vec![ptr::null_mut(); MAX_LEVEL],
+        }
+    }
+
+    // Locate `key` and populate `path` with predecessor and successor at
+    // every level. Return the node, if key is already in the list.
+    fn find<'a, Q>(&'a self, key: &Q, path: &mut Path<'a, K, V>) -> Option<&'a Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut tower: &'a [AtomicPtr<Node<K, V>>] = &self.head;
+        let mut found = None;
+        for level in (0..MAX_LEVEL).rev() {
+            let succ = loop {
+                let next = tower[level].load(SeqCst);
+                match unsafe { next.as_ref() } {
+                    Some(node) => match node.key.borrow().cmp(key) {
+                        Ordering::Less => tower = &node.next,
+                        Ordering::Equal => {
+                            found = Some(node);
+                            break next;
+                        }
+                        Ordering::Greater => break next,
+                    },
+                    None => break next,
+                }
+            };
+            path.preds[level] = tower;
+            path.succs[level] = succ;
+        }
+        found
+    }
+
+    // Return the node for `key`, if present.
+    fn find_node<'a, Q>(&'a self, key: &Q) -> Option<&'a Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut tower: &'a [AtomicPtr<Node<K, V>>] = &self.head;
+        for level in (0..MAX_LEVEL).rev() {
+            while let Some(node) = as_node(&tower[level]) {
+                match node.key.borrow().cmp(key) {
+                    Ordering::Less => tower = &node.next,
+                    Ordering::Equal => return Some(node),
+                    Ordering::Greater => break,
+                }
+            }
+        }
+        None
+    }
+
+    // Return the first node that fall after the `low` bound.
+    fn find_first<'a, Q>(&'a self, low: Bound<&Q>) -> Option<&'a Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut tower: &'a [AtomicPtr<Node<K, V>>] = &self.head;
+        for level in (0..MAX_LEVEL).rev() {
+            while let Some(node) = as_node(&tower[level]) {
+                let before = match low {
+                    Bound::Included(low) => node.key.borrow().cmp(low) == Ordering::Less,
+                    Bound::Excluded(low) => node.key.borrow().cmp(low) != Ordering::Greater,
+                    Bound::Unbounded => false,
+                };
+                if before {
+                    tower = &node.next
+                } else {
+                    break;
+                }
+            }
+        }
+        as_node(&tower[0])
+    }
+
+    // Return the last node that fall before the `high` bound.
+    fn find_last<'a, Q>(&'a self, high: Bound<&Q>) -> Option<&'a Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut tower: &'a [AtomicPtr<Node<K, V>>] = &self.head;
+        let mut last = None;
+        for level in (0..MAX_LEVEL).rev() {
+            while let Some(node) = as_node(&tower[level]) {
+                let before = match high {
+                    Bound::Included(high) => node.key.borrow().cmp(high) != Ordering::Greater,
+                    Bound::Excluded(high) => node.key.borrow().cmp(high) == Ordering::Less,
+                    Bound::Unbounded => true,
+                };
+                if before {
+                    tower = &node.next;
+                    last = Some(node);
+                } else {
+                    break;
+                }
+            }
+        }
+        last
+    }
+
+    // Return upto `n_shards - 1` keys, evenly spaced, that can split the
+    // index into `n_shards`.
+    fn to_shard_keys(&self, n_shards: usize) -> Vec<K> {
+        for level in (0..MAX_LEVEL).rev() {
+            let mut keys = vec![];
+            let mut node = as_node(&self.head[level]);
+            while let Some(nref) = node {
+                keys.push(&nref.key);
+                node = nref.as_next(level);
+            }
+            if keys.len() >= n_shards || level == 0 {
+                let n = keys.len();
+                return (1..n_shards)
+                    .filter_map(|i| keys.get((i * n) / n_shards))
+                    .map(|key| (*key).clone())
+                    .collect();
+            }
+        }
+        vec![]
+    }
+}
+
+// Write operations, applied on a node's entry while holding its latch.
+enum WriteOp<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    Set(V),
+    SetCAS(V, u64),
+    Delete,
+    Merge(Entry<K, V>),
+}
+
+impl<K, V> WriteOp<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    // whether this operation shall create an entry for a missing key.
+    fn is_insert(&self, lsm: bool) -> bool {
+        match self {
+            WriteOp::Set(_) | WriteOp::Merge(_) => true,
+            WriteOp::SetCAS(_, cas) => *cas == 0,
+            WriteOp::Delete => lsm,
+        }
+    }
+}
+
+/// Create/Update/Delete operations on Skiplist index. Unlike Llrb, these
+/// operations can be invoked concurrently on the same index.
+impl<K, V> Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Set {key, value} in index. Return the seqno (index) for this
+    /// mutation and older entry if present.
+    ///
+    /// *LSM mode*: Add a new version for the key, perserving the old value.
+    pub fn set_index(
+        &self,
+        key: K,
+        value: V,
+        seqno: Option<u64>,
+    ) -> Result<(u64, Option<Entry<K, V>>)> {
+        let (seqno, res) = self.do_write(&key, seqno, WriteOp::Set(value))?;
+        Ok((seqno, res?))
+    }
+
+    /// Similar to set, but succeeds only when CAS matches with entry's
+    /// last `seqno`. To create a fresh entry, pass ``cas`` as ZERO.
+    /// Return the seqno (index) for this mutation and older entry
+    /// if present.
+    ///
+    /// *LSM mode*: Add a new version for the key, perserving the old value.
+    pub fn set_cas_index(
+        &self,
+        key: K,
+        value: V,
+        cas: u64,
+        seqno: Option<u64>,
+    ) -> Result<(u64, Result<Option<Entry<K, V>>>)> {
+        self.do_write(&key, seqno, WriteOp::SetCAS(value, cas))
+    }
+
+    /// Delete key from index. Return the seqno (index) for this mutation
+    /// and entry if present.
+    ///
+    /// *LSM mode*: Mark the entry as deleted along with seqno at which it
+    /// deleted.
+    pub fn delete_index<Q>(
+        &self,
+        key: &Q,
+        seqno: Option<u64>, // seqno for this delete
+    ) -> Result<(u64, Result<Option<Entry<K, V>>>)>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        self.do_write(key, seqno, WriteOp::Delete)
+    }
+
+    // Merge entry, along with its versions, into index.
+    fn set_index_entry(&self, entry: Entry<K, V>) -> Result<Option<Entry<K, V>>> {
+        let key = entry.to_key();
+        let seqno = Some(entry.to_seqno());
+        let (_seqno, res) = self.do_write(&key, seqno, WriteOp::Merge(entry))?;
+        res
+    }
+
+    fn do_write<Q>(
+        &self,
+        key: &Q,
+        seqno: Option<u64>,
+        op: WriteOp<K, V>,
+    ) -> Result<(u64, Result<Option<Entry<K, V>>>)>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let mut path = self.new_path();
+        if let Some(node) = self.find(key, &mut path) {
+            return self.write_node(node, seqno, op);
+        }
+
+        if !op.is_insert(self.lsm) {
+            // key is missing and there is nothing to insert.
+            let seqno = self.to_next_seqno(seqno);
+            let res = self.apply(&key.to_owned(), &mut None, seqno, op)?;
+            return Ok((seqno, res));
+        }
+
+        let height = random_height();
+        let nptr = Box::into_raw(Node::new(key.to_owned(), height));
+        let node = unsafe { nptr.as_ref().unwrap() };
+        // hold the latch until the entry is populated, concurrent readers
+        // and writers on this key shall wait.
+        let latch = node.latch.acquire_write(self.spin);
+        loop {
+            let succ = path.succs[0];
+            node.next[0].store(succ, SeqCst);
+            if path.preds[0][0]
+                .compare_exchange(succ, nptr, SeqCst, SeqCst)
+                .is_ok()
+            {
+                break;
+            }
+            // list has changed underneath, locate the key again.
+            if let Some(other) = self.find(key, &mut path) {
+                // lost the race with another writer inserting the same key.
+                mem::drop(latch);
+                mem::drop(unsafe { Box::from_raw(nptr) });
+                return self.write_node(other, seqno, op);
+            }
+        }
+        self.n_removed.fetch_add(1, SeqCst);
+        self.tree_footprint
+            .fetch_add(Node::<K, V>::overhead(height)?, SeqCst);
+
+        let seqno = self.to_next_seqno(seqno);
+        let res = {
+            let entry = unsafe { node.as_mut_entry() };
+            self.apply(&node.key, entry, seqno, op)?
+        };
+        mem::drop(latch);
+
+        // upper levels are only short-cuts for lookup.
+        for level in 1..height {
+            loop {
+                let succ = path.succs[level];
+                node.next[level].store(succ, SeqCst);
+                if path.preds[level][level]
+                    .compare_exchange(succ, nptr, SeqCst, SeqCst)
+                    .is_ok()
+                {
+                    break;
+                }
+                self.find(key, &mut path);
+            }
+        }
+
+        Ok((seqno, res))
+    }
+
+    fn write_node(
+        &self,
+        node: &Node<K, V>,
+        seqno: Option<u64>,
+        op: WriteOp<K, V>,
+    ) -> Result<(u64, Result<Option<Entry<K, V>>>)> {
+        let _latch = node.latch.acquire_write(self.spin);
+        // seqno is assigned under the latch, so that versions for the
+        // same key are in seqno order.
+        let seqno = self.to_next_seqno(seqno);
+        let entry = unsafe { node.as_mut_entry() };
+        let res = self.apply(&node.key, entry, seqno, op)?;
+        Ok((seqno, res))
+    }
+
+    fn to_next_seqno(&self, seqno: Option<u64>) -> u64 {
+        match seqno {
+            Some(seqno) => loop {
+                let current = self.seqno.load(SeqCst);
+                if current >= seqno {
+                    break seqno;
+                }
+                if self
+                    .seqno
+                    .compare_exchange_weak(current, seqno, SeqCst, SeqCst)
+                    .is_ok()
+                {
+                    break seqno;
+                }
+            },
+            None => self.seqno.fetch_add(1, SeqCst) + 1,
+        }
+    }
+
+    // Apply write operation on node's `entry`, caller should hold the
+    // node's write latch.
+    fn apply(
+        &self,
+        key: &K,
+        entry: &mut Option<Entry<K, V>>,
+        seqno: u64,
+        op: WriteOp<K, V>,
+    ) -> Result<Result<Option<Entry<K, V>>>> {
+        let lsm = self.lsm;
+        match (entry.take(), op) {
+            (None, WriteOp::Set(value)) | (None, WriteOp::SetCAS(value, 0)) => {
+                let nentry = Entry::new(key.clone(), Value::new_upsert_value(value, seqno));
+                self.on_insert(&nentry)?;
+                *entry = Some(nentry);
+                Ok(Ok(None))
+            }
+            (None, WriteOp::SetCAS(_, _)) => Ok(Err(Error::InvalidCAS(0))),
+            (None, WriteOp::Delete) if lsm => {
+                let nentry = Entry::new(key.clone(), Value::new_delete(seqno));
+                self.on_insert(&nentry)?;
+                *entry = Some(nentry);
+                Ok(Ok(None))
+            }
+            (None, WriteOp::Delete) => Ok(Ok(None)),
+            (None, WriteOp::Merge(nentry)) => {
+                self.on_insert(&nentry)?;
+                *entry = Some(nentry);
+                Ok(Ok(None))
+            }
+            (Some(old), WriteOp::SetCAS(_, cas)) if is_invalid_cas(&old, cas) => {
+                let seqno = old.to_seqno();
+                *entry = Some(old);
+                Ok(Err(Error::InvalidCAS(seqno)))
+            }
+            (Some(old), WriteOp::Set(value)) | (Some(old), WriteOp::SetCAS(value, _)) => {
+                let mut new = old.clone();
+                let nentry = Entry::new(key.clone(), Value::new_upsert_value(value, seqno));
+                let size = new.prepend_version(nentry, lsm)?;
+                self.tree_footprint.fetch_add(size, SeqCst);
+                if old.is_deleted() {
+                    self.n_deleted.fetch_sub(1, SeqCst);
+                }
+                *entry = Some(new);
+                Ok(Ok(Some(old)))
+            }
+            (Some(old), WriteOp::Delete) if lsm => {
+                let mut new = old.clone();
+                let size = new.delete(seqno)?;
+                self.tree_footprint.fetch_add(size, SeqCst);
+                if !old.is_deleted() {
+                    self.n_deleted.fetch_add(1, SeqCst);
+                }
+                *entry = Some(new);
+                Ok(Ok(Some(old)))
+            }
+            (Some(old), WriteOp::Delete) => {
+                // in non-lsm mode remove the entry, node stays in the list.
+                self.on_remove(&old)?;
+                Ok(Ok(Some(old)))
+            }
+            (Some(old), WriteOp::Merge(nentry)) => {
+                let new = if lsm {
+                    old.clone().xmerge(nentry)?
+                } else {
+                    let mut new = old.clone();
+                    new.prepend_version(nentry, lsm)?;
+                    new
+                };
+                let size = new.footprint()? - old.footprint()?;
+                self.tree_footprint.fetch_add(size, SeqCst);
+                match (old.is_deleted(), new.is_deleted()) {
+                    (true, false) => self.n_deleted.fetch_sub(1, SeqCst),
+                    (false, true) => self.n_deleted.fetch_add(1, SeqCst),
+                    _ => 0,
+                };
+                *entry = Some(new);
+                Ok(Ok(Some(old)))
+            }
+        }
+    }
+
+    // book-keeping for an entry populated in a node.
+    fn on_insert(&self, entry: &Entry<K, V>) -> Result<()> {
+        self.n_count.fetch_add(1, SeqCst);
+        self.n_removed.fetch_sub(1, SeqCst);
+        if entry.is_deleted() {
+            self.n_deleted.fetch_add(1, SeqCst);
+        }
+        let size = util::key_footprint(entry.as_key())?;
+        self.key_footprint.fetch_add(size, SeqCst);
+        self.tree_footprint.fetch_add(entry.footprint()?, SeqCst);
+        Ok(())
+    }
+
+    // book-keeping for an entry removed from its node.
+    fn on_remove(&self, entry: &Entry<K, V>) -> Result<()> {
+        self.n_count.fetch_sub(1, SeqCst);
+        self.n_removed.fetch_add(1, SeqCst);
+        if entry.is_deleted() {
+            self.n_deleted.fetch_sub(1, SeqCst);
+        }
+        let size = util::key_footprint(entry.as_key())?;
+        self.key_footprint.fetch_sub(size, SeqCst);
+        self.tree_footprint.fetch_sub(entry.footprint()?, SeqCst);
+        Ok(())
+    }
+}
+
+fn is_invalid_cas<K, V>(entry: &Entry<K, V>, cas: u64) -> bool
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    let seqno = entry.to_seqno();
+    if entry.is_deleted() {
+        cas != 0 && cas != seqno
+    } else {
+        cas != seqno
+    }
+}
+
+/// Create/Update/Delete operations on Skiplist index.
+impl<K, V> Writer<K, V> for Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Set {key, value} pair into index. If key is already
+    /// present, update the value and return the previous entry, else
+    /// create a new entry.
+    ///
+    /// *LSM mode*: Add a new version for the key, perserving the old value.
+    fn set(&mut self, key: K, value: V) -> Result<Option<Entry<K, V>>> {
+        let (_seqno, old_entry) = self.set_index(key, value, None)?;
+        Ok(old_entry)
+    }
+
+    /// Similar to set, but succeeds only when CAS matches with entry's
+    /// last `seqno`. In other words, since seqno is unique to each mutation,
+    /// we use `seqno` of the mutation as the CAS value. Use CAS == 0 to
+    /// enforce a create operation.
+    ///
+    /// *LSM mode*: Add a new version for the key, perserving the old value.
+    fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
+        let (_seqno, entry) = self.set_cas_index(key, value, cas, None)?;
+        entry
+    }
+
+    /// Delete the given key.
+    ///
+    /// *LSM mode*: Mark the entry as deleted along with seqno at which it
+    /// deleted
+    ///
+    /// NOTE: K should be borrowable as &Q and Q must be convertable to
+    /// owned K. This is require in lsm mode, where owned K must be
+    /// inserted into the list.
+    fn delete<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let (_seqno, entry) = self.delete_index(key, None)?;
+        entry
+    }
+}
+
+impl<K, V> Index<K, V> for Box<Skiplist<K, V>>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    type W = SkiplistWriter<K, V>;
+    type R = SkiplistReader<K, V>;
+
+    #[inline]
+    fn to_name(&self) -> Result<String> {
+        Ok(self.as_ref().to_name())
+    }
+
+    #[inline]
+    fn to_metadata(&self) -> Result<Vec<u8>> {
+        self.as_ref().to_metadata()
+    }
+
+    #[inline]
+    fn to_seqno(&self) -> Result<u64> {
+        self.as_ref().to_seqno()
+    }
+
+    #[inline]
+    fn set_seqno(&mut self, seqno: u64) -> Result<()> {
+        self.as_mut().set_seqno(seqno)
+    }
+
+    /// Create a new reader handle, for multi-threading.
+    fn to_reader(&mut self) -> Result<Self::R> {
+        self.as_mut().to_reader()
+    }
+
+    /// Create a new writer handle, for multi-threading. Writers can
+    /// concurrently mutate the index.
+    fn to_writer(&mut self) -> Result<Self::W> {
+        self.as_mut().to_writer()
+    }
+
+    fn commit<C, F>(&mut self, scanner: CommitIter<K, V, C>, metacb: F) -> Result<()>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        self.as_mut().commit(scanner, metacb)
+    }
+
+    fn compact(&mut self, cutoff: Cutoff) -> Result<usize> {
+        self.as_mut().compact(cutoff)
+    }
+
+    fn close(self) -> Result<()> {
+        (*self).close()
+    }
+
+    /// End of index life-cycle. Also clears persisted data (in disk).
+    fn purge(self) -> Result<()> {
+        (*self).purge()
+    }
+}
+
+impl<K, V> Index<K, V> for Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    type W = SkiplistWriter<K, V>;
+    type R = SkiplistReader<K, V>;
+
+    fn to_name(&self) -> Result<String> {
+        Ok(self.name.clone())
+    }
+
+    fn to_metadata(&self) -> Result<Vec<u8>> {
+        Ok(self.metadata.clone())
+    }
+
+    fn to_seqno(&self) -> Result<u64> {
+        Ok(self.seqno.load(SeqCst))
+    }
+
+    fn set_seqno(&mut self, seqno: u64) -> Result<()> {
+        let n = self.multi_rw();
+        if n == 0 {
+            debug!(
+                target: "skipl ", "{}, set-seqno {}->{}",
+                self.name, self.seqno.load(SeqCst), seqno
+            );
+
+            self.seqno.store(seqno, SeqCst);
+            Ok(())
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Create a new reader handle, for multi-threading.
+    fn to_reader(&mut self) -> Result<Self::R> {
+        let index = unsafe {
+            // transmute self as void pointer.
+            Box::from_raw(self as *mut Skiplist<K, V> as *mut ffi::c_void)
+        };
+        let reader = Arc::clone(&self.readers);
+        Ok(SkiplistReader::<K, V>::new(index, reader))
+    }
+
+    /// Create a new writer handle, for multi-threading. Writers can
+    /// concurrently mutate the index.
+    fn to_writer(&mut self) -> Result<Self::W> {
+        let index = unsafe {
+            // transmute self as void pointer.
+            Box::from_raw(self as *mut Skiplist<K, V> as *mut ffi::c_void)
+        };
+        let writer = Arc::clone(&self.writers);
+        Ok(SkiplistWriter::<K, V>::new(index, writer))
+    }
+
+    // NOTE: Error returned by commit are fatal, it leaves the index in
+    // in-consistent state.
+    fn commit<C, F>(&mut self, mut scanner: CommitIter<K, V, C>, metacb: F) -> Result<()>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        debug!(target: "skipl ", "{}, commit started ...", self.name);
+
+        let mut count = 0;
+        for entry in scanner.scan()? {
+            self.set_index_entry(entry?)?;
+            count += 1;
+        }
+
+        self.metadata = metacb(self.metadata.clone());
+
+        info!(target: "skipl ", "{}, committed {} items", self.name, count);
+        Ok(())
+    }
+
+    /// Purge older versions as per `cutoff`. Nodes whose entry is
+    /// removed shall be unlinked from the list only when there are no
+    /// active read/write handles.
+    fn compact(&mut self, cutoff: Cutoff) -> Result<usize> {
+        let c_seqno = cutoff.to_bound();
+
+        // before proceeding with compaction, verify the cutoff argument for
+        // unusual values.
+        match c_seqno {
+            Bound::Unbounded => {
+                warn!(target: "skipl ", "compact with unbounded cutoff");
+            }
+            Bound::Included(seqno) if seqno >= self.to_seqno()? => {
+                warn!(target: "skipl ", "compact the entire index {}", seqno);
+            }
+            Bound::Excluded(seqno) if seqno > self.to_seqno()? => {
+                warn!(target: "skipl ", "compact the entire index {}", seqno);
+            }
+            _ => (),
+        }
+
+        let mut count = 0;
+        let mut node = as_node(&self.head[0]);
+        while let Some(nref) = node {
+            let _latch = nref.latch.acquire_write(self.spin);
+            let entry = unsafe { nref.as_mut_entry() };
+            if let Some(old) = entry.take() {
+                count += 1;
+                match old.clone().purge(cutoff) {
+                    None => self.on_remove(&old)?,
+                    Some(new) => {
+                        let size = new.footprint()? - old.footprint()?;
+                        self.tree_footprint.fetch_add(size, SeqCst);
+                        *entry = Some(new);
+                    }
+                }
+            }
+            node = nref.as_next(0);
+        }
+
+        let n = self.multi_rw();
+        let unlinked = if n == 0 { self.unlink_removed()? } else { 0 };
+
+        info!(
+            target: "skipl ",
+            "{}, compacted {} items, unlinked {} nodes", self.name, count, unlinked
+        );
+        Ok(count)
+    }
+
+    fn close(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn purge(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<K, V> Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    // unlink and free nodes without an entry, caller should make sure
+    // that there are no active handles.
+    fn unlink_removed(&mut self) -> Result<usize> {
+        let mut count = 0;
+        for level in (0..MAX_LEVEL).rev() {
+            let mut tower: &[AtomicPtr<Node<K, V>>] = &self.head;
+            loop {
+                let next = tower[level].load(SeqCst);
+                let node = match unsafe { next.as_ref() } {
+                    Some(node) => node,
+                    None => break,
+                };
+                let removed = unsafe { (*node.entry.get()).is_none() };
+                if !removed {
+                    tower = &node.next;
+                    continue;
+                }
+
+                tower[level].store(node.next[level].load(SeqCst), SeqCst);
+                if level == 0 {
+                    let size = Node::<K, V>::overhead(node.next.len())?;
+                    self.tree_footprint.fetch_sub(size, SeqCst);
+                    self.n_removed.fetch_sub(1, SeqCst);
+                    mem::drop(unsafe { Box::from_raw(next) });
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+}
+
+impl<K, V> Footprint for Box<Skiplist<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn footprint(&self) -> Result<isize> {
+        self.as_ref().footprint()
+    }
+}
+
+impl<K, V> Footprint for Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn footprint(&self) -> Result<isize> {
+        Ok(self.tree_footprint.load(SeqCst))
+    }
+}
+
+/// Read operations on Skiplist index.
+impl<K, V> Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn get_entry<Q>(&self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self
+            .find_node(key)
+            .and_then(|node| node.to_entry(self.spin))
+        {
+            Some(entry) => Ok(entry),
+            None => Err(Error::KeyNotFound),
+        }
+    }
+
+    fn range_entries<'a, R, Q>(&'a self, range: R) -> Result<IndexIter<'a, K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let node = self.find_first(range.start_bound());
+        Ok(Box::new(Range {
+            spin: self.spin,
+            range,
+            node,
+            high: marker::PhantomData,
+        }))
+    }
+
+    fn reverse_entries<'a, R, Q>(&'a self, range: R) -> Result<IndexIter<'a, K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        Ok(Box::new(Reverse {
+            index: self,
+            range,
+            last: None,
+            done: false,
+            low: marker::PhantomData,
+        }))
+    }
+
+    fn pw_scan_entries<G>(&self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let node = match &from {
+            Bound::Included(low) => self.find_first(Bound::Included(low)),
+            Bound::Excluded(low) => self.find_first(Bound::Excluded(low)),
+            Bound::Unbounded => self.find_first(Bound::<&K>::Unbounded),
+        };
+        let (start, end) = util::to_start_end(within);
+        Ok(Box::new(IterPWScan {
+            spin: self.spin,
+            start,
+            end,
+            node,
+        }))
+    }
+}
+
+/// Read operations on Skiplist index.
+impl<K, V> Reader<K, V> for Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Get the entry for `key`.
+    fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.get_entry(key)
+    }
+
+    /// Return an iterator over all entries in this index.
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        let range = (Bound::<K>::Unbounded, Bound::<K>::Unbounded);
+        self.range_entries(range)
+    }
+
+    /// Range over all entries from low to high.
+    fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.range_entries(range)
+    }
+
+    /// Reverse range over all entries from high to low.
+    fn reverse<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reverse_entries(range)
+    }
+
+    /// Short circuited to get().
+    fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.get(key)
+    }
+
+    /// Short circuited to iter().
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        self.iter()
+    }
+
+    /// Short circuited to range().
+    fn range_with_versions<'a, R, Q>(
+        &'a mut self, // reader cannot be shared
+        rng: R,
+    ) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.range(rng)
+    }
+
+    /// Short circuited to reverse()
+    fn reverse_with_versions<'a, R, Q>(
+        &'a mut self, // reader cannot be shared
+        r: R,
+    ) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reverse(r)
+    }
+}
+
+impl<K, V> Replay<K, V> for Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<()> {
+        Skiplist::set_index(self, key, value, Some(seqno))?;
+        Ok(())
+    }
+
+    fn set_cas_index(&mut self, key: K, value: V, cas: u64, seqno: u64) -> Result<()> {
+        Skiplist::set_cas_index(self, key, value, cas, Some(seqno))?.1?;
+        Ok(())
+    }
+
+    fn delete_index(&mut self, key: K, seqno: u64) -> Result<()> {
+        Skiplist::delete_index(self, &key, Some(seqno))?.1?;
+        Ok(())
+    }
+}
+
+impl<K, V> CommitIterator<K, V> for Box<Skiplist<K, V>>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        self.as_mut().scan(within)
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        self.as_mut().scans(n_shards, within)
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        N: Clone + RangeBounds<K>,
+        G: Clone + RangeBounds<u64>,
+    {
+        self.as_mut().range_scans(ranges, within)
+    }
+}
+
+impl<K, V> CommitIterator<K, V> for &mut Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        (*self).scan(within)
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        (*self).scans(n_shards, within)
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        N: Clone + RangeBounds<K>,
+        G: Clone + RangeBounds<u64>,
+    {
+        (*self).range_scans(ranges, within)
+    }
+}
+
+impl<K, V> CommitIterator<K, V> for Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let mut ss = Box::new(scans::SkipScan::new(self.to_reader()?));
+        ss.set_seqno_range(within)?;
+        Ok(ss)
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        match n_shards {
+            0 => return Ok(vec![]),
+            1 => return Ok(vec![self.scan(within)?]),
+            _ => (),
+        }
+
+        let keys = self.to_shard_keys(n_shards);
+
+        let mut scans: Vec<IndexIter<K, V>> = vec![];
+        let mut lkey = Bound::Unbounded;
+        for hkey in keys {
+            let range = (lkey.clone(), Bound::Excluded(hkey.clone()));
+            if self.range(range.clone())?.next().is_some() {
+                let mut ss = Box::new(scans::SkipScan::new(self.to_reader()?));
+                ss.set_key_range(range)?.set_seqno_range(within.clone())?;
+                lkey = Bound::Included(hkey);
+                scans.push(ss);
+            }
+        }
+
+        let range = (lkey, Bound::Unbounded);
+        if self.range(range.clone())?.next().is_some() {
+            let mut ss = Box::new(scans::SkipScan::new(self.to_reader()?));
+            ss.set_key_range(range)?.set_seqno_range(within)?;
+            scans.push(ss);
+        }
+
+        // If there are not enough shards push empty iterators.
+        for _ in scans.len()..n_shards {
+            let ss = vec![];
+            scans.push(Box::new(ss.into_iter()));
+        }
+
+        assert_eq!(scans.len(), n_shards);
+
+        Ok(scans)
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        N: Clone + RangeBounds<K>,
+        G: Clone + RangeBounds<u64>,
+    {
+        let mut scans: Vec<IndexIter<K, V>> = vec![];
+        for range in ranges {
+            let mut ss = Box::new(scans::SkipScan::new(self.to_reader()?));
+            ss.set_key_range(range)?.set_seqno_range(within.clone())?;
+            scans.push(ss);
+        }
+        Ok(scans)
+    }
+}
+
+impl<K, V> PiecewiseScan<K, V> for Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries greater than from bound,
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        self.pw_scan_entries(from, within)
+    }
+}
+
+impl<K, V> ToStats<Stats> for Box<Skiplist<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn to_stats(&self) -> Result<Stats> {
+        self.as_ref().to_stats()
+    }
+}
+
+impl<K, V> ToStats<Stats> for Skiplist<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn to_stats(&self) -> Result<Stats> {
+        Skiplist::to_stats(self)
+    }
+}
+
+impl<K, V> Validate<Stats> for Box<Skiplist<K, V>>
+where
+    K: Clone + Ord + Footprint + fmt::Debug,
+    V: Clone + Diff + Footprint,
+{
+    fn validate(&mut self) -> Result<Stats> {
+        self.as_mut().validate()
+    }
+}
+
+/// Deep walk validate of Skiplist index.
+impl<K, V> Validate<Stats> for Skiplist<K, V>
+where
+    K: Clone + Ord + Footprint + fmt::Debug,
+    V: Clone + Diff + Footprint,
+{
+    /// Validate Skiplist with following rules:
+    ///
+    /// * Keys are sorted, without duplicates, at every level.
+    /// * Node's tower is taller than the levels it is linked into.
+    /// * Book-keeping for entries and footprint match with a full walk.
+    ///
+    /// Additionally return full statistics on the list. Refer to [`Stats`]
+    /// for more information. Should not be called while there are
+    /// concurrent writers.
+    fn validate(&mut self) -> Result<Stats> {
+        let mut levels = vec![];
+        for level in 0..MAX_LEVEL {
+            let (mut n, mut prev): (usize, Option<&K>) = (0, None);
+            let mut node = as_node(&self.head[level]);
+            while let Some(nref) = node {
+                if nref.next.len() <= level {
+                    let msg = format!("validate, {:?} height at level {}", nref.key, level);
+                    return err_at!(Fatal, msg: msg);
+                }
+                match prev {
+                    Some(prev) if prev.ge(&nref.key) => {
+                        let msg = format!("validate, sort order {:?} >= {:?}", prev, nref.key);
+                        return err_at!(Fatal, msg: msg);
+                    }
+                    _ => (),
+                }
+                prev = Some(&nref.key);
+                n += 1;
+                node = nref.as_next(level);
+            }
+            if n == 0 {
+                break;
+            }
+            levels.push(n);
+        }
+
+        let (mut n_count, mut n_deleted, mut n_removed) = (0, 0, 0);
+        let (mut key_footprint, mut tree_footprint, mut seqno) = (0, 0, 0);
+        let mut node = as_node(&self.head[0]);
+        while let Some(nref) = node {
+            tree_footprint += Node::<K, V>::overhead(nref.next.len())?;
+            match nref.to_entry(self.spin) {
+                Some(entry) => {
+                    n_count += 1;
+                    if entry.is_deleted() {
+                        n_deleted += 1;
+                    }
+                    key_footprint += util::key_footprint(entry.as_key())?;
+                    tree_footprint += entry.footprint()?;
+                    seqno = cmp::max(seqno, entry.to_seqno());
+                }
+                None => n_removed += 1,
+            }
+            node = nref.as_next(0);
+        }
+
+        let stats = self.to_stats()?;
+        if n_count != stats.entries {
+            let msg = format!("validate, n_count {} != {}", n_count, stats.entries);
+            return err_at!(Fatal, msg: msg);
+        }
+        if n_deleted != stats.n_deleted {
+            let msg = format!("validate, n_deleted {} != {}", n_deleted, stats.n_deleted);
+            return err_at!(Fatal, msg: msg);
+        }
+        if n_removed != stats.n_removed {
+            let msg = format!("validate, n_removed {} != {}", n_removed, stats.n_removed);
+            return err_at!(Fatal, msg: msg);
+        }
+        if key_footprint != stats.key_footprint {
+            let (x, y) = (key_footprint, stats.key_footprint);
+            let msg = format!("validate, key_footprint {} != {}", x, y);
+            return err_at!(Fatal, msg: msg);
+        }
+        if tree_footprint != stats.tree_footprint {
+            let (x, y) = (tree_footprint, stats.tree_footprint);
+            let msg = format!("validate, tree_footprint {} != {}", x, y);
+            return err_at!(Fatal, msg: msg);
+        }
+        if seqno > self.to_seqno()? {
+            let msg = format!("validate, seqno {} > {}", seqno, self.to_seqno()?);
+            return err_at!(Fatal, msg: msg);
+        }
+
+        let mut stats = stats;
+        stats.levels = Some(levels);
+        Ok(stats)
+    }
+}
+
+/// Iterator type, to do range scan between a _lower-bound_ and
+/// _higher-bound_.
+struct Range<'a, K, V, R, Q>
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone + Diff,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+{
+    spin: bool,
+    range: R,
+    node: Option<&'a Node<K, V>>,
+    high: marker::PhantomData<Q>,
+}
+
+impl<'a, K, V, R, Q> Iterator for Range<'a, K, V, R, Q>
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone + Diff,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.node.take()?;
+            let ok = match self.range.end_bound() {
+                Bound::Unbounded => true,
+                Bound::Included(high) => node.key.borrow().cmp(high) != Ordering::Greater,
+                Bound::Excluded(high) => node.key.borrow().cmp(high) == Ordering::Less,
+            };
+            if !ok {
+                break None;
+            }
+            self.node = node.as_next(0);
+            // skip nodes whose entry is removed.
+            if let Some(entry) = node.to_entry(self.spin) {
+                break Some(Ok(entry));
+            }
+        }
+    }
+}
+
+/// Iterator type, to do range scan between a _higher-bound_ and
+/// _lower-bound_.
+struct Reverse<'a, K, V, R, Q>
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone + Diff,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+{
+    index: &'a Skiplist<K, V>,
+    range: R,
+    last: Option<&'a Node<K, V>>,
+    done: bool,
+    low: marker::PhantomData<Q>,
+}
+
+impl<'a, K, V, R, Q> Iterator for Reverse<'a, K, V, R, Q>
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone + Diff,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                break None;
+            }
+            // list has only forward pointers, search for predecessor.
+            let node = match self.last {
+                Some(last) => self.index.find_last(Bound::Excluded(&last.key)),
+                None => self.index.find_last(self.range.end_bound()),
+            };
+            let node = match node {
+                Some(node) => node,
+                None => {
+                    self.done = true;
+                    break None;
+                }
+            };
+            let ok = match self.range.start_bound() {
+                Bound::Unbounded => true,
+                Bound::Included(low) => node.key.borrow().cmp(low) != Ordering::Less,
+                Bound::Excluded(low) => node.key.borrow().cmp(low) == Ordering::Greater,
+            };
+            if !ok {
+                self.done = true;
+                break None;
+            }
+            self.last = Some(node);
+            // skip nodes whose entry is removed.
+            if let Some(entry) = node.to_entry(self.index.spin) {
+                break Some(Ok(entry));
+            }
+        }
+    }
+}
+
+/// Iterator type, for piece-wise scan of entries, filtered by seqno.
+struct IterPWScan<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    spin: bool,
+    start: Bound<u64>,
+    end: Bound<u64>,
+    node: Option<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterPWScan<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    type Item = Result<ScanEntry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // loop for a maximum of 1000 entries.
+        let mut limit = 1000; // TODO: avoid magic constants
+        loop {
+            let node = self.node.take()?;
+            self.node = node.as_next(0);
+
+            // include if entry was within the visible time-range
+            let (a, z) = (self.start.clone(), self.end.clone());
+            let entry = node.to_entry(self.spin);
+            if let Some(entry) = entry.and_then(|entry| entry.filter_within(a, z)) {
+                break Some(Ok(ScanEntry::Found(entry)));
+            }
+
+            limit -= 1;
+            if limit == 0 {
+                break Some(Ok(ScanEntry::Retry(node.key.clone())));
+            }
+        }
+    }
+}
+
+/// Read handle into [Skiplist] index.
+pub struct SkiplistReader<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    _refn: Arc<u32>,
+    id: usize,
+    index: Option<Box<ffi::c_void>>, // Box<Skiplist<K, V>>
+    phantom_key: marker::PhantomData<K>,
+    phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V> SkiplistReader<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn new(index: Box<ffi::c_void>, _refn: Arc<u32>) -> SkiplistReader<K, V> {
+        let id = Arc::strong_count(&_refn) - 1;
+        let mut r = SkiplistReader {
+            _refn,
+            id,
+            index: Some(index),
+            phantom_key: marker::PhantomData,
+            phantom_val: marker::PhantomData,
+        };
+
+        let index: &mut Skiplist<K, V> = r.as_mut();
+        trace!(target: "skipl ", "{}, new reader {} ...", index.name, id);
+
+        r
+    }
+}
+
+impl<K, V> Drop for SkiplistReader<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn drop(&mut self) {
+        let id = self.id;
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        trace!(target: "skipl ", "{}, dropping reader {}", index.name, id);
+
+        // leak this index, it is only a reference
+        Box::leak(self.index.take().unwrap());
+    }
+}
+
+impl<K, V> AsMut<Skiplist<K, V>> for SkiplistReader<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn as_mut(&mut self) -> &mut Skiplist<K, V> {
+        unsafe {
+            // transmute void pointer to mutable reference into index.
+            let index_ptr = self.index.as_mut().unwrap().as_mut();
+            let index_ptr = index_ptr as *mut ffi::c_void;
+            (index_ptr as *mut Skiplist<K, V>).as_mut().unwrap()
+        }
+    }
+}
+
+impl<K, V> Reader<K, V> for SkiplistReader<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Get ``key`` from index.
+    fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.get(key)
+    }
+
+    /// Iterate over all entries in this index.
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.iter()
+    }
+
+    /// Iterate from lower bound to upper bound.
+    fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.range(range)
+    }
+
+    /// Iterate from upper bound to lower bound.
+    fn reverse<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.reverse(range)
+    }
+
+    /// Short circuited to get().
+    fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.get(key)
+    }
+
+    /// Short circuited to iter().
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        self.iter()
+    }
+
+    /// Short circuited to range().
+    fn range_with_versions<'a, R, Q>(
+        &'a mut self, // reader cannot be shared
+        r: R,
+    ) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.range(r)
+    }
+
+    /// Short circuited to reverse()
+    fn reverse_with_versions<'a, R, Q>(
+        &'a mut self, // reader cannot be shared
+        r: R,
+    ) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reverse(r)
+    }
+}
+
+impl<K, V> CommitIterator<K, V> for SkiplistReader<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.scan(within)
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.scans(n_shards, within)
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        N: Clone + RangeBounds<K>,
+        G: Clone + RangeBounds<u64>,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.range_scans(ranges, within)
+    }
+}
+
+impl<K, V> PiecewiseScan<K, V> for SkiplistReader<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries greater than from bound,
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.pw_scan(from, within)
+    }
+}
+
+/// Write handle into [Skiplist] index. Any number of writer handles
+/// can concurrently mutate the index.
+pub struct SkiplistWriter<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    _refn: Arc<u32>,
+    id: usize,
+    index: Option<Box<ffi::c_void>>,
+    phantom_key: marker::PhantomData<K>,
+    phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V> SkiplistWriter<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn new(index: Box<ffi::c_void>, _refn: Arc<u32>) -> SkiplistWriter<K, V> {
+        let id = Arc::strong_count(&_refn) - 2;
+        let mut w = SkiplistWriter {
+            _refn,
+            id,
+            index: Some(index),
+            phantom_key: marker::PhantomData,
+            phantom_val: marker::PhantomData,
+        };
+
+        let index: &mut Skiplist<K, V> = w.as_mut();
+        trace!(target: "skipl ", "{}, new writer {}", index.name, id);
+
+        w
+    }
+}
+
+impl<K, V> Drop for SkiplistWriter<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn drop(&mut self) {
+        let id = self.id;
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        trace!(target: "skipl ", "{}, dropping writer {}", index.name, id);
+
+        // leak this index, it is only a reference
+        Box::leak(self.index.take().unwrap());
+    }
+}
+
+impl<K, V> AsMut<Skiplist<K, V>> for SkiplistWriter<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn as_mut(&mut self) -> &mut Skiplist<K, V> {
+        unsafe {
+            // transmute void pointer to mutable reference into index.
+            let index_ptr = self.index.as_mut().unwrap().as_mut();
+            let index_ptr = index_ptr as *mut ffi::c_void;
+            (index_ptr as *mut Skiplist<K, V>).as_mut().unwrap()
+        }
+    }
+}
+
+impl<K, V> SkiplistWriter<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Refer Skiplist::set_index() for more details.
+    pub fn set_index(
+        &mut self,
+        key: K,
+        value: V,
+        seqno: Option<u64>,
+    ) -> Result<(u64, Option<Entry<K, V>>)> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.set_index(key, value, seqno)
+    }
+
+    /// Refer Skiplist::set_cas_index() for more details.
+    pub fn set_cas_index(
+        &mut self,
+        key: K,
+        value: V,
+        cas: u64,
+        seqno: Option<u64>,
+    ) -> Result<(u64, Result<Option<Entry<K, V>>>)> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.set_cas_index(key, value, cas, seqno)
+    }
+
+    /// Refer Skiplist::delete_index() for more details.
+    pub fn delete_index<Q>(
+        &mut self,
+        key: &Q,
+        seqno: Option<u64>,
+    ) -> Result<(u64, Result<Option<Entry<K, V>>>)>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        index.delete_index(key, seqno)
+    }
+}
+
+impl<K, V> Writer<K, V> for SkiplistWriter<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Set {key, value} pair into index. If key is already
+    /// present, update the value and return the previous entry, else
+    /// create a new entry.
+    ///
+    /// *LSM mode*: Add a new version for the key, perserving the old value.
+    fn set(&mut self, key: K, value: V) -> Result<Option<Entry<K, V>>> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        let (_seqno, old_entry) = index.set_index(key, value, None)?;
+        Ok(old_entry)
+    }
+
+    /// Similar to set, but succeeds only when CAS matches with entry's
+    /// last `seqno`. In other words, since seqno is unique to each mutation,
+    /// we use `seqno` of the mutation as the CAS value. Use CAS == 0 to
+    /// enforce a create operation.
+    ///
+    /// *LSM mode*: Add a new version for the key, perserving the old value.
+    fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        let (_seqno, entry) = index.set_cas_index(key, value, cas, None)?;
+        entry
+    }
+
+    /// Delete the given key.
+    ///
+    /// *LSM mode*: Mark the entry as deleted along with seqno at which it
+    /// deleted
+    ///
+    /// NOTE: K should be borrowable as &Q and Q must be convertable to
+    /// owned K. This is require in lsm mode, where owned K must be
+    /// inserted into the list.
+    fn delete<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        let (_seqno, entry) = index.delete_index(key, None)?;
+        entry
+    }
+}
+
+/// Create/Update/Delete operations on Skiplist index.
+impl<K, V> WalWriter<K, V> for SkiplistWriter<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<Option<Entry<K, V>>> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        let (_seqno, old_entry) = index.set_index(key, value, Some(seqno))?;
+        Ok(old_entry)
+    }
+
+    fn set_cas_index(
+        &mut self,
+        key: K,
+        value: V,
+        cas: u64,
+        seqno: u64,
+    ) -> Result<Option<Entry<K, V>>> {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        let (_seqno, res) = index.set_cas_index(key, value, cas, Some(seqno))?;
+        res
+    }
+
+    fn delete_index<Q>(
+        &mut self,
+        key: &Q,
+        seqno: u64, // seqno for this delete
+    ) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let index: &mut Skiplist<K, V> = self.as_mut();
+        let (_seqno, res) = index.delete_index(key, Some(seqno))?;
+        res
+    }
+}
+
+/// Statistic type, for [`Skiplist`] index.
+pub struct Stats {
+    pub name: String,
+    pub entries: usize,
+    pub n_deleted: usize,
+    pub n_removed: usize,
+    pub node_size: usize,
+    pub key_footprint: isize,
+    pub tree_footprint: isize,
+    pub levels: Option<Vec<usize>>,
+}
+
+impl Stats {
+    pub(crate) fn new(name: &str) -> Stats {
+        Stats {
+            name: name.to_string(),
+            entries: Default::default(),
+            n_deleted: Default::default(),
+            n_removed: Default::default(),
+            node_size: Default::default(),
+            key_footprint: Default::default(),
+            tree_footprint: Default::default(),
+            levels: Default::default(),
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let none = "none".to_string();
+        let l = self.levels.as_ref().map_or(none, |x| format!("{:?}", x));
+        writeln!(f, "skiplist.name = {}", self.name)?;
+        writeln!(
+            f,
+            "skiplist = {{ entries={}, n_deleted={}, n_removed={}, node_size={} }}",
+            self.entries, self.n_deleted, self.n_removed, self.node_size,
+        )?;
+        writeln!(
+            f,
+            "skiplist = {{ key_footprint={}, tree_footprint={} }}",
+            self.key_footprint, self.tree_footprint,
+        )?;
+        write!(f, "skiplist.levels = {}", l)
+    }
+}
+
+impl ToJson for Stats {
+    fn to_json(&self) -> String {
+        let null = "null".to_string();
+        format!(
+            concat!(
+                r#"{{ "skiplist": {{ "name": {:?}, "entries": {}, "n_deleted": {}, "#,
+                r#""n_removed": {}, "key_footprint": {}, "tree_footprint": {}, "#,
+                r#""node_size": {}, "levels": {} }} }}"#,
+            ),
+            self.name,
+            self.entries,
+            self.n_deleted,
+            self.n_removed,
+            self.key_footprint,
+            self.tree_footprint,
+            self.node_size,
+            self.levels.as_ref().map_or(null, |x| format!("{:?}", x)),
+        )
+    }
+}
+
+#[cfg(test)]
+#[path = "skiplist_test.rs"]
+mod skiplist_test;
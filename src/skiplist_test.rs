@@ -0,0 +1,471 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use std::{ops::Bound, thread};
+
+use super::*;
+use crate::{
+    core::{CommitIterator, Index, Reader, Validate, Writer},
+    llrb::Llrb,
+    scans,
+};
+
+include!("./ref_test.rs");
+
+#[test]
+fn test_name() {
+    let mut skl: Box<Skiplist<i64, i64>> = Skiplist::new("test-skiplist");
+    assert_eq!(skl.to_name().unwrap(), "test-skiplist".to_string());
+    assert!(skl.is_empty());
+    assert!(skl.validate().is_ok());
+}
+
+#[test]
+fn test_random_height() {
+    let mut heights = vec![0_usize; MAX_LEVEL + 1];
+    for _ in 0..100_000 {
+        let height = random_height();
+        assert!((1..=MAX_LEVEL).contains(&height), "{}", height);
+        heights[height] += 1;
+    }
+    // branching factor is 4.
+    assert!(heights[1] > heights[2] * 2, "{:?}", heights);
+    assert!(heights[2] > heights[3] * 2, "{:?}", heights);
+}
+
+#[test]
+fn test_crud() {
+    let size = 1000;
+    let mut skl: Box<Skiplist<i64, i64>> = Skiplist::new("test-skiplist");
+    let mut refns = RefNodes::new(false /*lsm*/, size);
+
+    for _ in 0..20000 {
+        let key: i64 = (random::<i64>() % (size as i64)).abs();
+        let value: i64 = random();
+        let op: i64 = (random::<i64>() % 3).abs();
+        match op {
+            0 => {
+                let entry = skl.set(key, value).unwrap();
+                let refn = refns.set(key, value);
+                check_node(entry, refn);
+            }
+            1 => {
+                let off: usize = key.try_into().unwrap();
+                let refn = &refns.entries[off];
+                let cas = if refn.versions.len() > 0 {
+                    refn.to_seqno()
+                } else {
+                    0
+                };
+
+                let entry = skl.set_cas(key, value, cas).ok().unwrap();
+                let refn = refns.set_cas(key, value, cas);
+                check_node(entry, refn);
+            }
+            2 => {
+                let entry = skl.delete(&key).unwrap();
+                let refn = refns.delete(key);
+                check_node(entry, refn);
+            }
+            op => panic!("unreachable {}", op),
+        };
+
+        assert!(skl.validate().is_ok());
+    }
+
+    assert_eq!(refns.to_seqno(), skl.to_seqno().unwrap());
+
+    {
+        // test get
+        for key in 0..(size as i64) {
+            let entry = skl.get(&key).ok();
+            check_node(entry, refns.get(key));
+        }
+    }
+
+    {
+        // test iter
+        let (mut iter, mut iter_ref) = (skl.iter().unwrap(), refns.iter());
+        loop {
+            let item = iter.next().transpose().unwrap();
+            if check_node(item, iter_ref.next().cloned()) == false {
+                break;
+            }
+        }
+    }
+
+    // ranges and reverses
+    for _ in 0..3000 {
+        let (low, high) = random_low_high(size);
+
+        {
+            let mut iter = skl.range((low, high)).unwrap();
+            let mut iter_ref = refns.range(low, high);
+            loop {
+                let item = iter.next().transpose().unwrap();
+                if check_node(item, iter_ref.next().cloned()) == false {
+                    break;
+                }
+            }
+        }
+
+        {
+            let mut iter = skl.reverse((low, high)).unwrap();
+            let mut iter_ref = refns.reverse(low, high);
+            loop {
+                let item = iter.next().transpose().unwrap();
+                if check_node(item, iter_ref.next().cloned()) == false {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_crud_lsm() {
+    let size = 1000;
+    let mut skl: Box<Skiplist<i64, i64>> = Skiplist::new_lsm("test-skiplist");
+    let mut refns = RefNodes::new(true /*lsm*/, size as usize);
+
+    for _i in 0..20000 {
+        let key: i64 = (random::<i64>() % size).abs();
+        let value: i64 = random();
+        let op: i64 = (random::<i64>() % 3).abs();
+        match op {
+            0 => {
+                let entry = skl.set(key, value).unwrap();
+                let refn = refns.set(key, value);
+                check_node(entry, refn);
+            }
+            1 => {
+                let off: usize = key.try_into().unwrap();
+                let refn = &refns.entries[off];
+                let cas = if refn.versions.len() > 0 {
+                    refn.to_seqno()
+                } else {
+                    0
+                };
+
+                let entry = skl.set_cas(key, value, cas).ok().unwrap();
+                let refn = refns.set_cas(key, value, cas);
+                check_node(entry, refn);
+            }
+            2 => {
+                let entry = skl.delete(&key).unwrap();
+                let refn = refns.delete(key);
+                check_node(entry, refn);
+            }
+            op => panic!("unreachable {}", op),
+        };
+        assert_eq!(skl.to_seqno().unwrap(), refns.to_seqno());
+
+        assert!(skl.validate().is_ok());
+    }
+
+    {
+        // test iter
+        let (mut iter, mut iter_ref) = (skl.iter().unwrap(), refns.iter());
+        loop {
+            let item = iter.next().transpose().unwrap();
+            if check_node(item, iter_ref.next().cloned()) == false {
+                break;
+            }
+        }
+    }
+
+    // ranges and reverses
+    for _ in 0..3000 {
+        let (low, high) = random_low_high(size as usize);
+
+        {
+            let mut iter = skl.range((low, high)).unwrap();
+            let mut iter_ref = refns.range(low, high);
+            loop {
+                let item = iter.next().transpose().unwrap();
+                if check_node(item, iter_ref.next().cloned()) == false {
+                    break;
+                }
+            }
+        }
+
+        {
+            let mut iter = skl.reverse((low, high)).unwrap();
+            let mut iter_ref = refns.reverse(low, high);
+            loop {
+                let item = iter.next().transpose().unwrap();
+                if check_node(item, iter_ref.next().cloned()) == false {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_concurrent_writers() {
+    let (n_threads, n_ops, n_keys) = (32, 2000, 1000_i64);
+
+    for &lsm in [false, true].iter() {
+        let mut skl: Box<Skiplist<i64, i64>> = if lsm {
+            Skiplist::new_lsm("test-skiplist")
+        } else {
+            Skiplist::new("test-skiplist")
+        };
+
+        let mut handles = vec![];
+        for id in 0..n_threads {
+            let mut w = skl.to_writer().unwrap();
+            let mut r = skl.to_reader().unwrap();
+            handles.push(thread::spawn(move || {
+                let seed: u128 = random();
+                let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+                for i in 0..n_ops {
+                    let key: i64 = rng.gen::<i64>().abs() % n_keys;
+                    match rng.gen::<u8>() % 3 {
+                        0 | 1 => {
+                            w.set(key, (id * n_ops) + i).unwrap();
+                        }
+                        _ => {
+                            w.delete(&key).unwrap();
+                        }
+                    }
+                    if let Ok(entry) = r.get(&key) {
+                        assert_eq!(entry.to_key(), key);
+                    }
+                }
+            }));
+        }
+        for handle in handles.into_iter() {
+            handle.join().unwrap();
+        }
+
+        let total = (n_threads * n_ops) as u64;
+        assert_eq!(skl.to_seqno().unwrap(), total);
+        let stats = skl.validate().unwrap();
+        assert!(stats.entries <= (n_keys as usize));
+
+        let mut n_versions = 0;
+        for entry in skl.iter().unwrap() {
+            let entry = entry.unwrap();
+            let seqnos: Vec<u64> = entry.versions().map(|e| e.to_seqno()).collect();
+            for item in seqnos.windows(2) {
+                assert!(item[0] > item[1], "{:?}", seqnos);
+            }
+            n_versions += seqnos.len();
+        }
+        if lsm {
+            assert_eq!(n_versions as u64, total);
+        }
+    }
+}
+
+#[test]
+fn test_pw_scan() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed {}", seed);
+
+    let mut skl: Box<Skiplist<i64, i64>> = Skiplist::new_lsm("test-skiplist");
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+
+    let n_ops = 20_000;
+    for _ in 0..n_ops {
+        let key: i64 = rng.gen::<i64>().abs() % 5000;
+        let value: i64 = rng.gen();
+        match rng.gen::<u8>() % 3 {
+            0 | 1 => {
+                skl.set(key, value).unwrap();
+                llrb.set(key, value).unwrap();
+            }
+            _ => {
+                skl.delete(&key).unwrap();
+                llrb.delete(&key).unwrap();
+            }
+        }
+    }
+
+    for _ in 0..20 {
+        let (s1, s2) = (rng.gen::<u64>() % n_ops, rng.gen::<u64>() % n_ops);
+        let within = (Bound::Excluded(s1), Bound::Included(s2));
+
+        let mut iter = scans::SkipScan::new(skl.to_reader().unwrap());
+        iter.set_seqno_range(within.clone()).unwrap();
+        let mut ref_iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+        ref_iter.set_seqno_range(within).unwrap();
+
+        loop {
+            match (iter.next(), ref_iter.next()) {
+                (Some(entry), Some(ref_entry)) => {
+                    check_entry(entry.unwrap(), ref_entry.unwrap());
+                }
+                (None, None) => break,
+                (_, _) => panic!("mismatch in pw_scan {} {}", s1, s2),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_scans() {
+    let mut skl: Box<Skiplist<i64, i64>> = Skiplist::new("test-skiplist");
+    for key in 0..10_000 {
+        skl.set(key, key * 10).unwrap();
+    }
+
+    for n_shards in 1..20 {
+        let iters = skl.scans(n_shards, ..).unwrap();
+        assert_eq!(iters.len(), n_shards);
+
+        let mut key = 0;
+        for iter in iters.into_iter() {
+            for entry in iter {
+                let entry = entry.unwrap();
+                assert_eq!(entry.to_key(), key);
+                assert_eq!(entry.to_native_value(), Some(key * 10));
+                key += 1;
+            }
+        }
+        assert_eq!(key, 10_000);
+    }
+}
+
+#[test]
+fn test_commit() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed {}", seed);
+
+    for _i in 0..20 {
+        let lsm: bool = rng.gen();
+        let (mut index, mut other, mut rindex) = if lsm {
+            (
+                Skiplist::<i64, i64>::new_lsm("test-index"),
+                Llrb::<i64, i64>::new_lsm("test-other"),
+                Llrb::<i64, i64>::new_lsm("test-ref-index"),
+            )
+        } else {
+            (
+                Skiplist::<i64, i64>::new("test-index"),
+                Llrb::<i64, i64>::new("test-other"),
+                Llrb::<i64, i64>::new("test-ref-index"),
+            )
+        };
+
+        let n_ops = 1 + (rng.gen::<usize>() % 1000);
+        other.set_seqno(n_ops as u64).unwrap();
+        for i in 0..(n_ops * 2) {
+            let key: i64 = rng.gen::<i64>().abs() % (n_ops as i64 * 3);
+            let value: i64 = rng.gen();
+            if i < n_ops {
+                index.set(key, value).unwrap();
+            } else {
+                other.set(key, value).unwrap();
+            }
+            rindex.set(key, value).unwrap();
+        }
+
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = CommitIter::new(other.to_reader().unwrap(), within);
+        index.commit(scanner, |meta| meta.clone()).unwrap();
+
+        assert!(index.validate().is_ok());
+        assert_eq!(index.to_seqno().unwrap(), rindex.to_seqno().unwrap());
+        assert_eq!(index.len(), rindex.len());
+
+        let mut iter = index.iter().unwrap();
+        let mut ref_iter = rindex.iter().unwrap();
+        loop {
+            match (iter.next(), ref_iter.next()) {
+                (Some(entry), Some(ref_entry)) => {
+                    check_entry(entry.unwrap(), ref_entry.unwrap());
+                }
+                (None, None) => break,
+                (_, _) => panic!("mismatch in commit"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compact() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    println!("seed {}", seed);
+
+    for _i in 0..20 {
+        let lsm: bool = rng.gen();
+        let (mut index, mut rindex) = if lsm {
+            (
+                Skiplist::<i64, i64>::new_lsm("test-index"),
+                Llrb::<i64, i64>::new_lsm("test-ref-index"),
+            )
+        } else {
+            (
+                Skiplist::<i64, i64>::new("test-index"),
+                Llrb::<i64, i64>::new("test-ref-index"),
+            )
+        };
+
+        let n_ops = 2 + (rng.gen::<usize>() % 20_000);
+        for _ in 0..n_ops {
+            let key: i64 = rng.gen::<i64>().abs() % (n_ops as i64 / 2);
+            let value: i64 = rng.gen();
+            match rng.gen::<u8>() % 2 {
+                0 => {
+                    index.set(key, value).unwrap();
+                    rindex.set(key, value).unwrap();
+                }
+                _ => {
+                    index.delete(&key).unwrap();
+                    rindex.delete(&key).unwrap();
+                }
+            }
+        }
+
+        let cutoff = match rng.gen::<u8>() % 3 {
+            0 => Bound::Excluded(rng.gen::<u64>() % (n_ops as u64)),
+            1 => Bound::Included(rng.gen::<u64>() % (n_ops as u64)),
+            _ => Bound::Unbounded,
+        };
+        let cutoff = match rng.gen::<u8>() % 2 {
+            0 => Cutoff::new_tombstone(cutoff),
+            _ => Cutoff::new_lsm(cutoff),
+        };
+        println!("lsm:{} n_ops:{} cutoff:{:?}", lsm, n_ops, cutoff);
+
+        let count = index.compact(cutoff).unwrap();
+        let ref_count = rindex.compact(cutoff).unwrap();
+        assert_eq!(count, ref_count);
+
+        let stats = index.validate().unwrap();
+        assert_eq!(stats.n_removed, 0);
+        assert_eq!(stats.entries, rindex.len());
+
+        let mut iter = index.iter().unwrap();
+        let mut ref_iter = rindex.iter().unwrap();
+        loop {
+            match (iter.next(), ref_iter.next()) {
+                (Some(entry), Some(ref_entry)) => {
+                    check_entry(entry.unwrap(), ref_entry.unwrap());
+                }
+                (None, None) => break,
+                (_, _) => panic!("mismatch in compact"),
+            }
+        }
+    }
+}
+
+fn check_entry(entry: Entry<i64, i64>, ref_entry: Entry<i64, i64>) {
+    let key = entry.to_key();
+    assert_eq!(key, ref_entry.to_key());
+
+    let versions: Vec<Entry<i64, i64>> = entry.versions().collect();
+    let ref_versions: Vec<Entry<i64, i64>> = ref_entry.versions().collect();
+    assert_eq!(versions.len(), ref_versions.len(), "key {}", key);
+    for (ver, ref_ver) in versions.into_iter().zip(ref_versions.into_iter()) {
+        assert_eq!(ver.to_seqno(), ref_ver.to_seqno(), "key {}", key);
+        assert_eq!(ver.is_deleted(), ref_ver.is_deleted(), "key {}", key);
+        let (value, ref_value) = (ver.to_native_value(), ref_ver.to_native_value());
+        assert_eq!(value, ref_value, "key {}", key);
+    }
+}
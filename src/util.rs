@@ -96,6 +96,30 @@ pub(crate) fn open_file_w(file: &ffi::OsString) -> Result<fs::File> {
     Ok(err_at!(IoError, opts.append(true).open(os_file))?)
 }
 
+// canonicalize `dir`, which may not exist yet. Missing components are
+// appended to the canonical form of its nearest existing ancestor.
+pub(crate) fn to_canonical_path(dir: &ffi::OsStr) -> Result<path::PathBuf> {
+    let dir = {
+        let cwd = err_at!(IoError, std::env::current_dir())?;
+        cwd.join(dir)
+    };
+    for base in dir.ancestors() {
+        if base.exists() {
+            let rest = err_at!(InvalidInput, dir.strip_prefix(base))?;
+            let mut cdir = err_at!(IoError, fs::canonicalize(base))?;
+            for c in rest.components() {
+                match c {
+                    path::Component::Normal(c) => cdir.push(c),
+                    path::Component::CurDir => (),
+                    _ => err_at!(InvalidInput, msg: format!("{:?}", dir))?,
+                }
+            }
+            return Ok(cdir);
+        }
+    }
+    err_at!(InvalidInput, msg: format!("{:?}", dir))
+}
+
 // open file for reading.
 pub(crate) fn open_file_r(file: &ffi::OsStr) -> Result<fs::File> {
     let os_file = path::Path::new(file);
@@ -198,6 +222,40 @@ where
     }
 }
 
+pub(crate) fn to_owned_range<K, R>(range: &R) -> (Bound<K>, Bound<K>)
+where
+    K: Clone,
+    R: RangeBounds<K>,
+{
+    let low = match range.start_bound() {
+        Bound::Included(lk) => Bound::Included(lk.clone()),
+        Bound::Excluded(lk) => Bound::Excluded(lk.clone()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let high = match range.end_bound() {
+        Bound::Included(hk) => Bound::Included(hk.clone()),
+        Bound::Excluded(hk) => Bound::Excluded(hk.clone()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    (low, high)
+}
+
+// return true if ranges `a` and `b` can have keys in common.
+pub(crate) fn is_overlapping<K>(a: &(Bound<K>, Bound<K>), b: &(Bound<K>, Bound<K>)) -> bool
+where
+    K: Ord,
+{
+    // return true if all keys upto `high` are less than keys from `low`.
+    let is_below = |high: &Bound<K>, low: &Bound<K>| match (high, low) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+        (Bound::Included(hk), Bound::Included(lk)) => hk < lk,
+        (Bound::Included(hk), Bound::Excluded(lk)) => hk <= lk,
+        (Bound::Excluded(hk), Bound::Included(lk)) => hk <= lk,
+        (Bound::Excluded(hk), Bound::Excluded(lk)) => hk <= lk,
+    };
+    !is_below(&a.1, &b.0) && !is_below(&b.1, &a.0)
+}
+
 #[cfg(test)]
 #[path = "util_test.rs"]
 mod util_test;
@@ -112,3 +112,62 @@ fn test_as_sharded_array() {
         }
     }
 }
+
+#[test]
+fn test_is_overlapping() {
+    use std::ops::Bound::{Excluded as E, Included as I, Unbounded as U};
+
+    let testcases: Vec<((Bound<i32>, Bound<i32>), bool)> = vec![
+        ((U, U), true),
+        ((U, E(10)), false),
+        ((U, I(10)), true),
+        ((I(20), U), true),
+        ((E(20), U), false),
+        ((E(10), E(20)), true),
+        ((I(12), I(18)), true),
+        ((I(0), E(10)), false),
+        ((E(20), I(30)), false),
+        ((I(0), I(100)), true),
+    ];
+    let range = (I(10), I(20));
+    for (other, res) in testcases.into_iter() {
+        assert_eq!(is_overlapping(&range, &other), res, "{:?}", other);
+        assert_eq!(is_overlapping(&other, &range), res, "{:?}", other);
+    }
+}
+
+#[test]
+fn test_to_canonical_path() {
+    let mut dir = std::env::temp_dir();
+    dir.push("test-to-canonical-path");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    let cdir = fs::canonicalize(&dir).unwrap();
+
+    let mut alias = dir.clone();
+    alias.push(".");
+    alias.push("a");
+    alias.push("..");
+    let mut missing = cdir.clone();
+    missing.push("x");
+    missing.push("y");
+    let mut ref_missing = dir.clone();
+    ref_missing.push("x");
+    ref_missing.push(".");
+    ref_missing.push("y");
+
+    assert_eq!(to_canonical_path(dir.as_os_str()).unwrap(), cdir);
+    fs::create_dir_all(&{
+        let mut a = dir.clone();
+        a.push("a");
+        a
+    })
+    .unwrap();
+    assert_eq!(to_canonical_path(alias.as_os_str()).unwrap(), cdir);
+    assert_eq!(to_canonical_path(ref_missing.as_os_str()).unwrap(), missing);
+
+    let mut bad = dir.clone();
+    bad.push("x");
+    bad.push("..");
+    assert!(to_canonical_path(bad.as_os_str()).is_err());
+}
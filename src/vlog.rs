@@ -1,24 +1,35 @@
+use crc::crc32;
+
 use std::{
     convert::TryInto,
-    fs,
+    ffi, fs,
     io::{self, Read, Seek},
 };
 
 use crate::{
     core::{self, Diff, Footprint, Result, Serialize},
     error::Error,
+    robt::Compression,
 };
 
+// payload is compressed, applicable for both value and delta.
+const COMPRESSED_FLAG: u64 = 0x2000000000000000;
+// payload is followed by crc32 checksum, applicable for value and delta.
+const CHECKSUM_FLAG: u64 = 0x4000000000000000;
+const LEN_MASK: u64 = 0x0FFFFFFFFFFFFFFF;
+
 // *-----*------------------------------------*
 // |flags|        60-bit length               |
 // *-----*------------------------------------*
 // |                 payload                  |
 // *-------------------*----------------------*
+// |     checksum      |
+// *-------------------*
 //
 // Flags:
 // * bit 60 shall be set.
-// * bit 61 reserved
-// * bit 62 reserved
+// * bit 61 payload is compressed, using index's compression codec.
+// * bit 62 header and payload is followed by 32-bit crc32 checksum.
 // * bit 63 reserved
 
 #[derive(Clone)]
@@ -92,7 +103,11 @@ where
     V: Serialize,
 {
     // Return (optional-fpos, the size of header + payload).
-    pub(crate) fn encode(&self, buf: &mut Vec<u8>) -> Result<(Option<u64>, usize)> {
+    pub(crate) fn encode(
+        &self,
+        buf: &mut Vec<u8>,
+        compression: Compression,
+    ) -> Result<(Option<u64>, usize)> {
         match self {
             Value::Native { value } => {
                 let m = buf.len();
@@ -106,11 +121,17 @@ where
                     vlen
                 };
 
+                let (vlen, ok) = compress_payload(buf, m, compression)?;
+
                 let mut hdr1: u64 = convert_at!(vlen)?;
-                hdr1 |= Value::<V>::VALUE_FLAG;
+                hdr1 |= Value::<V>::VALUE_FLAG | CHECKSUM_FLAG;
+                if ok {
+                    hdr1 |= COMPRESSED_FLAG;
+                }
                 buf[m..m + 8].copy_from_slice(&hdr1.to_be_bytes());
+                append_checksum(buf, m);
 
-                Ok((None, vlen + 8))
+                Ok((None, vlen + 12))
             }
             Value::Reference { fpos, length, .. } => {
                 let length: usize = convert_at!((*length))?;
@@ -134,13 +155,18 @@ where
     }
 }
 
-pub(crate) fn fetch_value<V>(fpos: u64, n: u64, fd: &mut fs::File) -> Result<Value<V>>
+pub(crate) fn fetch_value<V>(
+    fpos: u64,
+    n: u64,
+    fd: &mut fs::File,
+    compression: Compression,
+) -> Result<Value<V>>
 where
     V: Default + Serialize,
 {
     let block = read_file!(fd, fpos, n, "reading value from vlog")?;
     let mut value: V = Default::default();
-    value.decode(&block[8..])?;
+    value.decode(&decode_payload(block, fpos, compression)?)?;
     Ok(Value::new_native(value))
 }
 
@@ -152,8 +178,8 @@ where
 //
 // Flags:
 // * bit 60 shall be clear.
-// * bit 61 reserved
-// * bit 62 reserved
+// * bit 61 payload is compressed, using index's compression codec.
+// * bit 62 header and payload is followed by 32-bit crc32 checksum.
 // * bit 63 reserved
 
 #[derive(Clone)]
@@ -222,7 +248,7 @@ where
     V: Diff,
     <V as Diff>::D: Serialize,
 {
-    pub(crate) fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+    pub(crate) fn encode(&self, buf: &mut Vec<u8>, compression: Compression) -> Result<usize> {
         match self {
             Delta::Native { diff } => {
                 let m = buf.len();
@@ -236,27 +262,115 @@ where
                     dlen
                 };
 
-                let hdr1: u64 = convert_at!(dlen)?;
+                let (dlen, ok) = compress_payload(buf, m, compression)?;
+
+                let mut hdr1: u64 = convert_at!(dlen)?;
+                hdr1 |= CHECKSUM_FLAG;
+                if ok {
+                    hdr1 |= COMPRESSED_FLAG;
+                }
                 buf[m..m + 8].copy_from_slice(&hdr1.to_be_bytes());
+                append_checksum(buf, m);
 
-                Ok(dlen + 8)
+                Ok(dlen + 12)
             }
             _ => err_at!(Fatal, msg: format!("not-native-delta")),
         }
     }
 }
 
-pub(crate) fn fetch_delta<V>(fpos: u64, n: u64, fd: &mut fs::File) -> Result<Delta<V>>
+pub(crate) fn fetch_delta<V>(
+    fpos: u64,
+    n: u64,
+    fd: &mut fs::File,
+    compression: Compression,
+) -> Result<Delta<V>>
 where
     V: Diff,
     <V as Diff>::D: Default + Serialize,
 {
     let block = read_file!(fd, fpos, n, "reading delta from vlog")?;
     let mut delta: <V as Diff>::D = Default::default();
-    delta.decode(&block[8..])?;
+    delta.decode(&decode_payload(block, fpos, compression)?)?;
     Ok(Delta::new_native(delta))
 }
 
+// compress the payload of value/delta encoded at `buf[m..]`, in place.
+// Return the payload length and whether it is compressed. Payload is
+// left as is if compression can't make it smaller.
+fn compress_payload(buf: &mut Vec<u8>, m: usize, cmp: Compression) -> Result<(usize, bool)> {
+    let n = buf.len() - (m + 8);
+    match cmp {
+        Compression::None => Ok((n, false)),
+        cmp => {
+            let data = cmp.compress(&buf[m + 8..])?;
+            if data.len() < n {
+                buf.truncate(m + 8);
+                buf.extend_from_slice(&data);
+                Ok((data.len(), true))
+            } else {
+                Ok((n, false))
+            }
+        }
+    }
+}
+
+// append checksum for value/delta encoded at `buf[m..]`.
+fn append_checksum(buf: &mut Vec<u8>, m: usize) {
+    let crc = crc32::checksum_ieee(&buf[m..]);
+    buf.extend_from_slice(&crc.to_be_bytes());
+}
+
+// verify checksum, if present, and return the payload, decompressed
+// using `compression` codec. Corrupted value/delta is reported with an
+// empty file name, caller shall fill in the value-log file name.
+fn decode_payload(mut block: Vec<u8>, fpos: u64, compression: Compression) -> Result<Vec<u8>> {
+    let corrupted = || Err(Error::Corrupted(ffi::OsString::new(), fpos));
+
+    let hdr1 = u64::from_be_bytes(array_at!(block[..8])?);
+    let n: usize = convert_at!((hdr1 & LEN_MASK))?;
+    if (hdr1 & CHECKSUM_FLAG) != 0 {
+        if (n + 12) != block.len() {
+            return corrupted();
+        }
+        let crc = u32::from_be_bytes(array_at!(block[n + 8..])?);
+        if crc != crc32::checksum_ieee(&block[..n + 8]) {
+            return corrupted();
+        }
+    } else if (n + 8) != block.len() {
+        return corrupted();
+    }
+    block.truncate(n + 8);
+
+    if (hdr1 & COMPRESSED_FLAG) == 0 {
+        Ok(block.split_off(8))
+    } else {
+        compression.decompress(&block[8..])
+    }
+}
+
+// Return the uncompressed size of values and deltas encoded in `blob`,
+// using `compression` codec.
+pub(crate) fn to_raw_len(blob: &[u8], compression: Compression) -> Result<usize> {
+    let mut n = 0;
+    let mut off = 0;
+    while off < blob.len() {
+        let hdr1 = u64::from_be_bytes(array_at!(blob[off..off + 8])?);
+        let len: usize = convert_at!((hdr1 & LEN_MASK))?;
+        let payload = &blob[off + 8..off + 8 + len];
+        let c = if (hdr1 & CHECKSUM_FLAG) == 0 { 0 } else { 4 };
+        n += 8
+            + c
+            + if (hdr1 & COMPRESSED_FLAG) == 0 {
+                len
+            } else {
+                compression.decompress_len(payload)?
+            };
+        off += 8 + len + c;
+    }
+    Ok(n)
+}
+
 #[cfg(test)]
 #[path = "vlog_test.rs"]
 mod vlog_test;
@@ -1,4 +1,4 @@
-use crate::{core::Footprint, vlog};
+use crate::{core::Footprint, robt::Compression, vlog};
 
 #[test]
 fn test_value() {
@@ -6,8 +6,15 @@ fn test_value() {
     assert_eq!(value.footprint().unwrap(), 0);
     // encode
     let mut out = vec![];
-    assert_eq!(value.encode(&mut out).unwrap(), (None, 12));
-    assert_eq!(out, vec![16, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 10]);
+    assert_eq!(
+        value.encode(&mut out, Compression::None).unwrap(),
+        (None, 16)
+    );
+    assert_eq!(
+        out[..12].to_vec(),
+        vec![80, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 10]
+    );
+    assert_eq!(out.len(), 16);
     // to_native_value
     assert_eq!(value.to_native_value(), Some(10));
 
@@ -19,8 +26,13 @@ fn test_value() {
     assert_eq!(value.footprint().unwrap(), 3);
     // encode
     let mut out = vec![];
-    assert_eq!(value.encode(&mut out).unwrap(), (None, 15));
-    assert_eq!(out, vec![16, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 3, 10, 20, 30]);
+    assert_eq!(
+        value.encode(&mut out, Compression::None).unwrap(),
+        (None, 19)
+    );
+    let refb = vec![80, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 3, 10, 20, 30];
+    assert_eq!(out[..15].to_vec(), refb);
+    assert_eq!(out.len(), 19);
     // to_native_value
     assert_eq!(value.to_native_value(), Some(vec![10_u8, 20, 30]));
 }
@@ -32,7 +44,7 @@ fn test_fetch_value() {
 
     let value = vlog::Value::new_native(vec![10_u8, 20, 30]);
     let mut refb = vec![];
-    value.encode(&mut refb).unwrap();
+    value.encode(&mut refb, Compression::None).unwrap();
 
     std::fs::write(path.clone(), &refb).expect("io failure");
     let out = std::fs::read(path).unwrap();
@@ -45,8 +57,12 @@ fn test_delta() {
     assert_eq!(delta.footprint().unwrap(), 0);
     // encode
     let mut out = vec![];
-    assert_eq!(delta.encode(&mut out).unwrap(), 12);
-    assert_eq!(out, vec![0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 10]);
+    assert_eq!(delta.encode(&mut out, Compression::None).unwrap(), 16);
+    assert_eq!(
+        out[..12].to_vec(),
+        vec![64, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 10]
+    );
+    assert_eq!(out.len(), 16);
     // into_native_delta
     assert_eq!(delta.into_native_delta(), Some(10));
 
@@ -58,8 +74,10 @@ fn test_delta() {
     assert_eq!(delta.footprint().unwrap(), 3);
     // encode
     let mut out = vec![];
-    assert_eq!(delta.encode(&mut out).unwrap(), 15);
-    assert_eq!(out, vec![0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 3, 10, 20, 30]);
+    assert_eq!(delta.encode(&mut out, Compression::None).unwrap(), 19);
+    let refb = vec![64, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 3, 10, 20, 30];
+    assert_eq!(out[..15].to_vec(), refb);
+    assert_eq!(out.len(), 19);
     // into_native_delta
     assert_eq!(delta.into_native_delta(), Some(vec![10_u8, 20, 30]));
 }
@@ -71,9 +89,71 @@ fn test_fetch_delta() {
 
     let delta = vlog::Delta::<Vec<u8>>::new_native(vec![10_u8, 20, 30]);
     let mut refb = vec![];
-    delta.encode(&mut refb).unwrap();
+    delta.encode(&mut refb, Compression::None).unwrap();
 
     std::fs::write(path.clone(), &refb).expect("io failure");
     let out = std::fs::read(path).unwrap();
     assert_eq!(refb, out);
 }
+
+#[test]
+fn test_compressed_entries() {
+    let mut path = std::env::temp_dir();
+    path.push("test_compressed_entries.data");
+
+    let payload: Vec<u8> = (0..1024).map(|i| (i % 16) as u8).collect();
+
+    let mut blob = vec![];
+    let value = vlog::Value::new_native(payload.clone());
+    let (_, vlen) = value.encode(&mut blob, Compression::Snappy).unwrap();
+    assert!(vlen < payload.len());
+    let delta = vlog::Delta::<Vec<u8>>::new_native(payload.clone());
+    let dlen = delta.encode(&mut blob, Compression::Snappy).unwrap();
+    assert!(dlen < payload.len());
+    // small payloads are left uncompressed.
+    let delta = vlog::Delta::<i32>::new_native(10);
+    assert_eq!(delta.encode(&mut blob, Compression::Snappy).unwrap(), 16);
+
+    let raw_len = vlog::to_raw_len(&blob, Compression::Snappy).unwrap();
+    assert_eq!(raw_len, ((8 + 4 + payload.len() + 4) * 2) + 16);
+
+    std::fs::write(path.clone(), &blob).expect("io failure");
+    let mut fd = std::fs::File::open(path).unwrap();
+    let (vpos, dpos) = (0_u64, vlen as u64);
+    match vlog::fetch_value::<Vec<u8>>(vpos, vlen as u64, &mut fd, Compression::Snappy).unwrap() {
+        vlog::Value::Native { value } => assert_eq!(value, payload),
+        _ => unreachable!(),
+    }
+    let delta =
+        vlog::fetch_delta::<Vec<u8>>(dpos, dlen as u64, &mut fd, Compression::Snappy).unwrap();
+    assert_eq!(delta.into_native_delta(), Some(payload));
+}
+
+#[test]
+fn test_corrupted_entries() {
+    use crate::error::Error;
+
+    let mut path = std::env::temp_dir();
+    path.push("test_corrupted_entries.data");
+
+    let mut blob = vec![];
+    let value = vlog::Value::new_native(vec![10_u8, 20, 30]);
+    let (_, vlen) = value.encode(&mut blob, Compression::None).unwrap();
+    let delta = vlog::Delta::<Vec<u8>>::new_native(vec![10_u8, 20, 30]);
+    let dlen = delta.encode(&mut blob, Compression::None).unwrap();
+    // flip a bit in value's payload and in delta's header.
+    blob[13] ^= 0x1;
+    blob[vlen + 7] ^= 0x1;
+
+    std::fs::write(path.clone(), &blob).expect("io failure");
+    let mut fd = std::fs::File::open(path).unwrap();
+    let (vpos, dpos) = (0_u64, vlen as u64);
+    match vlog::fetch_value::<Vec<u8>>(vpos, vlen as u64, &mut fd, Compression::None) {
+        Err(Error::Corrupted(_, fpos)) => assert_eq!(fpos, vpos),
+        _ => unreachable!(),
+    }
+    match vlog::fetch_delta::<Vec<u8>>(dpos, dlen as u64, &mut fd, Compression::None) {
+        Err(Error::Corrupted(_, fpos)) => assert_eq!(fpos, dpos),
+        _ => unreachable!(),
+    }
+}
@@ -45,25 +45,34 @@
 //!     +---------------+
 //! ```
 //!
+//! **Reading Wal**:
+//!
+//! [Wal::iter_from] and [Wal::tail_from] can be used to read logged
+//! operations, across all shards, in global seqno order. They read the
+//! journal files directly and don't interfere with active writers. Useful
+//! for change-data-capture and log-shipping.
+//!
 //! [wal-link]: https://en.wikipedia.org/wiki/Write-ahead_logging
 
 use log::debug;
 
 use std::{
     borrow::Borrow,
-    convert::{self, TryInto},
+    collections::VecDeque,
+    convert::{self, TryFrom, TryInto},
     ffi, fmt, fs,
     hash::{BuildHasher, Hash, Hasher},
+    io::{self, Read, Seek},
     ops::Bound,
-    result,
+    path, result,
     sync::{atomic::AtomicU64, atomic::Ordering::SeqCst, Arc},
 };
 
 use crate::{
     core::{Diff, Replay, Result, Serialize},
     dlog::{Dlog, DlogState, OpRequest, OpResponse},
-    dlog_entry::DEntry,
-    dlog_journal::Shard,
+    dlog_entry::{Batch, DEntry},
+    dlog_journal::{Journal, JournalFile, Shard},
     error::Error,
     thread as rt,
 };
@@ -84,6 +93,7 @@ where
 
     seqno: Arc<AtomicU64>, // seqno
     threads: Vec<rt::Thread<OpRequest<Op<K, V>>, OpResponse, Shard<State, Op<K, V>>>>,
+    n_dropped: usize,
 }
 
 impl<K, V, H> fmt::Debug for Wal<K, V, H>
@@ -105,6 +115,7 @@ where
 {
     /// Convert dlog instance into Wal.
     pub fn from_dlog(dl: Dlog<State, Op<K, V>>, h: H) -> Wal<K, V, H> {
+        let n_dropped = dl.to_dropped();
         let mut wl = Wal {
             dir: dl.dir,
             name: dl.name,
@@ -112,6 +123,7 @@ where
             hash_builder: h,
             seqno: dl.seqno,
             threads: Default::default(),
+            n_dropped,
         };
 
         debug!(target: "wal   ", "{:?}/{} from dlog", wl.dir, wl.name);
@@ -176,6 +188,38 @@ where
         self.seqno.load(SeqCst)
     }
 
+    /// Return the number of entries dropped from torn or corrupted
+    /// batches, while loading this [Wal], refer to [Dlog::to_dropped].
+    pub fn to_dropped(&self) -> usize {
+        self.n_dropped
+    }
+
+    /// Return an iterator over all logged operations whose seqno is
+    /// greater-than-or-equal-to `seqno`, in global seqno order. Iteration
+    /// stops at the last flushed batch, at the time of reading.
+    pub fn iter_from(&self, seqno: u64) -> Result<WalIter<K, V>> {
+        debug!(
+            target: "wal   ",
+            "iter from seqno:{} for {:?}/{}", seqno, self.dir, self.name
+        );
+        WalIter::new(self, seqno, false /*tail*/)
+    }
+
+    /// Same as [Wal::iter_from], but follows the active journals while
+    /// writers keep appending. When the iterator has caught up with the
+    /// writers it returns `None`, calling `next()` again shall poll for
+    /// newly flushed operations.
+    ///
+    /// An operation is returned only when all operations with lesser
+    /// seqno are returned, or are known to be lost (say, torn batch).
+    pub fn tail_from(&self, seqno: u64) -> Result<WalIter<K, V>> {
+        debug!(
+            target: "wal   ",
+            "tail from seqno:{} for {:?}/{}", seqno, self.dir, self.name
+        );
+        WalIter::new(self, seqno, true /*tail*/)
+    }
+
     /// Create a new writer handle.
     pub fn to_writer(&mut self) -> Result<Writer<K, V, H>> {
         debug!(target: "wal   ", "new writer for {:?}/{}", self.dir, self.name);
@@ -218,52 +262,98 @@ where
         );
 
         let mut ops = 0;
-
-        // println!("threads: {} seqno:{}", self.threads.len(), seqno);
         for thread in self.threads.into_iter() {
             let journals = thread.close_wait()?.into_journals();
-            for journal in journals.into_iter() {
-                // println!("journal li:{:?}", journal.to_last_seqno());
-                if journal.is_cold() {
+            for journal in journals.iter() {
+                ops += Self::replay_journal(journal, db, seqno)?;
+            }
+        }
+
+        Ok(ops)
+    }
+
+    /// Same as [Wal::from_dlog], except that, before spawning the shard
+    /// threads, all mutations greater-than `seqno` are replayed on `db`.
+    /// Subsequent mutations on returned [Wal] are guaranteed to get a
+    /// sequence-number greater than `seqno`.
+    ///
+    /// Return the [Wal] instance along with total number of operations
+    /// replayed on DB.
+    pub fn from_dlog_replay<P>(
+        dl: Dlog<State, Op<K, V>>,
+        h: H,
+        db: &mut P,
+        seqno: u64,
+    ) -> Result<(Wal<K, V, H>, usize)>
+    where
+        V: Diff,
+        P: Replay<K, V>,
+    {
+        debug!(
+            target: "wal   ",
+            "replay from seqno:{} for {:?}/{}", seqno, dl.dir, dl.name
+        );
+
+        let mut ops = 0;
+        for shard in dl.shards.iter() {
+            for journal in shard.to_journals().into_iter() {
+                ops += Self::replay_journal(journal, db, seqno)?;
+            }
+        }
+
+        if dl.seqno.load(SeqCst) <= seqno {
+            dl.seqno.store(seqno + 1, SeqCst);
+        }
+
+        Ok((Wal::from_dlog(dl, h), ops))
+    }
+
+    fn replay_journal<P>(
+        journal: &Journal<State, Op<K, V>>,
+        db: &mut P,
+        seqno: u64,
+    ) -> Result<usize>
+    where
+        V: Diff,
+        P: Replay<K, V>,
+    {
+        if journal.is_cold() {
+            return Ok(0);
+        }
+        match journal.to_last_seqno()? {
+            Some(last_seqno) if last_seqno <= seqno => return Ok(0),
+            _ => (),
+        }
+
+        let mut fd = {
+            let file_path = journal.to_file_path();
+            let mut opts = fs::OpenOptions::new();
+            err_at!(IoError, opts.read(true).write(false).open(file_path))?
+        };
+
+        let mut ops = 0;
+        for batch in journal.to_batches()? {
+            match batch.to_last_seqno() {
+                Some(last_seqno) if last_seqno <= seqno => continue,
+                _ => (),
+            }
+            for entry in batch.into_active(&mut fd)?.into_entries()? {
+                let (e_seqno, op) = entry.into_seqno_op();
+                if e_seqno <= seqno {
                     continue;
                 }
-                match journal.to_last_seqno()? {
-                    Some(last_seqno) if last_seqno <= seqno => continue,
-                    _ => (),
-                }
-
-                let mut fd = {
-                    let file_path = journal.to_file_path();
-                    let mut opts = fs::OpenOptions::new();
-                    err_at!(IoError, opts.read(true).write(false).open(file_path))?
-                };
-
-                for batch in journal.into_batches()? {
-                    // println!("batch li:{:?}", batch.to_last_seqno());
-                    match batch.to_last_seqno() {
-                        Some(last_seqno) if last_seqno <= seqno => continue,
-                        _ => (),
+                match op {
+                    Op::Set { key, value } => {
+                        db.set_index(key, value, e_seqno)?;
+                    }
+                    Op::SetCAS { key, value, cas } => {
+                        db.set_cas_index(key, value, cas, e_seqno)?;
                     }
-                    for entry in batch.into_active(&mut fd)?.into_entries()? {
-                        let (e_seqno, op) = entry.into_seqno_op();
-                        if e_seqno <= seqno {
-                            continue;
-                        }
-                        // println!("seqno {}", e_seqno);
-                        match op {
-                            Op::Set { key, value } => {
-                                db.set_index(key, value, e_seqno)?;
-                            }
-                            Op::SetCAS { key, value, cas } => {
-                                db.set_cas_index(key, value, cas, e_seqno)?;
-                            }
-                            Op::Delete { key } => {
-                                db.delete_index(key, e_seqno)?;
-                            }
-                        }
-                        ops += 1;
+                    Op::Delete { key } => {
+                        db.delete_index(key, e_seqno)?;
                     }
                 }
+                ops += 1;
             }
         }
 
@@ -353,6 +443,235 @@ where
     }
 }
 
+/// Iterator over operations logged in [Wal], created using
+/// [Wal::iter_from] or [Wal::tail_from].
+pub struct WalIter<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    seqno: u64, // next seqno to return.
+    tail: bool,
+    shards: Vec<ShardIter<K, V>>,
+}
+
+impl<K, V> WalIter<K, V>
+where
+    K: 'static + Send + Clone + Default + Serialize,
+    V: 'static + Send + Clone + Default + Serialize,
+{
+    fn new<H>(wl: &Wal<K, V, H>, seqno: u64, tail: bool) -> Result<WalIter<K, V>>
+    where
+        H: Clone + BuildHasher,
+    {
+        let shards = (0..wl.threads.len())
+            .map(|shard_id| ShardIter::new(wl.dir.clone(), wl.name.clone(), shard_id))
+            .collect();
+
+        Ok(WalIter {
+            seqno: std::cmp::max(seqno, 1), // seqno starts from 1
+            tail,
+            shards,
+        })
+    }
+
+    // in tail mode, check whether it is safe to return `seqno`.
+    fn is_next(&self, seqno: u64) -> bool {
+        if !self.tail || seqno == self.seqno {
+            return true;
+        }
+        // every shard has either moved past `seqno`, or holds a
+        // buffered operation, which can only be greater than `seqno`.
+        self.shards
+            .iter()
+            .all(|shard| !shard.entries.is_empty() || shard.last_seqno >= seqno)
+    }
+}
+
+impl<K, V> Iterator for WalIter<K, V>
+where
+    K: 'static + Send + Clone + Default + Serialize,
+    V: 'static + Send + Clone + Default + Serialize,
+{
+    type Item = Result<(u64, Op<K, V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for shard in self.shards.iter_mut() {
+            if shard.entries.is_empty() {
+                match shard.refill(self.seqno) {
+                    Ok(_) => (),
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        }
+
+        let (i, seqno) = self
+            .shards
+            .iter()
+            .enumerate()
+            .filter_map(|(i, shard)| shard.entries.front().map(|(seqno, _)| (i, *seqno)))
+            .min_by_key(|(_, seqno)| *seqno)?;
+
+        if self.is_next(seqno) {
+            let (seqno, op) = self.shards[i].entries.pop_front()?;
+            self.seqno = seqno + 1;
+            Some(Ok((seqno, op)))
+        } else {
+            None
+        }
+    }
+}
+
+// read operations from a single shard's journal files.
+struct ShardIter<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    dir: ffi::OsString,
+    name: String,
+    shard_id: usize,
+
+    num: usize, // current journal number, ZERO if not opened.
+    fd: Option<fs::File>,
+    fpos: u64,
+    last_seqno: u64, // last seqno read from this shard.
+    entries: VecDeque<(u64, Op<K, V>)>,
+}
+
+impl<K, V> ShardIter<K, V>
+where
+    K: 'static + Send + Clone + Default + Serialize,
+    V: 'static + Send + Clone + Default + Serialize,
+{
+    fn new(dir: ffi::OsString, name: String, shard_id: usize) -> ShardIter<K, V> {
+        ShardIter {
+            dir,
+            name,
+            shard_id,
+
+            num: 0,
+            fd: None,
+            fpos: 0,
+            last_seqno: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    // read batches, from current position, till at least one operation
+    // greater-than-or-equal-to `from` is buffered, or till there is
+    // nothing more to read.
+    fn refill(&mut self, from: u64) -> Result<()> {
+        while self.entries.is_empty() {
+            if self.fd.is_none() && !self.open_next()? {
+                break;
+            }
+            if self.read_batch(from)? {
+                continue;
+            }
+            // current journal is exhausted, a newer journal means current
+            // journal is not going to be appended any more. But re-read
+            // the current journal once, it could have been appended after
+            // we hit its end and before the newer journal was created.
+            match self.find_journal()? {
+                Some(_) if self.read_batch(from)? => (),
+                Some(_) => {
+                    self.fd.take();
+                }
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    // open next journal file, return false if there is none.
+    fn open_next(&mut self) -> Result<bool> {
+        loop {
+            let num = match self.find_journal()? {
+                Some(num) => num,
+                None => break Ok(false),
+            };
+            self.num = num;
+            self.fpos = 0;
+
+            let file_path = {
+                let typ = "wal".to_string();
+                let jf: JournalFile = (self.name.clone(), typ, self.shard_id, num).into();
+                let mut fp = path::PathBuf::new();
+                fp.push(&self.dir);
+                fp.push(ffi::OsString::from(jf));
+                fp.into_os_string()
+            };
+            let mut opts = fs::OpenOptions::new();
+            match opts.read(true).open(&file_path) {
+                Ok(fd) => {
+                    self.fd = Some(fd);
+                    break Ok(true);
+                }
+                // journal purged before we could open it, try the next one.
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => err_at!(IoError, Err(err))?,
+            }
+        }
+    }
+
+    // find the oldest journal newer than the current one.
+    fn find_journal(&self) -> Result<Option<usize>> {
+        let mut nums = vec![];
+        for item in err_at!(IoError, fs::read_dir(&self.dir))? {
+            let file_name = err_at!(IoError, item)?.file_name();
+            let jf = JournalFile::from(file_name);
+            match <(String, String, usize, usize)>::try_from(jf) {
+                Ok((name, typ, id, num)) => {
+                    let ok = name == self.name && typ == "wal";
+                    if ok && id == self.shard_id && num > self.num {
+                        nums.push(num)
+                    }
+                }
+                Err(_) => (),
+            }
+        }
+
+        Ok(nums.into_iter().min())
+    }
+
+    // read next batch from current journal, return false if the journal
+    // has no more flushed batch.
+    fn read_batch(&mut self, from: u64) -> Result<bool> {
+        let fd = match self.fd.as_mut() {
+            Some(fd) => fd,
+            None => return Ok(false),
+        };
+        let till = err_at!(IoError, fd.metadata())?.len();
+        if (self.fpos + 24) > till {
+            return Ok(false);
+        }
+
+        let buf = read_file!(fd, self.fpos, 24, "wal-iter batch-header")?;
+        let (length, start_seqno, last_seqno) = Batch::<State, Op<K, V>>::decode_header(&buf)?;
+        let n: u64 = convert_at!(length)?;
+        if (self.fpos + n) > till {
+            return Ok(false); // batch is not yet fully flushed.
+        }
+
+        if last_seqno >= from {
+            let batch: Batch<State, Op<K, V>> =
+                Batch::new_refer(self.fpos, length, start_seqno, last_seqno);
+            for entry in batch.into_active(fd)?.into_entries()? {
+                let (seqno, op) = entry.into_seqno_op();
+                if seqno >= from {
+                    self.entries.push_back((seqno, op));
+                }
+            }
+        }
+        self.fpos += n;
+        self.last_seqno = last_seqno;
+
+        Ok(true)
+    }
+}
+
 /// Wal state, expected by Dlog implementation.
 #[derive(Clone, Default, PartialEq)]
 pub struct State;
@@ -296,6 +296,97 @@ fn test_wal_replay() {
     }
 }
 
+#[test]
+fn test_wal_iter() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-iter");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let nshards = (rng.gen::<usize>() % 4) + 1;
+    let journal_limit = (rng.gen::<usize>() % 100_000) + 1_000;
+    let batch_size = (rng.gen::<usize>() % 100) + 1;
+    println!(
+        "seed:{} nshards:{} journal_limit:{} batch_size:{}",
+        seed, nshards, journal_limit, batch_size
+    );
+
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+
+    let n_ops = 10_000_u64;
+
+    // tail the log while writers are appending.
+    let mut tail = wl.tail_from(1).unwrap();
+    let mut threads = vec![];
+    for i in 0..4 {
+        let mut w = wl.to_writer().unwrap();
+        threads.push(thread::spawn(move || {
+            for j in 0..(n_ops / 4) {
+                let key = (i * n_ops + j) as i64;
+                w.set(key, key).unwrap();
+            }
+        }));
+    }
+
+    let mut tail_items = vec![];
+    while (tail_items.len() as u64) < n_ops {
+        match tail.next() {
+            Some(item) => tail_items.push(item.unwrap()),
+            None => thread::sleep(std::time::Duration::from_millis(1)),
+        }
+    }
+    assert!(tail.next().is_none());
+    for thread in threads.into_iter() {
+        thread.join().unwrap();
+    }
+
+    for (i, (seqno, _)) in tail_items.iter().enumerate() {
+        assert_eq!(*seqno, (i as u64) + 1);
+    }
+
+    // iterate from a random seqno, after all the writes are flushed.
+    let from = (rng.gen::<u64>() % n_ops) + 1;
+    let items: Vec<(u64, Op<i64, i64>)> = loop {
+        let items: Vec<(u64, Op<i64, i64>)> = wl
+            .iter_from(from)
+            .unwrap()
+            .map(|item| item.unwrap())
+            .collect();
+        if (items.len() as u64) == (n_ops - from + 1) {
+            break items;
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+    };
+    let ref_items = tail_items[((from - 1) as usize)..].to_vec();
+    assert_eq!(items.len(), ref_items.len());
+    for (item, ref_item) in items.into_iter().zip(ref_items.into_iter()) {
+        assert_eq!(item.0, ref_item.0);
+        assert!(item.1 == ref_item.1);
+    }
+
+    wl.purge().unwrap();
+}
+
 fn create_wal(
     seed: u128,
     wl: &mut Wal<i64, i64, RandomState>, // wal
@@ -0,0 +1,311 @@
+//! Module `xorfilter` implement the [Bloom] trait for static
+//! [xor filter][xor-filter].
+//!
+//! Xor filters are built once, from the full set of keys, which fits
+//! robt's build-once model. Keys added to the filter are collected as
+//! 32-bit digests, using hasher `H` that defaults to [Crc32], and the
+//! filter is constructed while serializing it via
+//! [to_vec][Bloom::to_vec]. With 8-bit fingerprints, false-positive-rate is
+//! about 0.4% and space is about 9.84 bits per key.
+//!
+//! Since xor filters cannot be updated once built, merging filters, like
+//! in robt's incremental build, shall stack them as segments. A key is
+//! present if any of the segment says so. Compaction shall rebuild the
+//! filter as a single segment.
+//!
+//! [Bloom]: crate::core::Bloom
+//! [xor-filter]: https://arxiv.org/abs/1912.08258
+
+use std::{collections::HashSet, convert::TryInto, hash::Hash};
+
+use crate::core::{Bloom, HasherId, Result};
+use crate::error::Error;
+use crate::hasher::{self, Crc32};
+
+// maximum attempts to find a seed that can peel all the keys.
+const MAX_ATTEMPTS: usize = 1024;
+
+/// Static xor filter, with 8-bit fingerprints.
+pub struct Xor8<H = Crc32>
+where
+    H: HasherId,
+{
+    builder: H,
+    // number of keys added so far, yet to be built into a segment.
+    n_digests: usize,
+    // digests of keys added so far, yet to be built into a segment.
+    digests: HashSet<u32>,
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone)]
+struct Segment {
+    seed: u64,
+    n_keys: usize,
+    block_length: usize,
+    fingerprints: Vec<u8>,
+}
+
+impl<H> Xor8<H>
+where
+    H: HasherId,
+{
+    fn new(n_digests: usize, digests: HashSet<u32>, segments: Vec<Segment>) -> Xor8<H> {
+        Xor8 {
+            builder: Default::default(),
+            n_digests,
+            digests,
+            segments,
+        }
+    }
+
+    // build a new segment from digests, if there are any.
+    fn to_segments(&self) -> Result<Vec<Segment>> {
+        let mut segments = self.segments.clone();
+        if self.digests.len() > 0 {
+            let digests = self.digests.iter().cloned().collect();
+            segments.push(Segment::build(digests)?);
+        }
+        Ok(segments)
+    }
+}
+
+fn murmur64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    h
+}
+
+fn splitmix64(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn fingerprint(hash: u64) -> u8 {
+    (hash ^ (hash >> 32)) as u8
+}
+
+// map `hash` into [0, n) without division.
+fn reduce(hash: u32, n: usize) -> usize {
+    (((hash as u64) * (n as u64)) >> 32) as usize
+}
+
+impl Segment {
+    fn build(mut digests: Vec<u32>) -> Result<Segment> {
+        // digests are unique, sort them to build a deterministic filter.
+        digests.sort();
+
+        let n_keys = digests.len();
+        let capacity = {
+            let capacity = 32 + ((1.23 * (n_keys as f64)).ceil() as usize);
+            (capacity / 3) * 3
+        };
+        let block_length = capacity / 3;
+
+        let mut rng_state = 0x726b_2b9d_438b_9d4d_u64;
+        for _ in 0..MAX_ATTEMPTS {
+            let seed = splitmix64(&mut rng_state);
+            let mut segment = Segment {
+                seed,
+                n_keys,
+                block_length,
+                fingerprints: vec![0; capacity],
+            };
+            if let Some(stack) = segment.peel(&digests) {
+                for (index, hash) in stack.into_iter().rev() {
+                    let [h0, h1, h2] = segment.to_positions(hash);
+                    let fps = &segment.fingerprints;
+                    let fp = fingerprint(hash) ^ fps[h0] ^ fps[h1] ^ fps[h2];
+                    segment.fingerprints[index] = fp;
+                }
+                return Ok(segment);
+            }
+        }
+
+        err_at!(Fatal, msg: format!("xor filter, failed to build {} keys", n_keys))
+    }
+
+    // peel keys from the 3-partite hyper-graph, return the order in which
+    // keys were peeled, as (position, hash), if all keys can be peeled.
+    fn peel(&self, digests: &[u32]) -> Option<Vec<(usize, u64)>> {
+        let capacity = self.fingerprints.len();
+        let mut masks = vec![0_u64; capacity];
+        let mut counts = vec![0_u32; capacity];
+        for digest in digests.iter() {
+            let hash = self.to_hash(*digest);
+            for pos in self.to_positions(hash).iter() {
+                masks[*pos] ^= hash;
+                counts[*pos] += 1;
+            }
+        }
+
+        let mut queue: Vec<usize> = (0..capacity).filter(|i| counts[*i] == 1).collect();
+        let mut stack = Vec::with_capacity(digests.len());
+        while let Some(index) = queue.pop() {
+            if counts[index] == 0 {
+                continue;
+            }
+            let hash = masks[index];
+            stack.push((index, hash));
+            for pos in self.to_positions(hash).iter() {
+                masks[*pos] ^= hash;
+                counts[*pos] -= 1;
+                if counts[*pos] == 1 {
+                    queue.push(*pos);
+                }
+            }
+        }
+
+        if stack.len() == digests.len() {
+            Some(stack)
+        } else {
+            None
+        }
+    }
+
+    fn to_hash(&self, digest: u32) -> u64 {
+        murmur64((digest as u64).wrapping_add(self.seed))
+    }
+
+    fn to_positions(&self, hash: u64) -> [usize; 3] {
+        let n = self.block_length;
+        [
+            reduce(hash as u32, n),
+            reduce(hash.rotate_left(21) as u32, n) + n,
+            reduce(hash.rotate_left(42) as u32, n) + 2 * n,
+        ]
+    }
+
+    fn contains(&self, digest: u32) -> bool {
+        if self.n_keys == 0 {
+            return false;
+        }
+        let hash = self.to_hash(digest);
+        let [h0, h1, h2] = self.to_positions(hash);
+        let fps = &self.fingerprints;
+        fingerprint(hash) == (fps[h0] ^ fps[h1] ^ fps[h2])
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.seed.to_be_bytes());
+        buf.extend_from_slice(&(self.n_keys as u64).to_be_bytes());
+        buf.extend_from_slice(&(self.block_length as u64).to_be_bytes());
+        buf.extend_from_slice(&self.fingerprints);
+    }
+
+    fn decode(buf: &[u8]) -> Result<(Segment, usize)> {
+        if buf.len() < 24 {
+            err_at!(DecodeFail, msg: format!("xor filter segment {}", buf.len()))?
+        }
+        let seed = u64::from_be_bytes(array_at!(buf[..8])?);
+        let n_keys: usize = convert_at!(u64::from_be_bytes(array_at!(buf[8..16])?))?;
+        let block_length: usize = convert_at!(u64::from_be_bytes(array_at!(buf[16..24])?))?;
+        let n = 24 + block_length * 3;
+        if buf.len() < n {
+            let msg = format!("xor filter segment {} < {}", buf.len(), n);
+            err_at!(DecodeFail, msg: msg)?
+        }
+        let segment = Segment {
+            seed,
+            n_keys,
+            block_length,
+            fingerprints: buf[24..n].to_vec(),
+        };
+        Ok((segment, n))
+    }
+}
+
+impl<H> Bloom for Xor8<H>
+where
+    H: HasherId,
+{
+    #[inline]
+    fn create() -> Self {
+        Self::new(0, HashSet::new(), vec![])
+    }
+
+    #[inline]
+    fn len(&self) -> Result<usize> {
+        let n: usize = self.segments.iter().map(|s| s.n_keys).sum();
+        Ok(n + self.n_digests)
+    }
+
+    #[inline]
+    fn add_key<Q: ?Sized + Hash>(&mut self, element: &Q) {
+        let digest = hasher::to_digest32(&self.builder, element);
+        self.add_digest32(digest);
+    }
+
+    #[inline]
+    fn add_digest32(&mut self, digest: u32) {
+        self.digests.insert(digest);
+        self.n_digests += 1;
+    }
+
+    #[inline]
+    fn contains<Q: ?Sized + Hash>(&self, element: &Q) -> bool {
+        let digest = hasher::to_digest32(&self.builder, element);
+        self.segments.iter().any(|s| s.contains(digest)) || self.digests.contains(&digest)
+    }
+
+    /// Serialize the filter, building a new segment for keys added so far.
+    ///
+    /// Format: `n_segments:u32 | [segment]...` where each segment is
+    /// `seed:u64 | n_keys:u64 | block_length:u64 | [fingerprint:u8]...`,
+    /// all integers are encoded in big-endian.
+    fn to_vec(&self) -> Result<Vec<u8>> {
+        let segments = self.to_segments()?;
+        let n_segments: u32 = convert_at!(segments.len())?;
+
+        let mut buf = vec![];
+        buf.extend_from_slice(&n_segments.to_be_bytes());
+        segments.iter().for_each(|s| s.encode(&mut buf));
+        Ok(buf)
+    }
+
+    fn from_vec(buf: &[u8]) -> Result<Xor8<H>> {
+        if buf.len() < 4 {
+            err_at!(DecodeFail, msg: format!("xor filter {}", buf.len()))?
+        }
+        let n_segments = u32::from_be_bytes(array_at!(buf[..4])?);
+
+        let (mut segments, mut off) = (vec![], 4);
+        for _ in 0..n_segments {
+            let (segment, n) = Segment::decode(&buf[off..])?;
+            segments.push(segment);
+            off += n;
+        }
+        if off != buf.len() {
+            let msg = format!("xor filter {} != {}", off, buf.len());
+            err_at!(DecodeFail, msg: msg)?
+        }
+
+        Ok(Self::new(0, HashSet::new(), segments))
+    }
+
+    /// Merge two filters, segments from both the filters are stacked
+    /// together. Digests, yet to be built, from both the filters are
+    /// combined to build a single segment.
+    fn or(&self, other: &Xor8<H>) -> Result<Xor8<H>> {
+        let mut segments = self.segments.clone();
+        segments.extend_from_slice(&other.segments);
+        let n_digests = self.n_digests + other.n_digests;
+        let digests = self.digests.union(&other.digests).cloned().collect();
+        Ok(Self::new(n_digests, digests, segments))
+    }
+
+    #[inline]
+    fn to_hasher_id() -> String {
+        H::to_hasher_id()
+    }
+}
+
+#[cfg(test)]
+#[path = "xorfilter_test.rs"]
+mod xorfilter_test;
@@ -0,0 +1,83 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use super::*;
+
+#[test]
+fn test_xor8() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let keys: Vec<u64> = (0..100_000).map(|_| rng.gen::<u64>() | 1).collect();
+
+    let mut filter: Xor8 = Bloom::create();
+    assert_eq!(filter.len().unwrap(), 0);
+    keys.iter().for_each(|key| filter.add_key(key));
+    assert_eq!(filter.len().unwrap(), keys.len());
+    keys.iter().for_each(|key| assert!(filter.contains(key)));
+
+    let buf = filter.to_vec().unwrap();
+    // about 1.23 bytes per key.
+    assert!(buf.len() < (keys.len() * 125 / 100), "{}", buf.len());
+    let filter = Xor8::<Crc32>::from_vec(&buf).unwrap();
+    assert_eq!(filter.len().unwrap(), keys.len());
+    // no false negatives.
+    keys.iter().for_each(|key| assert!(filter.contains(key)));
+
+    // false positives, even numbers were never added.
+    let n = 100_000;
+    let fps = (0..n)
+        .filter(|_| filter.contains(&(rng.gen::<u64>() & !1)))
+        .count();
+    println!("xor8 fpr:{}", (fps as f64) / (n as f64));
+    assert!(fps < (n / 100), "{}/{}", fps, n);
+}
+
+#[test]
+fn test_xor8_duplicates() {
+    let mut filter: Xor8 = Bloom::create();
+    for key in 0..1000_u64 {
+        filter.add_key(&key);
+        filter.add_key(&key);
+    }
+    let filter = Xor8::<Crc32>::from_vec(&filter.to_vec().unwrap()).unwrap();
+    assert_eq!(filter.len().unwrap(), 1000);
+    (0..1000_u64).for_each(|key| assert!(filter.contains(&key)));
+}
+
+#[test]
+fn test_xor8_empty() {
+    let filter: Xor8 = Bloom::create();
+    let filter = Xor8::<Crc32>::from_vec(&filter.to_vec().unwrap()).unwrap();
+    assert_eq!(filter.len().unwrap(), 0);
+    assert!(!filter.contains(&10_u64));
+
+    assert!(Xor8::<Crc32>::from_vec(&[0, 0, 0, 1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_xor8_or() {
+    let (keys1, keys2): (Vec<u64>, Vec<u64>) = ((0..1000).collect(), (1000..3000).collect());
+
+    let mut f1: Xor8 = Bloom::create();
+    keys1.iter().for_each(|key| f1.add_key(key));
+    let mut f2: Xor8 = Bloom::create();
+    keys2.iter().for_each(|key| f2.add_key(key));
+
+    // digests with digests.
+    let f = f1.or(&f2).unwrap();
+    assert_eq!(f.len().unwrap(), 3000);
+    let f = Xor8::<Crc32>::from_vec(&f.to_vec().unwrap()).unwrap();
+    assert_eq!(f.segments.len(), 1);
+    keys1.iter().for_each(|key| assert!(f.contains(key)));
+    keys2.iter().for_each(|key| assert!(f.contains(key)));
+
+    // segments with digests, like in incremental build.
+    let old = Xor8::<Crc32>::from_vec(&f1.to_vec().unwrap()).unwrap();
+    let f = old.or(&f2).unwrap();
+    assert_eq!(f.len().unwrap(), 3000);
+    let f = Xor8::<Crc32>::from_vec(&f.to_vec().unwrap()).unwrap();
+    assert_eq!(f.segments.len(), 2);
+    keys1.iter().for_each(|key| assert!(f.contains(key)));
+    keys2.iter().for_each(|key| assert!(f.contains(key)));
+}